@@ -0,0 +1,496 @@
+// The requests and responses of the backend's HTTP API, shared by the backend
+// and its clients so that a change to the contract breaks both at compile time.
+// Query string parameters are here too; `routes` has the paths. With the
+// `openapi` feature the types also describe themselves for the backend's
+// OpenAPI document
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub mod routes;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AuthResponse {
+    pub message: String,
+    pub token: String,
+    // Only ever set when logging in
+    #[serde(default)]
+    pub password_reset_required: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RegisterRequest {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct User {
+    pub id: String,
+    pub username: String,
+    pub email: String,
+    pub display_name: Option<String>,
+    // IANA name, e.g. "Europe/Berlin"
+    pub timezone: String,
+    // BCP 47 language tag, e.g. "en" or "de-CH"
+    pub locale: String,
+    pub avatar_url: Option<String>,
+    // "user" or "admin"
+    pub role: String,
+    // Set by an admin; only the profile and password endpoints work until it's cleared
+    pub password_reset_required: bool,
+    // Set while a new address waits for verification; `email` stays in use until then
+    pub pending_email: Option<String>,
+    // Set once the user asked for their account to be deleted, until it happens
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// An empty `display_name` or `avatar_url` clears it. A new `email` is only
+// used once the link sent to it is opened
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UserUpdate {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub timezone: Option<String>,
+    pub locale: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DeleteAccountRequest {
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Todo {
+    pub id: String,
+    pub user_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub position: String,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<i16>,
+    pub remind_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateTodoRequest {
+    pub title: String,
+    pub description: Option<String>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<i16>,
+    pub remind_at: Option<DateTime<Utc>>,
+}
+
+// Fields left out keep their value. The optional ones can also be set to null,
+// which clears them: `Some(None)` here
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TodoUpdate {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub completed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "::serde_with::rust::double_option")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<DateTime<Utc>>))]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "::serde_with::rust::double_option")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<i16>))]
+    pub priority: Option<Option<i16>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "::serde_with::rust::double_option")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<DateTime<Utc>>))]
+    pub remind_at: Option<Option<DateTime<Utc>>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct TodoQuery {
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct DeleteQuery {
+    #[serde(default)]
+    pub permanent: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TodoEvent {
+    pub id: String,
+    pub todo_id: String,
+    pub actor_id: String,
+    pub actor_username: Option<String>,
+    pub event_type: String,
+    pub changes: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum BulkAction {
+    Complete,
+    Uncomplete,
+    Delete,
+    Restore,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkFilter {
+    pub completed: Option<bool>,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+// Either `ids` or `filter`, not both
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkRequest {
+    pub action: BulkAction,
+    pub ids: Option<Vec<String>>,
+    pub filter: Option<BulkFilter>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkItemResult {
+    pub id: String,
+    pub success: bool,
+    pub error: Option<String>,
+    pub todo: Option<Todo>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkResponse {
+    pub results: Vec<BulkItemResult>,
+}
+
+// Places a todo between two neighbours: `previous_id` ends up directly above it and
+// `next_id` directly below it. Omit one of them to move the todo to the top or bottom.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MoveTodoRequest {
+    pub previous_id: Option<String>,
+    pub next_id: Option<String>,
+}
+
+// Which columns of a generic CSV file hold each todo field
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CsvMapping {
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportedTodo {
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<i16>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportError {
+    // Line in the file, or the 1-based entry index for JSON formats
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub created: Vec<ImportedTodo>,
+    pub duplicates: Vec<ImportedTodo>,
+    pub errors: Vec<ImportError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CalendarFeed {
+    pub token: String,
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AppPassword {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateAppPasswordRequest {
+    pub name: String,
+}
+
+// The password itself is only ever returned here, when it is created
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreatedAppPassword {
+    pub id: String,
+    pub name: String,
+    pub password: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+// Without a secret, a random one is generated
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub events: Vec<String>,
+    pub secret: Option<String>,
+}
+
+// The secret is only returned when the webhook is created
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WebhookUpdate {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub event_type: String,
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub payload: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Job {
+    pub id: String,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct JobQuery {
+    pub status: Option<String>,
+    pub kind: Option<String>,
+}
+
+// A user as admins see them
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AdminUser {
+    #[serde(flatten)]
+    pub user: User,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    // Todos not in the trash
+    pub todo_count: i64,
+}
+
+// `q` matches username, email and display name
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct AdminUserQuery {
+    pub q: Option<String>,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AdminUserUpdate {
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AdminStats {
+    pub users: i64,
+    // Logged in within the last 30 days
+    pub active_users: i64,
+    // Registered within the last 7 days
+    pub new_users: i64,
+    pub disabled_users: i64,
+    pub admins: i64,
+    pub todos: i64,
+    pub completed_todos: i64,
+    pub todos_in_trash: i64,
+    // Created within the last 7 days
+    pub new_todos: i64,
+    pub pending_jobs: i64,
+    pub failed_jobs: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NotificationPreferences {
+    pub email_reminders: bool,
+    pub push_reminders: bool,
+    // "off", "daily" or "weekly"
+    pub digest_frequency: String,
+    // Local hour, 0-23
+    pub digest_hour: i16,
+    // ISO weekday of weekly digests, 1 (Monday) to 7
+    pub digest_weekday: i16,
+    pub digest_next_at: Option<DateTime<Utc>>,
+    // IANA name, e.g. "Europe/Berlin"
+    pub timezone: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NotificationPreferencesUpdate {
+    pub email_reminders: Option<bool>,
+    pub push_reminders: Option<bool>,
+    pub digest_frequency: Option<String>,
+    pub digest_hour: Option<i16>,
+    pub digest_weekday: Option<i16>,
+    pub timezone: Option<String>,
+}
+
+// `format` is "html", the default, or "text"
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct DigestPreviewQuery {
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams), into_params(parameter_in = Query))]
+pub struct UnsubscribeQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PushPublicKey {
+    pub public_key: String,
+}
+
+// The JSON form of a browser's PushSubscription
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PushSubscriptionRequest {
+    pub endpoint: String,
+    pub keys: PushSubscriptionKeys,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PushSubscriptionKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PushUnsubscribeRequest {
+    pub endpoint: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn todo_updates_tell_null_from_left_out() {
+        let update: TodoUpdate = serde_json::from_value(json!({ "title": "Call mum", "due_at": null, "priority": 3 })).unwrap();
+        assert_eq!(update.title.as_deref(), Some("Call mum"));
+        assert_eq!(update.due_at, Some(None));
+        assert_eq!(update.priority, Some(Some(3)));
+        assert_eq!(update.remind_at, None);
+
+        // And send them back the same way
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            json!({ "title": "Call mum", "description": null, "completed": null, "due_at": null, "priority": 3 })
+        );
+    }
+
+    #[test]
+    fn bulk_actions_are_snake_case() {
+        let request: BulkRequest = serde_json::from_value(json!({ "action": "uncomplete", "ids": ["a"] })).unwrap();
+        assert_eq!(request.action, BulkAction::Uncomplete);
+        assert_eq!(serde_json::to_value(BulkAction::Restore).unwrap(), json!("restore"));
+    }
+}
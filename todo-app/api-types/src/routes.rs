@@ -0,0 +1,110 @@
+// Paths of the API routes, in the router's syntax: `:name` stands for one
+// path segment. The backend registers these as they are; clients fill them in
+// with `path`
+
+pub const REGISTER: &str = "/api/register";
+pub const LOGIN: &str = "/api/login";
+pub const TOKEN_REFRESH: &str = "/api/token/refresh";
+
+pub const ME: &str = "/api/me";
+pub const ME_RESTORE: &str = "/api/me/restore";
+pub const ME_EXPORT: &str = "/api/me/export";
+pub const ME_PASSWORD: &str = "/api/me/password";
+pub const ME_EMAIL_VERIFY: &str = "/api/me/email/verify";
+
+pub const TODOS: &str = "/api/todos";
+pub const TODOS_BULK: &str = "/api/todos/bulk";
+pub const TODO: &str = "/api/todos/:id";
+pub const TODO_RESTORE: &str = "/api/todos/:id/restore";
+pub const TODO_HISTORY: &str = "/api/todos/:id/history";
+pub const TODO_MOVE: &str = "/api/todos/:id/move";
+// Server-sent events announcing changes to the user's todos, each an
+// `EVENT_TODOS` event with no data
+pub const EVENTS: &str = "/api/events";
+pub const EVENT_TODOS: &str = "todos";
+
+pub const EXPORT: &str = "/api/export";
+pub const IMPORT: &str = "/api/import";
+
+pub const CALENDAR_TOKEN: &str = "/api/calendar/token";
+// `file` is the feed token followed by ".ics"
+pub const CALENDAR_FEED: &str = "/api/calendar/:file";
+
+pub const APP_PASSWORDS: &str = "/api/app-passwords";
+pub const APP_PASSWORD: &str = "/api/app-passwords/:id";
+
+pub const WEBHOOKS: &str = "/api/webhooks";
+pub const WEBHOOK: &str = "/api/webhooks/:id";
+pub const WEBHOOK_DELIVERIES: &str = "/api/webhooks/:id/deliveries";
+pub const WEBHOOK_DELIVERY_RETRY: &str = "/api/webhooks/:id/deliveries/:delivery_id/retry";
+
+pub const NOTIFICATION_PREFERENCES: &str = "/api/notifications/preferences";
+pub const DIGEST_PREVIEW: &str = "/api/digest/preview";
+pub const DIGEST_UNSUBSCRIBE: &str = "/api/digest/unsubscribe";
+pub const PUSH_PUBLIC_KEY: &str = "/api/push/public-key";
+pub const PUSH_SUBSCRIPTIONS: &str = "/api/push/subscriptions";
+
+pub const ADMIN_USERS: &str = "/api/admin/users";
+pub const ADMIN_USER: &str = "/api/admin/users/:id";
+pub const ADMIN_USER_DISABLE: &str = "/api/admin/users/:id/disable";
+pub const ADMIN_USER_ENABLE: &str = "/api/admin/users/:id/enable";
+pub const ADMIN_USER_RESET_PASSWORD: &str = "/api/admin/users/:id/reset-password";
+pub const ADMIN_STATS: &str = "/api/admin/stats";
+pub const ADMIN_JOBS: &str = "/api/admin/jobs";
+pub const ADMIN_JOB_RETRY: &str = "/api/admin/jobs/:id/retry";
+
+// The OpenAPI document, and Swagger UI showing it
+pub const OPENAPI: &str = "/api/openapi.json";
+pub const API_DOCS: &str = "/api/docs";
+
+// For load balancers and monitoring, outside /api: whether the process is up,
+// whether it can serve requests, and Prometheus metrics
+pub const HEALTHZ: &str = "/healthz";
+pub const READYZ: &str = "/readyz";
+pub const METRICS: &str = "/metrics";
+
+// CalDAV clients are pointed here
+pub const DAV_ROOT: &str = "/dav/";
+
+// Fills in a route's parameters, in order, e.g.
+// `path(TODO_HISTORY, &[id])` gives "/api/todos/<id>/history".
+// Panics if the number of parameters doesn't match
+pub fn path(route: &str, params: &[&str]) -> String {
+    let mut params = params.iter();
+    let segments: Vec<&str> = route
+        .split('/')
+        .map(|segment| {
+            if segment.starts_with(':') {
+                params.next().expect("missing route parameter")
+            } else {
+                segment
+            }
+        })
+        .collect();
+    assert!(params.next().is_none(), "too many route parameters for {}", route);
+    segments.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters_are_filled_in_order() {
+        assert_eq!(path(TODOS, &[]), "/api/todos");
+        assert_eq!(path(TODO_HISTORY, &["42"]), "/api/todos/42/history");
+        assert_eq!(path(WEBHOOK_DELIVERY_RETRY, &["w1", "d2"]), "/api/webhooks/w1/deliveries/d2/retry");
+    }
+
+    #[test]
+    #[should_panic(expected = "missing route parameter")]
+    fn missing_parameters_panic() {
+        path(WEBHOOK_DELIVERY_RETRY, &["w1"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "too many route parameters for /api/todos/:id")]
+    fn extra_parameters_panic() {
+        path(TODO, &["1", "2"]);
+    }
+}
@@ -0,0 +1,15 @@
+-- Manual ordering: todos are sorted by a lexicographic fractional index
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS position TEXT;
+
+-- Keep the existing newest-first order; the trailing 'V' leaves room before and after every key
+UPDATE todos
+SET position = ranked.position
+FROM (
+    SELECT id, LPAD(ROW_NUMBER() OVER (PARTITION BY user_id ORDER BY created_at DESC)::TEXT, 10, '0') || 'V' AS position
+    FROM todos
+) AS ranked
+WHERE todos.id = ranked.id AND todos.position IS NULL;
+
+ALTER TABLE todos ALTER COLUMN position SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_todos_user_position ON todos(user_id, position COLLATE "C");
@@ -0,0 +1,5 @@
+ALTER TABLE users DROP COLUMN IF EXISTS calendar_token;
+
+DROP INDEX IF EXISTS idx_todos_due_at;
+ALTER TABLE todos DROP COLUMN IF EXISTS priority;
+ALTER TABLE todos DROP COLUMN IF EXISTS due_at;
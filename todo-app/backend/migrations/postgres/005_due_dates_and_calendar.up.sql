@@ -0,0 +1,8 @@
+-- Due dates and RFC 5545 priorities (1 = highest, 9 = lowest)
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS due_at TIMESTAMPTZ;
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS priority SMALLINT CHECK (priority BETWEEN 1 AND 9);
+
+CREATE INDEX IF NOT EXISTS idx_todos_due_at ON todos(due_at);
+
+-- Secret token for the per-user iCalendar feed
+ALTER TABLE users ADD COLUMN IF NOT EXISTS calendar_token TEXT UNIQUE;
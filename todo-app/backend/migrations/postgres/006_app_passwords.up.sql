@@ -0,0 +1,12 @@
+-- Per-client passwords for HTTP Basic authentication (CalDAV); only a SHA-256
+-- hash of each randomly generated password is stored
+CREATE TABLE IF NOT EXISTS app_passwords (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    password_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_app_passwords_user_id ON app_passwords(user_id);
@@ -0,0 +1,5 @@
+-- Queued jobs are lost, pending webhook deliveries stay in their table
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due ON webhook_deliveries(next_attempt_at) WHERE status = 'pending';
+
+DROP TABLE IF EXISTS scheduled_jobs;
+DROP TABLE IF EXISTS jobs;
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS idx_notification_preferences_digest_due;
+ALTER TABLE notification_preferences DROP COLUMN IF EXISTS digest_next_at;
+ALTER TABLE notification_preferences DROP COLUMN IF EXISTS digest_weekday;
+ALTER TABLE notification_preferences DROP COLUMN IF EXISTS digest_hour;
+ALTER TABLE notification_preferences DROP COLUMN IF EXISTS digest_frequency;
+
+ALTER TABLE users DROP COLUMN IF EXISTS timezone;
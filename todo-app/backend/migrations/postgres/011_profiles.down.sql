@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS idx_users_email_verification_token_hash;
+ALTER TABLE users DROP COLUMN IF EXISTS email_verification_expires_at;
+ALTER TABLE users DROP COLUMN IF EXISTS email_verification_token_hash;
+ALTER TABLE users DROP COLUMN IF EXISTS pending_email;
+
+ALTER TABLE users DROP COLUMN IF EXISTS avatar_url;
+ALTER TABLE users DROP COLUMN IF EXISTS locale;
+ALTER TABLE users DROP COLUMN IF EXISTS display_name;
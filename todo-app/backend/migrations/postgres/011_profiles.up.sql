@@ -0,0 +1,13 @@
+-- Profile fields shown in the app; an empty display name falls back to the username
+ALTER TABLE users ADD COLUMN IF NOT EXISTS display_name TEXT;
+-- BCP 47 language tag, e.g. "en" or "de-CH"
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locale TEXT NOT NULL DEFAULT 'en';
+ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar_url TEXT;
+
+-- A changed email address only replaces `email` once the link sent to it is
+-- opened; the token is stored hashed, like app passwords
+ALTER TABLE users ADD COLUMN IF NOT EXISTS pending_email TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verification_token_hash TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verification_expires_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_users_email_verification_token_hash ON users(email_verification_token_hash);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_todos_caldav_name;
+ALTER TABLE todos DROP COLUMN IF EXISTS caldav_uid;
+ALTER TABLE todos DROP COLUMN IF EXISTS caldav_name;
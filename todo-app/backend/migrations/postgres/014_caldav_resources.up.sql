@@ -0,0 +1,7 @@
+-- Resources a CalDAV client created keep the name (without ".ics") and UID it
+-- gave them; other todos are served as <id>.ics, with their id as the UID
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS caldav_name TEXT;
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS caldav_uid TEXT;
+
+-- Each user's client picks names in its own collection
+CREATE UNIQUE INDEX IF NOT EXISTS idx_todos_caldav_name ON todos(user_id, caldav_name);
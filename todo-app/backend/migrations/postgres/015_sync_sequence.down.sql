@@ -0,0 +1,6 @@
+DROP TRIGGER IF EXISTS todos_sync_seq ON todos;
+DROP FUNCTION IF EXISTS todos_take_sync_seq();
+DROP TABLE IF EXISTS todo_tombstones;
+DROP INDEX IF EXISTS idx_todos_sync_seq;
+ALTER TABLE todos DROP COLUMN IF EXISTS sync_seq;
+ALTER TABLE users DROP COLUMN IF EXISTS sync_seq;
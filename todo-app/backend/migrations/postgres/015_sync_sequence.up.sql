@@ -0,0 +1,46 @@
+-- CalDAV sync tokens. Every change to a user's todos, deletions included,
+-- takes the next number of the user's sequence. Taking one locks the user's
+-- row until the change commits, so a user's numbers commit in order, and a
+-- token never passes over a change that is still to commit
+ALTER TABLE users ADD COLUMN IF NOT EXISTS sync_seq BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS sync_seq BIGINT NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS idx_todos_sync_seq ON todos(user_id, sync_seq);
+
+-- The CalDAV names of deleted todos, so syncs can report them gone
+CREATE TABLE IF NOT EXISTS todo_tombstones (
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    sync_seq BIGINT NOT NULL,
+    PRIMARY KEY (user_id, name)
+);
+CREATE INDEX IF NOT EXISTS idx_todo_tombstones_sync_seq ON todo_tombstones(user_id, sync_seq);
+
+-- Rows inserted with a number already, as restores do, keep it
+CREATE OR REPLACE FUNCTION todos_take_sync_seq() RETURNS TRIGGER AS $$
+DECLARE
+    seq BIGINT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        UPDATE users SET sync_seq = sync_seq + 1 WHERE id = OLD.user_id RETURNING sync_seq INTO seq;
+        -- Not found when the todo goes with its user
+        IF FOUND THEN
+            INSERT INTO todo_tombstones (user_id, name, sync_seq)
+            VALUES (OLD.user_id, COALESCE(OLD.caldav_name, OLD.id), seq)
+            ON CONFLICT (user_id, name) DO UPDATE SET sync_seq = EXCLUDED.sync_seq;
+        END IF;
+        RETURN OLD;
+    END IF;
+    IF TG_OP = 'INSERT' AND NEW.sync_seq <> 0 THEN
+        RETURN NEW;
+    END IF;
+    UPDATE users SET sync_seq = sync_seq + 1 WHERE id = NEW.user_id RETURNING sync_seq INTO NEW.sync_seq;
+    IF TG_OP = 'INSERT' THEN
+        DELETE FROM todo_tombstones WHERE user_id = NEW.user_id AND name = COALESCE(NEW.caldav_name, NEW.id);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS todos_sync_seq ON todos;
+CREATE TRIGGER todos_sync_seq BEFORE INSERT OR UPDATE OR DELETE ON todos
+    FOR EACH ROW EXECUTE FUNCTION todos_take_sync_seq();
@@ -0,0 +1,4 @@
+-- Soft delete: trashed todos keep their row until purged
+ALTER TABLE todos ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_todos_deleted_at ON todos(deleted_at);
@@ -0,0 +1,3 @@
+-- The order is lost; todos fall back to newest first
+DROP INDEX IF EXISTS idx_todos_user_position;
+ALTER TABLE todos DROP COLUMN position;
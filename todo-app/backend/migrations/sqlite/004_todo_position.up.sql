@@ -0,0 +1,17 @@
+-- Manual ordering: todos are sorted by a lexicographic fractional index
+ALTER TABLE todos ADD COLUMN position TEXT NOT NULL DEFAULT '';
+
+-- Keep the existing newest-first order; the trailing 'V' leaves room before and after every key
+UPDATE todos
+SET position = (
+    SELECT printf('%010dV', ranked.row_number)
+    FROM (
+        SELECT id, ROW_NUMBER() OVER (PARTITION BY user_id ORDER BY created_at DESC) AS row_number
+        FROM todos
+    ) AS ranked
+    WHERE ranked.id = todos.id
+)
+WHERE position = '';
+
+-- BINARY is byte order, which is what the "C" collation in queries means too
+CREATE INDEX IF NOT EXISTS idx_todos_user_position ON todos(user_id, position COLLATE BINARY);
@@ -0,0 +1,10 @@
+-- Due dates and RFC 5545 priorities (1 = highest, 9 = lowest)
+ALTER TABLE todos ADD COLUMN due_at TEXT;
+ALTER TABLE todos ADD COLUMN priority SMALLINT CHECK (priority BETWEEN 1 AND 9);
+
+CREATE INDEX IF NOT EXISTS idx_todos_due_at ON todos(due_at);
+
+-- Secret token for the per-user iCalendar feed
+ALTER TABLE users ADD COLUMN calendar_token TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_calendar_token ON users(calendar_token);
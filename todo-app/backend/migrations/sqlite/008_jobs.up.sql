@@ -0,0 +1,28 @@
+-- Durable background job queue
+CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload TEXT NOT NULL DEFAULT '{}',
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    run_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    locked_at TEXT,
+    last_error TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    finished_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_runnable ON jobs(run_at) WHERE status IN ('pending', 'running');
+CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(status, updated_at);
+
+-- When each cron-style schedule is next due; claiming a row enqueues its job
+CREATE TABLE IF NOT EXISTS scheduled_jobs (
+    kind TEXT PRIMARY KEY,
+    next_run_at TEXT NOT NULL,
+    last_run_at TEXT
+);
+
+-- Webhook delivery attempts are now driven by jobs
+DROP INDEX IF EXISTS idx_webhook_deliveries_due;
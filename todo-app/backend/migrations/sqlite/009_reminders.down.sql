@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS push_subscriptions;
+DROP TABLE IF EXISTS notification_preferences;
+
+DROP INDEX IF EXISTS idx_todos_reminders_due;
+ALTER TABLE todos DROP COLUMN reminded_at;
+ALTER TABLE todos DROP COLUMN remind_at;
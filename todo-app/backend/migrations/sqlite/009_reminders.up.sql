@@ -0,0 +1,25 @@
+-- When to remind the user of a todo, and when that reminder went out
+ALTER TABLE todos ADD COLUMN remind_at TEXT;
+ALTER TABLE todos ADD COLUMN reminded_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_todos_reminders_due ON todos(remind_at) WHERE reminded_at IS NULL AND deleted_at IS NULL;
+
+-- Users without a row get the defaults
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id TEXT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    email_reminders BOOLEAN NOT NULL DEFAULT TRUE,
+    push_reminders BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+-- Web Push subscriptions of the user's browsers
+CREATE TABLE IF NOT EXISTS push_subscriptions (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    endpoint TEXT NOT NULL UNIQUE,
+    p256dh TEXT NOT NULL,
+    auth TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_push_subscriptions_user ON push_subscriptions(user_id);
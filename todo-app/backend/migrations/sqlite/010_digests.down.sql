@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS idx_notification_preferences_digest_due;
+ALTER TABLE notification_preferences DROP COLUMN digest_next_at;
+ALTER TABLE notification_preferences DROP COLUMN digest_weekday;
+ALTER TABLE notification_preferences DROP COLUMN digest_hour;
+ALTER TABLE notification_preferences DROP COLUMN digest_frequency;
+
+ALTER TABLE users DROP COLUMN timezone;
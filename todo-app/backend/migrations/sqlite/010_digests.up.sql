@@ -0,0 +1,11 @@
+-- IANA time zone name, used to send digests in the user's morning
+ALTER TABLE users ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
+
+-- Opt-in digest emails: 'off', 'daily' or 'weekly', at a local hour and, for
+-- weekly digests, an ISO weekday (1 = Monday)
+ALTER TABLE notification_preferences ADD COLUMN digest_frequency TEXT NOT NULL DEFAULT 'off';
+ALTER TABLE notification_preferences ADD COLUMN digest_hour SMALLINT NOT NULL DEFAULT 8 CHECK (digest_hour BETWEEN 0 AND 23);
+ALTER TABLE notification_preferences ADD COLUMN digest_weekday SMALLINT NOT NULL DEFAULT 1 CHECK (digest_weekday BETWEEN 1 AND 7);
+ALTER TABLE notification_preferences ADD COLUMN digest_next_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_notification_preferences_digest_due ON notification_preferences(digest_next_at) WHERE digest_frequency <> 'off';
@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS idx_users_email_verification_token_hash;
+ALTER TABLE users DROP COLUMN email_verification_expires_at;
+ALTER TABLE users DROP COLUMN email_verification_token_hash;
+ALTER TABLE users DROP COLUMN pending_email;
+
+ALTER TABLE users DROP COLUMN avatar_url;
+ALTER TABLE users DROP COLUMN locale;
+ALTER TABLE users DROP COLUMN display_name;
@@ -0,0 +1,6 @@
+-- Accounts whose owner asked for deletion, and when they get purged. Every
+-- table holding a user's data references users(id) ON DELETE CASCADE, so
+-- deleting the row removes all of it
+ALTER TABLE users ADD COLUMN deletion_scheduled_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_users_deletion_scheduled_at ON users(deletion_scheduled_at) WHERE deletion_scheduled_at IS NOT NULL;
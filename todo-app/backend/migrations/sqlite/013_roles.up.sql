@@ -0,0 +1,8 @@
+-- 'user' or 'admin'; admins can use the /api/admin endpoints
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'user' CHECK (role IN ('user', 'admin'));
+
+-- Disabled accounts can't log in or use the API, CalDAV or calendar feeds
+ALTER TABLE users ADD COLUMN disabled_at TEXT;
+-- Set by an admin; the user can only change their password until they do
+ALTER TABLE users ADD COLUMN password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN last_login_at TEXT;
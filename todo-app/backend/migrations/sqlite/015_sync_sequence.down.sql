@@ -0,0 +1,7 @@
+DROP TRIGGER IF EXISTS todos_sync_seq_delete;
+DROP TRIGGER IF EXISTS todos_sync_seq_update;
+DROP TRIGGER IF EXISTS todos_sync_seq_insert;
+DROP TABLE IF EXISTS todo_tombstones;
+DROP INDEX IF EXISTS idx_todos_sync_seq;
+ALTER TABLE todos DROP COLUMN sync_seq;
+ALTER TABLE users DROP COLUMN sync_seq;
@@ -0,0 +1,39 @@
+-- CalDAV sync tokens. Every change to a user's todos, deletions included,
+-- takes the next number of the user's sequence. SQLite runs one write
+-- transaction at a time, so a user's numbers commit in order
+ALTER TABLE users ADD COLUMN sync_seq INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE todos ADD COLUMN sync_seq INTEGER NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS idx_todos_sync_seq ON todos(user_id, sync_seq);
+
+-- The CalDAV names of deleted todos, so syncs can report them gone
+CREATE TABLE IF NOT EXISTS todo_tombstones (
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    sync_seq INTEGER NOT NULL,
+    PRIMARY KEY (user_id, name)
+);
+CREATE INDEX IF NOT EXISTS idx_todo_tombstones_sync_seq ON todo_tombstones(user_id, sync_seq);
+
+-- Rows inserted with a number already, as restores do, keep it
+CREATE TRIGGER IF NOT EXISTS todos_sync_seq_insert AFTER INSERT ON todos WHEN NEW.sync_seq = 0
+BEGIN
+    UPDATE users SET sync_seq = sync_seq + 1 WHERE id = NEW.user_id;
+    UPDATE todos SET sync_seq = (SELECT sync_seq FROM users WHERE id = NEW.user_id) WHERE id = NEW.id;
+    DELETE FROM todo_tombstones WHERE user_id = NEW.user_id AND name = COALESCE(NEW.caldav_name, NEW.id);
+END;
+
+-- Not for the update the triggers make themselves
+CREATE TRIGGER IF NOT EXISTS todos_sync_seq_update AFTER UPDATE ON todos WHEN NEW.sync_seq = OLD.sync_seq
+BEGIN
+    UPDATE users SET sync_seq = sync_seq + 1 WHERE id = NEW.user_id;
+    UPDATE todos SET sync_seq = (SELECT sync_seq FROM users WHERE id = NEW.user_id) WHERE id = NEW.id;
+END;
+
+-- The user is gone already when the todo goes with them, so nothing is inserted
+CREATE TRIGGER IF NOT EXISTS todos_sync_seq_delete AFTER DELETE ON todos
+BEGIN
+    UPDATE users SET sync_seq = sync_seq + 1 WHERE id = OLD.user_id;
+    INSERT INTO todo_tombstones (user_id, name, sync_seq)
+    SELECT id, COALESCE(OLD.caldav_name, OLD.id), sync_seq FROM users WHERE id = OLD.user_id
+    ON CONFLICT (user_id, name) DO UPDATE SET sync_seq = excluded.sync_seq;
+END;
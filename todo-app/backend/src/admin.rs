@@ -0,0 +1,403 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use utoipa::OpenApi;
+use todo_api_types::{routes, AdminStats, AdminUser, AdminUserQuery, AdminUserUpdate, Job, JobQuery};
+use crate::{
+    auth::{self, Admin, RequireRole},
+    db::{self, Pool, Row},
+    jobs,
+    profile, AppState,
+};
+
+const USER_LIST_LIMIT: i64 = 100;
+const JOB_LIST_LIMIT: i64 = 100;
+// Users who logged in within this many days count as active
+const ACTIVE_USER_DAYS: i64 = 30;
+// What counts as new users and todos
+const RECENT_DAYS: i64 = 7;
+
+// Every route here requires the admin role
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(routes::ADMIN_USERS, get(get_users))
+        .route(routes::ADMIN_USER, get(get_user).patch(update_user))
+        .route(routes::ADMIN_USER_DISABLE, post(disable_user))
+        .route(routes::ADMIN_USER_ENABLE, post(enable_user))
+        .route(routes::ADMIN_USER_RESET_PASSWORD, post(require_password_reset))
+        .route(routes::ADMIN_STATS, get(get_stats))
+        .route(routes::ADMIN_JOBS, get(get_jobs))
+        .route(routes::ADMIN_JOB_RETRY, post(retry_job))
+}
+
+// The routes above, for the API document
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_users,
+    get_user,
+    update_user,
+    disable_user,
+    enable_user,
+    require_password_reset,
+    get_stats,
+    get_jobs,
+    retry_job,
+))]
+pub struct ApiDoc;
+
+const ADMIN_USER_COLUMNS: &str =
+    "u.*, (SELECT COUNT(*) FROM todos t WHERE t.user_id = u.id AND t.deleted_at IS NULL) AS todo_count";
+
+fn admin_user_from_row(row: &Row) -> Result<AdminUser, sqlx::Error> {
+    Ok(AdminUser {
+        user: profile::user_from_row(row)?,
+        disabled_at: row.try_get("disabled_at")?,
+        last_login_at: row.try_get("last_login_at")?,
+        todo_count: row.try_get("todo_count")?,
+    })
+}
+
+async fn load_admin_user(state: &AppState, id: &str) -> Result<AdminUser, StatusCode> {
+    db::query(&format!("SELECT {} FROM users u WHERE u.id = $1", ADMIN_USER_COLUMNS))
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(|row| admin_user_from_row(&row))
+        .transpose()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+// Newest first, 100 at a time
+#[utoipa::path(
+    get,
+    path = routes::ADMIN_USERS,
+    tag = "admin",
+    params(AdminUserQuery),
+    responses(
+        (status = 200, body = Vec<AdminUser>),
+    ),
+)]
+async fn get_users(
+    State(state): State<AppState>,
+    _admin: RequireRole<Admin>,
+    Query(query): Query<AdminUserQuery>,
+) -> Result<Json<Vec<AdminUser>>, StatusCode> {
+    // Wildcards typed by the admin are matched literally. ILIKE is LIKE on
+    // SQLite, which ignores case too
+    let pattern = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()).map(|q| {
+        format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+    });
+
+    let rows = db::query(&format!(
+        "SELECT {} FROM users u
+         WHERE $1 IS NULL OR u.username ILIKE $1 ESCAPE '\\' OR u.email ILIKE $1 ESCAPE '\\' OR u.display_name ILIKE $1 ESCAPE '\\'
+         ORDER BY u.created_at DESC LIMIT $2 OFFSET $3",
+        ADMIN_USER_COLUMNS
+    ))
+    .bind(pattern)
+    .bind(USER_LIST_LIMIT)
+    .bind(query.offset.max(0))
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    rows.iter()
+        .map(admin_user_from_row)
+        .collect::<Result<_, _>>()
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[utoipa::path(
+    get,
+    path = routes::ADMIN_USER,
+    tag = "admin",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = AdminUser),
+        (status = 404, description = "No such user"),
+    ),
+)]
+async fn get_user(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    _admin: RequireRole<Admin>,
+) -> Result<Json<AdminUser>, StatusCode> {
+    load_admin_user(&state, &id).await.map(Json)
+}
+
+// Admins can't take away their own role or disable themselves, so there is
+// always someone left to undo it
+fn reject_self(admin: &RequireRole<Admin>, id: &str) -> Result<(), StatusCode> {
+    if admin.claims.sub == id {
+        Err(StatusCode::BAD_REQUEST)
+    } else {
+        Ok(())
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = routes::ADMIN_USER,
+    tag = "admin",
+    params(("id" = String, Path, description = "User ID")),
+    request_body = AdminUserUpdate,
+    responses(
+        (status = 200, body = AdminUser),
+        (status = 400, description = "Unknown role, or admins taking away their own"),
+        (status = 404, description = "No such user"),
+    ),
+)]
+async fn update_user(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    admin: RequireRole<Admin>,
+    Json(payload): Json<AdminUserUpdate>,
+) -> Result<Json<AdminUser>, StatusCode> {
+    if let Some(role) = &payload.role {
+        if !auth::ROLES.contains(&role.as_str()) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        if role != auth::ROLE_ADMIN {
+            reject_self(&admin, &id)?;
+        }
+    }
+
+    let result = db::query("UPDATE users SET role = COALESCE($1, role) WHERE id = $2")
+        .bind(&payload.role)
+        .bind(&id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    load_admin_user(&state, &id).await.map(Json)
+}
+
+// Disabling again keeps the original time. False if there's no such user
+pub async fn set_account_disabled(db: &Pool, id: &str, disabled: bool) -> Result<bool, sqlx::Error> {
+    let result = db::query(
+        "UPDATE users SET disabled_at = CASE WHEN $1 THEN COALESCE(disabled_at, NOW()) ELSE NULL END WHERE id = $2"
+    )
+    .bind(disabled)
+    .bind(id)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// The user keeps logging in with their current password, but has to choose a
+// new one before they can do anything else. False if there's no such user
+pub async fn require_new_password(db: &Pool, id: &str) -> Result<bool, sqlx::Error> {
+    let result = db::query("UPDATE users SET password_reset_required = TRUE WHERE id = $1")
+        .bind(id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+async fn set_disabled(state: &AppState, id: &str, disabled: bool) -> Result<Json<AdminUser>, StatusCode> {
+    let found = set_account_disabled(&state.db, id, disabled).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !found {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    load_admin_user(state, id).await.map(Json)
+}
+
+#[utoipa::path(
+    post,
+    path = routes::ADMIN_USER_DISABLE,
+    tag = "admin",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = AdminUser),
+        (status = 400, description = "Admins can't disable themselves"),
+        (status = 404, description = "No such user"),
+    ),
+)]
+async fn disable_user(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    admin: RequireRole<Admin>,
+) -> Result<Json<AdminUser>, StatusCode> {
+    reject_self(&admin, &id)?;
+    set_disabled(&state, &id, true).await
+}
+
+#[utoipa::path(
+    post,
+    path = routes::ADMIN_USER_ENABLE,
+    tag = "admin",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = AdminUser),
+        (status = 404, description = "No such user"),
+    ),
+)]
+async fn enable_user(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    _admin: RequireRole<Admin>,
+) -> Result<Json<AdminUser>, StatusCode> {
+    set_disabled(&state, &id, false).await
+}
+
+#[utoipa::path(
+    post,
+    path = routes::ADMIN_USER_RESET_PASSWORD,
+    tag = "admin",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, body = AdminUser),
+        (status = 404, description = "No such user"),
+    ),
+)]
+async fn require_password_reset(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    _admin: RequireRole<Admin>,
+) -> Result<Json<AdminUser>, StatusCode> {
+    let found = require_new_password(&state.db, &id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !found {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    load_admin_user(&state, &id).await.map(Json)
+}
+
+#[utoipa::path(
+    get,
+    path = routes::ADMIN_STATS,
+    tag = "admin",
+    responses(
+        (status = 200, body = AdminStats),
+    ),
+)]
+async fn get_stats(
+    State(state): State<AppState>,
+    _admin: RequireRole<Admin>,
+) -> Result<Json<AdminStats>, StatusCode> {
+    let now = chrono::Utc::now();
+    let row = db::query(
+        "SELECT
+             (SELECT COUNT(*) FROM users) AS users,
+             (SELECT COUNT(*) FROM users WHERE last_login_at >= $1) AS active_users,
+             (SELECT COUNT(*) FROM users WHERE created_at >= $2) AS new_users,
+             (SELECT COUNT(*) FROM users WHERE disabled_at IS NOT NULL) AS disabled_users,
+             (SELECT COUNT(*) FROM users WHERE role = $3) AS admins,
+             (SELECT COUNT(*) FROM todos WHERE deleted_at IS NULL) AS todos,
+             (SELECT COUNT(*) FROM todos WHERE deleted_at IS NULL AND completed) AS completed_todos,
+             (SELECT COUNT(*) FROM todos WHERE deleted_at IS NOT NULL) AS todos_in_trash,
+             (SELECT COUNT(*) FROM todos WHERE created_at >= $2) AS new_todos,
+             (SELECT COUNT(*) FROM jobs WHERE status = $4) AS pending_jobs,
+             (SELECT COUNT(*) FROM jobs WHERE status = $5) AS failed_jobs"
+    )
+    .bind(now - chrono::Duration::days(ACTIVE_USER_DAYS))
+    .bind(now - chrono::Duration::days(RECENT_DAYS))
+    .bind(auth::ROLE_ADMIN)
+    .bind(jobs::STATUS_PENDING)
+    .bind(jobs::STATUS_FAILED)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(AdminStats {
+        users: row.get("users"),
+        active_users: row.get("active_users"),
+        new_users: row.get("new_users"),
+        disabled_users: row.get("disabled_users"),
+        admins: row.get("admins"),
+        todos: row.get("todos"),
+        completed_todos: row.get("completed_todos"),
+        todos_in_trash: row.get("todos_in_trash"),
+        new_todos: row.get("new_todos"),
+        pending_jobs: row.get("pending_jobs"),
+        failed_jobs: row.get("failed_jobs"),
+    }))
+}
+
+fn job_from_row(row: &Row) -> Job {
+    Job {
+        id: row.get("id"),
+        kind: row.get("kind"),
+        payload: row.get("payload"),
+        status: row.get("status"),
+        attempts: row.get("attempts"),
+        max_attempts: row.get("max_attempts"),
+        run_at: row.get("run_at"),
+        last_error: row.get("last_error"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        finished_at: row.get("finished_at"),
+    }
+}
+
+// Most recently updated first, optionally filtered by status and kind
+#[utoipa::path(
+    get,
+    path = routes::ADMIN_JOBS,
+    tag = "admin",
+    params(JobQuery),
+    responses(
+        (status = 200, body = Vec<Job>),
+    ),
+)]
+async fn get_jobs(
+    State(state): State<AppState>,
+    _admin: RequireRole<Admin>,
+    Query(query): Query<JobQuery>,
+) -> Result<Json<Vec<Job>>, StatusCode> {
+    let rows = db::query(
+        "SELECT * FROM jobs WHERE ($1 IS NULL OR status = $1) AND ($2 IS NULL OR kind = $2) ORDER BY updated_at DESC LIMIT $3"
+    )
+    .bind(&query.status)
+    .bind(&query.kind)
+    .bind(JOB_LIST_LIMIT)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(rows.iter().map(job_from_row).collect()))
+}
+
+// Runs a failed job again as soon as a worker is free, with a fresh set of attempts
+#[utoipa::path(
+    post,
+    path = routes::ADMIN_JOB_RETRY,
+    tag = "admin",
+    params(("id" = String, Path, description = "Job ID")),
+    responses(
+        (status = 200, body = Job),
+        (status = 404, description = "No such failed job"),
+    ),
+)]
+async fn retry_job(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    _admin: RequireRole<Admin>,
+) -> Result<Json<Job>, StatusCode> {
+    let row = db::query(
+        "UPDATE jobs SET status = $1, attempts = 0, run_at = NOW(), updated_at = NOW(), finished_at = NULL WHERE id = $2 AND status = $3 RETURNING *"
+    )
+    .bind(jobs::STATUS_PENDING)
+    .bind(&id)
+    .bind(jobs::STATUS_FAILED)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match row {
+        Some(row) => Ok(Json(job_from_row(&row))),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
@@ -1,71 +1,154 @@
 use axum::{
-    extract::Request,
+    extract::{FromRef, FromRequestParts, Request, State},
     http::StatusCode,
     middleware::Next,
     response::Response,
 };
 use axum::http::request::Parts;
 use axum::async_trait;
-use bcrypt::{hash, verify, DEFAULT_COST};
+use bcrypt::{hash, verify};
+use hmac::{Hmac, Mac};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
-use std::env;
+use sha2::{Digest, Sha256};
+use std::marker::PhantomData;
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
+use todo_api_types::routes;
+use uuid::Uuid;
 
-fn get_jwt_secret() -> String {
-    env::var("JWT_SECRET")
-        .expect("JWT_SECRET environment variable must be set")
-}
+use crate::{config::AuthConfig, repository::UserRepository, AppState};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String, // user id
     pub exp: u64,    // expiration time
+    // Issue time, so clients can tell when to refresh; missing from older tokens
+    #[serde(default)]
+    pub iat: u64,
 }
 
-pub fn create_token(user_id: &str) -> Result<String, jsonwebtoken::errors::Error> {
-    let expiration = SystemTime::now()
+pub fn create_token(config: &AuthConfig, user_id: &str) -> Result<String, jsonwebtoken::errors::Error> {
+    let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
-        .as_secs() + 86400; // 24 hours
+        .as_secs();
 
     let claims = Claims {
         sub: user_id.to_string(),
-        exp: expiration,
+        exp: now + config.token_lifetime_hours as u64 * 3600,
+        iat: now,
     };
 
     encode(
         &Header::default(),
         &claims,
-        &EncodingKey::from_secret(get_jwt_secret().as_ref()),
+        &EncodingKey::from_secret(config.jwt_secret.as_ref()),
     )
 }
 
-pub fn verify_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+pub fn verify_token(config: &AuthConfig, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
     decode::<Claims>(
         token,
-        &DecodingKey::from_secret(get_jwt_secret().as_ref()),
+        &DecodingKey::from_secret(config.jwt_secret.as_ref()),
         &Validation::new(Algorithm::HS256),
     )
     .map(|data| data.claims)
 }
 
-pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
-    hash(password, DEFAULT_COST)
+pub fn hash_password(config: &AuthConfig, password: &str) -> Result<String, bcrypt::BcryptError> {
+    hash(password, config.bcrypt_cost)
 }
 
 pub fn verify_password(password: &str, hash: &str) -> Result<bool, bcrypt::BcryptError> {
     verify(password, hash)
 }
 
+// Random secrets such as app passwords have enough entropy that a fast hash is
+// sufficient, which keeps per-request Basic authentication cheap
+pub fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+// Random token for secret URLs and one-time links
+pub fn new_secret_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn mac(config: &AuthConfig) -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(config.jwt_secret.as_bytes()).expect("HMAC accepts keys of any length")
+}
+
+// `<value>.<hex HMAC>`, for links in emails that must work without logging in
+pub fn sign(config: &AuthConfig, value: &str) -> String {
+    let mut mac = mac(config);
+    mac.update(value.as_bytes());
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}.{}", value, digest)
+}
+
+// The value of a token made by `sign`, if its signature is valid
+pub fn verify_signed<'a>(config: &AuthConfig, token: &'a str) -> Option<&'a str> {
+    let (value, digest) = token.rsplit_once('.')?;
+    let digest_bytes = (0..digest.len())
+        .step_by(2)
+        .map(|i| digest.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<Vec<u8>>>()?;
+
+    let mut mac = mac(config);
+    mac.update(value.as_bytes());
+    mac.verify_slice(&digest_bytes).ok()?;
+    Some(value)
+}
+
+pub const ROLE_USER: &str = "user";
+pub const ROLE_ADMIN: &str = "admin";
+
+pub const ROLES: [&str; 2] = [ROLE_USER, ROLE_ADMIN];
+
+// Until they choose a new password, users who must reset it can only reach these
+fn is_allowed_during_password_reset(path: &str) -> bool {
+    path == routes::ME || path == routes::ME_PASSWORD
+}
+
+// Reachable without a login, each for its own reason:
+const PUBLIC_PATHS: &[&str] = &[
+    // Where the login comes from
+    routes::REGISTER,
+    routes::LOGIN,
+    // Links in emails, which carry their own token in the query
+    routes::DIGEST_UNSUBSCRIBE,
+    routes::ME_EMAIL_VERIFY,
+    // The API document and its Swagger UI describe the API, not its data
+    routes::OPENAPI,
+    routes::API_DOCS,
+    // For load balancers and monitoring
+    routes::HEALTHZ,
+    routes::READYZ,
+    routes::METRICS,
+    // CalDAV authenticates with HTTP Basic and app passwords, see `caldav`
+    "/dav",
+    "/.well-known/caldav",
+];
+
+fn is_public(path: &str) -> bool {
+    // Calendar feeds carry their own secret token in the file name
+    let is_calendar_feed = path.starts_with("/api/calendar/") && path.ends_with(".ics");
+    PUBLIC_PATHS.contains(&path) || path.starts_with("/dav/") || is_calendar_feed
+}
+
 pub async fn auth_middleware(
+    State(state): State<AppState>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    // Skip auth for register and login endpoints
-    // This check must be done before trying to extract the token
-    let path = request.uri().path();
-    if path == "/api/register" || path == "/api/login" {
+    // Checked before looking for a token, which these don't need
+    if is_public(request.uri().path()) {
         return Ok(next.run(request).await);
     }
 
@@ -75,13 +158,18 @@ pub async fn auth_middleware(
         .and_then(|header| header.to_str().ok());
 
     if let Some(token_str) = auth_header.and_then(|s| s.strip_prefix("Bearer ")) {
-        match verify_token(token_str) {
-            Ok(claims) => {
-                request.extensions_mut().insert(claims);
-                return Ok(next.run(request).await);
-            }
-            Err(_) => return Err(StatusCode::UNAUTHORIZED),
+        let claims = verify_token(&state.config.auth, token_str).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+        // Tokens outlive account changes, so disabling takes effect on the next request
+        let status = state.users.account_status(&claims.sub).await?.ok_or(StatusCode::UNAUTHORIZED)?;
+        if status.disabled
+            || (status.password_reset_required && !is_allowed_during_password_reset(request.uri().path()))
+        {
+            return Err(StatusCode::FORBIDDEN);
         }
+
+        request.extensions_mut().insert(claims);
+        return Ok(next.run(request).await);
     }
 
     Err(StatusCode::UNAUTHORIZED)
@@ -105,4 +193,46 @@ where
             .cloned()
             .ok_or(StatusCode::UNAUTHORIZED)
     }
-}
\ No newline at end of file
+}
+
+// A role that handlers can ask for with `RequireRole`
+pub trait Role: Send + Sync {
+    const NAME: &'static str;
+}
+
+pub struct Admin;
+
+impl Role for Admin {
+    const NAME: &'static str = ROLE_ADMIN;
+}
+
+// Extractor for the claims of a user with role `R`; everyone else gets 403.
+// The role is read on every request, so changes apply without a new token
+pub struct RequireRole<R: Role> {
+    pub claims: Claims,
+    role: PhantomData<R>,
+}
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for RequireRole<R>
+where
+    Arc<dyn UserRepository>: FromRef<S>,
+    S: Send + Sync,
+    R: Role,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+
+        let status = Arc::<dyn UserRepository>::from_ref(state).account_status(&claims.sub).await?;
+
+        match status {
+            Some(status) if status.role == R::NAME => Ok(RequireRole { claims, role: PhantomData }),
+            _ => Err(StatusCode::FORBIDDEN),
+        }
+    }
+}
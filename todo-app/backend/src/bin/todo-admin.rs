@@ -0,0 +1,291 @@
+// `todo-admin`, for operators: migrations, accounts, trash and backups, run
+// straight against the database with the backend's own configuration
+// (config.toml, CONFIG_FILE and the environment, as for the server)
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use clap::{Parser, Subcommand};
+use dotenvy::dotenv;
+use rand::{distributions::Alphanumeric, rngs::OsRng, Rng, RngCore};
+use std::{
+    io::{BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+use uuid::Uuid;
+
+use todo_backend::{
+    admin, auth,
+    config::Config,
+    db::{self, Pool},
+    dump, jobs,
+    repository::{NewUser, RepositoryError, SqlRepository, UserRepository},
+};
+
+// Bytes of randomness in a generated JWT secret
+const JWT_SECRET_BYTES: usize = 48;
+// Length of generated passwords, which users have to change when they log in
+const PASSWORD_LENGTH: usize = 16;
+
+#[derive(Parser)]
+#[command(name = "todo-admin", version, about = "Administer a todo backend and its database")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(flatten)]
+    Database(DatabaseCommand),
+    // Needs no configuration, since it is how part of it is made
+    #[command(about = "Print a random secret for auth.jwt_secret (JWT_SECRET)")]
+    GenerateJwtSecret,
+}
+
+// The commands that connect to the database
+#[derive(Subcommand)]
+enum DatabaseCommand {
+    #[command(subcommand, about = "Apply, revert or list database migrations")]
+    Migrate(MigrateCommand),
+    #[command(subcommand, about = "Manage user accounts")]
+    User(UserCommand),
+    #[command(about = "Delete todos that have been in the trash for a while")]
+    PurgeTrash {
+        #[arg(long, help = "Days in the trash before a todo is deleted [default: jobs.trash_retention_days]")]
+        older_than_days: Option<i64>,
+    },
+    #[command(about = "Write the whole database as JSON")]
+    Dump {
+        #[arg(short, long, help = "Write to a file instead of standard output")]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "Load a dump into an empty database at the same schema version")]
+    Restore {
+        file: PathBuf,
+        #[arg(long, help = "Delete everything in the database first")]
+        replace: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MigrateCommand {
+    #[command(about = "Apply every pending migration")]
+    Run,
+    #[command(about = "Revert the last migration, or every one after --to")]
+    Revert {
+        #[arg(long, help = "Version to go back to; 0 reverts them all")]
+        to: Option<i64>,
+    },
+    #[command(about = "List migrations and whether they are applied")]
+    Status,
+}
+
+// Users are named by ID, username or email
+#[derive(Subcommand)]
+enum UserCommand {
+    #[command(about = "Create an account")]
+    Create {
+        #[arg(long)]
+        username: String,
+        #[arg(long)]
+        email: String,
+        #[arg(long, help = "Give it the admin role")]
+        admin: bool,
+        #[arg(long, help = "Read the password from standard input instead of generating one")]
+        password_stdin: bool,
+    },
+    #[command(about = "Stop an account from logging in or using the API")]
+    Disable { user: String },
+    #[command(about = "Let a disabled account log in again")]
+    Enable { user: String },
+    #[command(about = "Set a new password")]
+    ResetPassword {
+        user: String,
+        #[arg(long, help = "Read the password from standard input instead of generating one")]
+        password_stdin: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    if let Err(error) = run(Cli::parse()).await {
+        eprintln!("error: {:#}", error);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let command = match cli.command {
+        Command::GenerateJwtSecret => {
+            let mut secret = [0u8; JWT_SECRET_BYTES];
+            OsRng.fill_bytes(&mut secret);
+            println!("{}", URL_SAFE_NO_PAD.encode(secret));
+            return Ok(());
+        }
+        Command::Database(command) => command,
+    };
+
+    let config = Config::load()?;
+    let pool = Pool::connect(&config.database).await?;
+
+    match command {
+        DatabaseCommand::Migrate(command) => migrate(&pool, command).await,
+        DatabaseCommand::User(command) => user(&pool, &config, command).await,
+        DatabaseCommand::PurgeTrash { older_than_days } => {
+            let days = older_than_days.unwrap_or(config.jobs.trash_retention_days);
+            let purged = jobs::purge_trashed_before(&pool, Utc::now() - Duration::days(days)).await?;
+            println!("Deleted {} todos trashed more than {} days ago", purged, days);
+            Ok(())
+        }
+        DatabaseCommand::Dump { output } => {
+            let dump = dump::dump(&pool).await?;
+            match output {
+                Some(path) => {
+                    let file = std::fs::File::create(&path).with_context(|| format!("Can't write {}", path.display()))?;
+                    let mut writer = BufWriter::new(file);
+                    serde_json::to_writer(&mut writer, &dump)?;
+                    writer.flush()?;
+                }
+                None => {
+                    let mut stdout = std::io::stdout().lock();
+                    serde_json::to_writer(&mut stdout, &dump)?;
+                    writeln!(stdout)?;
+                }
+            }
+            Ok(())
+        }
+        DatabaseCommand::Restore { file, replace } => {
+            let reader = std::fs::File::open(&file).with_context(|| format!("Can't read {}", file.display()))?;
+            let dump: dump::Dump = serde_json::from_reader(BufReader::new(reader))
+                .with_context(|| format!("{} is not a dump", file.display()))?;
+            dump::restore(&pool, &dump, replace).await?;
+            let rows: usize = dump.tables.values().map(Vec::len).sum();
+            println!("Restored {} rows from {}", rows, dump.created_at.format("%Y-%m-%d %H:%M UTC"));
+            Ok(())
+        }
+    }
+}
+
+async fn migrate(pool: &Pool, command: MigrateCommand) -> Result<()> {
+    let applied = pool.applied_migrations().await?;
+    match command {
+        MigrateCommand::Run => {
+            pool.migrate().await?;
+            let now = pool.applied_migrations().await?;
+            match now.len() - applied.len() {
+                0 => println!("Already up to date"),
+                count => println!("Applied {} migrations, now at version {}", count, now.last().unwrap_or(&0)),
+            }
+        }
+        MigrateCommand::Revert { to } => {
+            let Some(&latest) = applied.last() else {
+                bail!("No migrations have been applied");
+            };
+            // Back to the one before the latest, by default
+            let target = to.unwrap_or_else(|| applied.iter().rev().nth(1).copied().unwrap_or(0));
+            if target >= latest {
+                bail!("The database is at version {}; nothing after {} to revert", latest, target);
+            }
+            pool.revert(target).await?;
+            println!("Reverted to version {}", target);
+        }
+        MigrateCommand::Status => {
+            for (version, description) in pool.known_migrations() {
+                let state = if applied.contains(&version) { "applied" } else { "pending" };
+                println!("{:>4}  {:<8}  {}", version, state, description);
+            }
+        }
+    }
+    Ok(())
+}
+
+// From standard input, or generated; the bool says which
+fn new_password(password_stdin: bool) -> Result<(String, bool)> {
+    if !password_stdin {
+        let password = OsRng.sample_iter(&Alphanumeric).take(PASSWORD_LENGTH).map(char::from).collect();
+        return Ok((password, true));
+    }
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    let password = line.trim_end_matches(['\r', '\n']).to_string();
+    if password.is_empty() {
+        bail!("The password is empty");
+    }
+    Ok((password, false))
+}
+
+// Generated passwords are shown once, and have to be changed on the next login
+async fn hand_out(pool: &Pool, id: &str, password: &str) -> Result<()> {
+    admin::require_new_password(pool, id).await?;
+    println!("Password: {}", password);
+    println!("It has to be changed on the first login");
+    Ok(())
+}
+
+// The ID of the user with this ID, username or email. One user's username can
+// be another's email, and then neither is changed
+async fn find_user(pool: &Pool, user: &str) -> Result<String> {
+    let mut ids: Vec<String> = db::query_scalar("SELECT id FROM users WHERE id = $1 OR username = $1 OR email = $1")
+        .bind(user)
+        .fetch_all(pool)
+        .await?;
+    match ids.len() {
+        0 => bail!("No user {}", user),
+        1 => Ok(ids.remove(0)),
+        _ => bail!("{} names more than one user: {}", user, ids.join(", ")),
+    }
+}
+
+fn repository_error(error: RepositoryError) -> anyhow::Error {
+    match error {
+        RepositoryError::Conflict => anyhow::anyhow!("The username or email is taken"),
+        RepositoryError::NotFound => anyhow::anyhow!("No such user"),
+        RepositoryError::TooMany => anyhow::anyhow!("Too many rows"),
+        RepositoryError::Database(error) => error.into(),
+    }
+}
+
+async fn user(pool: &Pool, config: &Config, command: UserCommand) -> Result<()> {
+    match command {
+        UserCommand::Create { username, email, admin, password_stdin } => {
+            let (password, generated) = new_password(password_stdin)?;
+            let id = Uuid::new_v4().to_string();
+            let password_hash = auth::hash_password(&config.auth, &password)?;
+            let user = NewUser { id: id.clone(), username: username.clone(), email, password_hash };
+            SqlRepository::new(pool.clone()).create(user).await.map_err(repository_error)?;
+
+            if admin {
+                db::query("UPDATE users SET role = $1 WHERE id = $2").bind(auth::ROLE_ADMIN).bind(&id).execute(pool).await?;
+            }
+            println!("Created {}{} with ID {}", if admin { "admin " } else { "" }, username, id);
+            if generated {
+                hand_out(pool, &id, &password).await?;
+            }
+            Ok(())
+        }
+        UserCommand::Disable { user } => {
+            admin::set_account_disabled(pool, &find_user(pool, &user).await?, true).await?;
+            println!("Disabled {}", user);
+            Ok(())
+        }
+        UserCommand::Enable { user } => {
+            admin::set_account_disabled(pool, &find_user(pool, &user).await?, false).await?;
+            println!("Enabled {}", user);
+            Ok(())
+        }
+        UserCommand::ResetPassword { user, password_stdin } => {
+            let id = find_user(pool, &user).await?;
+            let (password, generated) = new_password(password_stdin)?;
+            let password_hash = auth::hash_password(&config.auth, &password)?;
+            // Also lifts a reset required earlier
+            SqlRepository::new(pool.clone()).set_password(&id, &password_hash).await.map_err(repository_error)?;
+            println!("Changed the password of {}", user);
+            if generated {
+                hand_out(pool, &id, &password).await?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,831 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use chrono_tz::Tz;
+use todo_api_types::{routes::DAV_ROOT, Todo};
+use uuid::Uuid;
+
+use crate::{
+    auth::hash_secret,
+    db,
+    history, ical,
+    repository::{todo_from_row, top_position},
+    AppState,
+};
+
+// URL layout, all relative to the user's principal:
+//   /dav/                        root, only used for principal discovery
+//   /dav/<user id>/              principal and calendar home
+//   /dav/<user id>/todos/        the calendar collection
+//   /dav/<user id>/todos/<id>.ics  one VTODO per todo
+const COLLECTION: &str = "todos";
+
+const DAV_NS: &str = "DAV:";
+const CALDAV_NS: &str = "urn:ietf:params:xml:ns:caldav";
+const CALENDARSERVER_NS: &str = "http://calendarserver.org/ns/";
+
+const SYNC_TOKEN_PREFIX: &str = "http://todo-app/ns/sync/";
+const ALLOWED_METHODS: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT";
+
+struct DavUser {
+    id: String,
+    username: String,
+    // For due dates uploaded without a time zone
+    timezone: Tz,
+}
+
+enum Target {
+    Root,
+    Principal,
+    Collection,
+    Item(String),
+}
+
+enum Node<'a> {
+    Root,
+    Principal,
+    Collection,
+    Item(&'a Item),
+}
+
+// A todo as a calendar resource. Those a client created are named and
+// identified as it chose; the others by the todo's id
+struct Item {
+    todo: Todo,
+    name: String,
+    uid: String,
+}
+
+fn item_from_row(row: &db::Row) -> Result<Item, sqlx::Error> {
+    let todo = todo_from_row(row)?;
+    let name = row.try_get::<Option<String>, _>("caldav_name")?.unwrap_or_else(|| todo.id.clone());
+    let uid = row.try_get::<Option<String>, _>("caldav_uid")?.unwrap_or_else(|| todo.id.clone());
+    Ok(Item { todo, name, uid })
+}
+
+#[derive(Clone, PartialEq)]
+struct PropName {
+    ns: String,
+    name: String,
+}
+
+impl PropName {
+    fn new(ns: &str, name: &str) -> Self {
+        PropName { ns: ns.to_string(), name: name.to_string() }
+    }
+
+    // Element for the multistatus body, which declares the d, c and cs prefixes
+    fn render(&self, inner: &str) -> String {
+        let (open, close) = match self.ns.as_str() {
+            DAV_NS => (format!("d:{}", self.name), format!("d:{}", self.name)),
+            CALDAV_NS => (format!("c:{}", self.name), format!("c:{}", self.name)),
+            CALENDARSERVER_NS => (format!("cs:{}", self.name), format!("cs:{}", self.name)),
+            ns => (format!("x:{} xmlns:x=\"{}\"", self.name, xml_escape(ns)), format!("x:{}", self.name)),
+        };
+        if inner.is_empty() {
+            format!("<{}/>", open)
+        } else {
+            format!("<{}>{}</{}>", open, inner, close)
+        }
+    }
+}
+
+// `/.well-known/caldav` (RFC 6764) points clients at the DAV root
+pub async fn well_known() -> Response {
+    (StatusCode::MOVED_PERMANENTLY, [(header::LOCATION, DAV_ROOT)]).into_response()
+}
+
+pub async fn handle(
+    State(state): State<AppState>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if method == Method::OPTIONS {
+        return Response::builder()
+            .header("DAV", "1, 3, calendar-access")
+            .header(header::ALLOW, ALLOWED_METHODS)
+            .body(Body::empty())
+            .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response());
+    }
+
+    let user = match authenticate(&state, &headers).await {
+        Ok(user) => user,
+        Err(StatusCode::UNAUTHORIZED) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Basic realm=\"todo-app\", charset=\"UTF-8\"")],
+            )
+                .into_response()
+        }
+        Err(status) => return status.into_response(),
+    };
+
+    let result = match parse_target(uri.path(), &user) {
+        Ok(target) => dispatch(&state, &user, &method, target, &headers, &body).await,
+        Err(status) => Err(status),
+    };
+    result.unwrap_or_else(IntoResponse::into_response)
+}
+
+async fn dispatch(
+    state: &AppState,
+    user: &DavUser,
+    method: &Method,
+    target: Target,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Response, StatusCode> {
+    match (method.as_str(), target) {
+        ("PROPFIND", target) => propfind(state, user, target, headers, body).await,
+        ("REPORT", Target::Collection) => report(state, user, body).await,
+        ("GET" | "HEAD", Target::Item(name)) => get_item(state, user, &name).await,
+        ("PUT", Target::Item(name)) => put_item(state, user, &name, headers, body).await,
+        ("DELETE", Target::Item(name)) => delete_item(state, user, &name, headers).await,
+        _ => Err(StatusCode::METHOD_NOT_ALLOWED),
+    }
+}
+
+// HTTP Basic with the username or email and one of the user's app passwords
+async fn authenticate(state: &AppState, headers: &HeaderMap) -> Result<DavUser, StatusCode> {
+    let credentials = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let (login, password) = credentials.split_once(':').ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let row = db::query(
+        "SELECT p.id AS app_password_id, u.id, u.username, u.timezone FROM app_passwords p JOIN users u ON u.id = p.user_id
+         WHERE (u.username = $1 OR u.email = $1) AND p.password_hash = $2 AND u.disabled_at IS NULL"
+    )
+    .bind(login)
+    .bind(hash_secret(password))
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    db::query("UPDATE app_passwords SET last_used_at = NOW() WHERE id = $1")
+        .bind(row.get::<String, _>("app_password_id"))
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(DavUser {
+        id: row.get("id"),
+        username: row.get("username"),
+        timezone: row.get::<String, _>("timezone").parse().unwrap_or(Tz::UTC),
+    })
+}
+
+fn parse_target(path: &str, user: &DavUser) -> Result<Target, StatusCode> {
+    let segments: Vec<&str> = path
+        .trim_start_matches("/dav")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    if segments.first().is_some_and(|owner| *owner != user.id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    match segments.as_slice() {
+        [] => Ok(Target::Root),
+        [_] => Ok(Target::Principal),
+        [_, COLLECTION] => Ok(Target::Collection),
+        [_, COLLECTION, file] => file
+            .strip_suffix(".ics")
+            .filter(|name| is_valid_resource_name(name))
+            .map(|name| Target::Item(name.to_string()))
+            .ok_or(StatusCode::NOT_FOUND),
+        _ => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+// Clients choose the names of new resources; keep them safe to echo back in hrefs
+fn is_valid_resource_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 200
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.@+=".contains(c))
+}
+
+fn principal_href(user: &DavUser) -> String {
+    format!("{}{}/", DAV_ROOT, user.id)
+}
+
+fn collection_href(user: &DavUser) -> String {
+    format!("{}{}/{}/", DAV_ROOT, user.id, COLLECTION)
+}
+
+fn item_href(user: &DavUser, item: &Item) -> String {
+    format!("{}{}.ics", collection_href(user), item.name)
+}
+
+// Postgres stores microseconds, so this changes whenever the todo is saved
+fn etag(todo: &Todo) -> String {
+    format!("\"{}\"", todo.updated_at.timestamp_micros())
+}
+
+// Matches `If-Match` / `If-None-Match` values: `*` or a list of ETags
+fn etag_matches(header_value: &str, current: Option<&str>) -> bool {
+    match current {
+        None => false,
+        Some(_) if header_value.trim() == "*" => true,
+        Some(current) => header_value
+            .split(',')
+            .any(|candidate| candidate.trim().trim_start_matches("W/") == current),
+    }
+}
+
+// The user's change sequence, which every change to their todos moves on (see
+// migration 015); tokens are its value at the time
+async fn sync_seq(state: &AppState, user: &DavUser) -> Result<i64, StatusCode> {
+    db::query_scalar("SELECT sync_seq FROM users WHERE id = $1")
+        .bind(&user.id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn sync_token(seq: i64) -> String {
+    format!("{}{}", SYNC_TOKEN_PREFIX, seq)
+}
+
+async fn active_items(state: &AppState, user: &DavUser) -> Result<Vec<Item>, StatusCode> {
+    let rows = db::query(
+        "SELECT * FROM todos WHERE user_id = $1 AND deleted_at IS NULL ORDER BY position COLLATE \"C\", created_at DESC"
+    )
+    .bind(&user.id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    rows.iter().map(item_from_row).collect::<Result<_, _>>().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Names are looked up among the user's own todos only
+const NAMED: &str = "user_id = $1 AND COALESCE(caldav_name, id) = $2";
+
+async fn find_item(state: &AppState, user: &DavUser, name: &str) -> Result<Option<Item>, StatusCode> {
+    let row = db::query(&format!("SELECT * FROM todos WHERE {} AND deleted_at IS NULL", NAMED))
+        .bind(&user.id)
+        .bind(name)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    row.as_ref().map(item_from_row).transpose().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Everything returned for `allprop` and by PROPFIND without a body
+fn all_props(node: &Node) -> Vec<PropName> {
+    let mut props = vec![
+        PropName::new(DAV_NS, "resourcetype"),
+        PropName::new(DAV_NS, "displayname"),
+        PropName::new(DAV_NS, "current-user-principal"),
+    ];
+    match node {
+        Node::Root => {}
+        Node::Principal => {
+            props.push(PropName::new(DAV_NS, "principal-URL"));
+            props.push(PropName::new(CALDAV_NS, "calendar-home-set"));
+        }
+        Node::Collection => {
+            props.push(PropName::new(DAV_NS, "owner"));
+            props.push(PropName::new(DAV_NS, "sync-token"));
+            props.push(PropName::new(DAV_NS, "supported-report-set"));
+            props.push(PropName::new(DAV_NS, "current-user-privilege-set"));
+            props.push(PropName::new(CALDAV_NS, "supported-calendar-component-set"));
+            props.push(PropName::new(CALENDARSERVER_NS, "getctag"));
+        }
+        Node::Item(_) => {
+            props.push(PropName::new(DAV_NS, "getetag"));
+            props.push(PropName::new(DAV_NS, "getcontenttype"));
+        }
+    }
+    props
+}
+
+// The value of a property as XML, or None if the node doesn't have it
+fn prop_value(node: &Node, prop: &PropName, user: &DavUser, sync_token: &str) -> Option<String> {
+    let href = |href: String| format!("<d:href>{}</d:href>", xml_escape(&href));
+
+    match (prop.ns.as_str(), prop.name.as_str(), node) {
+        (DAV_NS, "resourcetype", Node::Root) => Some("<d:collection/>".to_string()),
+        (DAV_NS, "resourcetype", Node::Principal) => Some("<d:collection/><d:principal/>".to_string()),
+        (DAV_NS, "resourcetype", Node::Collection) => Some("<d:collection/><c:calendar/>".to_string()),
+        (DAV_NS, "resourcetype", Node::Item(_)) => Some(String::new()),
+        (DAV_NS, "displayname", Node::Principal) => Some(xml_escape(&user.username)),
+        (DAV_NS, "displayname", Node::Collection) => Some("Todos".to_string()),
+        (DAV_NS, "displayname", Node::Item(item)) => Some(xml_escape(&item.todo.title)),
+        (DAV_NS, "current-user-principal", _) => Some(href(principal_href(user))),
+        (DAV_NS, "principal-URL", Node::Principal) | (DAV_NS, "owner", Node::Collection) => {
+            Some(href(principal_href(user)))
+        }
+        (CALDAV_NS, "calendar-home-set", Node::Root | Node::Principal) => Some(href(principal_href(user))),
+        (DAV_NS, "sync-token", Node::Collection) | (CALENDARSERVER_NS, "getctag", Node::Collection) => {
+            Some(xml_escape(sync_token))
+        }
+        (DAV_NS, "supported-report-set", Node::Collection) => Some(
+            ["<c:calendar-query/>", "<c:calendar-multiget/>", "<d:sync-collection/>"]
+                .iter()
+                .map(|report| format!("<d:supported-report><d:report>{}</d:report></d:supported-report>", report))
+                .collect(),
+        ),
+        (DAV_NS, "current-user-privilege-set", Node::Collection | Node::Item(_)) => Some(
+            ["<d:read/>", "<d:write/>", "<d:write-content/>", "<d:bind/>", "<d:unbind/>"]
+                .iter()
+                .map(|privilege| format!("<d:privilege>{}</d:privilege>", privilege))
+                .collect(),
+        ),
+        (CALDAV_NS, "supported-calendar-component-set", Node::Collection) => {
+            Some("<c:comp name=\"VTODO\"/>".to_string())
+        }
+        (DAV_NS, "getetag", Node::Item(item)) => Some(xml_escape(&etag(&item.todo))),
+        (DAV_NS, "getcontenttype", Node::Item(_)) => {
+            Some("text/calendar; charset=utf-8; component=VTODO".to_string())
+        }
+        (CALDAV_NS, "calendar-data", Node::Item(item)) => {
+            Some(xml_escape(&ical::todo_calendar(&item.todo, &item.uid, Utc::now())))
+        }
+        _ => None,
+    }
+}
+
+// One <d:response>, with found properties under 200 and missing ones under 404
+fn prop_response(href: &str, node: &Node, props: &[PropName], user: &DavUser, sync_token: &str) -> String {
+    let mut found = String::new();
+    let mut missing = String::new();
+    for prop in props {
+        match prop_value(node, prop, user, sync_token) {
+            Some(value) => found.push_str(&prop.render(&value)),
+            None => missing.push_str(&prop.render("")),
+        }
+    }
+
+    let mut response = format!("<d:response><d:href>{}</d:href>", xml_escape(href));
+    if !found.is_empty() {
+        response.push_str(&format!(
+            "<d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>",
+            found
+        ));
+    }
+    if !missing.is_empty() {
+        response.push_str(&format!(
+            "<d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>",
+            missing
+        ));
+    }
+    response.push_str("</d:response>");
+    response
+}
+
+fn multistatus(responses: &[String], sync_token: Option<&str>) -> Result<Response, StatusCode> {
+    let mut body = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:multistatus xmlns:d=\"{}\" xmlns:c=\"{}\" xmlns:cs=\"{}\">",
+        DAV_NS, CALDAV_NS, CALENDARSERVER_NS
+    );
+    for response in responses {
+        body.push_str(response);
+    }
+    if let Some(token) = sync_token {
+        body.push_str(&format!("<d:sync-token>{}</d:sync-token>", xml_escape(token)));
+    }
+    body.push_str("</d:multistatus>");
+
+    Response::builder()
+        .status(StatusCode::MULTI_STATUS)
+        .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+        .body(Body::from(body))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// The properties named in the request's <d:prop>, or None for allprop
+fn requested_props(document: &roxmltree::Document) -> Option<Vec<PropName>> {
+    let prop = document
+        .root_element()
+        .children()
+        .find(|node| node.has_tag_name((DAV_NS, "prop")))?;
+
+    Some(
+        prop.children()
+            .filter(|node| node.is_element())
+            .map(|node| PropName::new(node.tag_name().namespace().unwrap_or_default(), node.tag_name().name()))
+            .collect(),
+    )
+}
+
+fn parse_xml(body: &[u8]) -> Result<roxmltree::Document<'_>, StatusCode> {
+    let text = std::str::from_utf8(body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    roxmltree::Document::parse(text).map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+async fn propfind(
+    state: &AppState,
+    user: &DavUser,
+    target: Target,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Response, StatusCode> {
+    let requested = if body.iter().all(u8::is_ascii_whitespace) {
+        None
+    } else {
+        requested_props(&parse_xml(body)?)
+    };
+    // Infinite depth isn't supported; treat it like depth 1
+    let depth_zero = headers.get("Depth").and_then(|value| value.to_str().ok()) == Some("0");
+    let sync_token = sync_token(sync_seq(state, user).await?);
+
+    let respond = |href: &str, node: &Node| {
+        let props = requested.clone().unwrap_or_else(|| all_props(node));
+        prop_response(href, node, &props, user, &sync_token)
+    };
+
+    let mut responses = Vec::new();
+    match target {
+        Target::Root => {
+            responses.push(respond(DAV_ROOT, &Node::Root));
+            if !depth_zero {
+                responses.push(respond(&principal_href(user), &Node::Principal));
+            }
+        }
+        Target::Principal => {
+            responses.push(respond(&principal_href(user), &Node::Principal));
+            if !depth_zero {
+                responses.push(respond(&collection_href(user), &Node::Collection));
+            }
+        }
+        Target::Collection => {
+            responses.push(respond(&collection_href(user), &Node::Collection));
+            if !depth_zero {
+                for item in active_items(state, user).await? {
+                    responses.push(respond(&item_href(user, &item), &Node::Item(&item)));
+                }
+            }
+        }
+        Target::Item(name) => {
+            let item = find_item(state, user, &name).await?.ok_or(StatusCode::NOT_FOUND)?;
+            responses.push(respond(&item_href(user, &item), &Node::Item(&item)));
+        }
+    }
+
+    multistatus(&responses, None)
+}
+
+async fn report(state: &AppState, user: &DavUser, body: &[u8]) -> Result<Response, StatusCode> {
+    let document = parse_xml(body)?;
+    let root = document.root_element();
+    let props = requested_props(&document).unwrap_or_else(|| {
+        vec![PropName::new(DAV_NS, "getetag"), PropName::new(CALDAV_NS, "calendar-data")]
+    });
+    let seq = sync_seq(state, user).await?;
+    let sync_token = sync_token(seq);
+
+    match (root.tag_name().namespace(), root.tag_name().name()) {
+        (Some(CALDAV_NS), "calendar-query") => {
+            let items = active_items(state, user).await?;
+            let responses = items
+                .iter()
+                .filter(|item| matches_filter(&root, &item.todo))
+                .map(|item| prop_response(&item_href(user, item), &Node::Item(item), &props, user, &sync_token))
+                .collect::<Vec<_>>();
+            multistatus(&responses, None)
+        }
+        (Some(CALDAV_NS), "calendar-multiget") => {
+            let mut responses = Vec::new();
+            for href in root.descendants().filter(|node| node.has_tag_name((DAV_NS, "href"))) {
+                let href = href.text().unwrap_or_default().trim();
+                // Hrefs may be absolute URLs
+                let path = href.find(DAV_ROOT).map_or(href, |start| &href[start..]);
+                let item = match parse_target(path, user) {
+                    Ok(Target::Item(name)) => find_item(state, user, &name).await?,
+                    _ => None,
+                };
+                responses.push(match item {
+                    Some(item) => prop_response(href, &Node::Item(&item), &props, user, &sync_token),
+                    None => not_found_response(href),
+                });
+            }
+            multistatus(&responses, None)
+        }
+        (Some(DAV_NS), "sync-collection") => sync_collection(state, user, &root, &props, seq).await,
+        _ => Err(StatusCode::FORBIDDEN),
+    }
+}
+
+fn not_found_response(href: &str) -> String {
+    format!(
+        "<d:response><d:href>{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>",
+        xml_escape(href)
+    )
+}
+
+// Supports the filters clients actually send: a VTODO comp-filter (anything else
+// matches nothing) and `is-not-defined` on COMPLETED to fetch open todos only
+fn matches_filter(query: &roxmltree::Node, todo: &Todo) -> bool {
+    let Some(filter) = query.children().find(|node| node.has_tag_name((CALDAV_NS, "filter"))) else {
+        return true;
+    };
+
+    for comp_filter in filter.descendants().filter(|node| node.has_tag_name((CALDAV_NS, "comp-filter"))) {
+        let name = comp_filter.attribute("name").unwrap_or_default();
+        if name != "VCALENDAR" && name != "VTODO" {
+            return false;
+        }
+    }
+
+    for prop_filter in filter.descendants().filter(|node| node.has_tag_name((CALDAV_NS, "prop-filter"))) {
+        let is_not_defined = prop_filter
+            .children()
+            .any(|node| node.has_tag_name((CALDAV_NS, "is-not-defined")));
+        if prop_filter.attribute("name") == Some("COMPLETED") && is_not_defined && todo.completed {
+            return false;
+        }
+    }
+
+    true
+}
+
+// RFC 6578: everything changed since the client's token. Todos moved to the
+// trash or deleted are reported as 404s, the deleted ones by their tombstones.
+// Tokens this database didn't give out, such as the timestamps tokens used to
+// be, are refused, so the client starts again from an initial sync
+async fn sync_collection(
+    state: &AppState,
+    user: &DavUser,
+    request: &roxmltree::Node<'_, '_>,
+    props: &[PropName],
+    seq: i64,
+) -> Result<Response, StatusCode> {
+    let client_token = request
+        .children()
+        .find(|node| node.has_tag_name((DAV_NS, "sync-token")))
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .unwrap_or_default();
+
+    let since = if client_token.is_empty() {
+        None
+    } else {
+        let since = client_token
+            .strip_prefix(SYNC_TOKEN_PREFIX)
+            .and_then(|since| since.parse::<i64>().ok())
+            .filter(|since| (0..=seq).contains(since));
+        match since {
+            Some(since) => Some(since),
+            None => return invalid_sync_token(),
+        }
+    };
+
+    let (rows, deleted) = match since {
+        // Initial sync: only what currently exists
+        None => {
+            let rows = db::query("SELECT * FROM todos WHERE user_id = $1 AND deleted_at IS NULL")
+                .bind(&user.id)
+                .fetch_all(&state.db)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            (rows, Vec::new())
+        }
+        Some(since) => {
+            // Only what committed by `seq`, so nothing later is skipped by the next sync
+            let rows = db::query("SELECT * FROM todos WHERE user_id = $1 AND sync_seq > $2 AND sync_seq <= $3")
+                .bind(&user.id)
+                .bind(since)
+                .bind(seq)
+                .fetch_all(&state.db)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let deleted: Vec<String> = db::query_scalar(
+                "SELECT name FROM todo_tombstones WHERE user_id = $1 AND sync_seq > $2 AND sync_seq <= $3",
+            )
+            .bind(&user.id)
+            .bind(since)
+            .bind(seq)
+            .fetch_all(&state.db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            (rows, deleted)
+        }
+    };
+
+    let sync_token = sync_token(seq);
+    let items = rows
+        .iter()
+        .map(item_from_row)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut responses = items
+        .iter()
+        .map(|item| {
+            let href = item_href(user, item);
+            if item.todo.deleted_at.is_some() {
+                not_found_response(&href)
+            } else {
+                prop_response(&href, &Node::Item(item), props, user, &sync_token)
+            }
+        })
+        .collect::<Vec<_>>();
+    responses.extend(deleted.iter().map(|name| not_found_response(&format!("{}{}.ics", collection_href(user), name))));
+
+    multistatus(&responses, Some(&sync_token))
+}
+
+fn invalid_sync_token() -> Result<Response, StatusCode> {
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+        .body(Body::from(format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:error xmlns:d=\"{}\"><d:valid-sync-token/></d:error>",
+            DAV_NS
+        )))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn get_item(state: &AppState, user: &DavUser, name: &str) -> Result<Response, StatusCode> {
+    let item = find_item(state, user, name).await?.ok_or(StatusCode::NOT_FOUND)?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, ical::CONTENT_TYPE)
+        .header(header::ETAG, etag(&item.todo))
+        .body(Body::from(ical::todo_calendar(&item.todo, &item.uid, Utc::now())))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Checks `If-Match` and `If-None-Match` against the current ETag, if any
+fn check_preconditions(headers: &HeaderMap, current: Option<&str>) -> Result<(), StatusCode> {
+    let header_str = |name| headers.get(name).and_then(|value: &HeaderValue| value.to_str().ok());
+
+    if let Some(if_match) = header_str(header::IF_MATCH) {
+        if !etag_matches(if_match, current) {
+            return Err(StatusCode::PRECONDITION_FAILED);
+        }
+    }
+    if let Some(if_none_match) = header_str(header::IF_NONE_MATCH) {
+        if etag_matches(if_none_match, current) {
+            return Err(StatusCode::PRECONDITION_FAILED);
+        }
+    }
+    Ok(())
+}
+
+async fn put_item(
+    state: &AppState,
+    user: &DavUser,
+    name: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Response, StatusCode> {
+    let content = std::str::from_utf8(body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    // CalDAV precondition supported-calendar-component: only VTODOs are stored
+    let parsed = ical::parse_vtodo(content, user.timezone).ok_or(StatusCode::FORBIDDEN)?;
+    let now = Utc::now();
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Todos in the trash keep their names, so those can't be taken until the
+    // todo is restored or purged
+    let current = db::query(&format!("SELECT * FROM todos WHERE {} FOR UPDATE", NAMED))
+        .bind(&user.id)
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(|row| todo_from_row(&row))
+        .transpose()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if current.as_ref().is_some_and(|current| current.deleted_at.is_some()) {
+        return Err(StatusCode::CONFLICT);
+    }
+    check_preconditions(headers, current.as_ref().map(etag).as_deref())?;
+
+    let (todo, status) = match current {
+        Some(current) => {
+            let row = db::query(
+                "UPDATE todos SET title = $1, description = $2, completed = $3, due_at = $4, priority = $5, updated_at = $6 WHERE id = $7 AND user_id = $8 RETURNING *"
+            )
+            .bind(&parsed.title)
+            .bind(parsed.description.as_deref())
+            .bind(parsed.completed)
+            .bind(parsed.due_at)
+            .bind(parsed.priority)
+            .bind(now)
+            .bind(&current.id)
+            .bind(&user.id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let updated = todo_from_row(&row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            let changes = history::diff_todos(&current, &updated);
+            if history::has_changes(&changes) {
+                let event_type = history::update_event_type(&current, &updated);
+                history::record_event(&mut tx, &updated, &user.id, event_type, changes, now)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            }
+
+            (updated, StatusCode::NO_CONTENT)
+        }
+        None => {
+            let position = top_position(&mut tx, &user.id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            // The todo gets an id of its own, like any other; the name and UID
+            // the client gave are kept to serve it back under
+            let row = db::query(
+                "INSERT INTO todos (id, user_id, title, description, completed, position, due_at, priority, created_at, updated_at, caldav_name, caldav_uid) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) RETURNING *"
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(&user.id)
+            .bind(&parsed.title)
+            .bind(parsed.description.as_deref())
+            .bind(parsed.completed)
+            .bind(&position)
+            .bind(parsed.due_at)
+            .bind(parsed.priority)
+            .bind(now)
+            .bind(now)
+            .bind(name)
+            .bind(parsed.uid.as_deref())
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let created = todo_from_row(&row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            let changes = history::initial_values(&created);
+            history::record_event(&mut tx, &created, &user.id, history::EVENT_CREATED, changes, now)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            (created, StatusCode::CREATED)
+        }
+    };
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.todo_changes.notify(&user.id);
+
+    Response::builder()
+        .status(status)
+        .header(header::ETAG, etag(&todo))
+        .body(Body::empty())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Moves the todo to the trash, like deleting it in the app
+async fn delete_item(
+    state: &AppState,
+    user: &DavUser,
+    name: &str,
+    headers: &HeaderMap,
+) -> Result<Response, StatusCode> {
+    let now = Utc::now();
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let current = db::query(&format!("SELECT * FROM todos WHERE {} AND deleted_at IS NULL FOR UPDATE", NAMED))
+        .bind(&user.id)
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(|row| todo_from_row(&row))
+        .transpose()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    check_preconditions(headers, Some(&etag(&current)))?;
+
+    let row = db::query("UPDATE todos SET deleted_at = $1 WHERE id = $2 AND user_id = $3 RETURNING *")
+        .bind(now)
+        .bind(&current.id)
+        .bind(&user.id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let deleted = todo_from_row(&row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    history::record_event(&mut tx, &deleted, &user.id, history::EVENT_DELETED, serde_json::json!({}), now)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.todo_changes.notify(&user.id);
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
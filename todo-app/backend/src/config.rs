@@ -0,0 +1,451 @@
+use anyhow::{bail, Context};
+use axum::http::HeaderValue;
+use serde::Deserialize;
+use std::env;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::db::Backend;
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+// bcrypt rejects costs outside this range
+const BCRYPT_COSTS: std::ops::RangeInclusive<u32> = 4..=31;
+// Anything shorter is easy to brute-force from a captured token
+const MIN_JWT_SECRET_LEN: usize = 32;
+
+// Backend settings, read once at startup from a TOML file and then from
+// environment variables, which take precedence
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub accounts: AccountsConfig,
+    pub jobs: JobsConfig,
+    pub mail: MailConfig,
+    pub push: PushConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen: SocketAddr,
+    // Base URL of this server, for links in emails
+    pub public_url: String,
+    // Origins the frontend is served from; "*" allows any
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listen: SocketAddr::from(([127, 0, 0, 1], 3001)),
+            public_url: "http://127.0.0.1:3001".to_string(),
+            allowed_origins: vec!["http://localhost:8000".to_string(), "http://127.0.0.1:8000".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    // How long a request waits for a free connection before failing
+    pub acquire_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig { url: String::new(), max_connections: 10, min_connections: 0, acquire_timeout_secs: 30 }
+    }
+}
+
+impl DatabaseConfig {
+    pub fn acquire_timeout(&self) -> Duration {
+        Duration::from_secs(self.acquire_timeout_secs)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    // Signs login tokens and the links in emails
+    pub jwt_secret: String,
+    pub token_lifetime_hours: i64,
+    // How long the link sent to a new email address stays valid
+    pub email_verification_hours: i64,
+    pub bcrypt_cost: u32,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            jwt_secret: String::new(),
+            token_lifetime_hours: 24,
+            email_verification_hours: 24,
+            bcrypt_cost: bcrypt::DEFAULT_COST,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountsConfig {
+    // Promoted to admin at startup, to bootstrap the first admins
+    pub admin_usernames: Vec<String>,
+    // Days between asking for account deletion and the data being purged
+    pub deletion_grace_days: i64,
+}
+
+impl Default for AccountsConfig {
+    fn default() -> Self {
+        AccountsConfig { admin_usernames: Vec::new(), deletion_grace_days: 30 }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobsConfig {
+    pub workers: usize,
+    pub trash_retention_days: i64,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        JobsConfig { workers: 4, trash_retention_days: 30 }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MailConfig {
+    // Emails are only logged without one
+    pub smtp_url: Option<String>,
+    pub from: String,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        MailConfig { smtp_url: None, from: "Todo App <noreply@localhost>".to_string() }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PushConfig {
+    // Base64url-encoded private key, as printed by `npx web-push
+    // generate-vapid-keys`; push is disabled without it
+    pub vapid_private_key: Option<String>,
+    // Contact for push services, a mailto: or https: URL
+    pub vapid_subject: String,
+}
+
+impl Default for PushConfig {
+    fn default() -> Self {
+        PushConfig { vapid_private_key: None, vapid_subject: "mailto:admin@localhost".to_string() }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("expected text or json".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub format: LogFormat,
+}
+
+impl Config {
+    // Reads `CONFIG_FILE`, or config.toml if it exists, then applies the
+    // environment. Every problem is reported at once rather than one per start
+    pub fn load() -> anyhow::Result<Config> {
+        let mut config = match env::var("CONFIG_FILE") {
+            Ok(path) => Config::from_file(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => Config::from_file(Path::new(DEFAULT_CONFIG_FILE))?,
+            Err(_) => Config::default(),
+        };
+
+        let mut errors = Vec::new();
+        config.apply_env(&mut errors);
+        config.validate(&mut errors);
+
+        if !errors.is_empty() {
+            bail!("invalid configuration:\n  - {}", errors.join("\n  - "));
+        }
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> anyhow::Result<Config> {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("can't read config file {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("invalid config file {}", path.display()))
+    }
+
+    fn apply_env(&mut self, errors: &mut Vec<String>) {
+        override_from_env("LISTEN_ADDR", &mut self.server.listen, errors);
+        override_from_env("PUBLIC_URL", &mut self.server.public_url, errors);
+        override_list_from_env("ALLOWED_ORIGINS", &mut self.server.allowed_origins);
+
+        override_from_env("DATABASE_URL", &mut self.database.url, errors);
+        override_from_env("DATABASE_MAX_CONNECTIONS", &mut self.database.max_connections, errors);
+        override_from_env("DATABASE_MIN_CONNECTIONS", &mut self.database.min_connections, errors);
+        override_from_env("DATABASE_ACQUIRE_TIMEOUT_SECS", &mut self.database.acquire_timeout_secs, errors);
+
+        override_from_env("JWT_SECRET", &mut self.auth.jwt_secret, errors);
+        override_from_env("TOKEN_LIFETIME_HOURS", &mut self.auth.token_lifetime_hours, errors);
+        override_from_env("EMAIL_VERIFICATION_HOURS", &mut self.auth.email_verification_hours, errors);
+        override_from_env("BCRYPT_COST", &mut self.auth.bcrypt_cost, errors);
+
+        override_list_from_env("ADMIN_USERNAMES", &mut self.accounts.admin_usernames);
+        override_from_env("ACCOUNT_DELETION_GRACE_DAYS", &mut self.accounts.deletion_grace_days, errors);
+
+        override_from_env("JOB_WORKERS", &mut self.jobs.workers, errors);
+        override_from_env("TRASH_RETENTION_DAYS", &mut self.jobs.trash_retention_days, errors);
+
+        if let Ok(smtp_url) = env::var("SMTP_URL") {
+            self.mail.smtp_url = Some(smtp_url);
+        }
+        override_from_env("MAIL_FROM", &mut self.mail.from, errors);
+
+        if let Ok(private_key) = env::var("VAPID_PRIVATE_KEY") {
+            self.push.vapid_private_key = Some(private_key);
+        }
+        override_from_env("VAPID_SUBJECT", &mut self.push.vapid_subject, errors);
+
+        override_from_env("LOG_FORMAT", &mut self.log.format, errors);
+    }
+
+    fn validate(&mut self, errors: &mut Vec<String>) {
+        self.server.public_url = self.server.public_url.trim_end_matches('/').to_string();
+        if reqwest::Url::parse(&self.server.public_url).is_err() {
+            errors.push(format!("server.public_url (PUBLIC_URL) is not a URL: {}", self.server.public_url));
+        }
+        for origin in &self.server.allowed_origins {
+            if origin != "*" && (reqwest::Url::parse(origin).is_err() || HeaderValue::from_str(origin).is_err()) {
+                errors.push(format!("server.allowed_origins (ALLOWED_ORIGINS) has an invalid origin: {}", origin));
+            }
+        }
+
+        if self.database.url.is_empty() {
+            errors.push("database.url (DATABASE_URL) must be set".to_string());
+        } else if Backend::from_url(&self.database.url).is_none() {
+            errors.push("database.url (DATABASE_URL) must start with postgres:// or sqlite://".to_string());
+        }
+        if self.database.max_connections == 0 {
+            errors.push("database.max_connections (DATABASE_MAX_CONNECTIONS) must be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            errors.push(format!(
+                "database.min_connections ({}) is larger than database.max_connections ({})",
+                self.database.min_connections, self.database.max_connections
+            ));
+        }
+
+        if self.auth.jwt_secret.len() < MIN_JWT_SECRET_LEN {
+            errors.push(format!(
+                "auth.jwt_secret (JWT_SECRET) must be set and at least {} characters long",
+                MIN_JWT_SECRET_LEN
+            ));
+        }
+        if !BCRYPT_COSTS.contains(&self.auth.bcrypt_cost) {
+            errors.push(format!(
+                "auth.bcrypt_cost (BCRYPT_COST) must be between {} and {}, not {}",
+                BCRYPT_COSTS.start(),
+                BCRYPT_COSTS.end(),
+                self.auth.bcrypt_cost
+            ));
+        }
+        for (name, value) in [
+            ("auth.token_lifetime_hours (TOKEN_LIFETIME_HOURS)", self.auth.token_lifetime_hours),
+            ("auth.email_verification_hours (EMAIL_VERIFICATION_HOURS)", self.auth.email_verification_hours),
+            ("accounts.deletion_grace_days (ACCOUNT_DELETION_GRACE_DAYS)", self.accounts.deletion_grace_days),
+            ("jobs.trash_retention_days (TRASH_RETENTION_DAYS)", self.jobs.trash_retention_days),
+        ] {
+            if value < 1 {
+                errors.push(format!("{} must be at least 1, not {}", name, value));
+            }
+        }
+        if self.jobs.workers == 0 {
+            errors.push("jobs.workers (JOB_WORKERS) must be at least 1".to_string());
+        }
+    }
+}
+
+// Replaces `target` with the parsed variable, if it is set
+fn override_from_env<T>(name: &str, target: &mut T, errors: &mut Vec<String>)
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let Ok(value) = env::var(name) else {
+        return;
+    };
+    match value.trim().parse() {
+        Ok(parsed) => *target = parsed,
+        Err(err) => errors.push(format!("{} is invalid ({}): {}", name, err, value)),
+    }
+}
+
+// A comma-separated list; blank entries are ignored
+fn override_list_from_env(name: &str, target: &mut Vec<String>) {
+    if let Ok(value) = env::var(name) {
+        *target = value
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(contents: &str) -> std::path::PathBuf {
+        let path = env::temp_dir().join(format!("todo-config-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn valid() -> Config {
+        let mut config = Config::default();
+        config.database.url = "sqlite::memory:".to_string();
+        config.auth.jwt_secret = "x".repeat(MIN_JWT_SECRET_LEN);
+        config
+    }
+
+    #[test]
+    fn files_fill_in_what_they_leave_out_with_defaults() {
+        let path = write_config(
+            r#"
+            [server]
+            listen = "0.0.0.0:8080"
+
+            [database]
+            url = "postgres://localhost/todos"
+            acquire_timeout_secs = 5
+
+            [log]
+            format = "json"
+            "#,
+        );
+        let config = Config::from_file(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(config.server.listen, SocketAddr::from(([0, 0, 0, 0], 8080)));
+        assert_eq!(config.server.public_url, "http://127.0.0.1:3001");
+        assert_eq!(config.database.url, "postgres://localhost/todos");
+        assert_eq!(config.database.max_connections, 10);
+        assert_eq!(config.database.acquire_timeout(), Duration::from_secs(5));
+        assert_eq!(config.auth.token_lifetime_hours, 24);
+        assert_eq!(config.jobs.workers, 4);
+        assert_eq!(config.log.format, LogFormat::Json);
+    }
+
+    #[test]
+    fn files_with_unknown_settings_are_rejected() {
+        let path = write_config("[database]\nurl = \"sqlite::memory:\"\nmax_conections = 5\n");
+        let err = Config::from_file(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(format!("{:#}", err).contains("max_conections"), "{:#}", err);
+
+        let missing = env::temp_dir().join(format!("todo-config-{}.toml", uuid::Uuid::new_v4()));
+        assert!(Config::from_file(&missing).unwrap_err().to_string().starts_with("can't read config file"));
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        let mut errors = Vec::new();
+        valid().validate(&mut errors);
+        assert_eq!(errors, Vec::<String>::new());
+
+        let mut config = Config::default();
+        config.server.public_url = "not a url".to_string();
+        config.server.allowed_origins = vec!["*".to_string(), "nope".to_string()];
+        config.database.min_connections = 20;
+        config.auth.bcrypt_cost = 3;
+        config.jobs.trash_retention_days = 0;
+        config.jobs.workers = 0;
+        config.validate(&mut errors);
+        assert_eq!(
+            errors,
+            [
+                "server.public_url (PUBLIC_URL) is not a URL: not a url",
+                "server.allowed_origins (ALLOWED_ORIGINS) has an invalid origin: nope",
+                "database.url (DATABASE_URL) must be set",
+                "database.min_connections (20) is larger than database.max_connections (10)",
+                "auth.jwt_secret (JWT_SECRET) must be set and at least 32 characters long",
+                "auth.bcrypt_cost (BCRYPT_COST) must be between 4 and 31, not 3",
+                "jobs.trash_retention_days (TRASH_RETENTION_DAYS) must be at least 1, not 0",
+                "jobs.workers (JOB_WORKERS) must be at least 1",
+            ]
+        );
+
+        let mut config = valid();
+        config.database.url = "mysql://localhost/todos".to_string();
+        config.server.public_url = "https://todo.example.com/".to_string();
+        let mut errors = Vec::new();
+        config.validate(&mut errors);
+        assert_eq!(errors, ["database.url (DATABASE_URL) must start with postgres:// or sqlite://"]);
+        assert_eq!(config.server.public_url, "https://todo.example.com");
+    }
+
+    // The only test that sets these variables, as the environment is shared
+    // by every test in the process
+    #[test]
+    fn the_environment_takes_precedence_over_the_file() {
+        let variables = [
+            ("ALLOWED_ORIGINS", " https://a.example.com, ,https://b.example.com "),
+            ("DATABASE_MAX_CONNECTIONS", " 3 "),
+            ("BCRYPT_COST", "high"),
+            ("LOG_FORMAT", "xml"),
+            ("SMTP_URL", "smtp://localhost:25"),
+        ];
+        for (name, value) in variables {
+            env::set_var(name, value);
+        }
+        let mut config = valid();
+        let mut errors = Vec::new();
+        config.apply_env(&mut errors);
+        for (name, _) in variables {
+            env::remove_var(name);
+        }
+
+        assert_eq!(config.server.allowed_origins, ["https://a.example.com", "https://b.example.com"]);
+        assert_eq!(config.database.max_connections, 3);
+        assert_eq!(config.mail.smtp_url.as_deref(), Some("smtp://localhost:25"));
+        assert_eq!(config.auth.bcrypt_cost, bcrypt::DEFAULT_COST);
+        assert_eq!(config.log.format, LogFormat::Text);
+        assert_eq!(
+            errors,
+            [
+                "BCRYPT_COST is invalid (invalid digit found in string): high",
+                "LOG_FORMAT is invalid (expected text or json): xml",
+            ]
+        );
+    }
+}
@@ -0,0 +1,720 @@
+// Storage backends. The same queries run on Postgres and SQLite: they are
+// written for Postgres, and the few constructs SQLite lacks are rewritten by
+// `sqlite_sql` before they reach it. Each backend has its own migration set,
+// since the schemas differ in types and defaults.
+//
+// On SQLite, timestamps are stored as RFC 3339 text in UTC, which sorts in
+// time order, lists such as webhook events as JSON arrays, and the "C"
+// collation used to order positions is registered on every connection.
+
+use chrono::{DateTime, Utc};
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use sqlx::{
+    migrate::{Migrate, MigrateError, Migrator},
+    pool::PoolConnection,
+    postgres::{PgArguments, PgConnectOptions, PgConnection, PgPool, PgPoolOptions, PgRow},
+    query::Query as SqlxQuery,
+    sqlite::{
+        SqliteArguments, SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePool, SqlitePoolOptions,
+        SqliteRow,
+    },
+    ColumnIndex, Decode, Postgres, Row as _, Sqlite, Type,
+};
+use std::borrow::Cow;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+
+use crate::config::DatabaseConfig;
+
+// Each migration has a down script next to it, so `revert` can undo it
+static POSTGRES_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/postgres");
+static SQLITE_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Postgres,
+    Sqlite,
+}
+
+impl Backend {
+    // Picked by the scheme of `DATABASE_URL`
+    pub fn from_url(url: &str) -> Option<Backend> {
+        let (scheme, _) = url.split_once(':')?;
+        match scheme {
+            "postgres" | "postgresql" => Some(Backend::Postgres),
+            "sqlite" => Some(Backend::Sqlite),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Pool {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
+
+impl Pool {
+    pub async fn connect(config: &DatabaseConfig) -> anyhow::Result<Pool> {
+        match Backend::from_url(&config.url) {
+            Some(Backend::Postgres) => {
+                let pool = PgPoolOptions::new()
+                    .max_connections(config.max_connections)
+                    .min_connections(config.min_connections)
+                    .acquire_timeout(config.acquire_timeout())
+                    .connect_with(PgConnectOptions::from_str(&config.url)?)
+                    .await?;
+                Ok(Pool::Postgres(pool))
+            }
+            Some(Backend::Sqlite) => {
+                let options = SqliteConnectOptions::from_str(&config.url)?
+                    .create_if_missing(true)
+                    .foreign_keys(true)
+                    .journal_mode(SqliteJournalMode::Wal)
+                    .collation("C", |a: &str, b: &str| a.cmp(b));
+                let pool = SqlitePoolOptions::new()
+                    .max_connections(config.max_connections)
+                    .min_connections(config.min_connections)
+                    .acquire_timeout(config.acquire_timeout())
+                    .connect_with(options)
+                    .await?;
+                Ok(Pool::Sqlite(pool))
+            }
+            None => anyhow::bail!("database.url must start with postgres:// or sqlite://"),
+        }
+    }
+
+    fn migrator(&self) -> &'static Migrator {
+        match self {
+            Pool::Postgres(_) => &POSTGRES_MIGRATIONS,
+            Pool::Sqlite(_) => &SQLITE_MIGRATIONS,
+        }
+    }
+
+    pub async fn migrate(&self) -> Result<(), MigrateError> {
+        match self {
+            Pool::Postgres(pool) => self.migrator().run(pool).await,
+            Pool::Sqlite(pool) => self.migrator().run(pool).await,
+        }
+    }
+
+    // Runs the down scripts of the applied migrations newer than `target`,
+    // newest first; 0 reverts them all
+    pub async fn revert(&self, target: i64) -> Result<(), MigrateError> {
+        match self {
+            Pool::Postgres(pool) => self.migrator().undo(pool, target).await,
+            Pool::Sqlite(pool) => self.migrator().undo(pool, target).await,
+        }
+    }
+
+    // Versions of the applied migrations, oldest first
+    pub async fn applied_migrations(&self) -> Result<Vec<i64>, MigrateError> {
+        let applied = match self {
+            Pool::Postgres(pool) => {
+                let mut connection = pool.acquire().await?;
+                connection.ensure_migrations_table().await?;
+                connection.list_applied_migrations().await?
+            }
+            Pool::Sqlite(pool) => {
+                let mut connection = pool.acquire().await?;
+                connection.ensure_migrations_table().await?;
+                connection.list_applied_migrations().await?
+            }
+        };
+        let mut versions: Vec<i64> = applied.into_iter().map(|migration| migration.version).collect();
+        versions.sort_unstable();
+        Ok(versions)
+    }
+
+    // Every migration this build has, as (version, description), oldest first
+    pub fn known_migrations(&self) -> Vec<(i64, String)> {
+        self.migrator()
+            .iter()
+            .filter(|migration| migration.migration_type.is_up_migration())
+            .map(|migration| (migration.version, migration.description.to_string()))
+            .collect()
+    }
+
+    // Open connections, and how many of them are idle
+    pub fn connections(&self) -> (u32, usize) {
+        match self {
+            Pool::Postgres(pool) => (pool.size(), pool.num_idle()),
+            Pool::Sqlite(pool) => (pool.size(), pool.num_idle()),
+        }
+    }
+
+    pub async fn begin(&self) -> Result<Transaction<'static>, sqlx::Error> {
+        let connection = match self {
+            Pool::Postgres(pool) => Connection::Postgres(Box::new(pool.begin().await?)),
+            Pool::Sqlite(pool) => Connection::Sqlite(SqliteTransaction::begin(pool).await?),
+        };
+        Ok(Transaction { connection })
+    }
+}
+
+// Dereferences to its connection, so `&mut *tx` runs a query inside it, as
+// with sqlx transactions
+pub struct Transaction<'c> {
+    connection: Connection<'c>,
+}
+
+pub enum Connection<'c> {
+    // Boxed because a Postgres connection is several times the size of an SQLite one
+    Postgres(Box<sqlx::Transaction<'c, Postgres>>),
+    Sqlite(SqliteTransaction),
+}
+
+// sqlx always starts SQLite transactions with a plain BEGIN, which takes the
+// write lock only at the first write. A transaction that has read by then can't
+// wait for the lock, and fails with "database is locked" instead, so these
+// take it at the start with BEGIN IMMEDIATE, waiting like for any other lock
+pub struct SqliteTransaction {
+    // Taken once the transaction is committed or rolled back
+    connection: Option<PoolConnection<Sqlite>>,
+}
+
+impl SqliteTransaction {
+    async fn begin(pool: &SqlitePool) -> Result<SqliteTransaction, sqlx::Error> {
+        let mut connection = pool.acquire().await?;
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut *connection).await?;
+        Ok(SqliteTransaction { connection: Some(connection) })
+    }
+
+    fn connection(&mut self) -> &mut SqliteConnection {
+        self.connection.as_mut().expect("the transaction is still open")
+    }
+
+    async fn commit(mut self) -> Result<(), sqlx::Error> {
+        // Left in place when COMMIT fails, so dropping rolls back
+        sqlx::query("COMMIT").execute(self.connection()).await?;
+        self.connection = None;
+        Ok(())
+    }
+}
+
+// Rolls back a transaction that wasn't committed before the connection goes
+// back to the pool. Dropping can't wait for that, so it runs in a task which
+// holds the connection until it is done
+impl Drop for SqliteTransaction {
+    fn drop(&mut self) {
+        let Some(mut connection) = self.connection.take() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            // Closing the connection rolls back too
+            drop(connection.detach());
+            return;
+        };
+        runtime.spawn(async move {
+            if let Err(err) = sqlx::query("ROLLBACK").execute(&mut *connection).await {
+                tracing::warn!("Failed to roll back an SQLite transaction, closing its connection: {}", err);
+                drop(connection.detach());
+            }
+        });
+    }
+}
+
+impl Transaction<'_> {
+    pub fn backend(&self) -> Backend {
+        match &self.connection {
+            Connection::Postgres(_) => Backend::Postgres,
+            Connection::Sqlite(_) => Backend::Sqlite,
+        }
+    }
+
+    pub async fn commit(self) -> Result<(), sqlx::Error> {
+        match self.connection {
+            Connection::Postgres(tx) => tx.commit().await,
+            Connection::Sqlite(tx) => tx.commit().await,
+        }
+    }
+}
+
+impl<'c> Deref for Transaction<'c> {
+    type Target = Connection<'c>;
+
+    fn deref(&self) -> &Connection<'c> {
+        &self.connection
+    }
+}
+
+impl DerefMut for Transaction<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.connection
+    }
+}
+
+// Where a query runs: a pool, or the connection of an open transaction
+pub enum Target<'c> {
+    PostgresPool(&'c PgPool),
+    PostgresConnection(&'c mut PgConnection),
+    SqlitePool(&'c SqlitePool),
+    SqliteConnection(&'c mut SqliteConnection),
+}
+
+pub trait Executor<'c>: Send {
+    fn target(self) -> Target<'c>;
+}
+
+impl<'c> Executor<'c> for &'c Pool {
+    fn target(self) -> Target<'c> {
+        match self {
+            Pool::Postgres(pool) => Target::PostgresPool(pool),
+            Pool::Sqlite(pool) => Target::SqlitePool(pool),
+        }
+    }
+}
+
+impl<'c> Executor<'c> for &'c mut Connection<'_> {
+    fn target(self) -> Target<'c> {
+        match self {
+            Connection::Postgres(tx) => Target::PostgresConnection(tx),
+            Connection::Sqlite(tx) => Target::SqliteConnection(tx.connection()),
+        }
+    }
+}
+
+pub enum Row {
+    Postgres(PgRow),
+    Sqlite(SqliteRow),
+}
+
+impl Row {
+    // Panics like `sqlx::Row::get` when the column is missing or has another type
+    pub fn get<'r, T, I>(&'r self, index: I) -> T
+    where
+        T: Decode<'r, Postgres> + Type<Postgres> + Decode<'r, Sqlite> + Type<Sqlite>,
+        I: ColumnIndex<PgRow> + ColumnIndex<SqliteRow>,
+    {
+        match self {
+            Row::Postgres(row) => row.get(index),
+            Row::Sqlite(row) => row.get(index),
+        }
+    }
+
+    // Like `get`, but a missing column or one of another type is an error
+    pub fn try_get<'r, T, I>(&'r self, index: I) -> Result<T, sqlx::Error>
+    where
+        T: Decode<'r, Postgres> + Type<Postgres> + Decode<'r, Sqlite> + Type<Sqlite>,
+        I: ColumnIndex<PgRow> + ColumnIndex<SqliteRow>,
+    {
+        match self {
+            Row::Postgres(row) => row.try_get(index),
+            Row::Sqlite(row) => row.try_get(index),
+        }
+    }
+
+    // A TEXT[] column on Postgres, a JSON array on SQLite
+    pub fn get_list<I>(&self, index: I) -> Vec<String>
+    where
+        I: ColumnIndex<PgRow> + ColumnIndex<SqliteRow>,
+    {
+        match self {
+            Row::Postgres(row) => row.get(index),
+            Row::Sqlite(row) => serde_json::from_str(row.get(index)).unwrap_or_default(),
+        }
+    }
+
+    pub fn try_get_list<I>(&self, index: I) -> Result<Vec<String>, sqlx::Error>
+    where
+        I: ColumnIndex<PgRow> + ColumnIndex<SqliteRow>,
+    {
+        match self {
+            Row::Postgres(row) => row.try_get(index),
+            Row::Sqlite(row) => Ok(serde_json::from_str(row.try_get(index)?).unwrap_or_default()),
+        }
+    }
+}
+
+pub struct QueryResult {
+    rows_affected: u64,
+}
+
+impl QueryResult {
+    pub fn rows_affected(&self) -> u64 {
+        self.rows_affected
+    }
+}
+
+// A value bound to a query parameter. NULLs keep their type, which Postgres
+// needs to plan queries like `$1 IS NULL OR status = $1`
+pub enum Value {
+    Bool(Option<bool>),
+    I16(Option<i16>),
+    I32(Option<i32>),
+    I64(Option<i64>),
+    Text(Option<String>),
+    Timestamp(Option<DateTime<Utc>>),
+    Json(Option<serde_json::Value>),
+    TextList(Option<Vec<String>>),
+}
+
+macro_rules! value_from {
+    ($variant:ident, $type:ty) => {
+        impl From<$type> for Value {
+            fn from(value: $type) -> Value {
+                Value::$variant(Some(value.into()))
+            }
+        }
+
+        impl From<Option<$type>> for Value {
+            fn from(value: Option<$type>) -> Value {
+                Value::$variant(value.map(Into::into))
+            }
+        }
+
+        impl From<&Option<$type>> for Value {
+            fn from(value: &Option<$type>) -> Value {
+                Value::$variant(value.clone().map(Into::into))
+            }
+        }
+    };
+}
+
+value_from!(Bool, bool);
+value_from!(I16, i16);
+value_from!(I32, i32);
+value_from!(I64, i64);
+value_from!(Text, String);
+value_from!(Text, &str);
+value_from!(Timestamp, DateTime<Utc>);
+value_from!(Json, serde_json::Value);
+
+impl From<&String> for Value {
+    fn from(value: &String) -> Value {
+        Value::Text(Some(value.clone()))
+    }
+}
+
+impl From<Option<&String>> for Value {
+    fn from(value: Option<&String>) -> Value {
+        Value::Text(value.cloned())
+    }
+}
+
+impl From<&DateTime<Utc>> for Value {
+    fn from(value: &DateTime<Utc>) -> Value {
+        Value::Timestamp(Some(*value))
+    }
+}
+
+impl From<&serde_json::Value> for Value {
+    fn from(value: &serde_json::Value) -> Value {
+        Value::Json(Some(value.clone()))
+    }
+}
+
+impl From<Vec<String>> for Value {
+    fn from(value: Vec<String>) -> Value {
+        Value::TextList(Some(value))
+    }
+}
+
+impl From<&Vec<String>> for Value {
+    fn from(value: &Vec<String>) -> Value {
+        Value::TextList(Some(value.clone()))
+    }
+}
+
+impl From<Option<Vec<String>>> for Value {
+    fn from(value: Option<Vec<String>>) -> Value {
+        Value::TextList(value)
+    }
+}
+
+impl Value {
+    fn bind_postgres<'q>(
+        self,
+        query: SqlxQuery<'q, Postgres, PgArguments>,
+    ) -> SqlxQuery<'q, Postgres, PgArguments> {
+        match self {
+            Value::Bool(value) => query.bind(value),
+            Value::I16(value) => query.bind(value),
+            Value::I32(value) => query.bind(value),
+            Value::I64(value) => query.bind(value),
+            Value::Text(value) => query.bind(value),
+            Value::Timestamp(value) => query.bind(value),
+            Value::Json(value) => query.bind(value),
+            Value::TextList(value) => query.bind(value),
+        }
+    }
+
+    fn bind_sqlite<'q>(
+        self,
+        query: SqlxQuery<'q, Sqlite, SqliteArguments<'q>>,
+    ) -> SqlxQuery<'q, Sqlite, SqliteArguments<'q>> {
+        match self {
+            Value::Bool(value) => query.bind(value),
+            Value::I16(value) => query.bind(value),
+            Value::I32(value) => query.bind(value),
+            Value::I64(value) => query.bind(value),
+            Value::Text(value) => query.bind(value),
+            Value::Timestamp(value) => query.bind(value),
+            Value::Json(value) => query.bind(value),
+            Value::TextList(value) => query.bind(value.map(serde_json::Value::from)),
+        }
+    }
+}
+
+// Words that start the Postgres constructs SQLite has no equivalent for, so
+// queries without any are used as they are
+const SQLITE_REWRITTEN: [&str; 4] = ["NOW", "GREATEST", "ILIKE", "FOR"];
+
+const SQLITE_NOW: &str = "strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')";
+
+// Splits SQL into words, runs of whitespace, single characters, and quoted
+// strings and comments, which are kept whole so nothing in them is rewritten
+fn sql_tokens(sql: &str) -> Vec<&str> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut tokens = Vec::new();
+    let mut rest = sql;
+    while let Some(c) = rest.chars().next() {
+        let len = if c == '\'' || c == '"' {
+            // A doubled quote inside ends one token and starts the next
+            rest[1..].find(c).map_or(rest.len(), |end| end + 2)
+        } else if rest.starts_with("--") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if is_word(c) {
+            rest.find(|c| !is_word(c)).unwrap_or(rest.len())
+        } else if c.is_whitespace() {
+            rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len())
+        } else {
+            c.len_utf8()
+        };
+        let (token, remaining) = rest.split_at(len);
+        tokens.push(token);
+        rest = remaining;
+    }
+    tokens
+}
+
+// The position of the first token after `i` that isn't whitespace
+fn next_token(tokens: &[&str], i: usize) -> Option<usize> {
+    (i + 1..tokens.len()).find(|&j| !tokens[j].starts_with(char::is_whitespace))
+}
+
+fn next_is(tokens: &[&str], i: usize, token: &str) -> Option<usize> {
+    next_token(tokens, i).filter(|&next| tokens[next] == token)
+}
+
+// What replaces the construct starting at token `i`, and the position of its
+// last token
+fn sqlite_rewrite(tokens: &[&str], i: usize) -> Option<(&'static str, usize)> {
+    match tokens[i] {
+        "NOW" => {
+            let open = next_is(tokens, i, "(")?;
+            Some((SQLITE_NOW, next_is(tokens, open, ")")?))
+        }
+        "GREATEST" => next_is(tokens, i, "(").map(|_| ("MAX", i)),
+        "ILIKE" => Some(("LIKE", i)),
+        // Row locks, "FOR UPDATE [OF table] [SKIP LOCKED]". SQLite doesn't need
+        // them, as it runs one write transaction at a time
+        "FOR" => {
+            let mut end = next_is(tokens, i, "UPDATE")?;
+            if let Some(of) = next_is(tokens, end, "OF") {
+                end = next_token(tokens, of).unwrap_or(of);
+            }
+            if let Some(locked) = next_is(tokens, end, "SKIP").and_then(|skip| next_is(tokens, skip, "LOCKED")) {
+                end = locked;
+            }
+            Some(("", end))
+        }
+        _ => None,
+    }
+}
+
+fn sqlite_sql(sql: &str) -> Cow<'_, str> {
+    if !SQLITE_REWRITTEN.iter().any(|word| sql.contains(word)) {
+        return Cow::Borrowed(sql);
+    }
+    let tokens = sql_tokens(sql);
+    let mut out = String::with_capacity(sql.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        match sqlite_rewrite(&tokens, i) {
+            Some((replacement, end)) => {
+                out.push_str(replacement);
+                i = end;
+            }
+            None => out.push_str(tokens[i]),
+        }
+        i += 1;
+    }
+    Cow::Owned(out)
+}
+
+// Like `sqlx::query`, for either backend
+pub fn query(sql: &str) -> Query<'_> {
+    Query { sql, arguments: Vec::new() }
+}
+
+pub struct Query<'q> {
+    sql: &'q str,
+    arguments: Vec<Value>,
+}
+
+impl<'q> Query<'q> {
+    pub fn bind(mut self, value: impl Into<Value>) -> Self {
+        self.arguments.push(value.into());
+        self
+    }
+
+    fn postgres(self) -> SqlxQuery<'q, Postgres, PgArguments> {
+        self.arguments.into_iter().fold(sqlx::query(self.sql), |query, value| value.bind_postgres(query))
+    }
+
+    fn sqlite<'s>(sql: &'s str, arguments: Vec<Value>) -> SqlxQuery<'s, Sqlite, SqliteArguments<'s>> {
+        arguments.into_iter().fold(sqlx::query(sql), |query, value| value.bind_sqlite(query))
+    }
+
+    pub async fn execute<'c>(self, executor: impl Executor<'c>) -> Result<QueryResult, sqlx::Error> {
+        let rows_affected = match executor.target() {
+            Target::PostgresPool(pool) => self.postgres().execute(pool).await?.rows_affected(),
+            Target::PostgresConnection(connection) => self.postgres().execute(connection).await?.rows_affected(),
+            Target::SqlitePool(pool) => {
+                let sql = sqlite_sql(self.sql);
+                Query::sqlite(&sql, self.arguments).execute(pool).await?.rows_affected()
+            }
+            Target::SqliteConnection(connection) => {
+                let sql = sqlite_sql(self.sql);
+                Query::sqlite(&sql, self.arguments).execute(connection).await?.rows_affected()
+            }
+        };
+        Ok(QueryResult { rows_affected })
+    }
+
+    pub async fn fetch_all<'c>(self, executor: impl Executor<'c>) -> Result<Vec<Row>, sqlx::Error> {
+        Ok(match executor.target() {
+            Target::PostgresPool(pool) => self.postgres().fetch_all(pool).await?.into_iter().map(Row::Postgres).collect(),
+            Target::PostgresConnection(connection) => {
+                self.postgres().fetch_all(connection).await?.into_iter().map(Row::Postgres).collect()
+            }
+            Target::SqlitePool(pool) => {
+                let sql = sqlite_sql(self.sql);
+                Query::sqlite(&sql, self.arguments).fetch_all(pool).await?.into_iter().map(Row::Sqlite).collect()
+            }
+            Target::SqliteConnection(connection) => {
+                let sql = sqlite_sql(self.sql);
+                Query::sqlite(&sql, self.arguments).fetch_all(connection).await?.into_iter().map(Row::Sqlite).collect()
+            }
+        })
+    }
+
+    pub async fn fetch_optional<'c>(self, executor: impl Executor<'c>) -> Result<Option<Row>, sqlx::Error> {
+        Ok(match executor.target() {
+            Target::PostgresPool(pool) => self.postgres().fetch_optional(pool).await?.map(Row::Postgres),
+            Target::PostgresConnection(connection) => self.postgres().fetch_optional(connection).await?.map(Row::Postgres),
+            Target::SqlitePool(pool) => {
+                let sql = sqlite_sql(self.sql);
+                Query::sqlite(&sql, self.arguments).fetch_optional(pool).await?.map(Row::Sqlite)
+            }
+            Target::SqliteConnection(connection) => {
+                let sql = sqlite_sql(self.sql);
+                Query::sqlite(&sql, self.arguments).fetch_optional(connection).await?.map(Row::Sqlite)
+            }
+        })
+    }
+
+    pub async fn fetch_one<'c>(self, executor: impl Executor<'c>) -> Result<Row, sqlx::Error> {
+        self.fetch_optional(executor).await?.ok_or(sqlx::Error::RowNotFound)
+    }
+
+    // Rows as they arrive. SQLite queries that need rewriting are read in
+    // full first, since the rewritten SQL can't outlive this call
+    pub fn fetch<'e>(self, pool: &'e Pool) -> BoxStream<'e, Result<Row, sqlx::Error>>
+    where
+        'q: 'e,
+    {
+        match pool {
+            Pool::Postgres(pool) => self.postgres().fetch(pool).map_ok(Row::Postgres).boxed(),
+            Pool::Sqlite(pool) => match sqlite_sql(self.sql) {
+                Cow::Borrowed(sql) => Query::sqlite(sql, self.arguments).fetch(pool).map_ok(Row::Sqlite).boxed(),
+                Cow::Owned(sql) => futures::stream::once(async move {
+                    Query::sqlite(&sql, self.arguments).fetch_all(pool).await
+                })
+                .map_ok(|rows| futures::stream::iter(rows.into_iter().map(|row| Ok(Row::Sqlite(row)))))
+                .try_flatten()
+                .boxed(),
+            },
+        }
+    }
+}
+
+// Like `sqlx::query_scalar`: the first column of each row
+pub fn query_scalar<T>(sql: &str) -> QueryScalar<'_, T> {
+    QueryScalar { query: query(sql), output: std::marker::PhantomData }
+}
+
+pub struct QueryScalar<'q, T> {
+    query: Query<'q>,
+    output: std::marker::PhantomData<T>,
+}
+
+impl<'q, T> QueryScalar<'q, T>
+where
+    T: for<'r> Decode<'r, Postgres> + Type<Postgres> + for<'r> Decode<'r, Sqlite> + Type<Sqlite>,
+{
+    pub fn bind(self, value: impl Into<Value>) -> Self {
+        QueryScalar { query: self.query.bind(value), output: self.output }
+    }
+
+    pub async fn fetch_optional<'c>(self, executor: impl Executor<'c>) -> Result<Option<T>, sqlx::Error> {
+        Ok(self.query.fetch_optional(executor).await?.map(|row| row.get(0)))
+    }
+
+    pub async fn fetch_one<'c>(self, executor: impl Executor<'c>) -> Result<T, sqlx::Error> {
+        Ok(self.query.fetch_one(executor).await?.get(0))
+    }
+
+    pub async fn fetch_all<'c>(self, executor: impl Executor<'c>) -> Result<Vec<T>, sqlx::Error> {
+        Ok(self.query.fetch_all(executor).await?.iter().map(|row| row.get(0)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn postgres_constructs_become_sqlite_ones() {
+        let cases = [
+            ("SELECT NOW()", format!("SELECT {}", SQLITE_NOW)),
+            ("SELECT NOW ( )", format!("SELECT {}", SQLITE_NOW)),
+            ("SELECT GREATEST(a, b)", "SELECT MAX(a, b)".to_string()),
+            ("WHERE name ILIKE $1", "WHERE name LIKE $1".to_string()),
+            ("SELECT * FROM jobs FOR UPDATE", "SELECT * FROM jobs ".to_string()),
+            ("SELECT * FROM jobs FOR UPDATE SKIP LOCKED LIMIT 1", "SELECT * FROM jobs  LIMIT 1".to_string()),
+            ("FROM todos t JOIN users u\n FOR UPDATE OF t SKIP LOCKED", "FROM todos t JOIN users u\n ".to_string()),
+            ("FOR UPDATE OF d", "".to_string()),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(sqlite_sql(sql), expected, "{}", sql);
+        }
+    }
+
+    #[test]
+    fn strings_comments_and_other_words_are_left_alone() {
+        let unchanged = [
+            "SELECT 'NOW()', \"GREATEST\" FROM t",
+            "SELECT 'it''s NOW()' ILIKE_ FROM t",
+            "SELECT 1 -- FOR UPDATE\n",
+            "SELECT NOWHERE, FORMAT FROM t",
+            "SELECT NOW FROM t",
+            "SELECT GREATEST FROM t",
+            "CREATE TRIGGER t AFTER INSERT ON todos FOR EACH ROW",
+            "SELECT 'unterminated NOW()",
+        ];
+        for sql in unchanged {
+            assert_eq!(sqlite_sql(sql), sql);
+        }
+        assert!(matches!(sqlite_sql("SELECT * FROM todos WHERE id = $1"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn sql_is_split_into_tokens_that_join_back_up() {
+        let sql = "SELECT a_b,'x''y' -- c\n  FROM \"t\"";
+        let tokens = sql_tokens(sql);
+        assert_eq!(tokens, ["SELECT", " ", "a_b", ",", "'x'", "'y'", " ", "-- c", "\n  ", "FROM", " ", "\"t\""]);
+        assert_eq!(tokens.concat(), sql);
+    }
+}
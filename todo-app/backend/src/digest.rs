@@ -0,0 +1,468 @@
+use askama::Template;
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde_json::json;
+use todo_api_types::NotificationPreferences;
+
+use crate::{
+    auth,
+    config::Config,
+    db::{self, Pool, Row},
+    history,
+    jobs::{self, Job, JobContext},
+    mailer::Email,
+};
+
+pub const FREQUENCY_OFF: &str = "off";
+pub const FREQUENCY_DAILY: &str = "daily";
+pub const FREQUENCY_WEEKLY: &str = "weekly";
+
+pub const FREQUENCIES: [&str; 3] = [FREQUENCY_OFF, FREQUENCY_DAILY, FREQUENCY_WEEKLY];
+
+// Longer sections end with "and N more"
+const SECTION_LIMIT: usize = 25;
+
+pub fn is_valid_timezone(timezone: &str) -> bool {
+    timezone.parse::<Tz>().is_ok()
+}
+
+// The first send time strictly after `after`: `hour` o'clock in the user's time
+// zone, every day or on the ISO `weekday` (1 = Monday). None when digests are off
+pub fn next_send_at(
+    frequency: &str,
+    hour: i16,
+    weekday: i16,
+    timezone: &str,
+    after: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if frequency == FREQUENCY_OFF {
+        return None;
+    }
+    let tz: Tz = timezone.parse().unwrap_or(Tz::UTC);
+    let time = NaiveTime::from_hms_opt(hour as u32, 0, 0)?;
+    let today = after.with_timezone(&tz).date_naive();
+
+    (0..=8)
+        .map(|days| today + Duration::days(days))
+        .filter(|date| frequency != FREQUENCY_WEEKLY || date.weekday().number_from_monday() == weekday as u32)
+        // Days where the hour falls into a DST gap are skipped
+        .filter_map(|date| tz.from_local_datetime(&date.and_time(time)).earliest())
+        .map(|at| at.with_timezone(&Utc))
+        .find(|at| *at > after)
+}
+
+// What users who never changed their preferences get: reminders on, digests off
+pub fn default_preferences(timezone: String) -> NotificationPreferences {
+    NotificationPreferences {
+        email_reminders: true,
+        push_reminders: true,
+        digest_frequency: FREQUENCY_OFF.to_string(),
+        digest_hour: 8,
+        digest_weekday: 1,
+        digest_next_at: None,
+        timezone,
+    }
+}
+
+// The user's preferences, with the defaults for those never set
+pub async fn load_preferences<'e, E: db::Executor<'e>>(
+    executor: E,
+    user_id: &str,
+) -> Result<Option<NotificationPreferences>, sqlx::Error> {
+    let row = db::query(
+        "SELECT u.timezone, p.email_reminders, p.push_reminders, p.digest_frequency, p.digest_hour, p.digest_weekday, p.digest_next_at
+         FROM users u LEFT JOIN notification_preferences p ON p.user_id = u.id
+         WHERE u.id = $1"
+    )
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let defaults = default_preferences(row.try_get("timezone")?);
+    Ok(Some(NotificationPreferences {
+        email_reminders: row.try_get::<Option<bool>, _>("email_reminders")?.unwrap_or(defaults.email_reminders),
+        push_reminders: row.try_get::<Option<bool>, _>("push_reminders")?.unwrap_or(defaults.push_reminders),
+        digest_frequency: row.try_get::<Option<String>, _>("digest_frequency")?.unwrap_or(defaults.digest_frequency),
+        digest_hour: row.try_get::<Option<i16>, _>("digest_hour")?.unwrap_or(defaults.digest_hour),
+        digest_weekday: row.try_get::<Option<i16>, _>("digest_weekday")?.unwrap_or(defaults.digest_weekday),
+        digest_next_at: row.try_get("digest_next_at")?,
+        timezone: defaults.timezone,
+    }))
+}
+
+// Sets when the next digest goes out from the schedule in `preferences`, and returns it
+pub async fn reschedule(
+    tx: &mut db::Transaction<'_>,
+    preferences: &NotificationPreferences,
+    user_id: &str,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let next_at = next_send_at(
+        &preferences.digest_frequency,
+        preferences.digest_hour,
+        preferences.digest_weekday,
+        &preferences.timezone,
+        Utc::now(),
+    );
+    db::query("UPDATE notification_preferences SET digest_next_at = $1 WHERE user_id = $2")
+        .bind(next_at)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(next_at)
+}
+
+pub struct DigestItem {
+    pub title: String,
+    // Due or completion time, in the user's time zone
+    pub at: String,
+}
+
+pub struct DigestSection {
+    pub items: Vec<DigestItem>,
+    pub more: usize,
+}
+
+impl DigestSection {
+    fn new(mut items: Vec<DigestItem>) -> DigestSection {
+        let more = items.len().saturating_sub(SECTION_LIMIT);
+        items.truncate(SECTION_LIMIT);
+        DigestSection { items, more }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+pub struct Digest {
+    pub username: String,
+    // "today" or "this week", for the completed section
+    pub period: &'static str,
+    pub overdue: DigestSection,
+    pub due_today: DigestSection,
+    pub completed: DigestSection,
+    pub unsubscribe_url: String,
+}
+
+impl Digest {
+    pub fn is_empty(&self) -> bool {
+        self.overdue.is_empty() && self.due_today.is_empty() && self.completed.is_empty()
+    }
+
+    pub fn subject(&self) -> String {
+        format!(
+            "Your todos: {} overdue, {} due today",
+            self.overdue.items.len() + self.overdue.more,
+            self.due_today.items.len() + self.due_today.more
+        )
+    }
+}
+
+#[derive(Template)]
+#[template(path = "digest.html")]
+pub struct DigestHtml<'a> {
+    pub digest: &'a Digest,
+}
+
+#[derive(Template)]
+#[template(path = "digest.txt")]
+pub struct DigestText<'a> {
+    pub digest: &'a Digest,
+}
+
+pub fn unsubscribe_url(config: &Config, user_id: &str) -> String {
+    format!("{}/api/digest/unsubscribe?token={}", config.server.public_url, auth::sign(&config.auth, user_id))
+}
+
+// Overdue and due-today todos as of `now` in the user's time zone, and todos
+// completed over the last day or week
+pub async fn build(
+    db: &Pool,
+    user_id: &str,
+    frequency: &str,
+    config: &Config,
+    now: DateTime<Utc>,
+) -> Result<Option<Digest>, sqlx::Error> {
+    let Some(user) = db::query("SELECT username, timezone FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let tz: Tz = user.get::<String, _>("timezone").parse().unwrap_or(Tz::UTC);
+
+    let today = now.with_timezone(&tz).date_naive();
+    let start_of_day = |date: chrono::NaiveDate| {
+        tz.from_local_datetime(&date.and_time(NaiveTime::MIN))
+            .earliest()
+            .map(|at| at.with_timezone(&Utc))
+            .unwrap_or(now)
+    };
+    let today_start = start_of_day(today);
+    let tomorrow_start = start_of_day(today + Duration::days(1));
+
+    let (period, since) = if frequency == FREQUENCY_WEEKLY {
+        ("this week", now - Duration::days(7))
+    } else {
+        ("today", now - Duration::days(1))
+    };
+
+    let item = |row: &Row, column: &str| DigestItem {
+        title: row.get("title"),
+        at: row
+            .get::<DateTime<Utc>, _>(column)
+            .with_timezone(&tz)
+            .format("%a %b %-d, %H:%M")
+            .to_string(),
+    };
+
+    let overdue = db::query(
+        "SELECT title, due_at FROM todos WHERE user_id = $1 AND NOT completed AND deleted_at IS NULL AND due_at < $2 ORDER BY due_at"
+    )
+    .bind(user_id)
+    .bind(today_start)
+    .fetch_all(db)
+    .await?;
+
+    let due_today = db::query(
+        "SELECT title, due_at FROM todos WHERE user_id = $1 AND NOT completed AND deleted_at IS NULL AND due_at >= $2 AND due_at < $3 ORDER BY due_at"
+    )
+    .bind(user_id)
+    .bind(today_start)
+    .bind(tomorrow_start)
+    .fetch_all(db)
+    .await?;
+
+    // Completion times come from the history, for todos that are still completed
+    let completed = db::query(
+        "SELECT t.title, MAX(e.created_at) AS completed_at FROM todo_events e JOIN todos t ON t.id = e.todo_id
+         WHERE t.user_id = $1 AND t.completed AND t.deleted_at IS NULL AND e.event_type = $2 AND e.created_at >= $3
+         GROUP BY t.id, t.title ORDER BY completed_at DESC"
+    )
+    .bind(user_id)
+    .bind(history::EVENT_COMPLETED)
+    .bind(since)
+    .fetch_all(db)
+    .await?;
+
+    Ok(Some(Digest {
+        username: user.get("username"),
+        period,
+        overdue: DigestSection::new(overdue.iter().map(|row| item(row, "due_at")).collect()),
+        due_today: DigestSection::new(due_today.iter().map(|row| item(row, "due_at")).collect()),
+        completed: DigestSection::new(completed.iter().map(|row| item(row, "completed_at")).collect()),
+        unsubscribe_url: unsubscribe_url(config, user_id),
+    }))
+}
+
+// Queues a digest for every user whose send time has come, and schedules their next one
+pub async fn dispatch(context: &JobContext) -> Result<(), String> {
+    let mut tx = context.db.begin().await.map_err(|e| e.to_string())?;
+    let now = Utc::now();
+
+    let due = db::query(
+        "SELECT p.user_id, p.digest_frequency, p.digest_hour, p.digest_weekday, u.timezone
+         FROM notification_preferences p JOIN users u ON u.id = p.user_id
+         WHERE p.digest_frequency <> $1 AND p.digest_next_at <= $2 AND u.deletion_scheduled_at IS NULL AND u.disabled_at IS NULL
+         FOR UPDATE OF p SKIP LOCKED"
+    )
+    .bind(FREQUENCY_OFF)
+    .bind(now)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for row in &due {
+        let user_id: String = row.get("user_id");
+        let frequency: String = row.get("digest_frequency");
+        let next_at = next_send_at(
+            &frequency,
+            row.get("digest_hour"),
+            row.get("digest_weekday"),
+            row.get("timezone"),
+            now,
+        );
+
+        db::query("UPDATE notification_preferences SET digest_next_at = $1 WHERE user_id = $2")
+            .bind(next_at)
+            .bind(&user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        jobs::enqueue(&mut *tx, jobs::JOB_SEND_DIGEST, json!({ "user_id": user_id, "frequency": frequency }), now)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+// Digests with nothing to report aren't sent
+pub async fn send(context: &JobContext, job: &Job) -> Result<(), String> {
+    let user_id = job.payload_str("user_id")?;
+    let frequency = job.payload["frequency"].as_str().unwrap_or(FREQUENCY_DAILY);
+
+    let digest = build(&context.db, user_id, frequency, &context.config, Utc::now())
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some(digest) = digest.filter(|digest| !digest.is_empty()) else {
+        return Ok(());
+    };
+
+    let email: String = db::query("SELECT email FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&context.db)
+        .await
+        .map_err(|e| e.to_string())?
+        .get("email");
+
+    context
+        .mailer
+        .send(Email {
+            to: email,
+            subject: digest.subject(),
+            text: DigestText { digest: &digest }.render().map_err(|e| e.to_string())?,
+            html: Some(DigestHtml { digest: &digest }.render().map_err(|e| e.to_string())?),
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use todo_api_types::{CreateTodoRequest, TodoUpdate};
+
+    use crate::{
+        config::DatabaseConfig,
+        mailer::SentMail,
+        repository::{NewUser, SqlRepository, TodoRepository, UserRepository},
+    };
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn digests_go_out_at_the_hour_in_the_users_zone() {
+        // 07:00 in Berlin is 06:00 UTC in winter
+        let next = |frequency, hour, after| next_send_at(frequency, hour, 1, "Europe/Berlin", after);
+        assert_eq!(next(FREQUENCY_DAILY, 8, utc(2026, 1, 15, 6)), Some(utc(2026, 1, 15, 7)));
+        assert_eq!(next(FREQUENCY_DAILY, 8, utc(2026, 1, 15, 7)), Some(utc(2026, 1, 16, 7)));
+        // The 15th is a Thursday
+        assert_eq!(next(FREQUENCY_WEEKLY, 8, utc(2026, 1, 15, 6)), Some(utc(2026, 1, 19, 7)));
+        assert_eq!(next(FREQUENCY_OFF, 8, utc(2026, 1, 15, 6)), None);
+        // 02:00 doesn't exist on the day clocks go forward
+        assert_eq!(next(FREQUENCY_DAILY, 2, utc(2026, 3, 28, 2)), Some(utc(2026, 3, 30, 0)));
+        // Unknown zones are UTC
+        assert_eq!(next_send_at(FREQUENCY_DAILY, 8, 1, "Mars/Olympus", utc(2026, 1, 15, 6)), Some(utc(2026, 1, 15, 8)));
+    }
+
+    // A migrated database with the user "user-1" in `timezone`
+    async fn repository(timezone: &str) -> (Pool, SqlRepository) {
+        let config = DatabaseConfig { url: "sqlite::memory:".to_string(), max_connections: 1, ..Default::default() };
+        let pool = Pool::connect(&config).await.unwrap();
+        pool.migrate().await.unwrap();
+        let repository = SqlRepository::new(pool.clone());
+        let user = NewUser {
+            id: "user-1".to_string(),
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            password_hash: "hash".to_string(),
+        };
+        UserRepository::create(&repository, user).await.unwrap();
+        db::query("UPDATE users SET timezone = $1").bind(timezone).execute(&pool).await.unwrap();
+        (pool, repository)
+    }
+
+    async fn todo(repository: &SqlRepository, title: &str, due_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> String {
+        let todo = CreateTodoRequest { title: title.to_string(), description: None, due_at, priority: None, remind_at: None };
+        TodoRepository::create(repository, "user-1", todo, now).await.unwrap().id
+    }
+
+    async fn complete(repository: &SqlRepository, id: &str, at: DateTime<Utc>) {
+        let changes = TodoUpdate { completed: Some(true), ..Default::default() };
+        repository.update("user-1", id, changes, at).await.unwrap();
+    }
+
+    fn titles(section: &DigestSection) -> Vec<&str> {
+        section.items.iter().map(|item| item.title.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn digests_sort_todos_by_the_users_day() {
+        let (pool, repository) = repository("Asia/Tokyo").await;
+        // Noon on the 15th in Tokyo, whose day runs from 15:00 UTC on the 14th
+        let now = utc(2026, 1, 15, 3);
+        let created = now - Duration::days(3);
+        todo(&repository, "Late yesterday", Some(utc(2026, 1, 14, 14)), created).await;
+        todo(&repository, "Late today", Some(utc(2026, 1, 15, 14)), created).await;
+        todo(&repository, "Tomorrow", Some(utc(2026, 1, 15, 15)), created).await;
+        let done = todo(&repository, "Done overdue", Some(utc(2026, 1, 13, 0)), created).await;
+        complete(&repository, &done, now - Duration::hours(2)).await;
+        let long_done = todo(&repository, "Done last week", None, created).await;
+        complete(&repository, &long_done, now - Duration::days(2)).await;
+        let trashed = todo(&repository, "Trashed", Some(utc(2026, 1, 13, 0)), created).await;
+        repository.trash("user-1", &trashed, now).await.unwrap();
+
+        let daily = build(&pool, "user-1", FREQUENCY_DAILY, &Config::default(), now).await.unwrap().unwrap();
+        assert_eq!(titles(&daily.overdue), ["Late yesterday"]);
+        assert_eq!(daily.overdue.items[0].at, "Wed Jan 14, 23:00");
+        assert_eq!(titles(&daily.due_today), ["Late today"]);
+        assert_eq!(titles(&daily.completed), ["Done overdue"]);
+        assert_eq!((daily.period, daily.subject().as_str()), ("today", "Your todos: 1 overdue, 1 due today"));
+
+        let weekly = build(&pool, "user-1", FREQUENCY_WEEKLY, &Config::default(), now).await.unwrap().unwrap();
+        assert_eq!(titles(&weekly.completed), ["Done overdue", "Done last week"]);
+        assert!(build(&pool, "user-2", FREQUENCY_DAILY, &Config::default(), now).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn long_sections_end_with_a_count() {
+        let (pool, repository) = repository("UTC").await;
+        let now = Utc::now();
+        for index in 0..SECTION_LIMIT + 3 {
+            todo(&repository, &format!("Todo {}", index), Some(now - Duration::days(2)), now).await;
+        }
+        let digest = build(&pool, "user-1", FREQUENCY_DAILY, &Config::default(), now).await.unwrap().unwrap();
+        assert_eq!((digest.overdue.items.len(), digest.overdue.more), (SECTION_LIMIT, 3));
+        assert!(digest.subject().starts_with(&format!("Your todos: {} overdue", SECTION_LIMIT + 3)));
+        let text = DigestText { digest: &digest }.render().unwrap();
+        assert!(text.contains("...and 3 more"), "{}", text);
+    }
+
+    fn send_job(payload: serde_json::Value) -> Job {
+        Job { id: "job-1".to_string(), kind: jobs::JOB_SEND_DIGEST.to_string(), payload, attempts: 1, max_attempts: 5 }
+    }
+
+    #[tokio::test]
+    async fn only_digests_with_something_in_them_are_sent() {
+        let (pool, repository) = repository("UTC").await;
+        let mail = Arc::new(SentMail::default());
+        let context = JobContext {
+            db: pool.clone(),
+            http: reqwest::Client::new(),
+            mailer: mail.clone(),
+            vapid: None,
+            config: Arc::new(Config::default()),
+        };
+
+        let job = send_job(json!({ "user_id": "user-1", "frequency": FREQUENCY_DAILY }));
+        send(&context, &job).await.unwrap();
+        assert!(mail.take().is_empty());
+
+        todo(&repository, "Pay rent", Some(Utc::now() - Duration::days(2)), Utc::now()).await;
+        send(&context, &job).await.unwrap();
+        let sent = mail.take().pop().unwrap();
+        assert_eq!((sent.to.as_str(), sent.subject.as_str()), ("alice@example.com", "Your todos: 1 overdue, 0 due today"));
+        assert!(sent.text.contains("- Pay rent (due ") && sent.text.contains("/api/digest/unsubscribe?token="), "{}", sent.text);
+        assert!(sent.html.unwrap().contains("Pay rent"));
+
+        assert_eq!(send(&context, &send_job(json!({ "frequency": FREQUENCY_DAILY }))).await, Err("missing user_id".to_string()));
+    }
+}
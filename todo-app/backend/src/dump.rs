@@ -0,0 +1,311 @@
+// The whole database as one JSON document, for backups and for moving between
+// Postgres and SQLite. Every table is listed here with the type of each
+// column, so values are read and written the same way on both backends: a
+// migration that adds a table or column has to add it here too
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+use std::collections::BTreeMap;
+
+use crate::db::{self, Backend, Pool, Row, Value};
+
+// Bumped when the layout of the document changes
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Bool,
+    SmallInt,
+    Int,
+    BigInt,
+    Text,
+    Timestamp,
+    Json,
+    TextList,
+}
+
+struct Table {
+    name: &'static str,
+    // The first column identifies rows, and orders them in the dump
+    columns: &'static [(&'static str, Kind)],
+}
+
+// Parents before children, so rows can be inserted in this order
+const TABLES: &[Table] = &[
+    Table {
+        name: "users",
+        columns: &[
+            ("id", Kind::Text),
+            ("username", Kind::Text),
+            ("email", Kind::Text),
+            ("password_hash", Kind::Text),
+            ("created_at", Kind::Timestamp),
+            ("calendar_token", Kind::Text),
+            ("timezone", Kind::Text),
+            ("display_name", Kind::Text),
+            ("locale", Kind::Text),
+            ("avatar_url", Kind::Text),
+            ("pending_email", Kind::Text),
+            ("email_verification_token_hash", Kind::Text),
+            ("email_verification_expires_at", Kind::Timestamp),
+            ("deletion_scheduled_at", Kind::Timestamp),
+            ("role", Kind::Text),
+            ("disabled_at", Kind::Timestamp),
+            ("password_reset_required", Kind::Bool),
+            ("last_login_at", Kind::Timestamp),
+            ("sync_seq", Kind::BigInt),
+        ],
+    },
+    Table {
+        name: "todos",
+        columns: &[
+            ("id", Kind::Text),
+            ("user_id", Kind::Text),
+            ("title", Kind::Text),
+            ("description", Kind::Text),
+            ("completed", Kind::Bool),
+            ("created_at", Kind::Timestamp),
+            ("updated_at", Kind::Timestamp),
+            ("deleted_at", Kind::Timestamp),
+            ("position", Kind::Text),
+            ("due_at", Kind::Timestamp),
+            ("priority", Kind::SmallInt),
+            ("remind_at", Kind::Timestamp),
+            ("reminded_at", Kind::Timestamp),
+            ("caldav_name", Kind::Text),
+            ("caldav_uid", Kind::Text),
+            // Kept on restore, where rows are inserted with it
+            ("sync_seq", Kind::BigInt),
+        ],
+    },
+    Table {
+        name: "todo_tombstones",
+        columns: &[("user_id", Kind::Text), ("name", Kind::Text), ("sync_seq", Kind::BigInt)],
+    },
+    Table {
+        name: "todo_events",
+        columns: &[
+            ("id", Kind::Text),
+            ("todo_id", Kind::Text),
+            ("user_id", Kind::Text),
+            ("actor_id", Kind::Text),
+            ("event_type", Kind::Text),
+            ("changes", Kind::Json),
+            ("created_at", Kind::Timestamp),
+        ],
+    },
+    Table {
+        name: "app_passwords",
+        columns: &[
+            ("id", Kind::Text),
+            ("user_id", Kind::Text),
+            ("name", Kind::Text),
+            ("password_hash", Kind::Text),
+            ("created_at", Kind::Timestamp),
+            ("last_used_at", Kind::Timestamp),
+        ],
+    },
+    Table {
+        name: "webhooks",
+        columns: &[
+            ("id", Kind::Text),
+            ("user_id", Kind::Text),
+            ("url", Kind::Text),
+            ("events", Kind::TextList),
+            ("secret", Kind::Text),
+            ("active", Kind::Bool),
+            ("created_at", Kind::Timestamp),
+        ],
+    },
+    Table {
+        name: "webhook_deliveries",
+        columns: &[
+            ("id", Kind::Text),
+            ("webhook_id", Kind::Text),
+            ("event_type", Kind::Text),
+            ("payload", Kind::Json),
+            ("status", Kind::Text),
+            ("attempts", Kind::Int),
+            ("next_attempt_at", Kind::Timestamp),
+            ("response_status", Kind::Int),
+            ("last_error", Kind::Text),
+            ("created_at", Kind::Timestamp),
+            ("delivered_at", Kind::Timestamp),
+        ],
+    },
+    Table {
+        name: "jobs",
+        columns: &[
+            ("id", Kind::Text),
+            ("kind", Kind::Text),
+            ("payload", Kind::Json),
+            ("status", Kind::Text),
+            ("attempts", Kind::Int),
+            ("max_attempts", Kind::Int),
+            ("run_at", Kind::Timestamp),
+            ("locked_at", Kind::Timestamp),
+            ("last_error", Kind::Text),
+            ("created_at", Kind::Timestamp),
+            ("updated_at", Kind::Timestamp),
+            ("finished_at", Kind::Timestamp),
+        ],
+    },
+    Table {
+        name: "scheduled_jobs",
+        columns: &[("kind", Kind::Text), ("next_run_at", Kind::Timestamp), ("last_run_at", Kind::Timestamp)],
+    },
+    Table {
+        name: "notification_preferences",
+        columns: &[
+            ("user_id", Kind::Text),
+            ("email_reminders", Kind::Bool),
+            ("push_reminders", Kind::Bool),
+            ("updated_at", Kind::Timestamp),
+            ("digest_frequency", Kind::Text),
+            ("digest_hour", Kind::SmallInt),
+            ("digest_weekday", Kind::SmallInt),
+            ("digest_next_at", Kind::Timestamp),
+        ],
+    },
+    Table {
+        name: "push_subscriptions",
+        columns: &[
+            ("id", Kind::Text),
+            ("user_id", Kind::Text),
+            ("endpoint", Kind::Text),
+            ("p256dh", Kind::Text),
+            ("auth", Kind::Text),
+            ("created_at", Kind::Timestamp),
+        ],
+    },
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Dump {
+    pub format_version: u32,
+    // The last migration applied to the database it came from; it can only be
+    // restored into a database at the same one
+    pub schema_version: i64,
+    pub created_at: DateTime<Utc>,
+    // Rows by table, each an object of column names to values
+    pub tables: BTreeMap<String, Vec<Map<String, Json>>>,
+}
+
+impl Table {
+    fn column_list(&self) -> String {
+        self.columns.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+    }
+}
+
+fn read(row: &Row, name: &str, kind: Kind) -> Json {
+    match kind {
+        Kind::Bool => row.get::<Option<bool>, _>(name).into(),
+        Kind::SmallInt => row.get::<Option<i16>, _>(name).into(),
+        Kind::Int => row.get::<Option<i32>, _>(name).into(),
+        Kind::BigInt => row.get::<Option<i64>, _>(name).into(),
+        Kind::Text => row.get::<Option<String>, _>(name).into(),
+        Kind::Timestamp => row.get::<Option<DateTime<Utc>>, _>(name).map(|time| time.to_rfc3339()).into(),
+        Kind::Json => row.get::<Option<Json>, _>(name).unwrap_or(Json::Null),
+        Kind::TextList => row.get_list(name).into(),
+    }
+}
+
+fn parse<T: DeserializeOwned>(value: &Json) -> Result<Option<T>, serde_json::Error> {
+    serde_json::from_value(value.clone())
+}
+
+fn write(value: &Json, kind: Kind) -> Result<Value, serde_json::Error> {
+    Ok(match kind {
+        Kind::Bool => Value::Bool(parse(value)?),
+        Kind::SmallInt => Value::I16(parse(value)?),
+        Kind::Int => Value::I32(parse(value)?),
+        Kind::BigInt => Value::I64(parse(value)?),
+        Kind::Text => Value::Text(parse(value)?),
+        Kind::Timestamp => Value::Timestamp(parse(value)?),
+        Kind::Json => Value::Json(parse(value)?),
+        Kind::TextList => Value::TextList(parse(value)?),
+    })
+}
+
+async fn schema_version(db: &Pool) -> anyhow::Result<i64> {
+    let applied = db.applied_migrations().await?;
+    applied.last().copied().context("The database has no schema yet; run the migrations first")
+}
+
+// Reads every table in one transaction, so the dump is consistent
+pub async fn dump(db: &Pool) -> anyhow::Result<Dump> {
+    let schema_version = schema_version(db).await?;
+    let mut tx = db.begin().await?;
+    if tx.backend() == Backend::Postgres {
+        // One snapshot for every table, rather than one per query
+        db::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ").execute(&mut *tx).await?;
+    }
+
+    let mut tables = BTreeMap::new();
+    for table in TABLES {
+        let sql = format!("SELECT {} FROM {} ORDER BY {}", table.column_list(), table.name, table.columns[0].0);
+        let rows = db::query(&sql).fetch_all(&mut *tx).await?;
+        let rows = rows
+            .iter()
+            .map(|row| table.columns.iter().map(|&(name, kind)| (name.to_string(), read(row, name, kind))).collect())
+            .collect();
+        tables.insert(table.name.to_string(), rows);
+    }
+    tx.commit().await?;
+
+    Ok(Dump { format_version: FORMAT_VERSION, schema_version, created_at: Utc::now(), tables })
+}
+
+// Loads a dump in one transaction. The database must be empty unless
+// `replace` is set, in which case everything in it is deleted first
+pub async fn restore(db: &Pool, dump: &Dump, replace: bool) -> anyhow::Result<()> {
+    if dump.format_version != FORMAT_VERSION {
+        bail!("This dump has format version {}, but only {} can be read", dump.format_version, FORMAT_VERSION);
+    }
+    let schema_version = schema_version(db).await?;
+    if dump.schema_version != schema_version {
+        bail!(
+            "The dump is of schema version {}, but the database is at {}; migrate it to {} first",
+            dump.schema_version,
+            schema_version,
+            dump.schema_version
+        );
+    }
+    if let Some(name) = dump.tables.keys().find(|name| !TABLES.iter().any(|table| table.name == *name)) {
+        bail!("The dump has a table \"{}\" this version doesn't know", name);
+    }
+
+    let mut tx = db.begin().await?;
+    for table in TABLES.iter().rev() {
+        let sql = format!("SELECT COUNT(*) FROM {}", table.name);
+        let count: i64 = db::query_scalar(&sql).fetch_one(&mut *tx).await?;
+        if count == 0 {
+            continue;
+        }
+        if !replace {
+            bail!("The database already has {} rows in {}; restore into an empty one, or replace its contents", count, table.name);
+        }
+        db::query(&format!("DELETE FROM {}", table.name)).execute(&mut *tx).await?;
+    }
+
+    for table in TABLES {
+        let Some(rows) = dump.tables.get(table.name) else {
+            continue;
+        };
+        let placeholders: Vec<String> = (1..=table.columns.len()).map(|index| format!("${}", index)).collect();
+        let sql = format!("INSERT INTO {} ({}) VALUES ({})", table.name, table.column_list(), placeholders.join(", "));
+        for (index, row) in rows.iter().enumerate() {
+            let mut query = db::query(&sql);
+            for &(name, kind) in table.columns {
+                let value = write(row.get(name).unwrap_or(&Json::Null), kind)
+                    .with_context(|| format!("{} row {}: invalid {}", table.name, index + 1, name))?;
+                query = query.bind(value);
+            }
+            query.execute(&mut *tx).await.with_context(|| format!("Can't restore {} row {}", table.name, index + 1))?;
+        }
+    }
+    tx.commit().await?;
+    Ok(())
+}
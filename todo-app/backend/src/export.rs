@@ -0,0 +1,234 @@
+use serde::Deserialize;
+use std::io::{Cursor, Write};
+
+use todo_api_types::Todo;
+
+#[derive(Debug, Deserialize, Clone, Copy, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Md,
+    Todotxt,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    pub format: ExportFormat,
+}
+
+const CSV_COLUMNS: [&str; 9] = [
+    "id",
+    "title",
+    "description",
+    "completed",
+    "position",
+    "due_at",
+    "priority",
+    "created_at",
+    "updated_at",
+];
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Md => "text/markdown; charset=utf-8",
+            ExportFormat::Todotxt => "text/plain; charset=utf-8",
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "todos.json",
+            ExportFormat::Csv => "todos.csv",
+            ExportFormat::Md => "todos.md",
+            ExportFormat::Todotxt => "todo.txt",
+        }
+    }
+
+    // Written before the first todo
+    pub fn header(self) -> String {
+        match self {
+            ExportFormat::Json => "[".to_string(),
+            ExportFormat::Csv => csv_line(&CSV_COLUMNS),
+            ExportFormat::Md => "# Todos\n\n".to_string(),
+            ExportFormat::Todotxt => String::new(),
+        }
+    }
+
+    // One todo; `index` is its zero-based position in the export
+    pub fn row(self, todo: &Todo, index: usize) -> String {
+        match self {
+            ExportFormat::Json => {
+                let separator = if index == 0 { "\n  " } else { ",\n  " };
+                format!("{}{}", separator, serde_json::to_string(todo).unwrap_or_default())
+            }
+            ExportFormat::Csv => csv_line(&[
+                todo.id.as_str(),
+                todo.title.as_str(),
+                todo.description.as_deref().unwrap_or(""),
+                if todo.completed { "true" } else { "false" },
+                todo.position.as_str(),
+                &todo.due_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
+                &todo.priority.map(|p| p.to_string()).unwrap_or_default(),
+                &todo.created_at.to_rfc3339(),
+                &todo.updated_at.to_rfc3339(),
+            ]),
+            ExportFormat::Md => markdown_item(todo),
+            ExportFormat::Todotxt => todotxt_line(todo),
+        }
+    }
+
+    // Written after the last todo
+    pub fn footer(self, count: usize) -> String {
+        match self {
+            ExportFormat::Json if count == 0 => "]\n".to_string(),
+            ExportFormat::Json => "\n]\n".to_string(),
+            ExportFormat::Md if count == 0 => "_No todos._\n".to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+// A ZIP archive of `(file name, content)` pairs, for the account data export
+pub fn zip_archive(files: &[(&str, Vec<u8>)]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for (name, content) in files {
+        writer.start_file(*name, options)?;
+        writer.write_all(content)?;
+    }
+
+    Ok(writer.finish()?.into_inner())
+}
+
+fn csv_line(fields: &[&str]) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let _ = writer.write_record(fields);
+    writer
+        .into_inner()
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .unwrap_or_default()
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn markdown_item(todo: &Todo) -> String {
+    let mut item = format!(
+        "- [{}] {}\n",
+        if todo.completed { "x" } else { " " },
+        single_line(&todo.title)
+    );
+
+    if let Some(description) = todo.description.as_deref().filter(|d| !d.trim().is_empty()) {
+        for line in description.lines() {
+            item.push_str(&format!("  {}\n", line));
+        }
+    }
+
+    item
+}
+
+// http://todotxt.org: "x <completion date> <creation date> <text>" for done tasks,
+// "(A) <creation date> <text>" otherwise; priorities 1-9 map onto A-I and the due
+// date is written as a `due:` tag
+fn todotxt_line(todo: &Todo) -> String {
+    let created = todo.created_at.format("%Y-%m-%d");
+    let mut text = single_line(&todo.title);
+    if let Some(due_at) = todo.due_at {
+        text.push_str(&format!(" due:{}", due_at.format("%Y-%m-%d")));
+    }
+
+    if todo.completed {
+        format!("x {} {} {}\n", todo.updated_at.format("%Y-%m-%d"), created, text)
+    } else {
+        match todo.priority.and_then(|p| u8::try_from(p - 1).ok()) {
+            Some(p) => format!("({}) {} {}\n", (b'A' + p) as char, created, text),
+            None => format!("{} {}\n", created, text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn todo(title: &str) -> Todo {
+        let created_at = Utc.with_ymd_and_hms(2026, 1, 2, 9, 30, 0).unwrap();
+        Todo {
+            id: "todo-1".to_string(),
+            user_id: "user-1".to_string(),
+            title: title.to_string(),
+            description: None,
+            completed: false,
+            position: "V".to_string(),
+            due_at: None,
+            priority: None,
+            remind_at: None,
+            created_at,
+            updated_at: created_at,
+            deleted_at: None,
+        }
+    }
+
+    fn export(format: ExportFormat, todos: &[Todo]) -> String {
+        let rows: String = todos.iter().enumerate().map(|(index, todo)| format.row(todo, index)).collect();
+        format!("{}{}{}", format.header(), rows, format.footer(todos.len()))
+    }
+
+    #[test]
+    fn json_is_an_array_of_todos() {
+        assert_eq!(serde_json::from_str::<Vec<Todo>>(&export(ExportFormat::Json, &[])).unwrap().len(), 0);
+        let todos = [todo("first"), todo("second")];
+        let parsed: Vec<Todo> = serde_json::from_str(&export(ExportFormat::Json, &todos)).unwrap();
+        assert_eq!(parsed.iter().map(|todo| todo.title.as_str()).collect::<Vec<_>>(), ["first", "second"]);
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        let mut todo = todo("Milk, eggs");
+        todo.description = Some("The \"good\" eggs\non two lines".to_string());
+        todo.priority = Some(2);
+        let mut reader = csv::Reader::from_reader(Cursor::new(export(ExportFormat::Csv, &[todo])));
+        assert_eq!(reader.headers().unwrap().iter().collect::<Vec<_>>(), CSV_COLUMNS);
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(&record[1], "Milk, eggs");
+        assert_eq!(&record[2], "The \"good\" eggs\non two lines");
+        assert_eq!(&record[6], "2");
+    }
+
+    #[test]
+    fn markdown_is_a_task_list() {
+        assert_eq!(export(ExportFormat::Md, &[]), "# Todos\n\n_No todos._\n");
+        let mut done = todo("Water\nthe plants");
+        done.completed = true;
+        done.description = Some("The ones\non the balcony".to_string());
+        assert_eq!(
+            export(ExportFormat::Md, &[done, todo("Feed the cat")]),
+            "# Todos\n\n- [x] Water the plants\n  The ones\n  on the balcony\n- [ ] Feed the cat\n"
+        );
+    }
+
+    #[test]
+    fn todotxt_has_priorities_dates_and_completion() {
+        let mut urgent = todo("Pay rent");
+        urgent.priority = Some(1);
+        urgent.due_at = Some(Utc.with_ymd_and_hms(2026, 2, 1, 0, 0, 0).unwrap());
+        let mut done = todo("Feed the cat");
+        done.completed = true;
+        done.priority = Some(3);
+        done.updated_at = Utc.with_ymd_and_hms(2026, 1, 5, 8, 0, 0).unwrap();
+        assert_eq!(
+            export(ExportFormat::Todotxt, &[urgent, done, todo("Call mum")]),
+            "(A) 2026-01-02 Pay rent due:2026-02-01\nx 2026-01-05 2026-01-02 Feed the cat\n2026-01-02 Call mum\n"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use todo_api_types::Todo;
+use uuid::Uuid;
+
+use crate::{db::{self, Transaction}, webhooks};
+
+pub const EVENT_CREATED: &str = "created";
+pub const EVENT_UPDATED: &str = "updated";
+pub const EVENT_COMPLETED: &str = "completed";
+pub const EVENT_UNCOMPLETED: &str = "uncompleted";
+pub const EVENT_DELETED: &str = "deleted";
+pub const EVENT_RESTORED: &str = "restored";
+
+// Field-level before/after values, keyed by field name
+pub fn diff_todos(before: &Todo, after: &Todo) -> Value {
+    let mut changes = Map::new();
+
+    if before.title != after.title {
+        changes.insert("title".to_string(), json!({ "before": before.title, "after": after.title }));
+    }
+    if before.description != after.description {
+        changes.insert(
+            "description".to_string(),
+            json!({ "before": before.description, "after": after.description }),
+        );
+    }
+    if before.completed != after.completed {
+        changes.insert(
+            "completed".to_string(),
+            json!({ "before": before.completed, "after": after.completed }),
+        );
+    }
+    if before.due_at != after.due_at {
+        changes.insert("due_at".to_string(), json!({ "before": before.due_at, "after": after.due_at }));
+    }
+    if before.priority != after.priority {
+        changes.insert(
+            "priority".to_string(),
+            json!({ "before": before.priority, "after": after.priority }),
+        );
+    }
+    if before.remind_at != after.remind_at {
+        changes.insert(
+            "remind_at".to_string(),
+            json!({ "before": before.remind_at, "after": after.remind_at }),
+        );
+    }
+
+    Value::Object(changes)
+}
+
+// Changes recorded when a todo is created: every field goes from nothing to its initial value
+pub fn initial_values(todo: &Todo) -> Value {
+    json!({
+        "title": { "before": null, "after": todo.title },
+        "description": { "before": null, "after": todo.description },
+        "completed": { "before": null, "after": todo.completed },
+        "due_at": { "before": null, "after": todo.due_at },
+        "priority": { "before": null, "after": todo.priority },
+        "remind_at": { "before": null, "after": todo.remind_at },
+    })
+}
+
+// Saves that change nothing leave no trace in the history
+pub fn has_changes(changes: &Value) -> bool {
+    changes.as_object().is_some_and(|fields| !fields.is_empty())
+}
+
+pub fn update_event_type(before: &Todo, after: &Todo) -> &'static str {
+    match (before.completed, after.completed) {
+        (false, true) => EVENT_COMPLETED,
+        (true, false) => EVENT_UNCOMPLETED,
+        _ => EVENT_UPDATED,
+    }
+}
+
+// Also queues webhook deliveries, so every recorded change is announced
+pub async fn record_event(
+    tx: &mut Transaction<'_>,
+    todo: &Todo,
+    actor_id: &str,
+    event_type: &str,
+    changes: Value,
+    at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    webhooks::enqueue(tx, todo, event_type, &changes, at).await?;
+    record_unannounced_event(tx, todo, actor_id, event_type, changes, at).await
+}
+
+// For imports, which can create thousands of todos at once: they are kept in
+// the history, but webhooks aren't told about each of them
+pub async fn record_unannounced_event(
+    tx: &mut Transaction<'_>,
+    todo: &Todo,
+    actor_id: &str,
+    event_type: &str,
+    changes: Value,
+    at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    db::query(
+        "INSERT INTO todo_events (id, todo_id, user_id, actor_id, event_type, changes, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&todo.id)
+    .bind(&todo.user_id)
+    .bind(actor_id)
+    .bind(event_type)
+    .bind(changes)
+    .bind(at)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
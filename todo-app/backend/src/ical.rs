@@ -0,0 +1,411 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use todo_api_types::Todo;
+
+pub const CONTENT_TYPE: &str = "text/calendar; charset=utf-8";
+
+// RFC 5545 section 3.1: lines longer than 75 octets are folded
+const MAX_LINE_OCTETS: usize = 75;
+
+// Every todo becomes a VTODO; todos with a due date are also published as a
+// zero-length VEVENT so calendar apps without task support still show them
+pub fn calendar(todos: &[Todo], now: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    line(&mut out, "BEGIN:VCALENDAR");
+    line(&mut out, "VERSION:2.0");
+    line(&mut out, "PRODID:-//todo-app//todos//EN");
+    line(&mut out, "CALSCALE:GREGORIAN");
+    line(&mut out, "X-WR-CALNAME:Todos");
+
+    for todo in todos {
+        vtodo(&mut out, todo, &todo.id, now);
+        if let Some(due_at) = todo.due_at {
+            vevent(&mut out, todo, due_at, now);
+        }
+    }
+
+    line(&mut out, "END:VCALENDAR");
+    out
+}
+
+// A single VCALENDAR holding one VTODO, as served for a CalDAV resource, whose
+// UID may be one the client chose
+pub fn todo_calendar(todo: &Todo, uid: &str, now: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    line(&mut out, "BEGIN:VCALENDAR");
+    line(&mut out, "VERSION:2.0");
+    line(&mut out, "PRODID:-//todo-app//todos//EN");
+    vtodo(&mut out, todo, uid, now);
+    line(&mut out, "END:VCALENDAR");
+    out
+}
+
+// The fields of a VTODO uploaded by a CalDAV client that map onto a todo
+#[derive(Debug)]
+pub struct ParsedTodo {
+    pub uid: Option<String>,
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<i16>,
+}
+
+// Reads the first VTODO of a calendar object; None if there isn't one. Floating
+// times, which have no time zone, are read in `timezone`, the user's
+pub fn parse_vtodo(content: &str, timezone: Tz) -> Option<ParsedTodo> {
+    let mut todo = ParsedTodo {
+        uid: None,
+        title: String::new(),
+        description: None,
+        completed: false,
+        due_at: None,
+        priority: None,
+    };
+    let mut found = false;
+    // Depth of components nested inside the VTODO, e.g. VALARM
+    let mut nested = 0;
+
+    for content_line in unfold(content) {
+        let Some((name_and_params, value)) = content_line.split_once(':') else {
+            continue;
+        };
+        let mut parts = name_and_params.split(';');
+        let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let mut is_date = false;
+        let mut tzid = None;
+        for param in parts {
+            match param.split_once('=') {
+                Some((key, value)) if key.eq_ignore_ascii_case("VALUE") => is_date = value.eq_ignore_ascii_case("DATE"),
+                Some((key, value)) if key.eq_ignore_ascii_case("TZID") => tzid = Some(value.trim_matches('"')),
+                _ => {}
+            }
+        }
+
+        if !found {
+            if name == "BEGIN" && value.eq_ignore_ascii_case("VTODO") {
+                found = true;
+            }
+            continue;
+        }
+
+        match name.as_str() {
+            "BEGIN" => nested += 1,
+            "END" if nested > 0 => nested -= 1,
+            "END" => return Some(todo),
+            _ if nested > 0 => {}
+            "UID" => todo.uid = Some(value.trim().to_string()).filter(|uid| !uid.is_empty()),
+            "SUMMARY" => todo.title = unescape_text(value),
+            "DESCRIPTION" => todo.description = Some(unescape_text(value)).filter(|d| !d.is_empty()),
+            "STATUS" => todo.completed |= value.eq_ignore_ascii_case("COMPLETED"),
+            "COMPLETED" => todo.completed = true,
+            "PRIORITY" => todo.priority = value.trim().parse().ok().filter(|p| (1..=9).contains(p)),
+            "DUE" => todo.due_at = parse_date_time(value.trim(), is_date, zone(tzid, timezone)),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+// RFC 5545 section 3.1: a CRLF followed by a space or tab continues the previous line
+fn unfold(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in content.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines
+}
+
+// The zone a TZID parameter names, or `timezone` for floating times. TZIDs are
+// looked up in the IANA database rather than the calendar's VTIMEZONEs, so ones
+// it doesn't know, such as Outlook's "W. Europe Standard Time", count as floating
+fn zone(tzid: Option<&str>, timezone: Tz) -> Tz {
+    tzid.and_then(|tzid| tzid.trim_start_matches('/').parse().ok()).unwrap_or(timezone)
+}
+
+// Dates are midnight UTC, as for imported dates; times ending in "Z" are UTC,
+// and others are local times in `tz`
+fn parse_date_time(value: &str, is_date: bool, tz: Tz) -> Option<DateTime<Utc>> {
+    if is_date || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)
+            .map(|d| d.and_utc());
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok().map(|d| d.and_utc());
+    }
+    let local = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    match tz.from_local_datetime(&local).earliest() {
+        Some(at) => Some(at.with_timezone(&Utc)),
+        // RFC 5545 section 3.3.5: a time skipped by a clock change is read with
+        // the offset from before the change
+        None => {
+            let before = tz.offset_from_utc_datetime(&(local - Duration::days(1))).fix();
+            Some((local - Duration::seconds(before.local_minus_utc().into())).and_utc())
+        }
+    }
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+fn vtodo(out: &mut String, todo: &Todo, uid: &str, now: DateTime<Utc>) {
+    line(out, "BEGIN:VTODO");
+    line(out, &format!("UID:{}", uid));
+    line(out, &format!("DTSTAMP:{}", timestamp(now)));
+    line(out, &format!("CREATED:{}", timestamp(todo.created_at)));
+    line(out, &format!("LAST-MODIFIED:{}", timestamp(todo.updated_at)));
+    common_properties(out, todo);
+    if let Some(due_at) = todo.due_at {
+        line(out, &format!("DUE:{}", timestamp(due_at)));
+    }
+    if todo.completed {
+        line(out, "STATUS:COMPLETED");
+        line(out, &format!("COMPLETED:{}", timestamp(todo.updated_at)));
+        line(out, "PERCENT-COMPLETE:100");
+    } else {
+        line(out, "STATUS:NEEDS-ACTION");
+    }
+    line(out, "END:VTODO");
+}
+
+fn vevent(out: &mut String, todo: &Todo, due_at: DateTime<Utc>, now: DateTime<Utc>) {
+    line(out, "BEGIN:VEVENT");
+    line(out, &format!("UID:{}-due", todo.id));
+    line(out, &format!("DTSTAMP:{}", timestamp(now)));
+    line(out, &format!("DTSTART:{}", timestamp(due_at)));
+    common_properties(out, todo);
+    line(out, "TRANSP:TRANSPARENT");
+    line(out, "END:VEVENT");
+}
+
+fn common_properties(out: &mut String, todo: &Todo) {
+    line(out, &format!("SUMMARY:{}", escape_text(&todo.title)));
+    if let Some(description) = todo.description.as_deref().filter(|d| !d.is_empty()) {
+        line(out, &format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    if let Some(priority) = todo.priority {
+        line(out, &format!("PRIORITY:{}", priority));
+    }
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// RFC 5545 section 3.3.11
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Appends a CRLF-terminated content line, folding it without splitting a UTF-8 character
+fn line(out: &mut String, content: &str) {
+    let mut width = 0;
+    for c in content.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            // The leading space of a continuation line counts towards its length
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn todo(title: &str) -> Todo {
+        let created_at = Utc.with_ymd_and_hms(2026, 1, 2, 9, 30, 0).unwrap();
+        Todo {
+            id: "todo-1".to_string(),
+            user_id: "user-1".to_string(),
+            title: title.to_string(),
+            description: None,
+            completed: false,
+            position: "V".to_string(),
+            due_at: None,
+            priority: None,
+            remind_at: None,
+            created_at,
+            updated_at: created_at,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn continuation_lines_are_joined() {
+        assert_eq!(unfold("SUMMARY:Buy\r\n  milk\r\n\tand eggs\r\nUID:1\r\n"), ["SUMMARY:Buy milkand eggs", "UID:1", ""]);
+        // Bare LFs, as some clients send
+        assert_eq!(unfold("A:1\n B\nC:2"), ["A:1B", "C:2"]);
+        // A continuation with nothing to continue is a line of its own
+        assert_eq!(unfold(" A:1"), [" A:1"]);
+    }
+
+    #[test]
+    fn escaped_text_is_unescaped() {
+        assert_eq!(unescape_text(r"Milk\, eggs\; bread\nand \\ butter\N"), "Milk, eggs; bread\nand \\ butter\n");
+        // A trailing backslash escapes nothing
+        assert_eq!(unescape_text("end\\"), "end");
+    }
+
+    #[test]
+    fn escaping_round_trips() {
+        for text in ["Milk, eggs; bread", "two\nlines", r"a \ backslash", r"\n is not a newline", ""] {
+            assert_eq!(unescape_text(&escape_text(text)), text);
+        }
+        assert_eq!(escape_text("a,b;c\r\nd\\"), r"a\,b\;c\nd\\");
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_octets() {
+        let mut out = String::new();
+        line(&mut out, &"a".repeat(75));
+        assert_eq!(out, format!("{}\r\n", "a".repeat(75)));
+
+        let mut out = String::new();
+        line(&mut out, &"a".repeat(200));
+        let lines: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.iter().map(|line| line.len()).collect::<Vec<_>>(), [75, 75, 52]);
+        assert_eq!(unfold(&out)[0], "a".repeat(200));
+    }
+
+    #[test]
+    fn folding_never_splits_a_character() {
+        // "€" is three octets: after "SUMMARY:" 22 of them fill 74, and the
+        // 23rd would cross octet 75
+        let content = format!("SUMMARY:{}", "€".repeat(60));
+        let mut out = String::new();
+        line(&mut out, &content);
+        let lines: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.iter().map(|line| line.len()).collect::<Vec<_>>(), [74, 73, 43]);
+        assert_eq!(unfold(&out)[0], content);
+    }
+
+    #[test]
+    fn calendars_parse_back() {
+        let mut todo = todo("Milk, eggs; and a very long title that needs to be folded onto a second line €€€");
+        todo.description = Some("two\nlines".to_string());
+        todo.priority = Some(3);
+        todo.due_at = Some(Utc.with_ymd_and_hms(2026, 3, 4, 17, 0, 0).unwrap());
+        todo.completed = true;
+        let parsed = parse_vtodo(&todo_calendar(&todo, "client-uid", Utc::now()), Tz::Asia__Tokyo).unwrap();
+        assert_eq!(parsed.uid.as_deref(), Some("client-uid"));
+        assert_eq!(parsed.title, todo.title);
+        assert_eq!(parsed.description, todo.description);
+        assert_eq!(parsed.priority, Some(3));
+        assert_eq!(parsed.due_at, todo.due_at);
+        assert!(parsed.completed);
+    }
+
+    #[test]
+    fn alarms_inside_a_todo_are_skipped() {
+        let content = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VTODO",
+            "UID:1",
+            "SUMMARY:Call mum",
+            "BEGIN:VALARM",
+            "ACTION:DISPLAY",
+            "DESCRIPTION:Reminder",
+            "TRIGGER:-PT15M",
+            "END:VALARM",
+            "PRIORITY:1",
+            "END:VTODO",
+            "BEGIN:VTODO",
+            "SUMMARY:Second todo",
+            "END:VTODO",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let parsed = parse_vtodo(&content, Tz::UTC).unwrap();
+        assert_eq!(parsed.title, "Call mum");
+        // The alarm's DESCRIPTION isn't the todo's, and the END:VALARM doesn't end the todo
+        assert_eq!(parsed.description, None);
+        assert_eq!(parsed.priority, Some(1));
+    }
+
+    #[test]
+    fn calendars_without_a_todo_are_rejected() {
+        assert!(parse_vtodo("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Party\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n", Tz::UTC).is_none());
+        // Nor is an unterminated one
+        assert!(parse_vtodo("BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nSUMMARY:Party\r\n", Tz::UTC).is_none());
+    }
+
+    fn due(property: &str, timezone: Tz) -> Option<DateTime<Utc>> {
+        let content = format!("BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nSUMMARY:Pay rent\r\n{}\r\nEND:VTODO\r\nEND:VCALENDAR\r\n", property);
+        parse_vtodo(&content, timezone).unwrap().due_at
+    }
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> Option<DateTime<Utc>> {
+        Some(Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn utc_times_ignore_the_users_zone() {
+        assert_eq!(due("DUE:20260115T090000Z", Tz::Europe__Berlin), utc(2026, 1, 15, 9, 0));
+        assert_eq!(due("DUE;TZID=America/New_York:20260115T090000Z", Tz::UTC), utc(2026, 1, 15, 9, 0));
+    }
+
+    #[test]
+    fn times_with_a_tzid_are_read_in_that_zone() {
+        assert_eq!(due("DUE;TZID=America/New_York:20260115T090000", Tz::Europe__Berlin), utc(2026, 1, 15, 14, 0));
+        // Summer time
+        assert_eq!(due("DUE;TZID=America/New_York:20260715T090000", Tz::UTC), utc(2026, 7, 15, 13, 0));
+        assert_eq!(due("DUE;VALUE=DATE-TIME;TZID=\"/Europe/Paris\":20260115T090000", Tz::UTC), utc(2026, 1, 15, 8, 0));
+        // Zones the time zone database doesn't know are floating
+        assert_eq!(due("DUE;TZID=W. Europe Standard Time:20260115T090000", Tz::Asia__Tokyo), utc(2026, 1, 15, 0, 0));
+    }
+
+    #[test]
+    fn floating_times_are_read_in_the_users_zone() {
+        assert_eq!(due("DUE:20260115T090000", Tz::UTC), utc(2026, 1, 15, 9, 0));
+        assert_eq!(due("DUE:20260115T090000", Tz::Europe__Berlin), utc(2026, 1, 15, 8, 0));
+        // Repeated when clocks go back: the first of the two
+        assert_eq!(due("DUE:20261025T023000", Tz::Europe__Berlin), utc(2026, 10, 25, 0, 30));
+        // Skipped when clocks go forward: read with the winter offset
+        assert_eq!(due("DUE:20260329T023000", Tz::Europe__Berlin), utc(2026, 3, 29, 1, 30));
+    }
+
+    #[test]
+    fn dates_are_midnight_utc() {
+        assert_eq!(due("DUE;VALUE=DATE:20260115", Tz::Asia__Tokyo), utc(2026, 1, 15, 0, 0));
+        assert_eq!(due("DUE:20260115", Tz::Asia__Tokyo), utc(2026, 1, 15, 0, 0));
+        assert_eq!(due("DUE:tomorrow", Tz::UTC), None);
+    }
+}
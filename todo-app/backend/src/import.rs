@@ -0,0 +1,441 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use todo_api_types::{CsvMapping, ImportError, ImportedTodo};
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Todotxt,
+    Csv,
+    Todoist,
+    Taskwarrior,
+}
+
+impl ImportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(value.trim().to_lowercase())).ok()
+    }
+}
+
+pub struct ParsedImport {
+    pub todos: Vec<ImportedTodo>,
+    pub errors: Vec<ImportError>,
+}
+
+pub fn parse(
+    format: ImportFormat,
+    content: &str,
+    mapping: Option<&CsvMapping>,
+) -> Result<ParsedImport, String> {
+    match format {
+        ImportFormat::Todotxt => Ok(parse_todotxt(content)),
+        ImportFormat::Csv => {
+            let mapping = mapping.ok_or("A column mapping is required for CSV imports")?;
+            parse_csv(content, mapping)
+        }
+        ImportFormat::Todoist => parse_todoist(content),
+        ImportFormat::Taskwarrior => parse_taskwarrior(content),
+    }
+}
+
+// Titles are compared case-insensitively with collapsed whitespace
+pub fn duplicate_key(title: &str) -> String {
+    title.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// Splits imported todos into new ones and duplicates: those whose title
+// matches an existing todo, or an earlier one in the same file
+pub fn split_duplicates(existing_titles: &[String], todos: Vec<ImportedTodo>) -> (Vec<ImportedTodo>, Vec<ImportedTodo>) {
+    let mut seen: HashSet<String> = existing_titles.iter().map(|title| duplicate_key(title)).collect();
+    todos.into_iter().partition(|todo| seen.insert(duplicate_key(&todo.title)))
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+fn parse_date(token: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(token, "%Y-%m-%d").ok()
+}
+
+fn is_date(token: &str) -> bool {
+    parse_date(token).is_some()
+}
+
+fn start_of_day(date: NaiveDate) -> Option<DateTime<Utc>> {
+    date.and_hms_opt(0, 0, 0).map(|start| start.and_utc())
+}
+
+// Priorities from formats with three levels: high, medium and low
+fn three_level_priority(level: usize) -> i16 {
+    [1, 5, 9][level]
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "true" | "1" | "yes" | "y" | "x" | "done" | "completed"
+    )
+}
+
+// http://todotxt.org: "x (A) 2024-01-02 2024-01-01 text due:2024-02-01"; completion
+// marker, priority and dates are optional. Priorities A-I map onto 1-9 and a `due:`
+// tag becomes the due date; the creation and completion dates are dropped
+fn parse_todotxt(content: &str) -> ParsedImport {
+    let mut todos = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let mut rest = line.trim();
+        if rest.is_empty() {
+            continue;
+        }
+
+        let completed = rest.starts_with("x ");
+        if completed {
+            rest = rest[2..].trim_start();
+        }
+
+        let mut priority = None;
+        let bytes = rest.as_bytes();
+        if bytes.len() >= 4 && bytes[0] == b'(' && bytes[1].is_ascii_uppercase() && bytes[2] == b')' && bytes[3] == b' ' {
+            priority = Some(i16::from(bytes[1] - b'A') + 1).filter(|p| *p <= 9);
+            rest = rest[4..].trim_start();
+        }
+
+        for _ in 0..2 {
+            let (token, remainder) = rest.split_once(' ').unwrap_or((rest, ""));
+            if !is_date(token) {
+                break;
+            }
+            rest = remainder.trim_start();
+        }
+
+        let mut due_at = None;
+        let words: Vec<&str> = rest
+            .split_whitespace()
+            .filter(|word| match word.strip_prefix("due:").and_then(parse_date) {
+                Some(date) => {
+                    due_at = start_of_day(date);
+                    false
+                }
+                None => true,
+            })
+            .collect();
+        if words.is_empty() {
+            errors.push(ImportError { line: index + 1, message: "Missing title".to_string() });
+            continue;
+        }
+
+        todos.push(ImportedTodo {
+            title: words.join(" "),
+            description: None,
+            completed,
+            due_at,
+            priority,
+        });
+    }
+
+    ParsedImport { todos, errors }
+}
+
+struct CsvRows {
+    headers: HashMap<String, usize>,
+    // (line number, record)
+    rows: Vec<(usize, csv::StringRecord)>,
+}
+
+fn csv_rows(content: &str) -> Result<CsvRows, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let headers: HashMap<String, usize> = reader
+        .headers()
+        .map_err(|e| format!("Invalid CSV header: {}", e))?
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.trim().to_string(), index))
+        .collect();
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        // Line 1 is the header
+        let line = index + 2;
+        let record = record.map_err(|e| format!("Invalid CSV on line {}: {}", line, e))?;
+        rows.push((line, record));
+    }
+
+    Ok(CsvRows { headers, rows })
+}
+
+fn column(headers: &HashMap<String, usize>, name: &str) -> Result<usize, String> {
+    headers
+        .get(name)
+        .copied()
+        .ok_or_else(|| format!("Column '{}' not found", name))
+}
+
+fn parse_csv(content: &str, mapping: &CsvMapping) -> Result<ParsedImport, String> {
+    let CsvRows { headers, rows } = csv_rows(content)?;
+    let title_column = column(&headers, &mapping.title)?;
+    let description_column = mapping.description.as_deref().map(|name| column(&headers, name)).transpose()?;
+    let completed_column = mapping.completed.as_deref().map(|name| column(&headers, name)).transpose()?;
+
+    let mut todos = Vec::new();
+    let mut errors = Vec::new();
+
+    for (line, record) in rows {
+        let Some(title) = non_empty(record.get(title_column)) else {
+            errors.push(ImportError { line, message: "Missing title".to_string() });
+            continue;
+        };
+
+        todos.push(ImportedTodo {
+            title,
+            description: description_column.and_then(|index| non_empty(record.get(index))),
+            completed: completed_column
+                .and_then(|index| record.get(index))
+                .is_some_and(is_truthy),
+            due_at: None,
+            priority: None,
+        });
+    }
+
+    Ok(ParsedImport { todos, errors })
+}
+
+// Todoist's PRIORITY runs from 4, the highest, down to 1, which is no priority
+fn todoist_priority(value: &str) -> Option<i16> {
+    match value.trim() {
+        "4" => Some(three_level_priority(0)),
+        "3" => Some(three_level_priority(1)),
+        "2" => Some(three_level_priority(2)),
+        _ => None,
+    }
+}
+
+// Todoist's DATE is what was typed, such as "2026-01-15", "2026-01-15 09:00"
+// or "every monday"; only the first two are dates, read as UTC
+fn todoist_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|time| time.and_utc())
+        .or_else(|| parse_date(value).and_then(start_of_day))
+}
+
+// Todoist project CSV exports: TYPE,CONTENT,DESCRIPTION,PRIORITY,INDENT,AUTHOR,
+// RESPONSIBLE,DATE,... Only "task" rows become todos; sections and notes are
+// skipped. Tasks whose date isn't one are kept without it, and reported
+fn parse_todoist(content: &str) -> Result<ParsedImport, String> {
+    let CsvRows { headers, rows } = csv_rows(content)?;
+    let type_column = column(&headers, "TYPE")?;
+    let content_column = column(&headers, "CONTENT")?;
+    let description_column = headers.get("DESCRIPTION").copied();
+    let priority_column = headers.get("PRIORITY").copied();
+    let date_column = headers.get("DATE").copied();
+
+    let mut todos = Vec::new();
+    let mut errors = Vec::new();
+
+    for (line, record) in rows {
+        if record.get(type_column).map(str::trim) != Some("task") {
+            continue;
+        }
+
+        let Some(title) = non_empty(record.get(content_column)) else {
+            errors.push(ImportError { line, message: "Missing task content".to_string() });
+            continue;
+        };
+
+        let date = date_column.and_then(|index| non_empty(record.get(index)));
+        let due_at = date.as_deref().and_then(todoist_date);
+        if let (Some(date), None) = (&date, due_at) {
+            errors.push(ImportError { line, message: format!("Imported without its date \"{}\", which isn't one", date) });
+        }
+
+        todos.push(ImportedTodo {
+            title,
+            description: description_column.and_then(|index| non_empty(record.get(index))),
+            completed: false,
+            due_at,
+            priority: priority_column.and_then(|index| record.get(index)).and_then(todoist_priority),
+        });
+    }
+
+    Ok(ParsedImport { todos, errors })
+}
+
+#[derive(Deserialize)]
+struct TaskwarriorAnnotation {
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct TaskwarriorTask {
+    description: Option<String>,
+    status: Option<String>,
+    // Such as "20260115T090000Z"
+    due: Option<String>,
+    priority: Option<String>,
+    #[serde(default)]
+    annotations: Vec<TaskwarriorAnnotation>,
+}
+
+fn taskwarrior_priority(value: &str) -> Option<i16> {
+    match value {
+        "H" => Some(three_level_priority(0)),
+        "M" => Some(three_level_priority(1)),
+        "L" => Some(three_level_priority(2)),
+        _ => None,
+    }
+}
+
+// `task export` output: a JSON array of tasks; deleted tasks are skipped and
+// annotations become the description
+fn parse_taskwarrior(content: &str) -> Result<ParsedImport, String> {
+    let tasks: Vec<TaskwarriorTask> =
+        serde_json::from_str(content).map_err(|e| format!("Invalid Taskwarrior JSON: {}", e))?;
+
+    let mut todos = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, task) in tasks.into_iter().enumerate() {
+        let status = task.status.as_deref().unwrap_or("pending");
+        if status == "deleted" {
+            continue;
+        }
+
+        let Some(title) = non_empty(task.description.as_deref()) else {
+            errors.push(ImportError { line: index + 1, message: "Missing task description".to_string() });
+            continue;
+        };
+
+        let annotations: Vec<String> = task
+            .annotations
+            .into_iter()
+            .map(|annotation| annotation.description)
+            .collect();
+
+        let due_at = match task.due.as_deref() {
+            Some(due) => match NaiveDateTime::parse_from_str(due, "%Y%m%dT%H%M%SZ") {
+                Ok(due) => Some(due.and_utc()),
+                Err(_) => {
+                    errors.push(ImportError { line: index + 1, message: format!("Invalid due date \"{}\"", due) });
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        todos.push(ImportedTodo {
+            title,
+            description: non_empty(Some(&annotations.join("\n"))),
+            completed: status == "completed",
+            due_at,
+            priority: task.priority.as_deref().and_then(taskwarrior_priority),
+        });
+    }
+
+    Ok(ParsedImport { todos, errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> Option<DateTime<Utc>> {
+        Some(Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn todotxt_lines_keep_their_completion_priority_and_due_date() {
+        let parsed = parse_todotxt("x (B) 2026-01-03 2026-01-01 Pay rent due:2026-02-01\n\n(C) Call mum +family\nplain task");
+        assert!(parsed.errors.is_empty());
+        let todo = &parsed.todos[0];
+        assert_eq!(todo.title, "Pay rent");
+        assert!(todo.completed);
+        assert_eq!(todo.priority, Some(2));
+        assert_eq!(todo.due_at, utc(2026, 2, 1, 0, 0));
+        assert_eq!(parsed.todos[1].title, "Call mum +family");
+        assert_eq!(parsed.todos[1].priority, Some(3));
+        assert_eq!(parsed.todos[2].title, "plain task");
+        assert_eq!(parsed.todos[2].priority, None);
+    }
+
+    #[test]
+    fn todotxt_lines_without_a_title_are_errors() {
+        let parsed = parse_todotxt("Buy milk\ndue:2026-01-01\n(A) 2026-01-01\nx 2026-01-02");
+        assert_eq!(parsed.todos.len(), 1);
+        assert_eq!(parsed.errors.iter().map(|error| error.line).collect::<Vec<_>>(), [2, 3, 4]);
+        assert_eq!(parsed.errors[0].message, "Missing title");
+    }
+
+    #[test]
+    fn csv_columns_are_read_by_the_mapping() {
+        let mapping = CsvMapping { title: "Task".to_string(), description: Some("Notes".to_string()), completed: Some("Done".to_string()) };
+        let parsed = parse_csv("Task,Notes,Done\nBuy milk,\"Two, maybe three\",yes\n,no title,no\nCall mum,,", &mapping).unwrap();
+        assert_eq!(parsed.todos.len(), 2);
+        assert_eq!(parsed.todos[0].description.as_deref(), Some("Two, maybe three"));
+        assert!(parsed.todos[0].completed && !parsed.todos[1].completed);
+        assert_eq!(parsed.errors, [ImportError { line: 3, message: "Missing title".to_string() }]);
+
+        let mapping = CsvMapping { title: "Name".to_string(), description: None, completed: None };
+        assert!(parse_csv("Task\nBuy milk", &mapping).is_err());
+    }
+
+    #[test]
+    fn todoist_tasks_keep_their_priority_and_date() {
+        let content = "TYPE,CONTENT,DESCRIPTION,PRIORITY,INDENT,AUTHOR,RESPONSIBLE,DATE,DATE_LANG,TIMEZONE\n\
+            section,Errands,,,,,,,,\n\
+            task,Pay rent,By transfer,4,1,,,2026-02-01,en,Europe/Berlin\n\
+            task,Call mum,,3,1,,,2026-02-01 18:30,en,Europe/Berlin\n\
+            task,Water the plants,,1,1,,,every monday,en,Europe/Berlin\n\
+            note,A note,,,,,,,,\n\
+            task,,,1,1,,,,,";
+        let parsed = parse_todoist(content).unwrap();
+        let titles: Vec<&str> = parsed.todos.iter().map(|todo| todo.title.as_str()).collect();
+        assert_eq!(titles, ["Pay rent", "Call mum", "Water the plants"]);
+        assert_eq!(parsed.todos[0].description.as_deref(), Some("By transfer"));
+        assert_eq!(parsed.todos.iter().map(|todo| todo.priority).collect::<Vec<_>>(), [Some(1), Some(5), None]);
+        assert_eq!(parsed.todos[0].due_at, utc(2026, 2, 1, 0, 0));
+        assert_eq!(parsed.todos[1].due_at, utc(2026, 2, 1, 18, 30));
+        assert_eq!(parsed.todos[2].due_at, None);
+        let lines: Vec<usize> = parsed.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, [5, 7]);
+        assert!(parsed.errors[0].message.contains("every monday"));
+    }
+
+    #[test]
+    fn taskwarrior_tasks_keep_their_priority_and_due_date() {
+        let content = r#"[
+            {"description": "Pay rent", "status": "pending", "priority": "H", "due": "20260201T090000Z",
+             "annotations": [{"entry": "20260101T000000Z", "description": "By transfer"}]},
+            {"description": "Call mum", "status": "completed", "priority": "L"},
+            {"description": "Old", "status": "deleted"},
+            {"description": "Broken", "due": "tomorrow"},
+            {"status": "pending"}
+        ]"#;
+        let parsed = parse_taskwarrior(content).unwrap();
+        assert_eq!(parsed.todos.len(), 2);
+        assert_eq!(parsed.todos[0].priority, Some(1));
+        assert_eq!(parsed.todos[0].due_at, utc(2026, 2, 1, 9, 0));
+        assert_eq!(parsed.todos[0].description.as_deref(), Some("By transfer"));
+        assert!(parsed.todos[1].completed);
+        assert_eq!(parsed.todos[1].priority, Some(9));
+        assert_eq!(parsed.errors.iter().map(|error| error.line).collect::<Vec<_>>(), [4, 5]);
+        assert!(parse_taskwarrior("not json").is_err());
+    }
+
+    #[test]
+    fn duplicates_ignore_case_and_spacing() {
+        let todos = parse_todotxt("Buy   Milk\nCall mum\ncall  MUM\nPay rent").todos;
+        let (created, duplicates) = split_duplicates(&["buy milk".to_string()], todos);
+        assert_eq!(created.iter().map(|todo| todo.title.as_str()).collect::<Vec<_>>(), ["Call mum", "Pay rent"]);
+        assert_eq!(duplicates.iter().map(|todo| todo.title.as_str()).collect::<Vec<_>>(), ["Buy Milk", "call MUM"]);
+    }
+}
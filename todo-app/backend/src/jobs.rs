@@ -0,0 +1,434 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{config::Config, db::{self, Pool}, digest, mailer::Mailer, profile, push::Vapid, reminders, webhooks};
+
+pub const JOB_PURGE_TRASH: &str = "purge_trash";
+pub const JOB_PURGE_ACCOUNTS: &str = "purge_accounts";
+pub const JOB_PRUNE_JOBS: &str = "prune_jobs";
+pub const JOB_DELIVER_WEBHOOK: &str = "deliver_webhook";
+pub const JOB_DISPATCH_REMINDERS: &str = "dispatch_reminders";
+pub const JOB_SEND_REMINDER_EMAIL: &str = "send_reminder_email";
+pub const JOB_SEND_REMINDER_PUSH: &str = "send_reminder_push";
+pub const JOB_DISPATCH_DIGESTS: &str = "dispatch_digests";
+pub const JOB_SEND_DIGEST: &str = "send_digest";
+pub const JOB_SEND_EMAIL_VERIFICATION: &str = "send_email_verification";
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+pub const STATUS_FAILED: &str = "failed";
+
+// Cron expressions with a leading seconds field: sec min hour day-of-month month day-of-week
+const SCHEDULES: [(&str, &str); 5] = [
+    (JOB_DISPATCH_REMINDERS, "0 * * * * *"),
+    (JOB_DISPATCH_DIGESTS, "0 * * * * *"),
+    (JOB_PURGE_TRASH, "0 0 * * * *"),
+    (JOB_PURGE_ACCOUNTS, "0 0 * * * *"),
+    (JOB_PRUNE_JOBS, "0 30 3 * * *"),
+];
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(15);
+// A running job whose worker hasn't finished it after this long is assumed lost and run again
+const LOCK_TIMEOUT_SECS: i64 = 600;
+const SUCCEEDED_JOB_RETENTION_DAYS: i64 = 7;
+
+pub struct RetryPolicy {
+    pub max_attempts: i32,
+    pub initial_backoff: chrono::Duration,
+    pub max_backoff: chrono::Duration,
+}
+
+impl RetryPolicy {
+    // Exponential delay before the next try, after `attempts` failed ones
+    pub fn backoff(&self, attempts: i32) -> chrono::Duration {
+        let doubled = self.initial_backoff * (1 << (attempts - 1).clamp(0, 20));
+        doubled.min(self.max_backoff)
+    }
+}
+
+pub fn retry_policy(kind: &str) -> RetryPolicy {
+    match kind {
+        JOB_DELIVER_WEBHOOK => webhooks::RETRY_POLICY,
+        _ => RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: chrono::Duration::seconds(10),
+            max_backoff: chrono::Duration::minutes(10),
+        },
+    }
+}
+
+// What job handlers get to work with
+#[derive(Clone)]
+pub struct JobContext {
+    pub db: Pool,
+    pub http: reqwest::Client,
+    pub mailer: Arc<dyn Mailer>,
+    // None when Web Push isn't configured
+    pub vapid: Option<Arc<Vapid>>,
+    pub config: Arc<Config>,
+}
+
+pub struct Job {
+    pub id: String,
+    pub kind: String,
+    pub payload: Value,
+    // Including the current one
+    pub attempts: i32,
+    pub max_attempts: i32,
+}
+
+impl Job {
+    pub fn is_last_attempt(&self) -> bool {
+        self.attempts >= self.max_attempts
+    }
+
+    // A string the job was queued with; jobs without it fail rather than run
+    // with an empty one
+    pub fn payload_str(&self, key: &str) -> Result<&str, String> {
+        self.payload[key].as_str().ok_or_else(|| format!("missing {}", key))
+    }
+}
+
+// Accepts a pool or an open transaction, so jobs can be queued atomically with
+// the change that needs them
+pub async fn enqueue<'e, E: db::Executor<'e>>(
+    executor: E,
+    kind: &str,
+    payload: Value,
+    run_at: DateTime<Utc>,
+) -> Result<String, sqlx::Error> {
+    let id = Uuid::new_v4().to_string();
+
+    db::query(
+        "INSERT INTO jobs (id, kind, payload, max_attempts, run_at, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, NOW(), NOW())"
+    )
+    .bind(&id)
+    .bind(kind)
+    .bind(payload)
+    .bind(retry_policy(kind).max_attempts)
+    .bind(run_at)
+    .execute(executor)
+    .await?;
+
+    Ok(id)
+}
+
+// Starts the worker pool and the cron scheduler
+pub fn start(context: JobContext, workers: usize) {
+    for _ in 0..workers {
+        tokio::spawn(work(context.clone()));
+    }
+    tokio::spawn(schedule(context.db));
+}
+
+async fn work(context: JobContext) {
+    loop {
+        match claim(&context.db).await {
+            Ok(Some(job)) => run(&context, job).await,
+            Ok(None) => tokio::time::sleep(POLL_INTERVAL).await,
+            Err(err) => {
+                tracing::error!("Failed to claim a job: {}", err);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+// SKIP LOCKED lets any number of workers, in any number of server instances,
+// poll the same table without ever picking up the same job twice
+async fn claim(db: &Pool) -> Result<Option<Job>, sqlx::Error> {
+    let row = db::query(
+        "UPDATE jobs SET status = $1, attempts = attempts + 1, locked_at = NOW(), updated_at = NOW()
+         WHERE id = (
+             SELECT id FROM jobs
+             WHERE (status = $2 AND run_at <= NOW())
+                OR (status = $1 AND locked_at < $3)
+             ORDER BY run_at
+             LIMIT 1
+             FOR UPDATE SKIP LOCKED
+         )
+         RETURNING id, kind, payload, attempts, max_attempts"
+    )
+    .bind(STATUS_RUNNING)
+    .bind(STATUS_PENDING)
+    .bind(Utc::now() - chrono::Duration::seconds(LOCK_TIMEOUT_SECS))
+    .fetch_optional(db)
+    .await?;
+
+    Ok(row.map(|row| Job {
+        id: row.get("id"),
+        kind: row.get("kind"),
+        payload: row.get("payload"),
+        attempts: row.get("attempts"),
+        max_attempts: row.get("max_attempts"),
+    }))
+}
+
+async fn run(context: &JobContext, job: Job) {
+    let result = match job.kind.as_str() {
+        JOB_PURGE_TRASH => purge_trash(context).await,
+        JOB_PURGE_ACCOUNTS => purge_accounts(context).await,
+        JOB_PRUNE_JOBS => prune_jobs(context).await,
+        JOB_DELIVER_WEBHOOK => webhooks::deliver(context, &job).await,
+        JOB_DISPATCH_REMINDERS => reminders::dispatch(context).await,
+        JOB_SEND_REMINDER_EMAIL => reminders::send_email(context, &job).await,
+        JOB_SEND_REMINDER_PUSH => reminders::send_push(context, &job).await,
+        JOB_DISPATCH_DIGESTS => digest::dispatch(context).await,
+        JOB_SEND_DIGEST => digest::send(context, &job).await,
+        JOB_SEND_EMAIL_VERIFICATION => profile::send_email_verification(context, &job).await,
+        kind => Err(format!("Unknown job kind '{}'", kind)),
+    };
+
+    let now = Utc::now();
+    let update = match &result {
+        Ok(()) => db::query(
+            "UPDATE jobs SET status = $1, last_error = NULL, locked_at = NULL, updated_at = $2, finished_at = $2 WHERE id = $3"
+        )
+        .bind(STATUS_SUCCEEDED)
+        .bind(now)
+        .bind(&job.id),
+        Err(err) if job.is_last_attempt() => {
+            tracing::error!("Job {} ({}) failed for good: {}", job.id, job.kind, err);
+            db::query(
+                "UPDATE jobs SET status = $1, last_error = $2, locked_at = NULL, updated_at = $3, finished_at = $3 WHERE id = $4"
+            )
+            .bind(STATUS_FAILED)
+            .bind(err)
+            .bind(now)
+            .bind(&job.id)
+        }
+        Err(err) => {
+            tracing::warn!("Job {} ({}) failed on attempt {}: {}", job.id, job.kind, job.attempts, err);
+            let run_at = now + retry_policy(&job.kind).backoff(job.attempts);
+            db::query(
+                "UPDATE jobs SET status = $1, last_error = $2, locked_at = NULL, run_at = $3, updated_at = $4 WHERE id = $5"
+            )
+            .bind(STATUS_PENDING)
+            .bind(err)
+            .bind(run_at)
+            .bind(now)
+            .bind(&job.id)
+        }
+    };
+
+    if let Err(err) = update.execute(&context.db).await {
+        tracing::error!("Failed to record the outcome of job {}: {}", job.id, err);
+    }
+}
+
+// Enqueues each scheduled job once per occurrence, however many instances run
+async fn schedule(db: Pool) {
+    let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        for (kind, expression) in SCHEDULES {
+            if let Err(err) = enqueue_if_due(&db, kind, expression).await {
+                tracing::error!("Failed to schedule {} jobs: {}", kind, err);
+            }
+        }
+    }
+}
+
+async fn enqueue_if_due(db: &Pool, kind: &str, expression: &str) -> Result<(), sqlx::Error> {
+    let schedule = cron::Schedule::from_str(expression).expect("schedules are valid cron expressions");
+    let now = Utc::now();
+    let Some(next_run_at) = schedule.after(&now).next() else {
+        return Ok(());
+    };
+
+    let mut tx = db.begin().await?;
+
+    db::query("INSERT INTO scheduled_jobs (kind, next_run_at) VALUES ($1, $2) ON CONFLICT (kind) DO NOTHING")
+        .bind(kind)
+        .bind(next_run_at)
+        .execute(&mut *tx)
+        .await?;
+
+    let due = db::query(
+        "UPDATE scheduled_jobs SET next_run_at = $1, last_run_at = $2 WHERE kind = $3 AND next_run_at <= $2 RETURNING kind"
+    )
+    .bind(next_run_at)
+    .bind(now)
+    .bind(kind)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if due.is_some() {
+        enqueue(&mut *tx, kind, json!({}), now).await?;
+    }
+
+    tx.commit().await
+}
+
+// Permanently removes todos that have been in the trash longer than the retention period
+async fn purge_trash(context: &JobContext) -> Result<(), String> {
+    let cutoff = Utc::now() - chrono::Duration::days(context.config.jobs.trash_retention_days);
+    let purged = purge_trashed_before(&context.db, cutoff).await.map_err(|e| e.to_string())?;
+
+    if purged > 0 {
+        tracing::info!("Purged {} todos from trash", purged);
+    }
+    Ok(())
+}
+
+// Deletes the todos trashed before `cutoff`, with their history, and returns how many
+pub async fn purge_trashed_before(db: &Pool, cutoff: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    let result = db::query("DELETE FROM todos WHERE deleted_at IS NOT NULL AND deleted_at < $1")
+        .bind(cutoff)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+// Deletes the accounts whose grace period ran out; their data goes with them
+// through ON DELETE CASCADE
+async fn purge_accounts(context: &JobContext) -> Result<(), String> {
+    let result = db::query("DELETE FROM users WHERE deletion_scheduled_at <= $1")
+        .bind(Utc::now())
+        .execute(&context.db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if result.rows_affected() > 0 {
+        tracing::info!("Deleted {} accounts", result.rows_affected());
+    }
+    Ok(())
+}
+
+// Failed jobs are kept for inspection; finished ones only for a while
+async fn prune_jobs(context: &JobContext) -> Result<(), String> {
+    let cutoff = Utc::now() - chrono::Duration::days(SUCCEEDED_JOB_RETENTION_DAYS);
+    db::query("DELETE FROM jobs WHERE status = $1 AND finished_at < $2")
+        .bind(STATUS_SUCCEEDED)
+        .bind(cutoff)
+        .execute(&context.db)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use todo_api_types::CreateTodoRequest;
+
+    use crate::{
+        config::DatabaseConfig,
+        mailer::LogMailer,
+        repository::{NewUser, SqlRepository, TodoRepository, UserRepository},
+    };
+
+    async fn context() -> JobContext {
+        let config = DatabaseConfig { url: "sqlite::memory:".to_string(), max_connections: 1, ..Default::default() };
+        let pool = Pool::connect(&config).await.unwrap();
+        pool.migrate().await.unwrap();
+        JobContext {
+            db: pool,
+            http: reqwest::Client::new(),
+            mailer: Arc::new(LogMailer),
+            vapid: None,
+            config: Arc::new(Config::default()),
+        }
+    }
+
+    #[tokio::test]
+    async fn jobs_queued_without_their_ids_fail() {
+        let context = context().await;
+        let pool = context.db.clone();
+
+        let kinds = [
+            (JOB_SEND_DIGEST, "user_id"),
+            (JOB_SEND_EMAIL_VERIFICATION, "user_id"),
+            (JOB_SEND_REMINDER_EMAIL, "todo_id"),
+            (JOB_DELIVER_WEBHOOK, "delivery_id"),
+        ];
+        for (kind, key) in kinds {
+            // Before NOW(), which SQLite only has to the millisecond
+            let id = enqueue(&pool, kind, json!({}), Utc::now() - chrono::Duration::seconds(1)).await.unwrap();
+            db::query("UPDATE jobs SET max_attempts = 1 WHERE id = $1").bind(&id).execute(&pool).await.unwrap();
+            let job = claim(&pool).await.unwrap().unwrap();
+            assert_eq!(job.id, id);
+            run(&context, job).await;
+
+            let row = db::query("SELECT status, last_error FROM jobs WHERE id = $1").bind(&id).fetch_one(&pool).await.unwrap();
+            assert_eq!(row.get::<String, _>("status"), STATUS_FAILED, "{}", kind);
+            assert_eq!(row.get::<String, _>("last_error"), format!("missing {}", key), "{}", kind);
+        }
+    }
+
+    // A row in every table that holds a user's data
+    async fn own_everything(context: &JobContext, user_id: &str) {
+        let repository = SqlRepository::new(context.db.clone());
+        let user = NewUser {
+            id: user_id.to_string(),
+            username: user_id.to_string(),
+            email: format!("{}@example.com", user_id),
+            password_hash: "hash".to_string(),
+        };
+        UserRepository::create(&repository, user).await.unwrap();
+        let statements = [
+            "INSERT INTO app_passwords (id, user_id, name, password_hash) VALUES ($1 || '-password', $1, 'Phone', $1 || '-hash')",
+            "INSERT INTO webhooks (id, user_id, url, events, secret) VALUES ($1 || '-webhook', $1, 'https://example.com/', '[\"todo.created\"]', 's')",
+            "INSERT INTO notification_preferences (user_id) VALUES ($1)",
+            "INSERT INTO push_subscriptions (id, user_id, endpoint, p256dh, auth) VALUES ($1 || '-push', $1, 'https://push.example.com/' || $1, 'k', 'a')",
+            "INSERT INTO todo_tombstones (user_id, name, sync_seq) VALUES ($1, 'purged', 1)",
+        ];
+        for statement in statements {
+            db::query(statement).bind(user_id).execute(&context.db).await.unwrap();
+        }
+        // With its history and a webhook delivery
+        let todo = CreateTodoRequest { title: "Water the plants".to_string(), description: None, due_at: None, priority: None, remind_at: None };
+        TodoRepository::create(&repository, user_id, todo, Utc::now()).await.unwrap();
+    }
+
+    // How many rows each table holding user data has for `user_id`
+    async fn rows_owned_by(context: &JobContext, user_id: &str) -> Vec<(String, i64)> {
+        let tables: Vec<String> = db::query_scalar(
+            "SELECT m.name FROM sqlite_master m JOIN pragma_table_info(m.name) c WHERE m.type = 'table' AND c.name = 'user_id' ORDER BY m.name"
+        )
+        .fetch_all(&context.db)
+        .await
+        .unwrap();
+        let mut counts = Vec::new();
+        for table in tables.into_iter().chain(["users".to_string()]) {
+            let column = if table == "users" { "id" } else { "user_id" };
+            let count = db::query_scalar(&format!("SELECT COUNT(*) FROM {} WHERE {} = $1", table, column))
+                .bind(user_id)
+                .fetch_one(&context.db)
+                .await
+                .unwrap();
+            counts.push((table, count));
+        }
+        counts
+    }
+
+    #[tokio::test]
+    async fn purged_accounts_take_everything_they_own_with_them() {
+        let context = context().await;
+        own_everything(&context, "gone").await;
+        own_everything(&context, "kept").await;
+        let now = Utc::now();
+        db::query("UPDATE users SET deletion_scheduled_at = $1 WHERE id = 'gone'").bind(now - chrono::Duration::minutes(1)).execute(&context.db).await.unwrap();
+        // Still in the grace period
+        db::query("UPDATE users SET deletion_scheduled_at = $1 WHERE id = 'kept'").bind(now + chrono::Duration::days(1)).execute(&context.db).await.unwrap();
+        let before = rows_owned_by(&context, "gone").await;
+        assert!(before.iter().all(|(_, count)| *count > 0), "{:?}", before);
+        let deliveries: i64 = db::query_scalar("SELECT COUNT(*) FROM webhook_deliveries").fetch_one(&context.db).await.unwrap();
+        assert_eq!(deliveries, 2);
+
+        purge_accounts(&context).await.unwrap();
+        let after = rows_owned_by(&context, "gone").await;
+        assert!(after.iter().all(|(_, count)| *count == 0), "{:?}", after);
+        let deliveries: i64 = db::query_scalar("SELECT COUNT(*) FROM webhook_deliveries").fetch_one(&context.db).await.unwrap();
+        assert_eq!(deliveries, 1);
+        assert_eq!(rows_owned_by(&context, "kept").await, before);
+    }
+}
@@ -0,0 +1,1626 @@
+use axum::{
+    body::Body,
+    extract::{DefaultBodyLimit, FromRef, Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    middleware,
+    response::{Json, Response},
+    routing::{any, delete, get, patch, post},
+    Router,
+};
+use std::{collections::HashSet, sync::Arc};
+use futures::TryStreamExt;
+use tokio_stream::wrappers::ReceiverStream;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use askama::Template;
+use uuid::Uuid;
+
+pub mod admin;
+pub mod auth;
+mod caldav;
+pub mod config;
+pub mod db;
+mod digest;
+pub mod dump;
+mod export;
+mod history;
+mod ical;
+mod import;
+pub mod jobs;
+pub mod live;
+pub mod mailer;
+pub mod monitoring;
+mod openapi;
+mod ordering;
+mod profile;
+pub mod push;
+mod reminders;
+pub mod repository;
+pub mod webhooks;
+
+use config::Config;
+use db::Pool;
+use auth::{auth_middleware, Claims, create_token, hash_password, hash_secret, new_secret_token, verify_password};
+use export::ExportQuery;
+use import::ImportFormat;
+use repository::{
+    todo_from_row, BulkTargets, NewUser, TodoRepository, UserRepository,
+};
+use todo_api_types::{
+    routes, AppPassword, AuthResponse, ChangePasswordRequest, DeleteAccountRequest, User, UserUpdate, VerifyEmailQuery, BulkRequest, BulkResponse, CalendarFeed, CreateAppPasswordRequest, CreateTodoRequest,
+    CsvMapping, DigestPreviewQuery, ImportReport, UnsubscribeQuery,
+    CreatedAppPassword, CreateWebhookRequest, CreatedWebhook, DeleteQuery,
+    LoginRequest, MoveTodoRequest, NotificationPreferences, NotificationPreferencesUpdate, PushPublicKey,
+    PushSubscriptionRequest, PushUnsubscribeRequest, RegisterRequest, Todo, TodoEvent, TodoQuery, TodoUpdate, Webhook,
+    WebhookDelivery, WebhookUpdate,
+};
+
+const MAX_BULK_ITEMS: usize = 1000;
+const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+const WEBHOOK_DELIVERY_LOG_LIMIT: i64 = 50;
+
+#[derive(Clone)]
+pub struct AppState {
+    // For everything the repositories don't cover yet
+    pub(crate) db: Pool,
+    pub(crate) users: Arc<dyn UserRepository>,
+    pub(crate) todos: Arc<dyn TodoRepository>,
+    pub(crate) config: Arc<Config>,
+    // None when Web Push isn't configured
+    pub(crate) vapid_public_key: Option<String>,
+    pub(crate) todo_changes: live::TodoChanges,
+    pub(crate) metrics: monitoring::Metrics,
+}
+
+impl AppState {
+    // Users and todos are kept in the same database as everything else
+    pub fn new(db: Pool, config: Arc<Config>, vapid_public_key: Option<String>) -> AppState {
+        let repository = Arc::new(repository::SqlRepository::new(db.clone()));
+        AppState {
+            db,
+            users: repository.clone(),
+            todos: repository,
+            config,
+            vapid_public_key,
+            todo_changes: Default::default(),
+            metrics: Default::default(),
+        }
+    }
+
+    // For tests that drive the router without a database: users and todos are
+    // kept in the returned repository, which tests can also look into. Routes
+    // the repositories don't cover, such as /readyz, get an empty SQLite
+    // database with no tables
+    #[cfg(feature = "test-support")]
+    pub fn in_memory(config: Config) -> (AppState, Arc<repository::MemoryRepository>) {
+        let db = Pool::Sqlite(sqlx::sqlite::SqlitePoolOptions::new().connect_lazy("sqlite::memory:").expect("valid URL"));
+        let repository = Arc::new(repository::MemoryRepository::default());
+        let state = AppState {
+            db,
+            users: repository.clone(),
+            todos: repository.clone(),
+            config: Arc::new(config),
+            vapid_public_key: None,
+            todo_changes: Default::default(),
+            metrics: Default::default(),
+        };
+        (state, repository)
+    }
+
+    // For tests of the routes the repositories don't cover: everything is kept
+    // in a migrated in-memory SQLite database
+    #[cfg(feature = "test-support")]
+    pub async fn sqlite(mut config: Config) -> AppState {
+        // One connection, since each has its own in-memory database. A request
+        // waiting for it while it is taken would wait forever, so that fails fast
+        config.database = config::DatabaseConfig {
+            url: "sqlite::memory:".to_string(),
+            max_connections: 1,
+            acquire_timeout_secs: 5,
+            ..Default::default()
+        };
+        let db = Pool::connect(&config.database).await.expect("SQLite opens in memory");
+        db.migrate().await.expect("the migrations apply");
+        AppState::new(db, Arc::new(config), None)
+    }
+
+    // For tests that set up what the API can't, such as the first admin
+    #[cfg(feature = "test-support")]
+    pub fn db(&self) -> &Pool {
+        &self.db
+    }
+}
+
+// For extractors that only need users, such as `RequireRole`
+impl FromRef<AppState> for Arc<dyn UserRepository> {
+    fn from_ref(state: &AppState) -> Arc<dyn UserRepository> {
+        state.users.clone()
+    }
+}
+
+// The API and CalDAV routes, behind authentication and CORS, and the
+// monitoring endpoints
+pub fn app(state: AppState) -> Router {
+    let allowed_origins = &state.config.server.allowed_origins;
+    let allow_origin = if allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        // Checked when the configuration was loaded
+        AllowOrigin::list(allowed_origins.iter().filter_map(|origin| origin.parse().ok()))
+    };
+    let cors = CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    Router::new()
+        .route(routes::REGISTER, post(register))
+        .route(routes::LOGIN, post(login))
+        .route(routes::TOKEN_REFRESH, post(refresh_token))
+        .route(routes::ME, get(get_me).patch(update_me).delete(delete_me))
+        .route(routes::ME_RESTORE, post(restore_me))
+        .route(routes::ME_EXPORT, get(export_me))
+        .route(routes::ME_PASSWORD, post(change_password))
+        .route(routes::ME_EMAIL_VERIFY, get(verify_email))
+        .route(routes::TODOS, get(get_todos).post(create_todo))
+        .route(routes::EVENTS, get(live::events))
+        .route(routes::TODOS_BULK, post(bulk_update_todos))
+        .route(routes::TODO, get(get_todo).patch(update_todo).delete(delete_todo))
+        .route(routes::TODO_RESTORE, post(restore_todo))
+        .route(routes::TODO_HISTORY, get(get_todo_history))
+        .route(routes::TODO_MOVE, post(move_todo))
+        .route(routes::EXPORT, get(export_todos))
+        .route(routes::CALENDAR_TOKEN, get(get_calendar_token).post(regenerate_calendar_token))
+        .route(routes::CALENDAR_FEED, get(calendar_feed))
+        .route(routes::APP_PASSWORDS, get(get_app_passwords).post(create_app_password))
+        .route(routes::APP_PASSWORD, delete(delete_app_password))
+        .route(routes::WEBHOOKS, get(get_webhooks).post(create_webhook))
+        .route(routes::WEBHOOK, patch(update_webhook).delete(delete_webhook))
+        .route(routes::WEBHOOK_DELIVERIES, get(get_webhook_deliveries))
+        .route(routes::WEBHOOK_DELIVERY_RETRY, post(retry_webhook_delivery))
+        .route(
+            routes::NOTIFICATION_PREFERENCES,
+            get(get_notification_preferences).patch(update_notification_preferences),
+        )
+        .route(routes::DIGEST_PREVIEW, get(preview_digest))
+        .route(routes::DIGEST_UNSUBSCRIBE, get(unsubscribe_digest))
+        .route(routes::PUSH_PUBLIC_KEY, get(get_push_public_key))
+        .route(routes::PUSH_SUBSCRIPTIONS, post(subscribe_push).delete(unsubscribe_push))
+        .merge(admin::router())
+        .route(routes::HEALTHZ, get(monitoring::healthz))
+        .route(routes::READYZ, get(monitoring::readyz))
+        .route(routes::METRICS, get(monitoring::metrics))
+        .route(routes::OPENAPI, get(openapi::document_json))
+        .route(routes::API_DOCS, get(openapi::docs_page))
+        .route("/.well-known/caldav", any(caldav::well_known))
+        .route("/dav", any(caldav::handle))
+        .route("/dav/", any(caldav::handle))
+        .route("/dav/*path", any(caldav::handle))
+        .route(
+            routes::IMPORT,
+            post(import_todos).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+        )
+        .route_layer(middleware::from_fn(monitoring::remember_route))
+        .layer(middleware::from_fn_with_state(state.clone(), auth_middleware))
+        .layer(middleware::from_fn_with_state(state.clone(), monitoring::track))
+        .layer(cors)
+        .with_state(state)
+}
+
+#[utoipa::path(
+    post,
+    path = routes::REGISTER,
+    tag = "auth",
+    request_body = RegisterRequest,
+    responses(
+        (status = 200, body = AuthResponse),
+        (status = 409, description = "Username or email taken"),
+    ),
+    security(()),
+)]
+async fn register(
+    State(state): State<AppState>,
+    Json(payload): Json<RegisterRequest>,
+) -> Result<Json<AuthResponse>, StatusCode> {
+    let hashed_password = hash_password(&state.config.auth, &payload.password)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let user_id = Uuid::new_v4().to_string();
+
+    state
+        .users
+        .create(NewUser {
+            id: user_id.clone(),
+            username: payload.username,
+            email: payload.email,
+            password_hash: hashed_password,
+        })
+        .await?;
+
+    let token = create_token(&state.config.auth, &user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(AuthResponse {
+        message: "User created successfully".to_string(),
+        token,
+        password_reset_required: false,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = routes::LOGIN,
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, body = AuthResponse),
+        (status = 401, description = "Wrong email or password"),
+        (status = 403, description = "Account disabled"),
+    ),
+    security(()),
+)]
+async fn login(
+    State(state): State<AppState>,
+    Json(payload): Json<LoginRequest>,
+) -> Result<Json<AuthResponse>, StatusCode> {
+    if let Some(credentials) = state.users.find_credentials(&payload.email).await? {
+        if verify_password(&payload.password, &credentials.password_hash)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        {
+            if credentials.disabled {
+                return Err(StatusCode::FORBIDDEN);
+            }
+
+            state.users.record_login(&credentials.user_id, chrono::Utc::now()).await?;
+
+            let token = create_token(&state.config.auth, &credentials.user_id)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            
+            return Ok(Json(AuthResponse {
+                message: "Login successful".to_string(),
+                token,
+                password_reset_required: credentials.password_reset_required,
+            }));
+        }
+    }
+
+    Err(StatusCode::UNAUTHORIZED)
+}
+
+// A new token for the holder of a valid one, so clients stay logged in without
+// asking for the password again
+#[utoipa::path(
+    post,
+    path = routes::TOKEN_REFRESH,
+    tag = "auth",
+    responses(
+        (status = 200, body = AuthResponse),
+    ),
+)]
+async fn refresh_token(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<AuthResponse>, StatusCode> {
+    let token = create_token(&state.config.auth, &claims.sub)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(AuthResponse {
+        message: "Token refreshed".to_string(),
+        token,
+        password_reset_required: false,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = routes::ME,
+    tag = "profile",
+    responses(
+        (status = 200, body = User),
+    ),
+)]
+async fn get_me(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<User>, StatusCode> {
+    state.users.find(&claims.sub).await?.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+// A new email address is only stored as pending, and a verification link is
+// sent to it; asking for the current address again cancels the change
+#[utoipa::path(
+    patch,
+    path = routes::ME,
+    tag = "profile",
+    request_body = UserUpdate,
+    responses(
+        (status = 200, body = User),
+        (status = 400, description = "Invalid field"),
+        (status = 409, description = "Username or email taken"),
+    ),
+)]
+async fn update_me(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<UserUpdate>,
+) -> Result<Json<User>, StatusCode> {
+    let username = payload.username.as_deref().map(str::trim);
+    let email = payload.email.as_deref().map(str::trim);
+    let display_name = payload.display_name.as_deref().map(str::trim);
+    let avatar_url = payload.avatar_url.as_deref().map(str::trim);
+
+    let valid = username.is_none_or(|username| !username.is_empty())
+        && email.is_none_or(profile::is_valid_email)
+        && payload.timezone.as_deref().is_none_or(digest::is_valid_timezone)
+        && payload.locale.as_deref().is_none_or(profile::is_valid_locale)
+        && avatar_url.is_none_or(|url| url.is_empty() || webhooks::is_valid_url(url));
+    if !valid {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let changes = UserUpdate {
+        username: username.map(str::to_string),
+        email: email.map(str::to_string),
+        display_name: display_name.map(str::to_string),
+        timezone: payload.timezone,
+        locale: payload.locale,
+        avatar_url: avatar_url.map(str::to_string),
+    };
+    Ok(Json(state.users.update_profile(&claims.sub, changes).await?))
+}
+
+// For actions that need the password again; a wrong one gets 403 rather than
+// 401, since the session itself is fine
+async fn confirm_password(state: &AppState, user_id: &str, password: &str) -> Result<(), StatusCode> {
+    let password_hash = state.users.password_hash(user_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+
+    if verify_password(password, &password_hash).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)? {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = routes::ME_PASSWORD,
+    tag = "profile",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "The new password is empty or unchanged"),
+        (status = 403, description = "Wrong current password"),
+    ),
+)]
+async fn change_password(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<StatusCode, StatusCode> {
+    if payload.new_password.is_empty() || payload.new_password == payload.current_password {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    confirm_password(&state, &claims.sub, &payload.current_password).await?;
+
+    let new_hash = hash_password(&state.config.auth, &payload.new_password)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    state.users.set_password(&claims.sub, &new_hash).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The account stays usable during the grace period, so the owner can change
+// their mind; the purge_accounts job deletes it and everything it owns afterwards
+#[utoipa::path(
+    delete,
+    path = routes::ME,
+    tag = "profile",
+    request_body = DeleteAccountRequest,
+    responses(
+        (status = 200, body = User, description = "Deletion scheduled"),
+        (status = 403, description = "Wrong password"),
+    ),
+)]
+async fn delete_me(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<DeleteAccountRequest>,
+) -> Result<Json<User>, StatusCode> {
+    confirm_password(&state, &claims.sub, &payload.password).await?;
+
+    let purge_at = chrono::Utc::now() + chrono::Duration::days(state.config.accounts.deletion_grace_days);
+    state.users.schedule_deletion(&claims.sub, purge_at).await?;
+
+    state.users.find(&claims.sub).await?.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+// Cancels a scheduled deletion
+#[utoipa::path(
+    post,
+    path = routes::ME_RESTORE,
+    tag = "profile",
+    responses(
+        (status = 200, body = User, description = "Deletion cancelled"),
+    ),
+)]
+async fn restore_me(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<User>, StatusCode> {
+    state.users.cancel_deletion(&claims.sub).await?;
+
+    state.users.find(&claims.sub).await?.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+// Everything stored about the user, as JSON files in a ZIP archive. Secrets
+// such as password hashes, app passwords and webhook secrets are left out
+#[utoipa::path(
+    get,
+    path = routes::ME_EXPORT,
+    tag = "profile",
+    responses(
+        (status = 200, description = "Everything stored about the user, as JSON files in a ZIP archive", content_type = "application/zip"),
+    ),
+)]
+async fn export_me(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Response, StatusCode> {
+    let account = state.users.export(&claims.sub).await?.ok_or(StatusCode::NOT_FOUND)?;
+    let (todos, history) = state.todos.export(&claims.sub).await?;
+
+    let files = [
+        ("profile.json", serde_json::to_vec_pretty(&account.user)),
+        ("todos.json", serde_json::to_vec_pretty(&todos)),
+        ("history.json", serde_json::to_vec_pretty(&history)),
+        ("notification_preferences.json", serde_json::to_vec_pretty(&account.notification_preferences)),
+        ("app_passwords.json", serde_json::to_vec_pretty(&account.app_passwords)),
+        ("webhooks.json", serde_json::to_vec_pretty(&account.webhooks)),
+    ]
+    .into_iter()
+    .map(|(name, content)| content.map(|content| (name, content)))
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let archive = export::zip_archive(&files).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(header::CONTENT_DISPOSITION, "attachment; filename=\"todo-account-export.zip\"")
+        .body(Body::from(archive))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// A minimal page for links opened from emails
+fn html_page(title: &str, message: &str) -> Result<Response, StatusCode> {
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(Body::from(format!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title></head><body><p>{}</p></body></html>",
+            title, message
+        )))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Linked from the verification email; the token stands in for a login
+#[utoipa::path(
+    get,
+    path = routes::ME_EMAIL_VERIFY,
+    tag = "profile",
+    params(VerifyEmailQuery),
+    responses(
+        (status = 200, description = "Confirmation page", body = String, content_type = "text/html"),
+        (status = 404, description = "Unknown or expired token"),
+        (status = 409, description = "Email taken in the meantime"),
+    ),
+    security(()),
+)]
+async fn verify_email(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<Response, StatusCode> {
+    match state.users.confirm_email(&hash_secret(&query.token), chrono::Utc::now()).await {
+        Ok(()) => html_page(
+            "Email address confirmed",
+            "Your new email address is confirmed. Use it to log in from now on.",
+        ),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Priorities follow RFC 5545: 1 is the highest, 9 the lowest
+fn is_valid_priority(priority: Option<i16>) -> bool {
+    priority.is_none_or(|p| (1..=9).contains(&p))
+}
+
+#[utoipa::path(
+    get,
+    path = routes::TODOS,
+    tag = "todos",
+    params(TodoQuery),
+    responses(
+        (status = 200, body = Vec<Todo>),
+    ),
+)]
+async fn get_todos(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<TodoQuery>,
+) -> Result<Json<Vec<Todo>>, StatusCode> {
+    Ok(Json(state.todos.list(&claims.sub, query.deleted).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = routes::TODO,
+    tag = "todos",
+    params(("id" = String, Path, description = "Todo ID")),
+    responses(
+        (status = 200, body = Todo),
+        (status = 404, description = "No such todo"),
+    ),
+)]
+async fn get_todo(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Todo>, StatusCode> {
+    state.todos.find(&claims.sub, &id).await?.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    post,
+    path = routes::TODOS,
+    tag = "todos",
+    request_body = CreateTodoRequest,
+    responses(
+        (status = 200, body = Todo),
+        (status = 400, description = "Invalid priority"),
+    ),
+)]
+async fn create_todo(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreateTodoRequest>,
+) -> Result<Json<Todo>, StatusCode> {
+    if !is_valid_priority(payload.priority) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let todo = state.todos.create(&claims.sub, payload, chrono::Utc::now()).await?;
+    state.todo_changes.notify(&claims.sub);
+    Ok(Json(todo))
+}
+
+#[utoipa::path(
+    patch,
+    path = routes::TODO,
+    tag = "todos",
+    params(("id" = String, Path, description = "Todo ID")),
+    request_body = TodoUpdate,
+    responses(
+        (status = 200, body = Todo),
+        (status = 400, description = "Invalid priority"),
+        (status = 404, description = "No such todo"),
+    ),
+)]
+async fn update_todo(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<TodoUpdate>,
+) -> Result<Json<Todo>, StatusCode> {
+    if !is_valid_priority(payload.priority.flatten()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let todo = state.todos.update(&claims.sub, &id, payload, chrono::Utc::now()).await?;
+    state.todo_changes.notify(&claims.sub);
+    Ok(Json(todo))
+}
+
+#[utoipa::path(
+    delete,
+    path = routes::TODO,
+    tag = "todos",
+    params(("id" = String, Path, description = "Todo ID"), DeleteQuery),
+    responses(
+        (status = 204, description = "Moved to the trash, or deleted for good with `permanent`"),
+        (status = 404, description = "No such todo, or with `permanent`, none in the trash"),
+    ),
+)]
+async fn delete_todo(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<DeleteQuery>,
+) -> Result<StatusCode, StatusCode> {
+    // Permanent deletes only apply to trashed todos, and take their history with them;
+    // regular deletes move the todo to the trash
+    if query.permanent {
+        state.todos.purge(&claims.sub, &id).await?;
+    } else {
+        state.todos.trash(&claims.sub, &id, chrono::Utc::now()).await?;
+    }
+    state.todo_changes.notify(&claims.sub);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = routes::TODO_RESTORE,
+    tag = "todos",
+    params(("id" = String, Path, description = "Todo ID")),
+    responses(
+        (status = 200, body = Todo),
+        (status = 404, description = "No such todo in the trash"),
+    ),
+)]
+async fn restore_todo(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Todo>, StatusCode> {
+    let todo = state.todos.restore(&claims.sub, &id, chrono::Utc::now()).await?;
+    state.todo_changes.notify(&claims.sub);
+    Ok(Json(todo))
+}
+
+#[utoipa::path(
+    get,
+    path = routes::TODO_HISTORY,
+    tag = "todos",
+    params(("id" = String, Path, description = "Todo ID")),
+    responses(
+        (status = 200, body = Vec<TodoEvent>),
+        (status = 404, description = "No such todo"),
+    ),
+)]
+async fn get_todo_history(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<TodoEvent>>, StatusCode> {
+    Ok(Json(state.todos.history(&claims.sub, &id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = routes::TODOS_BULK,
+    tag = "todos",
+    request_body = BulkRequest,
+    responses(
+        (status = 200, body = BulkResponse),
+        (status = 400, description = "Neither or both of `ids` and `filter`"),
+        (status = 413, description = "Too many todos; none was changed"),
+    ),
+)]
+async fn bulk_update_todos(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<BulkRequest>,
+) -> Result<Json<BulkResponse>, StatusCode> {
+    // Targets are either an explicit list of ids or every todo matching a filter, never both
+    let targets = match (payload.ids, payload.filter) {
+        (Some(mut ids), None) => {
+            // Checked before anything else is done with the list, duplicates included
+            if ids.len() > MAX_BULK_ITEMS {
+                return Err(StatusCode::PAYLOAD_TOO_LARGE);
+            }
+            let mut seen = HashSet::with_capacity(ids.len());
+            ids.retain(|id| seen.insert(id.clone()));
+            BulkTargets::Ids(ids)
+        }
+        (None, Some(filter)) => BulkTargets::Filter(filter),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let results = state
+        .todos
+        .bulk_update(&claims.sub, payload.action, targets, MAX_BULK_ITEMS, chrono::Utc::now())
+        .await?;
+    if results.iter().any(|result| result.success) {
+        state.todo_changes.notify(&claims.sub);
+    }
+
+    Ok(Json(BulkResponse { results }))
+}
+
+#[utoipa::path(
+    post,
+    path = routes::TODO_MOVE,
+    tag = "todos",
+    params(("id" = String, Path, description = "Todo ID")),
+    request_body = MoveTodoRequest,
+    responses(
+        (status = 200, body = Todo),
+        (status = 400, description = "No neighbour given, or the todo is its own neighbour"),
+        (status = 404, description = "No such todo or neighbour"),
+        (status = 409, description = "The neighbours are in the wrong order"),
+    ),
+)]
+async fn move_todo(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<MoveTodoRequest>,
+) -> Result<Json<Todo>, StatusCode> {
+    let previous_id = payload.previous_id.as_deref();
+    let next_id = payload.next_id.as_deref();
+    if (previous_id.is_none() && next_id.is_none()) || previous_id == Some(&id) || next_id == Some(&id) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let todo = state.todos.move_between(&claims.sub, &id, previous_id, next_id).await?;
+    state.todo_changes.notify(&claims.sub);
+    Ok(Json(todo))
+}
+
+#[utoipa::path(
+    get,
+    path = routes::EXPORT,
+    tag = "export",
+    params(ExportQuery),
+    responses(
+        (status = 200, description = "The todos as a file in the requested format", content(
+            (String = "application/json"),
+            (String = "text/csv"),
+            (String = "text/markdown"),
+            (String = "text/plain"),
+        )),
+        (status = 400, description = "Unknown format"),
+    ),
+)]
+async fn export_todos(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, StatusCode> {
+    let format = query.format;
+    let (sender, receiver) = tokio::sync::mpsc::channel::<Result<String, sqlx::Error>>(32);
+
+    // Rows are formatted as they arrive so large exports never sit in memory in full
+    tokio::spawn(async move {
+        if sender.send(Ok(format.header())).await.is_err() {
+            return;
+        }
+
+        let mut rows = db::query(
+            "SELECT * FROM todos WHERE user_id = $1 AND deleted_at IS NULL ORDER BY position COLLATE \"C\", created_at DESC"
+        )
+        .bind(&claims.sub)
+        .fetch(&state.db);
+
+        let mut count = 0;
+        loop {
+            let chunk = match rows.try_next().await {
+                Ok(Some(row)) => todo_from_row(&row).map(|todo| format.row(&todo, count)),
+                Ok(None) => break,
+                Err(err) => Err(err),
+            };
+            let failed = chunk.is_err();
+            if sender.send(chunk).await.is_err() || failed {
+                return;
+            }
+            count += 1;
+        }
+
+        let _ = sender.send(Ok(format.footer(count))).await;
+    });
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", format.file_name()),
+        )
+        .body(Body::from_stream(ReceiverStream::new(receiver)))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn calendar_feed_response(token: String) -> CalendarFeed {
+    CalendarFeed {
+        path: routes::path(routes::CALENDAR_FEED, &[&format!("{}.ics", token)]),
+        token,
+    }
+}
+
+// Returns the user's feed token. There is none until one is made with a POST,
+// so reading it never changes anything
+#[utoipa::path(
+    get,
+    path = routes::CALENDAR_TOKEN,
+    tag = "calendar",
+    responses(
+        (status = 200, body = CalendarFeed),
+        (status = 404, description = "No feed token has been made yet"),
+    ),
+)]
+async fn get_calendar_token(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<CalendarFeed>, StatusCode> {
+    let token: Option<String> = db::query_scalar("SELECT calendar_token FROM users WHERE id = $1")
+        .bind(&claims.sub)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .flatten();
+
+    token.map(|token| Json(calendar_feed_response(token))).ok_or(StatusCode::NOT_FOUND)
+}
+
+// Makes the feed token, or replaces it, so subscriptions using the old URL stop working
+#[utoipa::path(
+    post,
+    path = routes::CALENDAR_TOKEN,
+    tag = "calendar",
+    responses(
+        (status = 200, body = CalendarFeed),
+    ),
+)]
+async fn regenerate_calendar_token(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<CalendarFeed>, StatusCode> {
+    let token = new_secret_token();
+
+    let result = db::query("UPDATE users SET calendar_token = $1 WHERE id = $2")
+        .bind(&token)
+        .bind(&claims.sub)
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(calendar_feed_response(token)))
+}
+
+// Public: calendar apps can't send a bearer token, so the secret in the
+// `<token>.ics` file name identifies the user instead
+#[utoipa::path(
+    get,
+    path = routes::CALENDAR_FEED,
+    tag = "calendar",
+    params(("file" = String, Path, description = "The feed token followed by \".ics\"")),
+    responses(
+        (status = 200, description = "The todos with due dates, as iCalendar", body = String, content_type = "text/calendar"),
+        (status = 404, description = "Unknown feed token"),
+    ),
+    security(()),
+)]
+async fn calendar_feed(
+    State(state): State<AppState>,
+    Path(file): Path<String>,
+) -> Result<Response, StatusCode> {
+    let token = file.strip_suffix(".ics").ok_or(StatusCode::NOT_FOUND)?;
+
+    let user = db::query("SELECT id FROM users WHERE calendar_token = $1 AND disabled_at IS NULL")
+        .bind(token)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let user_id: String = user.get("id");
+
+    let rows = db::query(
+        "SELECT * FROM todos WHERE user_id = $1 AND deleted_at IS NULL ORDER BY position COLLATE \"C\", created_at DESC"
+    )
+    .bind(&user_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let todos: Vec<Todo> =
+        rows.iter().map(todo_from_row).collect::<Result<_, _>>().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, ical::CONTENT_TYPE)
+        .header(header::CONTENT_DISPOSITION, "inline; filename=\"todos.ics\"")
+        .body(Body::from(ical::calendar(&todos, chrono::Utc::now())))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[utoipa::path(
+    get,
+    path = routes::APP_PASSWORDS,
+    tag = "app passwords",
+    responses(
+        (status = 200, body = Vec<AppPassword>),
+    ),
+)]
+async fn get_app_passwords(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<AppPassword>>, StatusCode> {
+    let rows = db::query(
+        "SELECT id, name, created_at, last_used_at FROM app_passwords WHERE user_id = $1 ORDER BY created_at DESC"
+    )
+    .bind(&claims.sub)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let passwords = rows
+        .iter()
+        .map(|row| AppPassword {
+            id: row.get("id"),
+            name: row.get("name"),
+            created_at: row.get("created_at"),
+            last_used_at: row.get("last_used_at"),
+        })
+        .collect();
+
+    Ok(Json(passwords))
+}
+
+// App passwords are generated, never chosen, so each client can be revoked on its own
+#[utoipa::path(
+    post,
+    path = routes::APP_PASSWORDS,
+    tag = "app passwords",
+    request_body = CreateAppPasswordRequest,
+    responses(
+        (status = 200, body = CreatedAppPassword),
+        (status = 400, description = "Empty name"),
+    ),
+)]
+async fn create_app_password(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreateAppPasswordRequest>,
+) -> Result<Json<CreatedAppPassword>, StatusCode> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let id = Uuid::new_v4().to_string();
+    let password = new_secret_token();
+    let now = chrono::Utc::now();
+
+    db::query(
+        "INSERT INTO app_passwords (id, user_id, name, password_hash, created_at) VALUES ($1, $2, $3, $4, $5)"
+    )
+    .bind(&id)
+    .bind(&claims.sub)
+    .bind(name)
+    .bind(hash_secret(&password))
+    .bind(now)
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(CreatedAppPassword {
+        id,
+        name: name.to_string(),
+        password,
+        created_at: now,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = routes::APP_PASSWORD,
+    tag = "app passwords",
+    params(("id" = String, Path, description = "App password ID")),
+    responses(
+        (status = 204, description = "Revoked"),
+        (status = 404, description = "No such app password"),
+    ),
+)]
+async fn delete_app_password(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<StatusCode, StatusCode> {
+    let result = db::query("DELETE FROM app_passwords WHERE id = $1 AND user_id = $2")
+        .bind(&id)
+        .bind(&claims.sub)
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn is_valid_webhook_events(events: &[String]) -> bool {
+    !events.is_empty() && events.iter().all(|event| webhooks::EVENTS.contains(&event.as_str()))
+}
+
+#[utoipa::path(
+    get,
+    path = routes::WEBHOOKS,
+    tag = "webhooks",
+    responses(
+        (status = 200, body = Vec<Webhook>),
+    ),
+)]
+async fn get_webhooks(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<Webhook>>, StatusCode> {
+    let rows = db::query("SELECT * FROM webhooks WHERE user_id = $1 ORDER BY created_at DESC")
+        .bind(&claims.sub)
+        .fetch_all(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    rows.iter()
+        .map(webhooks::webhook_from_row)
+        .collect::<Result<_, _>>()
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[utoipa::path(
+    post,
+    path = routes::WEBHOOKS,
+    tag = "webhooks",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 200, body = CreatedWebhook),
+        (status = 400, description = "Invalid URL or event"),
+    ),
+)]
+async fn create_webhook(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CreateWebhookRequest>,
+) -> Result<Json<CreatedWebhook>, StatusCode> {
+    if !webhooks::is_valid_url(&payload.url) || !is_valid_webhook_events(&payload.events) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let secret = payload
+        .secret
+        .filter(|secret| !secret.is_empty())
+        .unwrap_or_else(new_secret_token);
+
+    let row = db::query(
+        "INSERT INTO webhooks (id, user_id, url, events, secret, created_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&claims.sub)
+    .bind(&payload.url)
+    .bind(&payload.events)
+    .bind(&secret)
+    .bind(chrono::Utc::now())
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(CreatedWebhook {
+        webhook: webhooks::webhook_from_row(&row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        secret,
+    }))
+}
+
+#[utoipa::path(
+    patch,
+    path = routes::WEBHOOK,
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook ID")),
+    request_body = WebhookUpdate,
+    responses(
+        (status = 200, body = Webhook),
+        (status = 400, description = "Invalid URL or event"),
+        (status = 404, description = "No such webhook"),
+    ),
+)]
+async fn update_webhook(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<WebhookUpdate>,
+) -> Result<Json<Webhook>, StatusCode> {
+    if payload.url.as_deref().is_some_and(|url| !webhooks::is_valid_url(url))
+        || payload.events.as_deref().is_some_and(|events| !is_valid_webhook_events(events))
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let row = db::query(
+        "UPDATE webhooks SET url = COALESCE($1, url), events = COALESCE($2, events), active = COALESCE($3, active) WHERE id = $4 AND user_id = $5 RETURNING *"
+    )
+    .bind(payload.url)
+    .bind(payload.events)
+    .bind(payload.active)
+    .bind(&id)
+    .bind(&claims.sub)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    webhooks::webhook_from_row(&row).map(Json).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[utoipa::path(
+    delete,
+    path = routes::WEBHOOK,
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook ID")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 404, description = "No such webhook"),
+    ),
+)]
+async fn delete_webhook(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<StatusCode, StatusCode> {
+    let result = db::query("DELETE FROM webhooks WHERE id = $1 AND user_id = $2")
+        .bind(&id)
+        .bind(&claims.sub)
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The most recent deliveries, newest first
+#[utoipa::path(
+    get,
+    path = routes::WEBHOOK_DELIVERIES,
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook ID")),
+    responses(
+        (status = 200, body = Vec<WebhookDelivery>),
+        (status = 404, description = "No such webhook"),
+    ),
+)]
+async fn get_webhook_deliveries(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<WebhookDelivery>>, StatusCode> {
+    let owned = db::query("SELECT 1 FROM webhooks WHERE id = $1 AND user_id = $2")
+        .bind(&id)
+        .bind(&claims.sub)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if owned.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let rows = db::query(
+        "SELECT * FROM webhook_deliveries WHERE webhook_id = $1 ORDER BY created_at DESC LIMIT $2"
+    )
+    .bind(&id)
+    .bind(WEBHOOK_DELIVERY_LOG_LIMIT)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let deliveries = rows
+        .iter()
+        .map(|row| WebhookDelivery {
+            id: row.get("id"),
+            event_type: row.get("event_type"),
+            status: row.get("status"),
+            attempts: row.get("attempts"),
+            response_status: row.get("response_status"),
+            last_error: row.get("last_error"),
+            payload: row.get("payload"),
+            created_at: row.get("created_at"),
+            next_attempt_at: row.get("next_attempt_at"),
+            delivered_at: row.get("delivered_at"),
+        })
+        .collect();
+
+    Ok(Json(deliveries))
+}
+
+// Puts a failed delivery back in the queue with a fresh set of attempts
+#[utoipa::path(
+    post,
+    path = routes::WEBHOOK_DELIVERY_RETRY,
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook ID"), ("delivery_id" = String, Path, description = "Delivery ID")),
+    responses(
+        (status = 202, description = "Queued again"),
+        (status = 404, description = "No such delivery"),
+        (status = 409, description = "The delivery hasn't failed"),
+    ),
+)]
+async fn retry_webhook_delivery(
+    Path((id, delivery_id)): Path<(String, String)>,
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<StatusCode, StatusCode> {
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let status: Option<String> = db::query(
+        "SELECT d.status FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id WHERE d.id = $1 AND w.id = $2 AND w.user_id = $3 FOR UPDATE OF d"
+    )
+    .bind(&delivery_id)
+    .bind(&id)
+    .bind(&claims.sub)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map(|row| row.get("status"));
+
+    match status.as_deref() {
+        None => return Err(StatusCode::NOT_FOUND),
+        Some(webhooks::STATUS_FAILED) => {}
+        Some(_) => return Err(StatusCode::CONFLICT),
+    }
+
+    db::query("UPDATE webhook_deliveries SET status = $1, attempts = 0, next_attempt_at = NOW() WHERE id = $2")
+        .bind(webhooks::STATUS_PENDING)
+        .bind(&delivery_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    jobs::enqueue(
+        &mut *tx,
+        jobs::JOB_DELIVER_WEBHOOK,
+        serde_json::json!({ "delivery_id": delivery_id }),
+        chrono::Utc::now(),
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    get,
+    path = routes::NOTIFICATION_PREFERENCES,
+    tag = "notifications",
+    responses(
+        (status = 200, body = NotificationPreferences),
+    ),
+)]
+async fn get_notification_preferences(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<NotificationPreferences>, StatusCode> {
+    digest::load_preferences(&state.db, &claims.sub)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+// The time zone is stored on the user, since it applies beyond notifications
+#[utoipa::path(
+    patch,
+    path = routes::NOTIFICATION_PREFERENCES,
+    tag = "notifications",
+    request_body = NotificationPreferencesUpdate,
+    responses(
+        (status = 200, body = NotificationPreferences),
+        (status = 400, description = "Invalid field"),
+    ),
+)]
+async fn update_notification_preferences(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<NotificationPreferencesUpdate>,
+) -> Result<Json<NotificationPreferences>, StatusCode> {
+    let valid = payload
+        .digest_frequency
+        .as_deref()
+        .is_none_or(|frequency| digest::FREQUENCIES.contains(&frequency))
+        && payload.digest_hour.is_none_or(|hour| (0..=23).contains(&hour))
+        && payload.digest_weekday.is_none_or(|weekday| (1..=7).contains(&weekday))
+        && payload.timezone.as_deref().is_none_or(digest::is_valid_timezone);
+    if !valid {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tx = state.db.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if let Some(timezone) = &payload.timezone {
+        db::query("UPDATE users SET timezone = $1 WHERE id = $2")
+            .bind(timezone)
+            .bind(&claims.sub)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    db::query(
+        "INSERT INTO notification_preferences (user_id, email_reminders, push_reminders, digest_frequency, digest_hour, digest_weekday, updated_at)
+         VALUES ($1, COALESCE($2, TRUE), COALESCE($3, TRUE), COALESCE($4, $7), COALESCE($5, 8), COALESCE($6, 1), NOW())
+         ON CONFLICT (user_id) DO UPDATE SET
+             email_reminders = COALESCE($2, notification_preferences.email_reminders),
+             push_reminders = COALESCE($3, notification_preferences.push_reminders),
+             digest_frequency = COALESCE($4, notification_preferences.digest_frequency),
+             digest_hour = COALESCE($5, notification_preferences.digest_hour),
+             digest_weekday = COALESCE($6, notification_preferences.digest_weekday),
+             updated_at = NOW()"
+    )
+    .bind(&claims.sub)
+    .bind(payload.email_reminders)
+    .bind(payload.push_reminders)
+    .bind(&payload.digest_frequency)
+    .bind(payload.digest_hour)
+    .bind(payload.digest_weekday)
+    .bind(digest::FREQUENCY_OFF)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut preferences = digest::load_preferences(&mut *tx, &claims.sub)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Any change to the schedule moves the next digest
+    preferences.digest_next_at =
+        digest::reschedule(&mut tx, &preferences, &claims.sub).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(preferences))
+}
+
+// Renders the digest the user would get now, without sending it
+#[utoipa::path(
+    get,
+    path = routes::DIGEST_PREVIEW,
+    tag = "notifications",
+    params(DigestPreviewQuery),
+    responses(
+        (status = 200, description = "The digest as it would be sent now", content(
+            (String = "text/html"),
+            (String = "text/plain"),
+        )),
+        (status = 400, description = "Unknown format"),
+    ),
+)]
+async fn preview_digest(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<DigestPreviewQuery>,
+) -> Result<Response, StatusCode> {
+    let preferences = digest::load_preferences(&state.db, &claims.sub)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    // Show a daily digest to users who haven't turned them on yet
+    let frequency = if preferences.digest_frequency == digest::FREQUENCY_OFF {
+        digest::FREQUENCY_DAILY
+    } else {
+        preferences.digest_frequency.as_str()
+    };
+
+    let preview = digest::build(&state.db, &claims.sub, frequency, &state.config, chrono::Utc::now())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let (content_type, body) = match query.format.as_deref() {
+        None | Some("html") => ("text/html; charset=utf-8", digest::DigestHtml { digest: &preview }.render()),
+        Some("text") => ("text/plain; charset=utf-8", digest::DigestText { digest: &preview }.render()),
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    let body = body.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Linked from every digest; the signed token stands in for a login
+#[utoipa::path(
+    get,
+    path = routes::DIGEST_UNSUBSCRIBE,
+    tag = "notifications",
+    params(UnsubscribeQuery),
+    responses(
+        (status = 200, description = "Confirmation page", body = String, content_type = "text/html"),
+        (status = 404, description = "Invalid token"),
+    ),
+    security(()),
+)]
+async fn unsubscribe_digest(
+    State(state): State<AppState>,
+    Query(query): Query<UnsubscribeQuery>,
+) -> Result<Response, StatusCode> {
+    let user_id = auth::verify_signed(&state.config.auth, &query.token).ok_or(StatusCode::NOT_FOUND)?;
+
+    db::query("UPDATE notification_preferences SET digest_frequency = $1, digest_next_at = NULL, updated_at = NOW() WHERE user_id = $2")
+        .bind(digest::FREQUENCY_OFF)
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    html_page(
+        "Unsubscribed",
+        "You won't receive digest emails anymore. You can turn them on again in your settings.",
+    )
+}
+
+// The key browsers need to subscribe; 404 when Web Push isn't configured
+#[utoipa::path(
+    get,
+    path = routes::PUSH_PUBLIC_KEY,
+    tag = "notifications",
+    responses(
+        (status = 200, body = PushPublicKey),
+        (status = 404, description = "Web Push isn't configured"),
+    ),
+)]
+async fn get_push_public_key(State(state): State<AppState>) -> Result<Json<PushPublicKey>, StatusCode> {
+    state
+        .vapid_public_key
+        .map(|public_key| Json(PushPublicKey { public_key }))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+// Subscribing a browser again replaces its keys, and moves it to the current user
+#[utoipa::path(
+    post,
+    path = routes::PUSH_SUBSCRIPTIONS,
+    tag = "notifications",
+    request_body = PushSubscriptionRequest,
+    responses(
+        (status = 201, description = "Subscribed"),
+        (status = 400, description = "Invalid endpoint or keys"),
+        (status = 404, description = "Web Push isn't configured"),
+    ),
+)]
+async fn subscribe_push(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<PushSubscriptionRequest>,
+) -> Result<StatusCode, StatusCode> {
+    if state.vapid_public_key.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if !webhooks::is_valid_url(&payload.endpoint)
+        || !push::is_valid_subscription_keys(&payload.keys.p256dh, &payload.keys.auth)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    db::query(
+        "INSERT INTO push_subscriptions (id, user_id, endpoint, p256dh, auth, created_at) VALUES ($1, $2, $3, $4, $5, NOW())
+         ON CONFLICT (endpoint) DO UPDATE SET user_id = $2, p256dh = $4, auth = $5"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&claims.sub)
+    .bind(&payload.endpoint)
+    .bind(&payload.keys.p256dh)
+    .bind(&payload.keys.auth)
+    .execute(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[utoipa::path(
+    delete,
+    path = routes::PUSH_SUBSCRIPTIONS,
+    tag = "notifications",
+    request_body = PushUnsubscribeRequest,
+    responses(
+        (status = 204, description = "Unsubscribed"),
+        (status = 404, description = "No such subscription"),
+    ),
+)]
+async fn unsubscribe_push(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<PushUnsubscribeRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let result = db::query("DELETE FROM push_subscriptions WHERE endpoint = $1 AND user_id = $2")
+        .bind(&payload.endpoint)
+        .bind(&claims.sub)
+        .execute(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The multipart form `import_todos` reads, only to describe it in the API document
+#[derive(utoipa::ToSchema)]
+#[allow(dead_code)]
+struct ImportForm {
+    // The file's content
+    file: String,
+    // todotxt, csv, todoist or taskwarrior
+    format: String,
+    // A JSON `CsvMapping`, for generic CSV only
+    mapping: Option<String>,
+    // "true" to only preview
+    dry_run: Option<String>,
+}
+
+// Reads the fields of `ImportForm`
+#[utoipa::path(
+    post,
+    path = routes::IMPORT,
+    tag = "export",
+    request_body(content = ImportForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = ImportReport),
+        (status = 400, description = "Missing or invalid field"),
+        (status = 413, description = "File over 10 MiB"),
+        (status = 422, description = "The file can't be read in that format"),
+    ),
+)]
+async fn import_todos(
+    State(state): State<AppState>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> Result<Json<ImportReport>, StatusCode> {
+    let mut content = None;
+    let mut format = None;
+    let mut mapping = None;
+    let mut dry_run = false;
+
+    while let Some(field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+        let name = field.name().unwrap_or_default().to_string();
+        let value = field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?;
+
+        match name.as_str() {
+            "file" => content = Some(value),
+            "format" => format = Some(ImportFormat::parse(&value).ok_or(StatusCode::BAD_REQUEST)?),
+            "mapping" => {
+                let parsed: CsvMapping =
+                    serde_json::from_str(&value).map_err(|_| StatusCode::BAD_REQUEST)?;
+                mapping = Some(parsed);
+            }
+            "dry_run" => dry_run = value.trim() == "true",
+            _ => {}
+        }
+    }
+
+    let (Some(content), Some(format)) = (content, format) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+
+    let parsed = import::parse(format, &content, mapping.as_ref())
+        .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+
+    let (created, duplicates) = if dry_run {
+        let existing_titles: Vec<String> =
+            state.todos.list(&claims.sub, false).await?.into_iter().map(|todo| todo.title).collect();
+        import::split_duplicates(&existing_titles, parsed.todos)
+    } else {
+        state.todos.import(&claims.sub, parsed.todos, chrono::Utc::now()).await?
+    };
+    if !dry_run && !created.is_empty() {
+        state.todo_changes.notify(&claims.sub);
+    }
+
+    Ok(Json(ImportReport {
+        dry_run,
+        created,
+        duplicates,
+        errors: parsed.errors,
+    }))
+}
@@ -0,0 +1,69 @@
+// Tells connected clients when a user's todos change, as server-sent events on
+// /api/events, so they can fetch them again instead of polling. Handlers call
+// `notify` once a change is saved. Only clients of this process hear about the
+// changes made through it
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::Stream;
+use std::convert::Infallible;
+use tokio::sync::broadcast;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    StreamExt,
+};
+
+use todo_api_types::routes;
+
+use crate::{auth::Claims, AppState};
+
+// Changes a slow client can fall behind by before it is told about them all at once
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Clone)]
+pub struct TodoChanges {
+    // IDs of users whose todos changed
+    sender: broadcast::Sender<String>,
+}
+
+impl Default for TodoChanges {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        TodoChanges { sender }
+    }
+}
+
+impl TodoChanges {
+    pub fn notify(&self, user_id: &str) {
+        // Fails only when no one is listening
+        let _ = self.sender.send(user_id.to_string());
+    }
+
+    // Clients connected to /api/events
+    pub fn listeners(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = routes::EVENTS,
+    tag = "todos",
+    responses(
+        (status = 200, description = "A `todos` event whenever the user's todos change", body = String, content_type = "text/event-stream"),
+    ),
+)]
+pub async fn events(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let changes = BroadcastStream::new(state.todo_changes.sender.subscribe()).filter_map(move |change| match change {
+        Ok(user_id) if user_id != claims.sub => None,
+        // Lagging behind only means some changes were missed, and one event covers them
+        Ok(_) | Err(BroadcastStreamRecvError::Lagged(_)) => Some(Ok(Event::default().event(routes::EVENT_TODOS).data(""))),
+    });
+
+    Sse::new(changes).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,231 @@
+// Endpoints for load balancers and monitoring, none of which need a login:
+// /healthz answers while the process is up, /readyz once the database can be
+// reached and every migration is applied, and /metrics gives Prometheus
+// metrics. Requests are counted by the `track` middleware; the gauges are read
+// when the metrics are scraped
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::{db, AppState};
+
+// Upper bounds of the latency histogram's buckets, in seconds
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+// The route of requests that reached none: unknown paths, and requests
+// refused by authentication, which happens before routing
+const UNMATCHED: &str = "unmatched";
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+#[derive(Clone, Default)]
+pub struct Metrics {
+    requests: Arc<Mutex<BTreeMap<RequestKey, Latencies>>>,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct RequestKey {
+    method: String,
+    // As registered, e.g. /api/todos/:id, so there is one series per route
+    route: String,
+    status: u16,
+}
+
+#[derive(Default)]
+struct Latencies {
+    // Requests that took at most each bucket's bound
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    seconds: f64,
+}
+
+impl Metrics {
+    fn record(&self, key: RequestKey, seconds: f64) {
+        let mut requests = self.requests.lock().unwrap();
+        let latencies = requests.entry(key).or_default();
+        for (bucket, bound) in latencies.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        latencies.count += 1;
+        latencies.seconds += seconds;
+    }
+
+    fn write_requests(&self, out: &mut String) {
+        let requests = self.requests.lock().unwrap();
+
+        header(out, "todo_http_requests_total", "counter", "HTTP requests by method, route and status");
+        for (key, latencies) in requests.iter() {
+            let _ = writeln!(out, "todo_http_requests_total{{{}}} {}", labels(key), latencies.count);
+        }
+
+        // For streams such as /api/events, the time until the response starts
+        let name = "todo_http_request_duration_seconds";
+        header(out, name, "histogram", "Time taken to answer HTTP requests, by method, route and status");
+        for (key, latencies) in requests.iter() {
+            let labels = labels(key);
+            for (count, bound) in latencies.buckets.iter().zip(BUCKETS) {
+                let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, bound, count);
+            }
+            let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, latencies.count);
+            let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, latencies.seconds);
+            let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, latencies.count);
+        }
+    }
+}
+
+// Label values may not contain unescaped backslashes, quotes or newlines
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn labels(key: &RequestKey) -> String {
+    format!("method=\"{}\",route=\"{}\",status=\"{}\"", escape(&key.method), escape(&key.route), key.status)
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    header(out, name, "gauge", help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+// Wraps the whole router, so requests refused by authentication are counted too
+pub async fn track(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    // Left on the response by `remember_route`
+    let route = response.extensions().get::<MatchedPath>().map_or(UNMATCHED, MatchedPath::as_str);
+    let key = RequestKey { method, route: route.to_string(), status: response.status().as_u16() };
+    state.metrics.record(key, started.elapsed().as_secs_f64());
+    response
+}
+
+// A route layer: the route a request matched is only known once it is routed,
+// which is after `track` sees it
+pub async fn remember_route(request: Request, next: Next) -> Response {
+    let route = request.extensions().get::<MatchedPath>().cloned();
+    let mut response = next.run(request).await;
+    if let Some(route) = route {
+        response.extensions_mut().insert(route);
+    }
+    response
+}
+
+pub async fn healthz() -> &'static str {
+    "ok\n"
+}
+
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, &'static str) {
+    let Ok(applied) = state.db.applied_migrations().await else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "database unreachable\n");
+    };
+    let latest = state.db.known_migrations().last().map(|(version, _)| *version);
+    if applied.last().copied() != latest {
+        return (StatusCode::SERVICE_UNAVAILABLE, "migrations pending\n");
+    }
+    (StatusCode::OK, "ready\n")
+}
+
+pub async fn metrics(State(state): State<AppState>) -> Response {
+    let mut out = String::new();
+    state.metrics.write_requests(&mut out);
+
+    let (connections, idle) = state.db.connections();
+    gauge(&mut out, "todo_db_pool_connections", "Open database connections", connections);
+    gauge(&mut out, "todo_db_pool_idle_connections", "Open database connections not in use", idle);
+    let max = state.config.database.max_connections;
+    gauge(&mut out, "todo_db_pool_max_connections", "Database connections the pool may open", max);
+    gauge(&mut out, "todo_event_stream_clients", "Clients connected to /api/events", state.todo_changes.listeners());
+
+    // Tokens aren't stored, so a session is a login or registration whose token
+    // hasn't expired yet
+    let sessions_since = Utc::now() - Duration::hours(state.config.auth.token_lifetime_hours);
+    let counts = db::query(
+        "SELECT
+             (SELECT COUNT(*) FROM users) AS users,
+             (SELECT COUNT(*) FROM users WHERE last_login_at >= $1 OR created_at >= $1) AS sessions,
+             (SELECT COUNT(*) FROM todos WHERE deleted_at IS NULL AND NOT completed) AS open_todos,
+             (SELECT COUNT(*) FROM todos WHERE deleted_at IS NULL AND completed) AS completed_todos,
+             (SELECT COUNT(*) FROM todos WHERE deleted_at IS NOT NULL) AS trashed_todos"
+    )
+    .bind(sessions_since)
+    .fetch_one(&state.db)
+    .await;
+
+    // The request metrics are still worth having while the database is down
+    match counts {
+        Ok(row) => {
+            gauge(&mut out, "todo_users", "User accounts", row.get::<i64, _>("users"));
+            let help = "Users who registered or logged in within the token lifetime";
+            gauge(&mut out, "todo_active_sessions", help, row.get::<i64, _>("sessions"));
+            header(&mut out, "todo_todos", "gauge", "Todos by state");
+            for state in ["open", "completed", "trashed"] {
+                let count: i64 = row.get(format!("{}_todos", state).as_str());
+                let _ = writeln!(out, "todo_todos{{state=\"{}\"}} {}", state, count);
+            }
+        }
+        Err(err) => tracing::warn!("Failed to count users and todos for the metrics: {}", err),
+    }
+
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], out).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(route: &str, status: u16) -> RequestKey {
+        RequestKey { method: "GET".to_string(), route: route.to_string(), status }
+    }
+
+    #[test]
+    fn requests_are_counted_in_every_bucket_they_fit() {
+        let metrics = Metrics::default();
+        metrics.record(key("/api/todos", 200), 0.02);
+        metrics.record(key("/api/todos", 200), 3.0);
+        metrics.record(key("/api/todos", 404), 0.001);
+
+        let mut out = String::new();
+        metrics.write_requests(&mut out);
+        let name = "todo_http_request_duration_seconds";
+        let labels = "method=\"GET\",route=\"/api/todos\",status=\"200\"";
+        for line in [
+            format!("todo_http_requests_total{{{}}} 2", labels),
+            format!("{}_bucket{{{},le=\"0.01\"}} 0", name, labels),
+            format!("{}_bucket{{{},le=\"0.025\"}} 1", name, labels),
+            format!("{}_bucket{{{},le=\"2.5\"}} 1", name, labels),
+            format!("{}_bucket{{{},le=\"5\"}} 2", name, labels),
+            format!("{}_bucket{{{},le=\"+Inf\"}} 2", name, labels),
+            format!("{}_sum{{{}}} 3.02", name, labels),
+            format!("{}_count{{{}}} 2", name, labels),
+            "todo_http_requests_total{method=\"GET\",route=\"/api/todos\",status=\"404\"} 1".to_string(),
+        ] {
+            assert!(out.lines().any(|metric| metric == line), "no {} in\n{}", line, out);
+        }
+        assert_eq!(out.matches("# TYPE ").count(), 2);
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        let labels = labels(&key("/a\"b\\c\nd", 200));
+        assert_eq!(labels, "method=\"GET\",route=\"/a\\\"b\\\\c\\nd\",status=\"200\"");
+    }
+}
@@ -0,0 +1,207 @@
+// The API as an OpenAPI 3.1 document, put together from the handlers'
+// `#[utoipa::path]` attributes and the schemas `todo-api-types` derives with
+// its `openapi` feature. Served with a Swagger UI page to try it out in
+
+use axum::response::{Html, Json};
+use utoipa::{
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        OpenApi as Document,
+    },
+    Modify, OpenApi,
+};
+
+use crate::admin;
+
+// Handlers that skip authentication say so with `security(())`
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Todo API",
+        description = "Requests need a token from `/api/login` or `/api/register` as a bearer token. \
+            Without a valid one they fail with 401; for disabled accounts, and for accounts that must \
+            change their password first, with 403."
+    ),
+    paths(
+        crate::register,
+        crate::login,
+        crate::refresh_token,
+        crate::get_me,
+        crate::update_me,
+        crate::delete_me,
+        crate::restore_me,
+        crate::export_me,
+        crate::change_password,
+        crate::verify_email,
+        crate::get_todos,
+        crate::live::events,
+        crate::create_todo,
+        crate::bulk_update_todos,
+        crate::get_todo,
+        crate::update_todo,
+        crate::delete_todo,
+        crate::restore_todo,
+        crate::get_todo_history,
+        crate::move_todo,
+        crate::export_todos,
+        crate::import_todos,
+        crate::get_calendar_token,
+        crate::regenerate_calendar_token,
+        crate::calendar_feed,
+        crate::get_app_passwords,
+        crate::create_app_password,
+        crate::delete_app_password,
+        crate::get_webhooks,
+        crate::create_webhook,
+        crate::update_webhook,
+        crate::delete_webhook,
+        crate::get_webhook_deliveries,
+        crate::retry_webhook_delivery,
+        crate::get_notification_preferences,
+        crate::update_notification_preferences,
+        crate::preview_digest,
+        crate::unsubscribe_digest,
+        crate::get_push_public_key,
+        crate::subscribe_push,
+        crate::unsubscribe_push,
+    ),
+    // Schemas only referenced from query parameters aren't picked up on their own
+    components(schemas(crate::export::ExportFormat)),
+    modifiers(&BearerAuth),
+    security(("bearer" = [])),
+)]
+struct ApiDoc;
+
+// The JWTs `auth_middleware` checks, named "bearer" as `security` above requires
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, document: &mut Document) {
+        let components = document.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
+
+pub fn document() -> Document {
+    let mut document = ApiDoc::openapi();
+    document.merge(admin::ApiDoc::openapi());
+
+    // The router writes parameters as `:id`, OpenAPI as `{id}`
+    let paths = std::mem::take(&mut document.paths.paths);
+    document.paths.paths = paths.into_iter().map(|(path, item)| (openapi_path(&path), item)).collect();
+    document
+}
+
+fn openapi_path(route: &str) -> String {
+    route
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub async fn document_json() -> Json<Document> {
+    Json(document())
+}
+
+// Swagger UI itself comes from a CDN, so the backend only serves this page
+pub async fn docs_page() -> Html<&'static str> {
+    Html(DOCS_PAGE)
+}
+
+const DOCS_PAGE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Todo API</title>
+<link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5.17.14/swagger-ui.css">
+</head>
+<body>
+<div id="swagger-ui"></div>
+<script src="https://unpkg.com/swagger-ui-dist@5.17.14/swagger-ui-bundle.js"></script>
+<script>
+SwaggerUIBundle({ url: "/api/openapi.json", dom_id: "#swagger-ui", persistAuthorization: true });
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use todo_api_types::routes;
+
+    #[test]
+    fn route_parameters_are_written_the_openapi_way() {
+        assert_eq!(openapi_path("/api/todos"), "/api/todos");
+        assert_eq!(openapi_path("/api/todos/:id/move"), "/api/todos/{id}/move");
+        assert_eq!(
+            openapi_path("/api/webhooks/:id/deliveries/:delivery_id/retry"),
+            "/api/webhooks/{id}/deliveries/{delivery_id}/retry"
+        );
+    }
+
+    #[test]
+    fn every_api_route_is_documented() {
+        let document = document();
+        // Besides the document and its page, and the CalDAV and monitoring
+        // routes, which aren't part of the JSON API
+        let undocumented: Vec<_> = [
+            routes::REGISTER, routes::LOGIN, routes::TOKEN_REFRESH, routes::ME, routes::ME_RESTORE, routes::ME_EXPORT,
+            routes::ME_PASSWORD, routes::ME_EMAIL_VERIFY, routes::TODOS, routes::TODOS_BULK, routes::TODO,
+            routes::TODO_RESTORE, routes::TODO_HISTORY, routes::TODO_MOVE, routes::EVENTS, routes::EXPORT,
+            routes::IMPORT, routes::CALENDAR_TOKEN, routes::CALENDAR_FEED, routes::APP_PASSWORDS, routes::APP_PASSWORD,
+            routes::WEBHOOKS, routes::WEBHOOK, routes::WEBHOOK_DELIVERIES, routes::WEBHOOK_DELIVERY_RETRY,
+            routes::NOTIFICATION_PREFERENCES, routes::DIGEST_PREVIEW, routes::DIGEST_UNSUBSCRIBE,
+            routes::PUSH_PUBLIC_KEY, routes::PUSH_SUBSCRIPTIONS, routes::ADMIN_USERS, routes::ADMIN_USER,
+            routes::ADMIN_USER_DISABLE, routes::ADMIN_USER_ENABLE, routes::ADMIN_USER_RESET_PASSWORD,
+            routes::ADMIN_STATS, routes::ADMIN_JOBS, routes::ADMIN_JOB_RETRY,
+        ]
+        .into_iter()
+        .map(openapi_path)
+        .filter(|path| !document.paths.paths.contains_key(path))
+        .collect();
+        assert!(undocumented.is_empty(), "{:?}", undocumented);
+    }
+
+    // Every `$ref` in `value`
+    fn references<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(fields) => {
+                for (name, field) in fields {
+                    match (name.as_str(), field) {
+                        ("$ref", Value::String(reference)) => found.push(reference),
+                        _ => references(field, found),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|item| references(item, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn every_schema_referenced_is_included() {
+        let document = serde_json::to_value(document()).unwrap();
+        let mut found = Vec::new();
+        references(&document, &mut found);
+        assert!(found.contains(&"#/components/schemas/Todo"));
+
+        let schemas = &document["components"]["schemas"];
+        let missing: Vec<_> = found
+            .into_iter()
+            .filter(|reference| {
+                let name = reference.strip_prefix("#/components/schemas/").unwrap_or(reference);
+                schemas[name].is_null()
+            })
+            .collect();
+        assert!(missing.is_empty(), "{:?}", missing);
+    }
+}
@@ -0,0 +1,152 @@
+// Fractional indexing: positions are strings of base-62 digits compared byte-wise
+// (ORDER BY position COLLATE "C"), so a key can always be generated between two
+// neighbours without renumbering any other row. Generated keys never end in '0',
+// which guarantees there is room between any two distinct keys.
+
+const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const BASE: usize = DIGITS.len();
+
+fn digit_value(byte: u8) -> Option<usize> {
+    DIGITS.iter().position(|&digit| digit == byte)
+}
+
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && !key.ends_with('0') && key.bytes().all(|byte| digit_value(byte).is_some())
+}
+
+// Returns a key that sorts strictly between `before` and `after`; `None` means unbounded.
+// Returns `None` if the bounds are invalid or not in ascending order.
+pub fn key_between(before: Option<&str>, after: Option<&str>) -> Option<String> {
+    if before.is_some_and(|key| !is_valid_key(key)) || after.is_some_and(|key| !is_valid_key(key)) {
+        return None;
+    }
+    if let (Some(before), Some(after)) = (before, after) {
+        if before >= after {
+            return None;
+        }
+    }
+
+    let lower = before.unwrap_or("").as_bytes();
+    let upper = after.map(str::as_bytes);
+    let mut bounded = upper.is_some();
+    let mut key = Vec::new();
+
+    for index in 0.. {
+        let low = lower.get(index).and_then(|&byte| digit_value(byte)).unwrap_or(0);
+        let high = match upper {
+            Some(upper) if bounded => upper.get(index).and_then(|&byte| digit_value(byte)).unwrap_or(0),
+            _ => BASE,
+        };
+
+        if low == high {
+            key.push(DIGITS[low]);
+            continue;
+        }
+
+        let middle = (low + high) / 2;
+        if middle > low {
+            key.push(DIGITS[middle]);
+            break;
+        }
+
+        // Adjacent digits: keep the lower one and look for room in the next position
+        key.push(DIGITS[low]);
+        bounded = false;
+    }
+
+    String::from_utf8(key).ok()
+}
+
+// Returns `count` ascending keys between `before` and `after`, spread out by repeated
+// bisection so their length grows with log(count) rather than count.
+pub fn keys_between(before: Option<&str>, after: Option<&str>, count: usize) -> Option<Vec<String>> {
+    if count == 0 {
+        return Some(Vec::new());
+    }
+
+    let middle = key_between(before, after)?;
+    let left_count = (count - 1) / 2;
+    let right_count = count - 1 - left_count;
+
+    let mut keys = keys_between(before, Some(&middle), left_count)?;
+    keys.push(middle.clone());
+    keys.extend(keys_between(Some(&middle), after, right_count)?);
+    Some(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The key between the bounds, checked to sort strictly between them
+    fn between(before: Option<&str>, after: Option<&str>) -> String {
+        let key = key_between(before, after).expect("a key");
+        assert!(is_valid_key(&key), "{:?}", key);
+        assert!(before.is_none_or(|before| before < key.as_str()), "{:?} < {:?}", before, key);
+        assert!(after.is_none_or(|after| key.as_str() < after), "{:?} < {:?}", key, after);
+        key
+    }
+
+    #[test]
+    fn unbounded_keys_start_in_the_middle() {
+        assert_eq!(between(None, None), "V");
+    }
+
+    #[test]
+    fn keys_go_after_a_lower_bound() {
+        assert_eq!(between(Some("V"), None), "k");
+        assert_eq!(between(Some("a"), None), "n");
+        between(Some("z"), None);
+        between(Some("zzz"), None);
+    }
+
+    #[test]
+    fn keys_go_before_an_upper_bound() {
+        assert_eq!(between(None, Some("V")), "F");
+        between(None, Some("1"));
+        between(None, Some("01"));
+        between(None, Some("001"));
+    }
+
+    #[test]
+    fn adjacent_keys_get_a_longer_one_between() {
+        assert_eq!(between(Some("a"), Some("b")), "aV");
+        between(Some("a"), Some("a1"));
+        between(Some("a"), Some("a01"));
+        between(Some("y"), Some("z"));
+        between(Some("yz"), Some("z"));
+        between(Some("zy"), Some("zz"));
+    }
+
+    #[test]
+    fn keys_keep_fitting_between_ever_closer_neighbours() {
+        let (mut low, high) = ("a".to_string(), "b".to_string());
+        for _ in 0..100 {
+            low = between(Some(&low), Some(&high));
+        }
+        let (low, mut high) = ("a".to_string(), "b".to_string());
+        for _ in 0..100 {
+            high = between(Some(&low), Some(&high));
+        }
+    }
+
+    #[test]
+    fn bounds_out_of_order_or_invalid_have_no_key() {
+        assert_eq!(key_between(Some("b"), Some("a")), None);
+        assert_eq!(key_between(Some("a"), Some("a")), None);
+        assert_eq!(key_between(Some("a0"), None), None);
+        assert_eq!(key_between(None, Some("")), None);
+        assert_eq!(key_between(Some("a-"), None), None);
+    }
+
+    #[test]
+    fn many_keys_are_ascending_and_short() {
+        assert_eq!(keys_between(None, None, 0), Some(Vec::new()));
+        let keys = keys_between(Some("a"), Some("b"), 1000).unwrap();
+        assert_eq!(keys.len(), 1000);
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(keys.first().unwrap().as_str() > "a" && keys.last().unwrap().as_str() < "b");
+        assert!(keys.iter().all(|key| is_valid_key(key) && key.len() <= 4), "{:?}", keys);
+        assert_eq!(keys_between(Some("b"), Some("a"), 2), None);
+    }
+}
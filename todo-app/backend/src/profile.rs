@@ -0,0 +1,159 @@
+use chrono::{Duration, Utc};
+use todo_api_types::User;
+
+use crate::{
+    auth,
+    db::{self, Row},
+    jobs::{Job, JobContext},
+    mailer::Email,
+};
+
+pub fn user_from_row(row: &Row) -> Result<User, sqlx::Error> {
+    Ok(User {
+        id: row.try_get("id")?,
+        username: row.try_get("username")?,
+        email: row.try_get("email")?,
+        display_name: row.try_get("display_name")?,
+        timezone: row.try_get("timezone")?,
+        locale: row.try_get("locale")?,
+        avatar_url: row.try_get("avatar_url")?,
+        role: row.try_get("role")?,
+        password_reset_required: row.try_get("password_reset_required")?,
+        pending_email: row.try_get("pending_email")?,
+        deletion_scheduled_at: row.try_get("deletion_scheduled_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+pub fn is_valid_email(email: &str) -> bool {
+    email.parse::<lettre::Address>().is_ok()
+}
+
+// A BCP 47 tag such as "en", "pt-BR" or "zh-Hant-TW": a 2-3 letter language
+// followed by alphanumeric subtags of 1-8 characters
+pub fn is_valid_locale(locale: &str) -> bool {
+    let mut subtags = locale.split('-');
+    let language_ok = subtags
+        .next()
+        .is_some_and(|language| (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic()));
+
+    language_ok
+        && locale.len() <= 35
+        && subtags.all(|subtag| (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+// Sends the verification link for the user's pending email address. Each
+// attempt issues a new token, so only the most recent link works
+pub async fn send_email_verification(context: &JobContext, job: &Job) -> Result<(), String> {
+    let user_id = job.payload_str("user_id")?;
+    let token = auth::new_secret_token();
+
+    let row = db::query(
+        "UPDATE users SET email_verification_token_hash = $1, email_verification_expires_at = $2
+         WHERE id = $3 AND pending_email IS NOT NULL RETURNING username, pending_email"
+    )
+    .bind(auth::hash_secret(&token))
+    .bind(Utc::now() + Duration::hours(context.config.auth.email_verification_hours))
+    .bind(user_id)
+    .fetch_optional(&context.db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // The change was cancelled or confirmed since the job was queued
+    let Some(row) = row else {
+        return Ok(());
+    };
+
+    let text = format!(
+        "Hi {},\n\nOpen this link to use this address for your todo account:\n\n{}/api/me/email/verify?token={}\n\n\
+         The link expires in {} hours. If you didn't ask for this, ignore this email and nothing will change.\n",
+        row.get::<String, _>("username"),
+        context.config.server.public_url,
+        token,
+        context.config.auth.email_verification_hours
+    );
+
+    context
+        .mailer
+        .send(Email {
+            to: row.get("pending_email"),
+            subject: "Confirm your new email address".to_string(),
+            text,
+            html: None,
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    use crate::{
+        config::{Config, DatabaseConfig},
+        db::Pool,
+        jobs::JOB_SEND_EMAIL_VERIFICATION,
+        mailer::SentMail,
+        repository::{NewUser, SqlRepository, UserRepository},
+    };
+
+    #[test]
+    fn locales_are_bcp_47_tags() {
+        for locale in ["en", "pt-BR", "zh-Hant-TW", "de-CH-1996", "ast"] {
+            assert!(is_valid_locale(locale), "{}", locale);
+        }
+        for locale in ["", "e", "english", "en_US", "en-", "en-toolongsubtag", "12", "en-B@"] {
+            assert!(!is_valid_locale(locale), "{}", locale);
+        }
+    }
+
+    #[test]
+    fn emails_need_a_user_and_a_domain() {
+        assert!(is_valid_email("alice@example.com"));
+        for email in ["", "alice", "@example.com", "alice@", "alice @example.com"] {
+            assert!(!is_valid_email(email), "{}", email);
+        }
+    }
+
+    #[tokio::test]
+    async fn verification_links_go_to_the_pending_address() {
+        let config = DatabaseConfig { url: "sqlite::memory:".to_string(), max_connections: 1, ..Default::default() };
+        let pool = Pool::connect(&config).await.unwrap();
+        pool.migrate().await.unwrap();
+        let user = NewUser {
+            id: "user-1".to_string(),
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            password_hash: "hash".to_string(),
+        };
+        UserRepository::create(&SqlRepository::new(pool.clone()), user).await.unwrap();
+        let mail = Arc::new(SentMail::default());
+        let context = JobContext {
+            db: pool.clone(),
+            http: reqwest::Client::new(),
+            mailer: mail.clone(),
+            vapid: None,
+            config: Arc::new(Config::default()),
+        };
+        let job = Job {
+            id: "job-1".to_string(),
+            kind: JOB_SEND_EMAIL_VERIFICATION.to_string(),
+            payload: json!({ "user_id": "user-1" }),
+            attempts: 1,
+            max_attempts: 5,
+        };
+
+        // Nothing is pending, say because the change was cancelled
+        send_email_verification(&context, &job).await.unwrap();
+        assert!(mail.take().is_empty());
+
+        db::query("UPDATE users SET pending_email = 'alice@example.net'").execute(&pool).await.unwrap();
+        send_email_verification(&context, &job).await.unwrap();
+        let sent = mail.take().pop().unwrap();
+        assert_eq!(sent.to, "alice@example.net");
+        let token = sent.text.split("/api/me/email/verify?token=").nth(1).unwrap().split_whitespace().next().unwrap();
+        let stored: String = db::query_scalar("SELECT email_verification_token_hash FROM users").fetch_one(&pool).await.unwrap();
+        assert_eq!(stored, auth::hash_secret(token));
+    }
+}
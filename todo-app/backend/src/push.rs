@@ -0,0 +1,264 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes128Gcm, Nonce,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hkdf::Hkdf;
+use p256::{
+    ecdh::diffie_hellman,
+    ecdsa::{signature::Signer, Signature, SigningKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+    PublicKey, SecretKey,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde_json::json;
+use sha2::Sha256;
+
+use crate::{config::PushConfig, webhooks};
+
+// How long the push service keeps a message for an offline browser
+const TTL_SECS: u32 = 24 * 60 * 60;
+const TOKEN_LIFETIME_SECS: i64 = 12 * 60 * 60;
+const RECORD_SIZE: u32 = 4096;
+
+pub enum PushError {
+    // The browser unsubscribed; the subscription should be forgotten
+    Gone,
+    Failed(String),
+}
+
+// Application server keys (RFC 8292), identifying this server to push services
+pub struct Vapid {
+    signing_key: SigningKey,
+    // Uncompressed public key, base64url: what browsers need as `applicationServerKey`
+    pub public_key: String,
+    subject: String,
+}
+
+impl Vapid {
+    // Push is disabled without a private key
+    pub fn from_config(config: &PushConfig) -> anyhow::Result<Option<Vapid>> {
+        let Some(private_key) = &config.vapid_private_key else {
+            return Ok(None);
+        };
+
+        let signing_key = SigningKey::from_slice(&URL_SAFE_NO_PAD.decode(private_key.trim())?)?;
+        let public_key = URL_SAFE_NO_PAD.encode(signing_key.verifying_key().to_encoded_point(false).as_bytes());
+        let subject = config.vapid_subject.clone();
+
+        Ok(Some(Vapid { signing_key, public_key, subject }))
+    }
+
+    // `vapid t=<ES256 JWT for the endpoint's origin>, k=<public key>`
+    fn authorization(&self, endpoint: &reqwest::Url) -> String {
+        let header = URL_SAFE_NO_PAD.encode(json!({ "typ": "JWT", "alg": "ES256" }).to_string());
+        let claims = URL_SAFE_NO_PAD.encode(
+            json!({
+                "aud": endpoint.origin().ascii_serialization(),
+                "exp": chrono::Utc::now().timestamp() + TOKEN_LIFETIME_SECS,
+                "sub": self.subject,
+            })
+            .to_string(),
+        );
+        let signing_input = format!("{}.{}", header, claims);
+        let signature: Signature = self.signing_key.sign(signing_input.as_bytes());
+
+        format!(
+            "vapid t={}.{}, k={}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.to_bytes()),
+            self.public_key
+        )
+    }
+}
+
+// Checks the keys a browser sent with its subscription
+pub fn is_valid_subscription_keys(p256dh: &str, auth: &str) -> bool {
+    let public_key_ok = URL_SAFE_NO_PAD
+        .decode(p256dh)
+        .is_ok_and(|bytes| PublicKey::from_sec1_bytes(&bytes).is_ok());
+    let auth_ok = URL_SAFE_NO_PAD.decode(auth).is_ok_and(|bytes| bytes.len() == 16);
+    public_key_ok && auth_ok
+}
+
+pub async fn send(
+    client: &reqwest::Client,
+    vapid: &Vapid,
+    endpoint: &str,
+    p256dh: &str,
+    auth: &str,
+    payload: &[u8],
+) -> Result<(), PushError> {
+    let url = reqwest::Url::parse(endpoint).map_err(|e| PushError::Failed(e.to_string()))?;
+    webhooks::check_destination(&url).map_err(PushError::Failed)?;
+    let body = encrypt(p256dh, auth, payload).ok_or_else(|| PushError::Failed("Invalid subscription keys".to_string()))?;
+
+    let response = client
+        .post(url.clone())
+        .header("TTL", TTL_SECS.to_string())
+        .header(reqwest::header::CONTENT_ENCODING, "aes128gcm")
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .header(reqwest::header::AUTHORIZATION, vapid.authorization(&url))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| PushError::Failed(e.to_string()))?;
+
+    match response.status().as_u16() {
+        200..=299 => Ok(()),
+        404 | 410 => Err(PushError::Gone),
+        status => Err(PushError::Failed(format!("HTTP {}", status))),
+    }
+}
+
+// Message encryption for Web Push (RFC 8291) as a single aes128gcm record (RFC 8188)
+fn encrypt(p256dh: &str, auth: &str, payload: &[u8]) -> Option<Vec<u8>> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    encrypt_with(&SecretKey::random(&mut OsRng), &salt, p256dh, auth, payload)
+}
+
+// `encrypt` with a given server key and salt, which must be new for every message
+fn encrypt_with(server_secret: &SecretKey, salt: &[u8; 16], p256dh: &str, auth: &str, payload: &[u8]) -> Option<Vec<u8>> {
+    let user_agent_key_bytes = URL_SAFE_NO_PAD.decode(p256dh).ok()?;
+    let user_agent_key = PublicKey::from_sec1_bytes(&user_agent_key_bytes).ok()?;
+    let auth_secret = URL_SAFE_NO_PAD.decode(auth).ok()?;
+
+    let server_key = server_secret.public_key().to_encoded_point(false);
+    let shared_secret = diffie_hellman(server_secret.to_nonzero_scalar(), user_agent_key.as_affine());
+
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(&user_agent_key_bytes);
+    key_info.extend_from_slice(server_key.as_bytes());
+    let mut input_key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&auth_secret), shared_secret.raw_secret_bytes())
+        .expand(&key_info, &mut input_key)
+        .ok()?;
+
+    let content_keys = Hkdf::<Sha256>::new(Some(salt), &input_key);
+    let mut content_key = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    content_keys.expand(b"Content-Encoding: aes128gcm\0", &mut content_key).ok()?;
+    content_keys.expand(b"Content-Encoding: nonce\0", &mut nonce).ok()?;
+
+    // 0x02 marks the last (and only) record
+    let mut plaintext = payload.to_vec();
+    plaintext.push(2);
+    let ciphertext = Aes128Gcm::new_from_slice(&content_key)
+        .ok()?
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .ok()?;
+
+    let mut body = salt.to_vec();
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(server_key.as_bytes().len() as u8);
+    body.extend_from_slice(server_key.as_bytes());
+    body.extend_from_slice(&ciphertext);
+    Some(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::Verifier, VerifyingKey};
+    use serde_json::Value;
+
+    // RFC 8291 section 5
+    const PLAINTEXT: &[u8] = b"When I grow up, I want to be a watermelon";
+    const SERVER_PRIVATE_KEY: &str = "yfWPiYE-n46HLnH0KqZOF1fJJU3MYrct3AELtAQ-oRw";
+    const USER_AGENT_PRIVATE_KEY: &str = "q1dXpw3UpT5VOmu_cf_v6ih07Aems3njxI-JWgLcM94";
+    const USER_AGENT_PUBLIC_KEY: &str =
+        "BCVxsr7N_eNgVRqvHtD0zTZsEc6-VV-JvLexhqUzORcxaOzi6-AYWXvTBHm4bjyPjs7Vd8pZGH6SRpkNtoIAiw4";
+    const AUTH_SECRET: &str = "BTBZMqHH6r4Tts7J_aSIgg";
+    const SALT: &str = "DGv6ra1nlYgDCS1FRnbzlw";
+    const MESSAGE: &str = "DGv6ra1nlYgDCS1FRnbzlwAAEABBBP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A_yl95bQpu6cVPTpK4Mqgkf1CXztLVBSt2Ks3oZwbuwXPXLWyouBWLVWGNWQexSgSxsj_Qulcy4a-fN";
+
+    fn decode(value: &str) -> Vec<u8> {
+        URL_SAFE_NO_PAD.decode(value).unwrap()
+    }
+
+    // What a browser does with a message: RFC 8291's key derivation from the
+    // user agent's side, then the record's decryption
+    fn decrypt(body: &[u8], user_agent_secret: &SecretKey, auth: &str) -> Vec<u8> {
+        let (salt, rest) = body.split_at(16);
+        assert_eq!(&rest[..4], RECORD_SIZE.to_be_bytes());
+        let key_length = rest[4] as usize;
+        let (server_key_bytes, ciphertext) = rest[5..].split_at(key_length);
+        let server_key = PublicKey::from_sec1_bytes(server_key_bytes).unwrap();
+
+        let shared_secret = diffie_hellman(user_agent_secret.to_nonzero_scalar(), server_key.as_affine());
+        let mut key_info = b"WebPush: info\0".to_vec();
+        key_info.extend_from_slice(user_agent_secret.public_key().to_encoded_point(false).as_bytes());
+        key_info.extend_from_slice(server_key_bytes);
+        let mut input_key = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&decode(auth)), shared_secret.raw_secret_bytes()).expand(&key_info, &mut input_key).unwrap();
+        let content_keys = Hkdf::<Sha256>::new(Some(salt), &input_key);
+        let mut content_key = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        content_keys.expand(b"Content-Encoding: aes128gcm\0", &mut content_key).unwrap();
+        content_keys.expand(b"Content-Encoding: nonce\0", &mut nonce).unwrap();
+
+        let mut plaintext = Aes128Gcm::new_from_slice(&content_key).unwrap().decrypt(Nonce::from_slice(&nonce), ciphertext).unwrap();
+        assert_eq!(plaintext.pop(), Some(2));
+        plaintext
+    }
+
+    #[test]
+    fn encrypts_the_rfc_8291_example() {
+        let server_secret = SecretKey::from_slice(&decode(SERVER_PRIVATE_KEY)).unwrap();
+        let salt: [u8; 16] = decode(SALT).try_into().unwrap();
+        let body = encrypt_with(&server_secret, &salt, USER_AGENT_PUBLIC_KEY, AUTH_SECRET, PLAINTEXT).unwrap();
+        assert_eq!(URL_SAFE_NO_PAD.encode(&body), MESSAGE);
+    }
+
+    #[test]
+    fn messages_decrypt_with_the_subscription_keys() {
+        let user_agent_secret = SecretKey::from_slice(&decode(USER_AGENT_PRIVATE_KEY)).unwrap();
+        let first = encrypt(USER_AGENT_PUBLIC_KEY, AUTH_SECRET, PLAINTEXT).unwrap();
+        let second = encrypt(USER_AGENT_PUBLIC_KEY, AUTH_SECRET, PLAINTEXT).unwrap();
+        // A fresh key and salt every time
+        assert_ne!(first[..16], second[..16]);
+        assert_ne!(first, second);
+        assert_eq!(decrypt(&first, &user_agent_secret, AUTH_SECRET), PLAINTEXT);
+        assert_eq!(decrypt(&second, &user_agent_secret, AUTH_SECRET), PLAINTEXT);
+    }
+
+    #[test]
+    fn subscription_keys_are_checked() {
+        assert!(is_valid_subscription_keys(USER_AGENT_PUBLIC_KEY, AUTH_SECRET));
+        assert!(!is_valid_subscription_keys("not a key", AUTH_SECRET));
+        assert!(!is_valid_subscription_keys(USER_AGENT_PUBLIC_KEY, "dG9vIHNob3J0"));
+        assert!(encrypt("BAAA", AUTH_SECRET, PLAINTEXT).is_none());
+    }
+
+    #[test]
+    fn vapid_tokens_are_signed_for_the_push_service() {
+        let config = PushConfig {
+            vapid_private_key: Some(SERVER_PRIVATE_KEY.to_string()),
+            vapid_subject: "mailto:admin@example.com".to_string(),
+        };
+        let vapid = Vapid::from_config(&config).unwrap().unwrap();
+        assert_eq!(URL_SAFE_NO_PAD.encode(decode(&vapid.public_key)), vapid.public_key);
+        let endpoint = reqwest::Url::parse("https://push.example.net:8443/send/abc?x=1").unwrap();
+
+        let authorization = vapid.authorization(&endpoint);
+        let (token, key) = authorization.strip_prefix("vapid t=").unwrap().split_once(", k=").unwrap();
+        assert_eq!(key, vapid.public_key);
+        let (signing_input, signature) = token.rsplit_once('.').unwrap();
+        let verifying_key = VerifyingKey::from_sec1_bytes(&decode(key)).unwrap();
+        let signature = Signature::from_slice(&decode(signature)).unwrap();
+        assert!(verifying_key.verify(signing_input.as_bytes(), &signature).is_ok());
+
+        let (header, claims) = signing_input.split_once('.').unwrap();
+        let header: Value = serde_json::from_slice(&decode(header)).unwrap();
+        assert_eq!(header, json!({ "typ": "JWT", "alg": "ES256" }));
+        let claims: Value = serde_json::from_slice(&decode(claims)).unwrap();
+        assert_eq!(claims["aud"], "https://push.example.net:8443");
+        assert_eq!(claims["sub"], "mailto:admin@example.com");
+        // RFC 8292 allows at most 24 hours
+        let expires_in = claims["exp"].as_i64().unwrap() - chrono::Utc::now().timestamp();
+        assert!(expires_in > 0 && expires_in <= 24 * 60 * 60, "{}", expires_in);
+
+        assert!(Vapid::from_config(&PushConfig { vapid_private_key: None, ..config }).unwrap().is_none());
+    }
+}
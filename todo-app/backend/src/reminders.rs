@@ -0,0 +1,160 @@
+use chrono::Utc;
+use serde_json::json;
+
+use crate::{
+    db,
+    jobs::{self, Job, JobContext},
+    mailer::Email,
+    push::{self, PushError},
+    repository::todo_from_row,
+};
+
+// Hands every reminder that has come due to the user's notification channels,
+// one job per email and per browser so each is retried on its own
+pub async fn dispatch(context: &JobContext) -> Result<(), String> {
+    let mut tx = context.db.begin().await.map_err(|e| e.to_string())?;
+    let now = Utc::now();
+
+    let due = db::query(
+        "SELECT t.id, t.user_id, COALESCE(p.email_reminders, TRUE) AS email_reminders, COALESCE(p.push_reminders, TRUE) AS push_reminders
+         FROM todos t JOIN users u ON u.id = t.user_id LEFT JOIN notification_preferences p ON p.user_id = u.id
+         WHERE u.deletion_scheduled_at IS NULL AND u.disabled_at IS NULL AND t.remind_at <= $1 AND t.reminded_at IS NULL AND t.deleted_at IS NULL AND NOT t.completed
+         FOR UPDATE OF t SKIP LOCKED"
+    )
+    .bind(now)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for row in &due {
+        let todo_id: String = row.get("id");
+        let user_id: String = row.get("user_id");
+
+        db::query("UPDATE todos SET reminded_at = $1 WHERE id = $2")
+            .bind(now)
+            .bind(&todo_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if row.get::<bool, _>("email_reminders") {
+            jobs::enqueue(&mut *tx, jobs::JOB_SEND_REMINDER_EMAIL, json!({ "todo_id": todo_id }), now)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        if row.get::<bool, _>("push_reminders") && context.vapid.is_some() {
+            let subscription_ids: Vec<String> = db::query("SELECT id FROM push_subscriptions WHERE user_id = $1")
+                .bind(&user_id)
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?
+                .iter()
+                .map(|row| row.get("id"))
+                .collect();
+
+            for subscription_id in subscription_ids {
+                jobs::enqueue(
+                    &mut *tx,
+                    jobs::JOB_SEND_REMINDER_PUSH,
+                    json!({ "todo_id": todo_id, "subscription_id": subscription_id }),
+                    now,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+pub async fn send_email(context: &JobContext, job: &Job) -> Result<(), String> {
+    let todo_id = job.payload_str("todo_id")?;
+
+    let row = db::query(
+        "SELECT t.*, u.email FROM todos t JOIN users u ON u.id = t.user_id WHERE t.id = $1 AND t.deleted_at IS NULL AND NOT t.completed"
+    )
+    .bind(todo_id)
+    .fetch_optional(&context.db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Completed or deleted since the reminder was queued
+    let Some(row) = row else {
+        return Ok(());
+    };
+    let todo = todo_from_row(&row).map_err(|e| e.to_string())?;
+
+    let mut text = format!("This is your reminder for \"{}\".\n", todo.title);
+    if let Some(due_at) = todo.due_at {
+        text.push_str(&format!("\nDue: {}\n", due_at.format("%Y-%m-%d %H:%M UTC")));
+    }
+    if let Some(description) = todo.description.as_deref().filter(|d| !d.is_empty()) {
+        text.push_str(&format!("\n{}\n", description));
+    }
+
+    context
+        .mailer
+        .send(Email {
+            to: row.get("email"),
+            subject: format!("Reminder: {}", todo.title),
+            text,
+            html: None,
+        })
+        .await
+}
+
+pub async fn send_push(context: &JobContext, job: &Job) -> Result<(), String> {
+    let Some(vapid) = context.vapid.as_deref() else {
+        return Ok(());
+    };
+    let todo_id = job.payload_str("todo_id")?;
+    let subscription_id = job.payload_str("subscription_id")?;
+
+    let row = db::query(
+        "SELECT t.title, s.endpoint, s.p256dh, s.auth FROM todos t JOIN push_subscriptions s ON s.user_id = t.user_id
+         WHERE t.id = $1 AND s.id = $2 AND t.deleted_at IS NULL AND NOT t.completed"
+    )
+    .bind(todo_id)
+    .bind(subscription_id)
+    .fetch_optional(&context.db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // The todo was completed or deleted, or the browser unsubscribed
+    let Some(row) = row else {
+        return Ok(());
+    };
+
+    // Shown by the frontend's service worker
+    let payload = json!({
+        "title": "Reminder",
+        "body": row.get::<String, _>("title"),
+        "todo_id": todo_id,
+        "url": format!("/todos/{}", todo_id),
+    });
+
+    let result = push::send(
+        &context.http,
+        vapid,
+        row.get("endpoint"),
+        row.get("p256dh"),
+        row.get("auth"),
+        payload.to_string().as_bytes(),
+    )
+    .await;
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(PushError::Gone) => {
+            db::query("DELETE FROM push_subscriptions WHERE id = $1")
+                .bind(subscription_id)
+                .execute(&context.db)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        }
+        Err(PushError::Failed(err)) => Err(err),
+    }
+}
@@ -0,0 +1,542 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::sync::{Mutex, MutexGuard};
+use todo_api_types::{
+    BulkAction, BulkItemResult, CreateTodoRequest, ImportedTodo, Todo, TodoEvent, TodoUpdate, User, UserUpdate,
+};
+use uuid::Uuid;
+
+use super::{
+    apply_bulk_action, apply_update, imported_todo, position_between, run_positions, shared_position, top_keys,
+    AccountExport, AccountStatus, BulkChange, BulkTargets, Credentials, NewUser, RepositoryError, RepositoryResult,
+    TodoRepository, UserRepository,
+};
+use crate::{auth, digest, history, import};
+
+// Users and todos kept in memory, for tests. Behaves like `SqlRepository`,
+// except that webhooks are never told about changes, and verification links
+// aren't sent. Nothing else is kept, so exports have the default notification
+// preferences and no app passwords or webhooks
+#[derive(Default)]
+pub struct MemoryRepository {
+    data: Mutex<Data>,
+}
+
+#[derive(Default)]
+struct Data {
+    users: Vec<StoredUser>,
+    todos: Vec<Todo>,
+    events: Vec<TodoEvent>,
+}
+
+struct StoredUser {
+    user: User,
+    password_hash: String,
+    disabled: bool,
+    last_login_at: Option<DateTime<Utc>>,
+    // The hash of the link sent to the pending email, and when it expires
+    email_verification: Option<(String, DateTime<Utc>)>,
+}
+
+impl MemoryRepository {
+    fn data(&self) -> MutexGuard<'_, Data> {
+        self.data.lock().expect("a test panicked while holding the repository")
+    }
+
+    // Disables an account, as an admin would
+    pub fn set_disabled(&self, id: &str, disabled: bool) {
+        if let Some(stored) = self.data().user_mut(id) {
+            stored.disabled = disabled;
+        }
+    }
+
+    // Requires a new password before anything else, as an admin would
+    pub fn require_password_reset(&self, id: &str) {
+        if let Some(stored) = self.data().user_mut(id) {
+            stored.user.password_reset_required = true;
+        }
+    }
+
+    // Stores a link for the pending email, as the job sending it would
+    pub fn set_email_verification(&self, id: &str, token_hash: &str, expires_at: DateTime<Utc>) {
+        if let Some(stored) = self.data().user_mut(id) {
+            stored.email_verification = Some((token_hash.to_string(), expires_at));
+        }
+    }
+
+    pub fn last_login_at(&self, id: &str) -> Option<DateTime<Utc>> {
+        self.data().users.iter().find(|stored| stored.user.id == id)?.last_login_at
+    }
+}
+
+impl Data {
+    fn user_mut(&mut self, id: &str) -> Option<&mut StoredUser> {
+        self.users.iter_mut().find(|stored| stored.user.id == id)
+    }
+
+    fn todo(&self, user_id: &str, id: &str) -> Option<&Todo> {
+        self.todos.iter().find(|todo| todo.id == id && todo.user_id == user_id)
+    }
+
+    fn todo_mut(&mut self, user_id: &str, id: &str) -> Option<&mut Todo> {
+        self.todos.iter_mut().find(|todo| todo.id == id && todo.user_id == user_id)
+    }
+
+    // `count` ascending positions that sort before every todo the user has
+    fn top_positions(&self, user_id: &str, count: usize) -> Vec<String> {
+        let first = self
+            .todos
+            .iter()
+            .filter(|todo| todo.user_id == user_id)
+            .map(|todo| todo.position.as_str())
+            .min_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        top_keys(first, count)
+    }
+
+    fn neighbour_position(&self, user_id: &str, id: Option<&str>) -> RepositoryResult<Option<String>> {
+        match id {
+            Some(id) => self
+                .todo(user_id, id)
+                .filter(|todo| todo.deleted_at.is_none())
+                .map(|todo| Some(todo.position.clone()))
+                .ok_or(RepositoryError::NotFound),
+            None => Ok(None),
+        }
+    }
+
+    // Spreads the todos sharing `shared` between the positions around them
+    fn renumber_run(&mut self, user_id: &str, shared: &str) -> RepositoryResult<()> {
+        let listed = self.list(user_id, false);
+        let lower = listed.iter().map(|todo| todo.position.as_str()).filter(|position| *position < shared).max();
+        let upper = listed.iter().map(|todo| todo.position.as_str()).find(|position| *position > shared);
+        let run: Vec<&Todo> = listed.iter().filter(|todo| todo.position == shared).collect();
+        let positions = run_positions(lower, upper, run.len())?;
+
+        for (todo, position) in run.iter().zip(positions) {
+            if let Some(stored) = self.todo_mut(user_id, &todo.id) {
+                stored.position = position;
+            }
+        }
+        Ok(())
+    }
+
+    // Todos in the trash, most recently deleted first, or the others by
+    // position, compared byte-wise as with the "C" collation
+    fn list(&self, user_id: &str, trashed: bool) -> Vec<Todo> {
+        let mut todos: Vec<Todo> = self
+            .todos
+            .iter()
+            .filter(|todo| todo.user_id == user_id && todo.deleted_at.is_some() == trashed)
+            .cloned()
+            .collect();
+
+        if trashed {
+            todos.sort_by_key(|todo| Reverse(todo.deleted_at));
+        } else {
+            todos.sort_by(|a, b| {
+                a.position.as_bytes().cmp(b.position.as_bytes()).then(b.created_at.cmp(&a.created_at))
+            });
+        }
+        todos
+    }
+
+    fn record_event(&mut self, todo: &Todo, actor_id: &str, event_type: &str, changes: Value, at: DateTime<Utc>) {
+        let actor_username = self
+            .users
+            .iter()
+            .find(|stored| stored.user.id == actor_id)
+            .map(|stored| stored.user.username.clone());
+
+        self.events.push(TodoEvent {
+            id: Uuid::new_v4().to_string(),
+            todo_id: todo.id.clone(),
+            actor_id: actor_id.to_string(),
+            actor_username,
+            event_type: event_type.to_string(),
+            changes,
+            created_at: at,
+        });
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+    async fn create(&self, user: NewUser) -> RepositoryResult<()> {
+        let mut data = self.data();
+        if data.users.iter().any(|stored| stored.user.username == user.username || stored.user.email == user.email) {
+            return Err(RepositoryError::Conflict);
+        }
+
+        data.users.push(StoredUser {
+            user: User {
+                id: user.id,
+                username: user.username,
+                email: user.email,
+                display_name: None,
+                timezone: "UTC".to_string(),
+                locale: "en".to_string(),
+                avatar_url: None,
+                role: auth::ROLE_USER.to_string(),
+                password_reset_required: false,
+                pending_email: None,
+                deletion_scheduled_at: None,
+                created_at: Utc::now(),
+            },
+            password_hash: user.password_hash,
+            disabled: false,
+            last_login_at: None,
+            email_verification: None,
+        });
+        Ok(())
+    }
+
+    async fn find(&self, id: &str) -> RepositoryResult<Option<User>> {
+        Ok(self.data().users.iter().find(|stored| stored.user.id == id).map(|stored| stored.user.clone()))
+    }
+
+    async fn find_credentials(&self, email: &str) -> RepositoryResult<Option<Credentials>> {
+        Ok(self.data().users.iter().find(|stored| stored.user.email == email).map(|stored| Credentials {
+            user_id: stored.user.id.clone(),
+            password_hash: stored.password_hash.clone(),
+            disabled: stored.disabled,
+            password_reset_required: stored.user.password_reset_required,
+        }))
+    }
+
+    async fn account_status(&self, id: &str) -> RepositoryResult<Option<AccountStatus>> {
+        Ok(self.data().users.iter().find(|stored| stored.user.id == id).map(|stored| AccountStatus {
+            role: stored.user.role.clone(),
+            disabled: stored.disabled,
+            password_reset_required: stored.user.password_reset_required,
+        }))
+    }
+
+    async fn password_hash(&self, id: &str) -> RepositoryResult<Option<String>> {
+        Ok(self.data().users.iter().find(|stored| stored.user.id == id).map(|stored| stored.password_hash.clone()))
+    }
+
+    async fn record_login(&self, id: &str, at: DateTime<Utc>) -> RepositoryResult<()> {
+        if let Some(stored) = self.data().user_mut(id) {
+            stored.last_login_at = Some(at);
+        }
+        Ok(())
+    }
+
+    async fn set_password(&self, id: &str, password_hash: &str) -> RepositoryResult<()> {
+        if let Some(stored) = self.data().user_mut(id) {
+            stored.password_hash = password_hash.to_string();
+            stored.user.password_reset_required = false;
+        }
+        Ok(())
+    }
+
+    async fn schedule_deletion(&self, id: &str, at: DateTime<Utc>) -> RepositoryResult<()> {
+        if let Some(stored) = self.data().user_mut(id) {
+            stored.user.deletion_scheduled_at.get_or_insert(at);
+        }
+        Ok(())
+    }
+
+    async fn cancel_deletion(&self, id: &str) -> RepositoryResult<()> {
+        if let Some(stored) = self.data().user_mut(id) {
+            stored.user.deletion_scheduled_at = None;
+        }
+        Ok(())
+    }
+
+    async fn update_profile(&self, id: &str, changes: UserUpdate) -> RepositoryResult<User> {
+        let mut data = self.data();
+        let taken = data.users.iter().filter(|stored| stored.user.id != id).any(|stored| {
+            changes.username.as_ref().is_some_and(|username| *username == stored.user.username)
+                || changes.email.as_ref().is_some_and(|email| *email == stored.user.email)
+        });
+        if taken {
+            return Err(RepositoryError::Conflict);
+        }
+
+        let stored = data.user_mut(id).ok_or(RepositoryError::NotFound)?;
+        let user = &mut stored.user;
+        if let Some(username) = changes.username {
+            user.username = username;
+        }
+        if let Some(display_name) = changes.display_name {
+            user.display_name = Some(display_name).filter(|name| !name.is_empty());
+        }
+        if let Some(timezone) = changes.timezone {
+            user.timezone = timezone;
+        }
+        if let Some(locale) = changes.locale {
+            user.locale = locale;
+        }
+        if let Some(avatar_url) = changes.avatar_url {
+            user.avatar_url = Some(avatar_url).filter(|url| !url.is_empty());
+        }
+        if let Some(email) = changes.email {
+            user.pending_email = Some(email).filter(|email| *email != user.email);
+            stored.email_verification = None;
+        }
+        Ok(stored.user.clone())
+    }
+
+    async fn confirm_email(&self, token_hash: &str, now: DateTime<Utc>) -> RepositoryResult<()> {
+        let mut data = self.data();
+        let index = data
+            .users
+            .iter()
+            .position(|stored| {
+                stored.user.pending_email.is_some()
+                    && stored.email_verification.as_ref().is_some_and(|(hash, expires_at)| hash == token_hash && *expires_at > now)
+            })
+            .ok_or(RepositoryError::NotFound)?;
+
+        // Someone else registered the address in the meantime
+        let email = data.users[index].user.pending_email.clone().unwrap_or_default();
+        if data.users.iter().any(|stored| stored.user.email == email) {
+            return Err(RepositoryError::Conflict);
+        }
+
+        let stored = &mut data.users[index];
+        stored.user.email = email;
+        stored.user.pending_email = None;
+        stored.email_verification = None;
+        Ok(())
+    }
+
+    async fn export(&self, id: &str) -> RepositoryResult<Option<AccountExport>> {
+        Ok(self.data().users.iter().find(|stored| stored.user.id == id).map(|stored| AccountExport {
+            user: stored.user.clone(),
+            notification_preferences: digest::default_preferences(stored.user.timezone.clone()),
+            app_passwords: Vec::new(),
+            webhooks: Vec::new(),
+        }))
+    }
+}
+
+#[async_trait]
+impl TodoRepository for MemoryRepository {
+    async fn list(&self, user_id: &str, trashed: bool) -> RepositoryResult<Vec<Todo>> {
+        Ok(self.data().list(user_id, trashed))
+    }
+
+    async fn find(&self, user_id: &str, id: &str) -> RepositoryResult<Option<Todo>> {
+        Ok(self.data().todo(user_id, id).cloned())
+    }
+
+    async fn create(&self, user_id: &str, new: CreateTodoRequest, now: DateTime<Utc>) -> RepositoryResult<Todo> {
+        let mut data = self.data();
+
+        let position = data.top_positions(user_id, 1).remove(0);
+
+        let todo = Todo {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            title: new.title,
+            description: new.description,
+            completed: false,
+            position,
+            due_at: new.due_at,
+            priority: new.priority,
+            remind_at: new.remind_at,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        };
+
+        data.todos.push(todo.clone());
+        data.record_event(&todo, user_id, history::EVENT_CREATED, history::initial_values(&todo), now);
+        Ok(todo)
+    }
+
+    async fn update(&self, user_id: &str, id: &str, changes: TodoUpdate, now: DateTime<Utc>) -> RepositoryResult<Todo> {
+        let mut data = self.data();
+
+        let current = data
+            .todo(user_id, id)
+            .filter(|todo| todo.deleted_at.is_none())
+            .cloned()
+            .ok_or(RepositoryError::NotFound)?;
+        let updated = apply_update(&current, changes, now);
+
+        if let Some(todo) = data.todo_mut(user_id, id) {
+            *todo = updated.clone();
+        }
+        let changes = history::diff_todos(&current, &updated);
+        if history::has_changes(&changes) {
+            data.record_event(&updated, user_id, history::update_event_type(&current, &updated), changes, now);
+        }
+        Ok(updated)
+    }
+
+    async fn trash(&self, user_id: &str, id: &str, now: DateTime<Utc>) -> RepositoryResult<Todo> {
+        let mut data = self.data();
+
+        let todo = data
+            .todo_mut(user_id, id)
+            .filter(|todo| todo.deleted_at.is_none())
+            .ok_or(RepositoryError::NotFound)?;
+        todo.deleted_at = Some(now);
+        let todo = todo.clone();
+
+        data.record_event(&todo, user_id, history::EVENT_DELETED, json!({}), now);
+        Ok(todo)
+    }
+
+    async fn restore(&self, user_id: &str, id: &str, now: DateTime<Utc>) -> RepositoryResult<Todo> {
+        let mut data = self.data();
+
+        let todo = data
+            .todo_mut(user_id, id)
+            .filter(|todo| todo.deleted_at.is_some())
+            .ok_or(RepositoryError::NotFound)?;
+        todo.deleted_at = None;
+        todo.updated_at = now;
+        let todo = todo.clone();
+
+        data.record_event(&todo, user_id, history::EVENT_RESTORED, json!({}), now);
+        Ok(todo)
+    }
+
+    async fn purge(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let mut data = self.data();
+
+        if data.todo(user_id, id).is_none_or(|todo| todo.deleted_at.is_none()) {
+            return Err(RepositoryError::NotFound);
+        }
+        data.todos.retain(|todo| todo.id != id);
+        data.events.retain(|event| event.todo_id != id);
+        Ok(())
+    }
+
+    async fn history(&self, user_id: &str, id: &str) -> RepositoryResult<Vec<TodoEvent>> {
+        let data = self.data();
+
+        if data.todo(user_id, id).is_none() {
+            return Err(RepositoryError::NotFound);
+        }
+        Ok(data.events.iter().filter(|event| event.todo_id == id).cloned().collect())
+    }
+
+    async fn bulk_update(
+        &self,
+        user_id: &str,
+        action: BulkAction,
+        targets: BulkTargets,
+        limit: usize,
+        now: DateTime<Utc>,
+    ) -> RepositoryResult<Vec<BulkItemResult>> {
+        let mut data = self.data();
+
+        let ids: Vec<String> = match targets {
+            BulkTargets::Ids(ids) => ids,
+            BulkTargets::Filter(filter) => {
+                let mut todos = data.list(user_id, filter.deleted);
+                // The trash is listed by deletion date, but filters go by creation date
+                if filter.deleted {
+                    todos.sort_by_key(|todo| Reverse(todo.created_at));
+                }
+                todos
+                    .into_iter()
+                    .filter(|todo| filter.completed.is_none_or(|completed| todo.completed == completed))
+                    .map(|todo| todo.id)
+                    .collect()
+            }
+        };
+
+        if ids.len() > limit {
+            return Err(RepositoryError::TooMany);
+        }
+
+        let mut results = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            let Some(current) = data.todo(user_id, &id).cloned() else {
+                results.push(BulkItemResult { id, success: false, error: Some("Todo not found".to_string()), todo: None });
+                continue;
+            };
+
+            let todo = match apply_bulk_action(action, &current, now) {
+                BulkChange::Rejected(error) => {
+                    results.push(BulkItemResult { id, success: false, error: Some(error.to_string()), todo: Some(current) });
+                    continue;
+                }
+                BulkChange::Unchanged => current,
+                BulkChange::Changed { todo, event_type, changes } => {
+                    if let Some(stored) = data.todo_mut(user_id, &id) {
+                        *stored = (*todo).clone();
+                    }
+                    data.record_event(&todo, user_id, event_type, changes, now);
+                    *todo
+                }
+            };
+
+            results.push(BulkItemResult { id, success: true, error: None, todo: Some(todo) });
+        }
+
+        Ok(results)
+    }
+
+    async fn move_between(
+        &self,
+        user_id: &str,
+        id: &str,
+        previous_id: Option<&str>,
+        next_id: Option<&str>,
+    ) -> RepositoryResult<Todo> {
+        let mut data = self.data();
+
+        if data.todo(user_id, id).is_none_or(|todo| todo.deleted_at.is_some()) {
+            return Err(RepositoryError::NotFound);
+        }
+        let mut previous = data.neighbour_position(user_id, previous_id)?;
+        let mut next = data.neighbour_position(user_id, next_id)?;
+        if let Some(shared) = shared_position(previous.as_deref(), next.as_deref()) {
+            data.renumber_run(user_id, &shared)?;
+            previous = data.neighbour_position(user_id, previous_id)?;
+            next = data.neighbour_position(user_id, next_id)?;
+        }
+        let mut new_position = position_between(previous.as_deref(), next.as_deref())?;
+        // Another todo got that key from a move between the same neighbours
+        while data.todos.iter().any(|todo| todo.user_id == user_id && todo.id != id && todo.position == new_position) {
+            new_position = position_between(Some(&new_position), next.as_deref())?;
+        }
+
+        let todo = data.todo_mut(user_id, id).ok_or(RepositoryError::NotFound)?;
+        todo.position = new_position;
+        Ok(todo.clone())
+    }
+
+    async fn import(
+        &self,
+        user_id: &str,
+        todos: Vec<ImportedTodo>,
+        now: DateTime<Utc>,
+    ) -> RepositoryResult<(Vec<ImportedTodo>, Vec<ImportedTodo>)> {
+        let mut data = self.data();
+        let existing_titles: Vec<String> = data.list(user_id, false).into_iter().map(|todo| todo.title).collect();
+        let (created, duplicates) = import::split_duplicates(&existing_titles, todos);
+
+        let positions = data.top_positions(user_id, created.len());
+        for (imported, position) in created.iter().zip(positions) {
+            let todo = imported_todo(user_id, imported, position, now);
+            data.todos.push(todo.clone());
+            data.record_event(&todo, user_id, history::EVENT_CREATED, history::initial_values(&todo), now);
+        }
+        Ok((created, duplicates))
+    }
+
+    async fn export(&self, user_id: &str) -> RepositoryResult<(Vec<Todo>, Vec<TodoEvent>)> {
+        let data = self.data();
+        let mut todos: Vec<Todo> = data.todos.iter().filter(|todo| todo.user_id == user_id).cloned().collect();
+        todos.sort_by_key(|todo| todo.created_at);
+        let mut history: Vec<TodoEvent> = data
+            .events
+            .iter()
+            .filter(|event| todos.iter().any(|todo| todo.id == event.todo_id))
+            .cloned()
+            .collect();
+        history.sort_by_key(|event| event.created_at);
+        Ok((todos, history))
+    }
+}
@@ -0,0 +1,288 @@
+// Storage of users and todos, behind traits so handlers don't depend on SQL:
+// `SqlRepository` keeps them in the database, on Postgres or SQLite, and
+// `MemoryRepository` in memory, for tests that drive the router without one.
+// Rules both share, such as how an update or bulk action changes a todo, live
+// here so the two can't drift apart.
+//
+// The account, todo, import and export routes go through these. Everything
+// else, such as CalDAV, calendar feeds, webhooks, notifications and the admin
+// routes, still queries `AppState::db`, so it is tested against a migrated
+// SQLite database instead, see `AppState::sqlite`
+
+use axum::{async_trait, http::StatusCode};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use todo_api_types::{
+    AppPassword, BulkAction, BulkFilter, BulkItemResult, CreateTodoRequest, ImportedTodo, NotificationPreferences, Todo,
+    TodoEvent, TodoUpdate, User, UserUpdate, Webhook,
+};
+
+use uuid::Uuid;
+
+use crate::{
+    history,
+    ordering,
+};
+
+mod memory;
+mod sql;
+
+pub use memory::MemoryRepository;
+pub use sql::SqlRepository;
+pub(crate) use sql::{todo_from_row, top_position};
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    NotFound,
+    // A unique value, such as a username or email, is taken; or todos that
+    // are out of order were given as neighbours
+    Conflict,
+    // More todos than allowed matched a bulk action
+    TooMany,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for RepositoryError {
+    fn from(error: sqlx::Error) -> Self {
+        if error.as_database_error().is_some_and(|e| e.is_unique_violation()) {
+            RepositoryError::Conflict
+        } else {
+            RepositoryError::Database(error)
+        }
+    }
+}
+
+impl From<RepositoryError> for StatusCode {
+    fn from(error: RepositoryError) -> Self {
+        match error {
+            RepositoryError::NotFound => StatusCode::NOT_FOUND,
+            RepositoryError::Conflict => StatusCode::CONFLICT,
+            RepositoryError::TooMany => StatusCode::PAYLOAD_TOO_LARGE,
+            RepositoryError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+pub type RepositoryResult<T> = Result<T, RepositoryError>;
+
+pub struct NewUser {
+    pub id: String,
+    pub username: String,
+    pub email: String,
+    pub password_hash: String,
+}
+
+// What logging in checks
+pub struct Credentials {
+    pub user_id: String,
+    pub password_hash: String,
+    pub disabled: bool,
+    pub password_reset_required: bool,
+}
+
+// What every authenticated request checks; read on each request, so changes
+// by an admin apply without a new token
+pub struct AccountStatus {
+    pub role: String,
+    pub disabled: bool,
+    pub password_reset_required: bool,
+}
+
+// What an account export holds besides the todos. Secrets, such as password
+// hashes and webhook secrets, are left out
+pub struct AccountExport {
+    pub user: User,
+    pub notification_preferences: NotificationPreferences,
+    pub app_passwords: Vec<AppPassword>,
+    pub webhooks: Vec<Webhook>,
+}
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    // Fails with `Conflict` if the username or email is taken
+    async fn create(&self, user: NewUser) -> RepositoryResult<()>;
+    async fn find(&self, id: &str) -> RepositoryResult<Option<User>>;
+    async fn find_credentials(&self, email: &str) -> RepositoryResult<Option<Credentials>>;
+    async fn account_status(&self, id: &str) -> RepositoryResult<Option<AccountStatus>>;
+    async fn password_hash(&self, id: &str) -> RepositoryResult<Option<String>>;
+    async fn record_login(&self, id: &str, at: DateTime<Utc>) -> RepositoryResult<()>;
+    // Also lifts a password reset required by an admin
+    async fn set_password(&self, id: &str, password_hash: &str) -> RepositoryResult<()>;
+    // Asking again keeps the date of the first request
+    async fn schedule_deletion(&self, id: &str, at: DateTime<Utc>) -> RepositoryResult<()>;
+    async fn cancel_deletion(&self, id: &str) -> RepositoryResult<()>;
+    // Takes trimmed, valid values. Empty strings clear the optional fields,
+    // absent ones keep them. A new email is only stored as pending, and a
+    // verification link queued for it; the current one cancels a pending
+    // change. Fails with `Conflict` if the username or email is taken
+    async fn update_profile(&self, id: &str, changes: UserUpdate) -> RepositoryResult<User>;
+    // Makes the pending email the one to log in with, given the hash of the
+    // link sent to it. Fails with `NotFound` if the link is unknown or expired,
+    // and `Conflict` if someone took the address in the meantime
+    async fn confirm_email(&self, token_hash: &str, now: DateTime<Utc>) -> RepositoryResult<()>;
+    async fn export(&self, id: &str) -> RepositoryResult<Option<AccountExport>>;
+}
+
+// Todos are only ever seen by their owner, so every method takes the owner's
+// id. Changes are recorded in the todo's history, by the owner
+#[async_trait]
+pub trait TodoRepository: Send + Sync {
+    // Todos in the trash, most recently deleted first, or the others in list order
+    async fn list(&self, user_id: &str, trashed: bool) -> RepositoryResult<Vec<Todo>>;
+    // Includes todos in the trash
+    async fn find(&self, user_id: &str, id: &str) -> RepositoryResult<Option<Todo>>;
+    // New todos go to the top of the list
+    async fn create(&self, user_id: &str, new: CreateTodoRequest, now: DateTime<Utc>) -> RepositoryResult<Todo>;
+    async fn update(&self, user_id: &str, id: &str, changes: TodoUpdate, now: DateTime<Utc>) -> RepositoryResult<Todo>;
+    // Moves a todo to the trash
+    async fn trash(&self, user_id: &str, id: &str, now: DateTime<Utc>) -> RepositoryResult<Todo>;
+    async fn restore(&self, user_id: &str, id: &str, now: DateTime<Utc>) -> RepositoryResult<Todo>;
+    // Deletes a todo in the trash for good, with its history
+    async fn purge(&self, user_id: &str, id: &str) -> RepositoryResult<()>;
+    async fn history(&self, user_id: &str, id: &str) -> RepositoryResult<Vec<TodoEvent>>;
+    // All or nothing: if more than `limit` todos are targeted, none is changed
+    async fn bulk_update(
+        &self,
+        user_id: &str,
+        action: BulkAction,
+        targets: BulkTargets,
+        limit: usize,
+        now: DateTime<Utc>,
+    ) -> RepositoryResult<Vec<BulkItemResult>>;
+    // Places a todo directly below `previous_id` and above `next_id`; leaving
+    // one out moves it to the top or bottom
+    async fn move_between(
+        &self,
+        user_id: &str,
+        id: &str,
+        previous_id: Option<&str>,
+        next_id: Option<&str>,
+    ) -> RepositoryResult<Todo>;
+    // Adds the todos whose titles no todo out of the trash has yet, above the
+    // others and in the given order. Returns those and the duplicates left out
+    async fn import(
+        &self,
+        user_id: &str,
+        todos: Vec<ImportedTodo>,
+        now: DateTime<Utc>,
+    ) -> RepositoryResult<(Vec<ImportedTodo>, Vec<ImportedTodo>)>;
+    // Every todo, in the trash or not, and every change to them, oldest first
+    async fn export(&self, user_id: &str) -> RepositoryResult<(Vec<Todo>, Vec<TodoEvent>)>;
+}
+
+// Todos a bulk action applies to
+pub enum BulkTargets {
+    // Without duplicates
+    Ids(Vec<String>),
+    // Every todo in the trash or out of it, in list order
+    Filter(BulkFilter),
+}
+
+// Fields missing from the update keep their current value; those set to null
+// are cleared
+pub(crate) fn apply_update(current: &Todo, changes: TodoUpdate, now: DateTime<Utc>) -> Todo {
+    Todo {
+        id: current.id.clone(),
+        user_id: current.user_id.clone(),
+        title: changes.title.unwrap_or_else(|| current.title.clone()),
+        description: changes.description.or_else(|| current.description.clone()),
+        completed: changes.completed.unwrap_or(current.completed),
+        position: current.position.clone(),
+        due_at: changes.due_at.unwrap_or(current.due_at),
+        priority: changes.priority.unwrap_or(current.priority),
+        remind_at: changes.remind_at.unwrap_or(current.remind_at),
+        created_at: current.created_at,
+        updated_at: now,
+        deleted_at: None,
+    }
+}
+
+pub(crate) fn imported_todo(user_id: &str, imported: &ImportedTodo, position: String, now: DateTime<Utc>) -> Todo {
+    Todo {
+        id: Uuid::new_v4().to_string(),
+        user_id: user_id.to_string(),
+        title: imported.title.clone(),
+        description: imported.description.clone(),
+        completed: imported.completed,
+        position,
+        due_at: imported.due_at,
+        priority: imported.priority,
+        remind_at: None,
+        created_at: now,
+        updated_at: now,
+        deleted_at: None,
+    }
+}
+
+// How a bulk action changes one todo
+pub(crate) enum BulkChange {
+    // The action doesn't apply, with the reason
+    Rejected(&'static str),
+    // Completing an already completed todo is a no-op and leaves no trace in the history
+    Unchanged,
+    Changed { todo: Box<Todo>, event_type: &'static str, changes: Value },
+}
+
+pub(crate) fn apply_bulk_action(action: BulkAction, current: &Todo, now: DateTime<Utc>) -> BulkChange {
+    let in_trash = current.deleted_at.is_some();
+    match action {
+        BulkAction::Restore if !in_trash => return BulkChange::Rejected("Todo is not in the trash"),
+        BulkAction::Complete | BulkAction::Uncomplete | BulkAction::Delete if in_trash => {
+            return BulkChange::Rejected("Todo is in the trash")
+        }
+        _ => {}
+    }
+
+    let mut updated = current.clone();
+    let event_type = match action {
+        BulkAction::Complete | BulkAction::Uncomplete => {
+            updated.completed = action == BulkAction::Complete;
+            history::update_event_type(current, &updated)
+        }
+        BulkAction::Delete => {
+            updated.deleted_at = Some(now);
+            history::EVENT_DELETED
+        }
+        BulkAction::Restore => {
+            updated.deleted_at = None;
+            history::EVENT_RESTORED
+        }
+    };
+
+    let changes = history::diff_todos(current, &updated);
+    if !history::has_changes(&changes) && updated.deleted_at == current.deleted_at {
+        return BulkChange::Unchanged;
+    }
+
+    updated.updated_at = now;
+    BulkChange::Changed { todo: Box::new(updated), event_type, changes }
+}
+
+// `count` ascending keys above `first`, the user's current top position. An
+// invalid first key, which the API never writes, is ignored
+pub(crate) fn top_keys(first: Option<&str>, count: usize) -> Vec<String> {
+    ordering::keys_between(None, first, count)
+        .or_else(|| ordering::keys_between(None, None, count))
+        .unwrap_or_default()
+}
+
+// Usually only the moved todo gets a new key; neighbours that are out of order
+// are a client error
+pub(crate) fn position_between(previous: Option<&str>, next: Option<&str>) -> RepositoryResult<String> {
+    ordering::key_between(previous, next).ok_or(RepositoryError::Conflict)
+}
+
+// The key both neighbours have, if they have the same one, as todos created at
+// the same moment could before positions were locked. There's no room between
+// them, so the todos with that key are renumbered first
+pub(crate) fn shared_position(previous: Option<&str>, next: Option<&str>) -> Option<String> {
+    match (previous, next) {
+        (Some(previous), Some(next)) if previous == next => Some(previous.to_string()),
+        _ => None,
+    }
+}
+
+// New keys for the `count` todos sharing a key, between the keys around them
+pub(crate) fn run_positions(lower: Option<&str>, upper: Option<&str>, count: usize) -> RepositoryResult<Vec<String>> {
+    ordering::keys_between(lower, upper, count).ok_or(RepositoryError::Conflict)
+}
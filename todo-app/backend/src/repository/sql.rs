@@ -0,0 +1,755 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use todo_api_types::{
+    AppPassword, BulkAction, BulkItemResult, CreateTodoRequest, ImportedTodo, Todo, TodoEvent, TodoUpdate, User, UserUpdate,
+};
+use uuid::Uuid;
+
+use super::{
+    apply_bulk_action, apply_update, imported_todo, position_between, run_positions, shared_position, top_keys, AccountExport,
+    AccountStatus, BulkChange, BulkTargets, Credentials, NewUser, RepositoryError, RepositoryResult, TodoRepository,
+    UserRepository,
+};
+use crate::{
+    db::{self, Pool, Row},
+    digest,
+    history,
+    import,
+    jobs,
+    profile,
+    webhooks,
+};
+
+// Users and todos in the database, with every change to a todo recorded in
+// its history and, except for imports, announced to webhooks in the same
+// transaction
+#[derive(Clone)]
+pub struct SqlRepository {
+    db: Pool,
+}
+
+impl SqlRepository {
+    pub fn new(db: Pool) -> Self {
+        SqlRepository { db }
+    }
+}
+
+pub(crate) fn todo_from_row(row: &Row) -> Result<Todo, sqlx::Error> {
+    Ok(Todo {
+        id: row.try_get("id")?,
+        user_id: row.try_get("user_id")?,
+        title: row.try_get("title")?,
+        description: row.try_get("description")?,
+        completed: row.try_get("completed")?,
+        position: row.try_get("position")?,
+        due_at: row.try_get("due_at")?,
+        priority: row.try_get("priority")?,
+        remind_at: row.try_get("remind_at")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+// Expects an `actor_username` column alongside the event's own
+fn todo_event_from_row(row: &Row) -> Result<TodoEvent, sqlx::Error> {
+    Ok(TodoEvent {
+        id: row.try_get("id")?,
+        todo_id: row.try_get("todo_id")?,
+        actor_id: row.try_get("actor_id")?,
+        actor_username: row.try_get("actor_username")?,
+        event_type: row.try_get("event_type")?,
+        changes: row.try_get("changes")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+// Positions are handed out one transaction at a time per user, so two todos
+// can't be given the same key. SQLite's single writer already does this
+async fn lock_positions(tx: &mut db::Transaction<'_>, user_id: &str) -> Result<(), sqlx::Error> {
+    db::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+// `count` ascending positions that sort before every todo the user currently
+// has; the positions stay locked until the transaction ends
+async fn top_positions(
+    tx: &mut db::Transaction<'_>,
+    user_id: &str,
+    count: usize,
+) -> Result<Vec<String>, sqlx::Error> {
+    lock_positions(tx, user_id).await?;
+    let first: Option<String> = db::query_scalar(
+        "SELECT MIN(position COLLATE \"C\") FROM todos WHERE user_id = $1"
+    )
+    .bind(user_id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(top_keys(first.as_deref(), count))
+}
+
+pub(crate) async fn top_position(tx: &mut db::Transaction<'_>, user_id: &str) -> Result<String, sqlx::Error> {
+    Ok(top_positions(tx, user_id, 1).await?.remove(0))
+}
+
+async fn find_user<'e, E: db::Executor<'e>>(executor: E, id: &str) -> Result<Option<User>, sqlx::Error> {
+    let row = db::query("SELECT * FROM users WHERE id = $1")
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+
+    row.as_ref().map(profile::user_from_row).transpose()
+}
+
+async fn lock_todo(
+    tx: &mut db::Transaction<'_>,
+    user_id: &str,
+    id: &str,
+) -> Result<Option<Todo>, sqlx::Error> {
+    let row = db::query("SELECT * FROM todos WHERE id = $1 AND user_id = $2 FOR UPDATE")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+    row.as_ref().map(todo_from_row).transpose()
+}
+
+#[async_trait]
+impl UserRepository for SqlRepository {
+    async fn create(&self, user: NewUser) -> RepositoryResult<()> {
+        db::query("INSERT INTO users (id, username, email, password_hash) VALUES ($1, $2, $3, $4)")
+            .bind(user.id)
+            .bind(user.username)
+            .bind(user.email)
+            .bind(user.password_hash)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn find(&self, id: &str) -> RepositoryResult<Option<User>> {
+        Ok(find_user(&self.db, id).await?)
+    }
+
+    async fn find_credentials(&self, email: &str) -> RepositoryResult<Option<Credentials>> {
+        let row = db::query(
+            "SELECT id, password_hash, disabled_at IS NOT NULL AS disabled, password_reset_required FROM users WHERE email = $1"
+        )
+        .bind(email)
+        .fetch_optional(&self.db)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(Credentials {
+            user_id: row.try_get("id")?,
+            password_hash: row.try_get("password_hash")?,
+            disabled: row.try_get("disabled")?,
+            password_reset_required: row.try_get("password_reset_required")?,
+        }))
+    }
+
+    async fn account_status(&self, id: &str) -> RepositoryResult<Option<AccountStatus>> {
+        let row = db::query(
+            "SELECT role, disabled_at IS NOT NULL AS disabled, password_reset_required FROM users WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(AccountStatus {
+            role: row.try_get("role")?,
+            disabled: row.try_get("disabled")?,
+            password_reset_required: row.try_get("password_reset_required")?,
+        }))
+    }
+
+    async fn password_hash(&self, id: &str) -> RepositoryResult<Option<String>> {
+        let password_hash = db::query_scalar("SELECT password_hash FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(password_hash)
+    }
+
+    async fn record_login(&self, id: &str, at: DateTime<Utc>) -> RepositoryResult<()> {
+        db::query("UPDATE users SET last_login_at = $1 WHERE id = $2")
+            .bind(at)
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_password(&self, id: &str, password_hash: &str) -> RepositoryResult<()> {
+        db::query("UPDATE users SET password_hash = $1, password_reset_required = FALSE WHERE id = $2")
+            .bind(password_hash)
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn schedule_deletion(&self, id: &str, at: DateTime<Utc>) -> RepositoryResult<()> {
+        db::query("UPDATE users SET deletion_scheduled_at = COALESCE(deletion_scheduled_at, $1) WHERE id = $2")
+            .bind(at)
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn cancel_deletion(&self, id: &str) -> RepositoryResult<()> {
+        db::query("UPDATE users SET deletion_scheduled_at = NULL WHERE id = $1")
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_profile(&self, id: &str, changes: UserUpdate) -> RepositoryResult<User> {
+        let mut tx = self.db.begin().await?;
+        let previous = find_user(&mut *tx, id).await?.ok_or(RepositoryError::NotFound)?;
+
+        db::query(
+            "UPDATE users SET
+                 username = COALESCE($2, username),
+                 display_name = CASE WHEN $3 IS NULL THEN display_name ELSE NULLIF($3, '') END,
+                 timezone = COALESCE($4, timezone),
+                 locale = COALESCE($5, locale),
+                 avatar_url = CASE WHEN $6 IS NULL THEN avatar_url ELSE NULLIF($6, '') END
+             WHERE id = $1"
+        )
+        .bind(id)
+        .bind(&changes.username)
+        .bind(&changes.display_name)
+        .bind(&changes.timezone)
+        .bind(&changes.locale)
+        .bind(&changes.avatar_url)
+        .execute(&mut *tx)
+        .await?;
+
+        // Digests go out at an hour in the user's time zone, so a new one moves the next
+        if changes.timezone.as_ref().is_some_and(|timezone| *timezone != previous.timezone) {
+            let preferences = digest::load_preferences(&mut *tx, id).await?.ok_or(RepositoryError::NotFound)?;
+            digest::reschedule(&mut tx, &preferences, id).await?;
+        }
+
+        match changes.email.as_deref() {
+            Some(email) if email != previous.email => {
+                let taken: Option<String> = db::query_scalar("SELECT id FROM users WHERE email = $1 AND id <> $2")
+                    .bind(email)
+                    .bind(id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                if taken.is_some() {
+                    return Err(RepositoryError::Conflict);
+                }
+
+                db::query(
+                    "UPDATE users SET pending_email = $1, email_verification_token_hash = NULL, email_verification_expires_at = NULL WHERE id = $2"
+                )
+                .bind(email)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+                jobs::enqueue(&mut *tx, jobs::JOB_SEND_EMAIL_VERIFICATION, serde_json::json!({ "user_id": id }), Utc::now())
+                    .await?;
+            }
+            Some(_) => {
+                db::query(
+                    "UPDATE users SET pending_email = NULL, email_verification_token_hash = NULL, email_verification_expires_at = NULL WHERE id = $1"
+                )
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            }
+            None => {}
+        }
+
+        let user = find_user(&mut *tx, id).await?.ok_or(RepositoryError::NotFound)?;
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    async fn confirm_email(&self, token_hash: &str, now: DateTime<Utc>) -> RepositoryResult<()> {
+        let result = db::query(
+            "UPDATE users SET email = pending_email, pending_email = NULL, email_verification_token_hash = NULL, email_verification_expires_at = NULL
+             WHERE email_verification_token_hash = $1 AND email_verification_expires_at > $2 AND pending_email IS NOT NULL"
+        )
+        .bind(token_hash)
+        .bind(now)
+        .execute(&self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn export(&self, id: &str) -> RepositoryResult<Option<AccountExport>> {
+        let Some(user) = find_user(&self.db, id).await? else {
+            return Ok(None);
+        };
+        let notification_preferences =
+            digest::load_preferences(&self.db, id).await?.ok_or(RepositoryError::NotFound)?;
+
+        let app_passwords = db::query(
+            "SELECT id, name, created_at, last_used_at FROM app_passwords WHERE user_id = $1 ORDER BY created_at"
+        )
+        .bind(id)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(AppPassword {
+                id: row.try_get("id")?,
+                name: row.try_get("name")?,
+                created_at: row.try_get("created_at")?,
+                last_used_at: row.try_get("last_used_at")?,
+            })
+        })
+        .collect::<Result<_, sqlx::Error>>()?;
+
+        let webhooks = db::query("SELECT * FROM webhooks WHERE user_id = $1 ORDER BY created_at")
+            .bind(id)
+            .fetch_all(&self.db)
+            .await?
+            .iter()
+            .map(webhooks::webhook_from_row)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some(AccountExport { user, notification_preferences, app_passwords, webhooks }))
+    }
+}
+
+#[async_trait]
+impl TodoRepository for SqlRepository {
+    async fn list(&self, user_id: &str, trashed: bool) -> RepositoryResult<Vec<Todo>> {
+        let sql = if trashed {
+            "SELECT * FROM todos WHERE user_id = $1 AND deleted_at IS NOT NULL ORDER BY deleted_at DESC"
+        } else {
+            "SELECT * FROM todos WHERE user_id = $1 AND deleted_at IS NULL ORDER BY position COLLATE \"C\", created_at DESC"
+        };
+
+        let rows = db::query(sql).bind(user_id).fetch_all(&self.db).await?;
+
+        Ok(rows.iter().map(todo_from_row).collect::<Result<_, _>>()?)
+    }
+
+    async fn find(&self, user_id: &str, id: &str) -> RepositoryResult<Option<Todo>> {
+        let row = db::query("SELECT * FROM todos WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(row.as_ref().map(todo_from_row).transpose()?)
+    }
+
+    async fn create(&self, user_id: &str, new: CreateTodoRequest, now: DateTime<Utc>) -> RepositoryResult<Todo> {
+        let mut tx = self.db.begin().await?;
+
+        let todo = Todo {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            title: new.title,
+            description: new.description,
+            completed: false,
+            position: top_position(&mut tx, user_id).await?,
+            due_at: new.due_at,
+            priority: new.priority,
+            remind_at: new.remind_at,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        };
+
+        db::query(
+            "INSERT INTO todos (id, user_id, title, description, completed, position, due_at, priority, remind_at, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"
+        )
+        .bind(&todo.id)
+        .bind(user_id)
+        .bind(&todo.title)
+        .bind(todo.description.as_deref().unwrap_or(""))
+        .bind(todo.completed)
+        .bind(&todo.position)
+        .bind(todo.due_at)
+        .bind(todo.priority)
+        .bind(todo.remind_at)
+        .bind(now)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        let changes = history::initial_values(&todo);
+        history::record_event(&mut tx, &todo, user_id, history::EVENT_CREATED, changes, now).await?;
+        tx.commit().await?;
+
+        Ok(todo)
+    }
+
+    async fn update(&self, user_id: &str, id: &str, changes: TodoUpdate, now: DateTime<Utc>) -> RepositoryResult<Todo> {
+        let mut tx = self.db.begin().await?;
+
+        let current = lock_todo(&mut tx, user_id, id)
+            .await?
+            .filter(|todo| todo.deleted_at.is_none())
+            .ok_or(RepositoryError::NotFound)?;
+        let updated = apply_update(&current, changes, now);
+
+        // A new reminder time means the reminder goes out again, and removing it
+        // forgets that it was sent
+        db::query(
+            "UPDATE todos SET title = $1, description = $2, completed = $3, due_at = $4, priority = $5, remind_at = $6,
+             reminded_at = CASE WHEN remind_at IS DISTINCT FROM $6 THEN NULL ELSE reminded_at END, updated_at = $7
+             WHERE id = $8 AND user_id = $9"
+        )
+        .bind(&updated.title)
+        .bind(updated.description.as_deref())
+        .bind(updated.completed)
+        .bind(updated.due_at)
+        .bind(updated.priority)
+        .bind(updated.remind_at)
+        .bind(now)
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let changes = history::diff_todos(&current, &updated);
+        if history::has_changes(&changes) {
+            let event_type = history::update_event_type(&current, &updated);
+            history::record_event(&mut tx, &updated, user_id, event_type, changes, now).await?;
+        }
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    async fn trash(&self, user_id: &str, id: &str, now: DateTime<Utc>) -> RepositoryResult<Todo> {
+        let mut tx = self.db.begin().await?;
+
+        let row = db::query("UPDATE todos SET deleted_at = $1 WHERE id = $2 AND user_id = $3 AND deleted_at IS NULL RETURNING *")
+            .bind(now)
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(RepositoryError::NotFound)?;
+        let todo = todo_from_row(&row)?;
+
+        history::record_event(&mut tx, &todo, user_id, history::EVENT_DELETED, serde_json::json!({}), now).await?;
+        tx.commit().await?;
+
+        Ok(todo)
+    }
+
+    async fn restore(&self, user_id: &str, id: &str, now: DateTime<Utc>) -> RepositoryResult<Todo> {
+        let mut tx = self.db.begin().await?;
+
+        let row = db::query(
+            "UPDATE todos SET deleted_at = NULL, updated_at = $1 WHERE id = $2 AND user_id = $3 AND deleted_at IS NOT NULL RETURNING *"
+        )
+        .bind(now)
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(RepositoryError::NotFound)?;
+        let todo = todo_from_row(&row)?;
+
+        history::record_event(&mut tx, &todo, user_id, history::EVENT_RESTORED, serde_json::json!({}), now).await?;
+        tx.commit().await?;
+
+        Ok(todo)
+    }
+
+    async fn purge(&self, user_id: &str, id: &str) -> RepositoryResult<()> {
+        let result = db::query("DELETE FROM todos WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn history(&self, user_id: &str, id: &str) -> RepositoryResult<Vec<TodoEvent>> {
+        if TodoRepository::find(self, user_id, id).await?.is_none() {
+            return Err(RepositoryError::NotFound);
+        }
+
+        let rows = db::query(
+            "SELECT e.*, u.username AS actor_username FROM todo_events e LEFT JOIN users u ON u.id = e.actor_id WHERE e.todo_id = $1 ORDER BY e.created_at ASC"
+        )
+        .bind(id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows.iter().map(todo_event_from_row).collect::<Result<_, _>>()?)
+    }
+
+    async fn bulk_update(
+        &self,
+        user_id: &str,
+        action: BulkAction,
+        targets: BulkTargets,
+        limit: usize,
+        now: DateTime<Utc>,
+    ) -> RepositoryResult<Vec<BulkItemResult>> {
+        let mut tx = self.db.begin().await?;
+
+        let ids: Vec<String> = match targets {
+            BulkTargets::Ids(ids) => ids,
+            BulkTargets::Filter(filter) => {
+                let sql = if filter.deleted {
+                    "SELECT id FROM todos WHERE user_id = $1 AND deleted_at IS NOT NULL AND ($2 IS NULL OR completed = $2) ORDER BY created_at DESC"
+                } else {
+                    "SELECT id FROM todos WHERE user_id = $1 AND deleted_at IS NULL AND ($2 IS NULL OR completed = $2) ORDER BY position COLLATE \"C\", created_at DESC"
+                };
+
+                db::query(sql)
+                    .bind(user_id)
+                    .bind(filter.completed)
+                    .fetch_all(&mut *tx)
+                    .await?
+                    .iter()
+                    .map(|row| row.try_get("id"))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        if ids.len() > limit {
+            return Err(RepositoryError::TooMany);
+        }
+
+        let mut results = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            let Some(current) = lock_todo(&mut tx, user_id, &id).await? else {
+                results.push(BulkItemResult { id, success: false, error: Some("Todo not found".to_string()), todo: None });
+                continue;
+            };
+
+            let todo = match apply_bulk_action(action, &current, now) {
+                BulkChange::Rejected(error) => {
+                    results.push(BulkItemResult { id, success: false, error: Some(error.to_string()), todo: Some(current) });
+                    continue;
+                }
+                BulkChange::Unchanged => current,
+                BulkChange::Changed { todo, event_type, changes } => {
+                    db::query(
+                        "UPDATE todos SET completed = $1, deleted_at = $2, updated_at = $3 WHERE id = $4 AND user_id = $5"
+                    )
+                    .bind(todo.completed)
+                    .bind(todo.deleted_at)
+                    .bind(now)
+                    .bind(&id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    history::record_event(&mut tx, &todo, user_id, event_type, changes, now).await?;
+                    *todo
+                }
+            };
+
+            results.push(BulkItemResult { id, success: true, error: None, todo: Some(todo) });
+        }
+
+        tx.commit().await?;
+
+        Ok(results)
+    }
+
+    async fn move_between(
+        &self,
+        user_id: &str,
+        id: &str,
+        previous_id: Option<&str>,
+        next_id: Option<&str>,
+    ) -> RepositoryResult<Todo> {
+        let mut tx = self.db.begin().await?;
+
+        let mut todo = lock_todo(&mut tx, user_id, id)
+            .await?
+            .filter(|todo| todo.deleted_at.is_none())
+            .ok_or(RepositoryError::NotFound)?;
+
+        lock_positions(&mut tx, user_id).await?;
+        let mut previous = neighbour_position(&mut tx, user_id, previous_id).await?;
+        let mut next = neighbour_position(&mut tx, user_id, next_id).await?;
+        if let Some(shared) = shared_position(previous.as_deref(), next.as_deref()) {
+            renumber_run(&mut tx, user_id, &shared).await?;
+            previous = neighbour_position(&mut tx, user_id, previous_id).await?;
+            next = neighbour_position(&mut tx, user_id, next_id).await?;
+        }
+
+        let mut new_position = position_between(previous.as_deref(), next.as_deref())?;
+        // Another todo got that key from a move between the same neighbours
+        while position_taken(&mut tx, user_id, id, &new_position).await? {
+            new_position = position_between(Some(&new_position), next.as_deref())?;
+        }
+
+        db::query("UPDATE todos SET position = $1 WHERE id = $2 AND user_id = $3")
+            .bind(&new_position)
+            .bind(id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        todo.position = new_position;
+        Ok(todo)
+    }
+
+    async fn import(
+        &self,
+        user_id: &str,
+        todos: Vec<ImportedTodo>,
+        now: DateTime<Utc>,
+    ) -> RepositoryResult<(Vec<ImportedTodo>, Vec<ImportedTodo>)> {
+        let mut tx = self.db.begin().await?;
+        let existing_titles: Vec<String> =
+            db::query_scalar("SELECT title FROM todos WHERE user_id = $1 AND deleted_at IS NULL")
+                .bind(user_id)
+                .fetch_all(&mut *tx)
+                .await?;
+        let (created, duplicates) = import::split_duplicates(&existing_titles, todos);
+        if created.is_empty() {
+            return Ok((created, duplicates));
+        }
+
+        let positions = top_positions(&mut tx, user_id, created.len()).await?;
+        for (imported, position) in created.iter().zip(positions) {
+            let todo = imported_todo(user_id, imported, position, now);
+
+            db::query(
+                "INSERT INTO todos (id, user_id, title, description, completed, position, due_at, priority, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+            )
+            .bind(&todo.id)
+            .bind(&todo.user_id)
+            .bind(&todo.title)
+            .bind(todo.description.as_deref())
+            .bind(todo.completed)
+            .bind(&todo.position)
+            .bind(todo.due_at)
+            .bind(todo.priority)
+            .bind(now)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+            let changes = history::initial_values(&todo);
+            history::record_unannounced_event(&mut tx, &todo, user_id, history::EVENT_CREATED, changes, now).await?;
+        }
+        tx.commit().await?;
+
+        Ok((created, duplicates))
+    }
+
+    async fn export(&self, user_id: &str) -> RepositoryResult<(Vec<Todo>, Vec<TodoEvent>)> {
+        let todos = db::query("SELECT * FROM todos WHERE user_id = $1 ORDER BY created_at")
+            .bind(user_id)
+            .fetch_all(&self.db)
+            .await?
+            .iter()
+            .map(todo_from_row)
+            .collect::<Result<_, _>>()?;
+
+        let history = db::query(
+            "SELECT e.*, u.username AS actor_username FROM todo_events e LEFT JOIN users u ON u.id = e.actor_id WHERE e.user_id = $1 ORDER BY e.created_at"
+        )
+        .bind(user_id)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(todo_event_from_row)
+        .collect::<Result<_, _>>()?;
+
+        Ok((todos, history))
+    }
+}
+
+async fn neighbour_position(
+    tx: &mut db::Transaction<'_>,
+    user_id: &str,
+    id: Option<&str>,
+) -> RepositoryResult<Option<String>> {
+    let Some(id) = id else {
+        return Ok(None);
+    };
+    let position: Option<String> =
+        db::query_scalar("SELECT position FROM todos WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL")
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+
+    Ok(Some(position.ok_or(RepositoryError::NotFound)?))
+}
+
+async fn position_taken(tx: &mut db::Transaction<'_>, user_id: &str, id: &str, position: &str) -> Result<bool, sqlx::Error> {
+    let taken: Option<String> = db::query_scalar("SELECT id FROM todos WHERE user_id = $1 AND position = $2 AND id <> $3 LIMIT 1")
+        .bind(user_id)
+        .bind(position)
+        .bind(id)
+        .fetch_optional(&mut **tx)
+        .await?;
+    Ok(taken.is_some())
+}
+
+// Spreads the todos sharing `shared` between the positions around them
+async fn renumber_run(tx: &mut db::Transaction<'_>, user_id: &str, shared: &str) -> RepositoryResult<()> {
+    let run: Vec<String> = db::query_scalar(
+        "SELECT id FROM todos WHERE user_id = $1 AND deleted_at IS NULL AND position = $2 ORDER BY created_at DESC"
+    )
+    .bind(user_id)
+    .bind(shared)
+    .fetch_all(&mut **tx)
+    .await?;
+    let row = db::query(
+        "SELECT
+            (SELECT MAX(position COLLATE \"C\") FROM todos WHERE user_id = $1 AND deleted_at IS NULL AND position COLLATE \"C\" < $2) AS lower,
+            (SELECT MIN(position COLLATE \"C\") FROM todos WHERE user_id = $1 AND deleted_at IS NULL AND position COLLATE \"C\" > $2) AS upper"
+    )
+    .bind(user_id)
+    .bind(shared)
+    .fetch_one(&mut **tx)
+    .await?;
+    let lower: Option<String> = row.try_get("lower")?;
+    let upper: Option<String> = row.try_get("upper")?;
+
+    for (id, position) in run.iter().zip(run_positions(lower.as_deref(), upper.as_deref(), run.len())?) {
+        db::query("UPDATE todos SET position = $1 WHERE id = $2 AND user_id = $3")
+            .bind(position)
+            .bind(id)
+            .bind(user_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
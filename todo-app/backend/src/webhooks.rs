@@ -0,0 +1,337 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use todo_api_types::{Todo, Webhook};
+use uuid::Uuid;
+
+use crate::{
+    db::{self, Backend},
+    history,
+    jobs::{self, Job, JobContext, RetryPolicy},
+};
+
+pub const EVENT_TODO_CREATED: &str = "todo.created";
+pub const EVENT_TODO_UPDATED: &str = "todo.updated";
+pub const EVENT_TODO_COMPLETED: &str = "todo.completed";
+pub const EVENT_TODO_DELETED: &str = "todo.deleted";
+
+pub const EVENTS: [&str; 4] = [
+    EVENT_TODO_CREATED,
+    EVENT_TODO_UPDATED,
+    EVENT_TODO_COMPLETED,
+    EVENT_TODO_DELETED,
+];
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+pub const STATUS_FAILED: &str = "failed";
+
+pub const SIGNATURE_HEADER: &str = "X-Todo-Signature-256";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Attempts are spaced 30s, 1m, 2m, ... apart, so the last one is about an hour after the first
+pub const RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: 8,
+    initial_backoff: chrono::Duration::seconds(30),
+    max_backoff: chrono::Duration::hours(1),
+};
+
+// Restores and un-completions are reported as plain updates
+pub fn event_for(history_event: &str) -> &'static str {
+    match history_event {
+        history::EVENT_CREATED => EVENT_TODO_CREATED,
+        history::EVENT_COMPLETED => EVENT_TODO_COMPLETED,
+        history::EVENT_DELETED => EVENT_TODO_DELETED,
+        _ => EVENT_TODO_UPDATED,
+    }
+}
+
+pub(crate) fn webhook_from_row(row: &db::Row) -> Result<Webhook, sqlx::Error> {
+    Ok(Webhook {
+        id: row.try_get("id")?,
+        url: row.try_get("url")?,
+        events: row.try_get_list("events")?,
+        active: row.try_get("active")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+// Only the form is checked here; where the URL points is checked when something
+// is sent to it, see `http_client`
+pub fn is_valid_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Shared address space for carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking
+        || (a == 198 && (b == 18 || b == 19))
+        // Reserved
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    // IPv4-mapped (::ffff:a.b.c.d) and NAT64 (64:ff9b::a.b.c.d) addresses reach
+    // the IPv4 address in them
+    if let Some(ipv4) = ip.to_ipv4_mapped() {
+        return is_public_ipv4(ipv4);
+    }
+    let segments = ip.segments();
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [_, _, _, _, _, _, high, low] = segments;
+        return is_public_ipv4(Ipv4Addr::from((u32::from(high) << 16) | u32::from(low)));
+    }
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local, fc00::/7
+        || (segments[0] & 0xfe00) == 0xfc00
+        // Link-local and the deprecated site-local, fe80::/10 and fec0::/10
+        || (segments[0] & 0xff80) == 0xfe80
+        || (segments[0] & 0xffc0) == 0xfec0
+        // Documentation, 2001:db8::/32
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8)
+        // Discard-only, 100::/64
+        || segments[..4] == [0x100, 0, 0, 0])
+}
+
+// Whether an address is reachable on the internet, rather than being the
+// server itself, its network or a cloud metadata service such as 169.254.169.254
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+// Hosts given as IP addresses are never resolved, so `PublicResolver` doesn't
+// see them; they are checked before sending
+pub fn check_destination(url: &reqwest::Url) -> Result<(), String> {
+    let host = url.host_str().ok_or("The URL has no host")?;
+    // IPv6 addresses are in brackets
+    let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() else {
+        return Ok(());
+    };
+    if !is_public_ip(ip) {
+        return Err(format!("{} is not a public address", ip));
+    }
+    Ok(())
+}
+
+// Resolves host names for outgoing requests, and refuses those with an address
+// that isn't public. Checking here rather than before the request means the
+// addresses checked are the ones connected to, however the name resolves later
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            if let Some(address) = addresses.iter().find(|address| !is_public_ip(address.ip())) {
+                return Err(format!("{} resolves to {}, which is not a public address", name.as_str(), address.ip()).into());
+            }
+            let addresses: reqwest::dns::Addrs = Box::new(addresses.into_iter());
+            Ok(addresses)
+        })
+    }
+}
+
+// `sha256=<hex>` of the HMAC-SHA256 of the raw request body
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("sha256={}", digest)
+}
+
+// Queues a delivery to every active webhook of the todo's owner that subscribes
+// to the event, in the same transaction as the change itself
+pub async fn enqueue(
+    tx: &mut db::Transaction<'_>,
+    todo: &Todo,
+    history_event: &str,
+    changes: &Value,
+    at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let event = event_for(history_event);
+
+    let subscribes = match tx.backend() {
+        Backend::Postgres => "$2 = ANY(events)",
+        Backend::Sqlite => "EXISTS (SELECT 1 FROM json_each(events) WHERE value = $2)",
+    };
+    let webhook_ids: Vec<String> = db::query(&format!(
+        "SELECT id FROM webhooks WHERE user_id = $1 AND active AND {}",
+        subscribes
+    ))
+    .bind(&todo.user_id)
+    .bind(event)
+    .fetch_all(&mut **tx)
+    .await?
+    .iter()
+    .map(|row| row.get("id"))
+    .collect();
+
+    if webhook_ids.is_empty() {
+        return Ok(());
+    }
+
+    let payload = json!({
+        "event": event,
+        "occurred_at": at,
+        "todo": todo,
+        "changes": changes,
+    });
+
+    for webhook_id in webhook_ids {
+        let delivery_id = Uuid::new_v4().to_string();
+
+        db::query(
+            "INSERT INTO webhook_deliveries (id, webhook_id, event_type, payload, next_attempt_at, created_at) VALUES ($1, $2, $3, $4, $5, $5)"
+        )
+        .bind(&delivery_id)
+        .bind(&webhook_id)
+        .bind(event)
+        .bind(&payload)
+        .bind(at)
+        .execute(&mut **tx)
+        .await?;
+
+        jobs::enqueue(&mut **tx, jobs::JOB_DELIVER_WEBHOOK, json!({ "delivery_id": delivery_id }), at).await?;
+    }
+
+    Ok(())
+}
+
+// For the URLs users give: webhooks and push endpoints. It only connects to
+// public addresses, and doesn't follow redirects, which could lead anywhere
+pub fn http_client() -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent("todo-app-webhooks")
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+}
+
+// Sends a delivery once; errors are what the delivery log shows
+async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    event_type: &str,
+    delivery_id: &str,
+    secret: &str,
+    body: Vec<u8>,
+) -> Result<reqwest::Response, String> {
+    let url = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    check_destination(&url)?;
+    client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Todo-Event", event_type)
+        .header("X-Todo-Delivery", delivery_id)
+        .header(SIGNATURE_HEADER, signature(secret, &body))
+        .body(body)
+        .send()
+        .await
+        // With the causes, such as a refused address, which reqwest's own message leaves out
+        .map_err(|err| format!("{:#}", anyhow::Error::new(err)))
+}
+
+// Makes one attempt at a delivery; an error makes the job queue retry it later
+pub async fn deliver(context: &JobContext, job: &Job) -> Result<(), String> {
+    let delivery_id = job.payload_str("delivery_id")?;
+
+    let row = db::query(
+        "SELECT d.id, d.event_type, d.payload, d.status, w.url, w.secret, w.active
+         FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id
+         WHERE d.id = $1"
+    )
+    .bind(delivery_id)
+    .fetch_optional(&context.db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // The webhook was deleted, or the delivery already went through. Failed
+    // deliveries are attempted again when an admin retries their job
+    let Some(row) = row.filter(|row| row.get::<String, _>("status") != STATUS_SUCCEEDED) else {
+        return Ok(());
+    };
+
+    let now = Utc::now();
+
+    if !row.get::<bool, _>("active") {
+        db::query("UPDATE webhook_deliveries SET status = $1, last_error = $2, next_attempt_at = $3 WHERE id = $4")
+            .bind(STATUS_FAILED)
+            .bind("Webhook disabled")
+            .bind(now)
+            .bind(delivery_id)
+            .execute(&context.db)
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let event_type: String = row.get("event_type");
+    let payload: Value = row.get("payload");
+    let url: String = row.get("url");
+    let secret: String = row.get("secret");
+
+    let body = serde_json::to_vec(&payload).unwrap_or_default();
+    let result = post(&context.http, &url, &event_type, delivery_id, &secret, body).await;
+
+    // Redirects aren't followed, so they fail like any other status
+    let (response_status, error) = match result {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16() as i32), None),
+        Ok(response) => (Some(response.status().as_u16() as i32), Some(format!("HTTP {}", response.status()))),
+        Err(err) => (None, Some(err)),
+    };
+
+    let (status, next_attempt_at, delivered_at) = match &error {
+        None => (STATUS_SUCCEEDED, now, Some(now)),
+        Some(_) if job.is_last_attempt() => (STATUS_FAILED, now, None),
+        Some(_) => (STATUS_PENDING, now + RETRY_POLICY.backoff(job.attempts), None),
+    };
+
+    db::query(
+        "UPDATE webhook_deliveries SET status = $1, attempts = $2, next_attempt_at = $3, response_status = $4, last_error = $5, delivered_at = $6 WHERE id = $7"
+    )
+    .bind(status)
+    .bind(job.attempts)
+    .bind(next_attempt_at)
+    .bind(response_status)
+    .bind(&error)
+    .bind(delivered_at)
+    .bind(delivery_id)
+    .execute(&context.db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,1223 @@
+// Drives the full router, authentication included: against the in-memory
+// repositories, which need no database, and for the routes those don't cover,
+// against a migrated in-memory SQLite database
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Timelike, Utc};
+use serde_json::{json, Value};
+use futures::StreamExt;
+use std::{sync::Arc, time::Duration};
+use tower::ServiceExt;
+
+use todo_backend::{app, auth, config::Config, db::{self, Pool}, jobs, repository::MemoryRepository, AppState};
+
+struct TestApp {
+    router: Router,
+    // None when users and todos are in the SQLite database
+    repository: Option<Arc<MemoryRepository>>,
+    db: Pool,
+}
+
+struct TestUser {
+    id: String,
+    token: String,
+}
+
+impl TestApp {
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.auth.jwt_secret = "a-secret-for-the-integration-tests".to_string();
+        // The lowest cost bcrypt accepts, to keep registering fast
+        config.auth.bcrypt_cost = 4;
+        config
+    }
+
+    fn new() -> TestApp {
+        let (state, repository) = AppState::in_memory(TestApp::config());
+        TestApp { db: state.db().clone(), router: app(state), repository: Some(repository) }
+    }
+
+    async fn sqlite() -> TestApp {
+        let state = AppState::sqlite(TestApp::config()).await;
+        TestApp { db: state.db().clone(), router: app(state), repository: None }
+    }
+
+    fn db(&self) -> &Pool {
+        &self.db
+    }
+
+    fn memory(&self) -> &MemoryRepository {
+        self.repository.as_deref().expect("an app made with TestApp::new")
+    }
+
+    // Without a token, for the responses that aren't JSON
+    async fn text(&self, path: &str) -> (StatusCode, String) {
+        let request = Request::builder().uri(path).body(Body::empty()).unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    async fn request(&self, method: Method, path: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(path);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, body)
+    }
+
+    // For the responses that are neither JSON nor text, such as archives
+    async fn download(&self, path: &str, user: &TestUser) -> (StatusCode, Vec<u8>) {
+        let request = Request::builder()
+            .uri(path)
+            .header(header::AUTHORIZATION, format!("Bearer {}", user.token))
+            .body(Body::empty())
+            .unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        (status, to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec())
+    }
+
+    async fn get(&self, path: &str, user: &TestUser) -> (StatusCode, Value) {
+        self.request(Method::GET, path, Some(&user.token), None).await
+    }
+
+    async fn send(&self, method: Method, path: &str, user: &TestUser, body: Value) -> (StatusCode, Value) {
+        self.request(method, path, Some(&user.token), Some(body)).await
+    }
+
+    async fn register(&self, name: &str) -> TestUser {
+        let (status, body) = self
+            .request(
+                Method::POST,
+                "/api/register",
+                None,
+                Some(json!({ "username": name, "email": format!("{}@example.com", name), "password": "password" })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let token = body["token"].as_str().unwrap().to_string();
+        let (_, me) = self.request(Method::GET, "/api/me", Some(&token), None).await;
+        TestUser { id: me["id"].as_str().unwrap().to_string(), token }
+    }
+
+    // As ADMIN_USERNAMES does at startup, for SQLite apps
+    async fn make_admin(&self, user: &TestUser) {
+        db::query("UPDATE users SET role = $1 WHERE id = $2").bind(auth::ROLE_ADMIN).bind(&user.id).execute(self.db()).await.unwrap();
+    }
+
+    // HTTP Basic credentials for CalDAV, with a new app password
+    async fn dav_credentials(&self, user: &TestUser, username: &str) -> String {
+        let (_, created) = self.send(Method::POST, "/api/app-passwords", user, json!({ "name": "CalDAV" })).await;
+        let credentials = format!("{}:{}", username, created["password"].as_str().unwrap());
+        format!("Basic {}", STANDARD.encode(credentials))
+    }
+
+    // The status, ETag and body of a CalDAV request
+    async fn dav(&self, method: &str, path: &str, credentials: &str, etag: Option<&str>, body: &str) -> (StatusCode, Option<String>, String) {
+        let mut request = Request::builder()
+            .method(Method::from_bytes(method.as_bytes()).unwrap())
+            .uri(path)
+            .header(header::AUTHORIZATION, credentials);
+        if let Some(etag) = etag {
+            request = request.header(header::IF_MATCH, etag);
+        }
+        let response = self.router.clone().oneshot(request.body(Body::from(body.to_string())).unwrap()).await.unwrap();
+        let status = response.status();
+        let etag = response.headers().get(header::ETAG).map(|etag| etag.to_str().unwrap().to_string());
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, etag, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    // An import form with the file, its format and whether it's a dry run
+    async fn import(&self, user: &TestUser, format: &str, file: &str, dry_run: bool) -> (StatusCode, Value) {
+        let boundary = "import-boundary";
+        let fields = [("format", format), ("dry_run", if dry_run { "true" } else { "false" }), ("file", file)];
+        let mut body: String = fields
+            .iter()
+            .map(|(name, value)| {
+                format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", boundary, name, value)
+            })
+            .collect();
+        body.push_str(&format!("--{}--\r\n", boundary));
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/import")
+            .header(header::AUTHORIZATION, format!("Bearer {}", user.token))
+            .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+            .body(Body::from(body))
+            .unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    // Stores the link of the verification email sent for a pending email, as
+    // the job sending it would
+    async fn email_verification(&self, user: &TestUser, token: &str, expires_at: DateTime<Utc>) {
+        let token_hash = auth::hash_secret(token);
+        match &self.repository {
+            Some(repository) => repository.set_email_verification(&user.id, &token_hash, expires_at),
+            None => {
+                db::query("UPDATE users SET email_verification_token_hash = $1, email_verification_expires_at = $2 WHERE id = $3")
+                    .bind(token_hash)
+                    .bind(expires_at)
+                    .bind(&user.id)
+                    .execute(self.db())
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+
+    async fn create_todo(&self, user: &TestUser, title: &str) -> String {
+        let (status, todo) = self.send(Method::POST, "/api/todos", user, json!({ "title": title })).await;
+        assert_eq!(status, StatusCode::OK);
+        todo["id"].as_str().unwrap().to_string()
+    }
+
+    async fn titles(&self, path: &str, user: &TestUser) -> Vec<String> {
+        let (status, todos) = self.get(path, user).await;
+        assert_eq!(status, StatusCode::OK);
+        todos
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|todo| todo["title"].as_str().unwrap().to_string())
+            .collect()
+    }
+}
+
+#[tokio::test]
+async fn register_and_log_in() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+
+    let (status, body) = app
+        .request(Method::POST, "/api/login", None, Some(json!({ "email": "alice@example.com", "password": "password" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["password_reset_required"], false);
+    assert!(app.memory().last_login_at(&alice.id).is_some());
+
+    let (status, _) = app
+        .request(Method::POST, "/api/login", None, Some(json!({ "email": "alice@example.com", "password": "wrong" })))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/register",
+            None,
+            Some(json!({ "username": "alice", "email": "other@example.com", "password": "password" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn requests_need_a_valid_token() {
+    let app = TestApp::new();
+
+    let (status, _) = app.request(Method::GET, "/api/todos", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = app.request(Method::GET, "/api/todos", Some("not-a-token"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn tokens_can_be_refreshed() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+
+    let (status, body) = app.request(Method::POST, "/api/token/refresh", Some(&alice.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let token = body["token"].as_str().unwrap();
+    assert_eq!(app.request(Method::GET, "/api/me", Some(token), None).await.0, StatusCode::OK);
+
+    let (status, _) = app.request(Method::POST, "/api/token/refresh", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn create_update_and_list_todos() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+
+    let first = app.create_todo(&alice, "first").await;
+    app.create_todo(&alice, "second").await;
+    assert_eq!(app.titles("/api/todos", &alice).await, ["second", "first"]);
+
+    let (status, todo) = app
+        .send(Method::PATCH, &format!("/api/todos/{}", first), &alice, json!({ "completed": true, "priority": 2 }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(todo["title"], "first");
+    assert_eq!(todo["completed"], true);
+    assert_eq!(todo["priority"], 2);
+
+    let (status, todo) = app.get(&format!("/api/todos/{}", first), &alice).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(todo["completed"], true);
+
+    let (status, history) = app.get(&format!("/api/todos/{}/history", first), &alice).await;
+    assert_eq!(status, StatusCode::OK);
+    let events: Vec<&str> = history.as_array().unwrap().iter().map(|event| event["event_type"].as_str().unwrap()).collect();
+    assert_eq!(events, ["created", "completed"]);
+    assert_eq!(history[1]["actor_username"], "alice");
+    assert_eq!(history[1]["changes"]["priority"], json!({ "before": null, "after": 2 }));
+}
+
+// Every kind of change, with the fields it changed
+async fn check_history(app: TestApp) {
+    let alice = app.register("alice").await;
+    let id = app.create_todo(&alice, "Water the plants").await;
+    let path = format!("/api/todos/{}", id);
+
+    app.send(Method::PATCH, &path, &alice, json!({ "title": "Water the ferns" })).await;
+    app.send(Method::PATCH, &path, &alice, json!({ "completed": true })).await;
+    app.send(Method::PATCH, &path, &alice, json!({ "completed": false })).await;
+    // Saving without a change leaves no trace
+    app.send(Method::PATCH, &path, &alice, json!({ "title": "Water the ferns" })).await;
+    app.request(Method::DELETE, &path, Some(&alice.token), None).await;
+    app.request(Method::POST, &format!("{}/restore", path), Some(&alice.token), None).await;
+
+    let (status, history) = app.get(&format!("{}/history", path), &alice).await;
+    assert_eq!(status, StatusCode::OK);
+    let history = history.as_array().unwrap();
+    let events: Vec<&str> = history.iter().map(|event| event["event_type"].as_str().unwrap()).collect();
+    assert_eq!(events, ["created", "updated", "completed", "uncompleted", "deleted", "restored"]);
+    assert!(history.iter().all(|event| event["actor_username"] == "alice"));
+    assert_eq!(history[0]["changes"]["title"], json!({ "before": null, "after": "Water the plants" }));
+    assert_eq!(history[1]["changes"], json!({ "title": { "before": "Water the plants", "after": "Water the ferns" } }));
+    assert_eq!(history[3]["changes"], json!({ "completed": { "before": true, "after": false } }));
+}
+
+#[tokio::test]
+async fn history_records_every_change() {
+    check_history(TestApp::new()).await;
+    check_history(TestApp::sqlite().await).await;
+}
+
+#[tokio::test]
+async fn null_clears_a_field_and_leaving_it_out_keeps_it() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+    let id = app.create_todo(&alice, "Water the plants").await;
+    let path = format!("/api/todos/{}", id);
+
+    let set = json!({ "due_at": "2030-01-01T12:00:00Z", "priority": 3, "remind_at": "2030-01-01T09:00:00Z" });
+    let (status, _) = app.send(Method::PATCH, &path, &alice, set).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, todo) = app.send(Method::PATCH, &path, &alice, json!({ "title": "Water the ferns" })).await;
+    assert_eq!(todo["due_at"], "2030-01-01T12:00:00Z");
+    assert_eq!(todo["priority"], 3);
+    assert_eq!(todo["remind_at"], "2030-01-01T09:00:00Z");
+
+    let clear = json!({ "due_at": null, "priority": null, "remind_at": null });
+    let (status, todo) = app.send(Method::PATCH, &path, &alice, clear).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(todo["due_at"], Value::Null);
+    assert_eq!(todo["priority"], Value::Null);
+    assert_eq!(todo["remind_at"], Value::Null);
+    assert_eq!(todo["title"], "Water the ferns");
+}
+
+#[tokio::test]
+async fn invalid_priorities_are_rejected() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+
+    let (status, _) = app.send(Method::POST, "/api/todos", &alice, json!({ "title": "urgent", "priority": 10 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn todos_are_private_to_their_owner() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let id = app.create_todo(&alice, "secret").await;
+    let path = format!("/api/todos/{}", id);
+
+    assert!(app.titles("/api/todos", &bob).await.is_empty());
+    assert_eq!(app.get(&path, &bob).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(app.get(&format!("{}/history", path), &bob).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(app.send(Method::PATCH, &path, &bob, json!({ "title": "mine" })).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(app.request(Method::DELETE, &path, Some(&bob.token), None).await.0, StatusCode::NOT_FOUND);
+
+    let (_, todo) = app.get(&path, &alice).await;
+    assert_eq!(todo["title"], "secret");
+}
+
+#[tokio::test]
+async fn trash_restore_and_purge() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+    let id = app.create_todo(&alice, "chore").await;
+    let path = format!("/api/todos/{}", id);
+    let purge = format!("{}?permanent=true", path);
+
+    // Only todos in the trash can be deleted for good
+    assert_eq!(app.request(Method::DELETE, &purge, Some(&alice.token), None).await.0, StatusCode::NOT_FOUND);
+
+    assert_eq!(app.request(Method::DELETE, &path, Some(&alice.token), None).await.0, StatusCode::NO_CONTENT);
+    assert!(app.titles("/api/todos", &alice).await.is_empty());
+    assert_eq!(app.titles("/api/todos?deleted=true", &alice).await, ["chore"]);
+
+    let (status, todo) = app.request(Method::POST, &format!("{}/restore", path), Some(&alice.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(todo["deleted_at"], Value::Null);
+    assert_eq!(app.titles("/api/todos", &alice).await, ["chore"]);
+
+    app.request(Method::DELETE, &path, Some(&alice.token), None).await;
+    assert_eq!(app.request(Method::DELETE, &purge, Some(&alice.token), None).await.0, StatusCode::NO_CONTENT);
+    assert_eq!(app.get(&path, &alice).await.0, StatusCode::NOT_FOUND);
+    assert!(app.titles("/api/todos?deleted=true", &alice).await.is_empty());
+}
+
+async fn check_bulk_actions(app: TestApp) {
+    let alice = app.register("alice").await;
+    let first = app.create_todo(&alice, "first").await;
+    app.create_todo(&alice, "second").await;
+
+    let (status, body) = app
+        .send(Method::POST, "/api/todos/bulk", &alice, json!({ "action": "complete", "filter": { "completed": false } }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result["success"] == true && result["todo"]["completed"] == true));
+
+    // Completing again succeeds without adding to the history
+    let (_, body) = app
+        .send(Method::POST, "/api/todos/bulk", &alice, json!({ "action": "complete", "ids": [first, "missing"] }))
+        .await;
+    assert_eq!(body["results"][0]["success"], true);
+    assert_eq!(body["results"][1]["success"], false);
+    assert_eq!(body["results"][1]["error"], "Todo not found");
+    let (_, history) = app.get(&format!("/api/todos/{}/history", first), &alice).await;
+    assert_eq!(history.as_array().unwrap().len(), 2);
+
+    let (_, body) = app.send(Method::POST, "/api/todos/bulk", &alice, json!({ "action": "restore", "ids": [first] })).await;
+    assert_eq!(body["results"][0]["error"], "Todo is not in the trash");
+
+    let (status, _) = app
+        .send(Method::POST, "/api/todos/bulk", &alice, json!({ "action": "delete", "ids": [first], "filter": {} }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Duplicates count towards the limit, and are only acted on once
+    let (_, body) = app
+        .send(Method::POST, "/api/todos/bulk", &alice, json!({ "action": "uncomplete", "ids": [first, first] }))
+        .await;
+    assert_eq!(body["results"].as_array().unwrap().len(), 1);
+    let ids = vec![first.clone(); 1001];
+    let (status, _) = app.send(Method::POST, "/api/todos/bulk", &alice, json!({ "action": "delete", "ids": ids })).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Through the trash and back, by filter
+    let (_, body) = app.send(Method::POST, "/api/todos/bulk", &alice, json!({ "action": "delete", "filter": {} })).await;
+    assert_eq!(body["results"].as_array().unwrap().len(), 2);
+    assert!(app.titles("/api/todos", &alice).await.is_empty());
+    let (_, body) = app
+        .send(Method::POST, "/api/todos/bulk", &alice, json!({ "action": "restore", "filter": { "deleted": true } }))
+        .await;
+    assert!(body["results"].as_array().unwrap().iter().all(|result| result["success"] == true));
+    assert_eq!(app.titles("/api/todos", &alice).await.len(), 2);
+}
+
+#[tokio::test]
+async fn bulk_actions() {
+    check_bulk_actions(TestApp::new()).await;
+    check_bulk_actions(TestApp::sqlite().await).await;
+}
+
+#[tokio::test]
+async fn move_todos() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+    let a = app.create_todo(&alice, "a").await;
+    let b = app.create_todo(&alice, "b").await;
+    let c = app.create_todo(&alice, "c").await;
+    assert_eq!(app.titles("/api/todos", &alice).await, ["c", "b", "a"]);
+
+    let move_a = format!("/api/todos/{}/move", a);
+    let (status, _) = app.send(Method::POST, &move_a, &alice, json!({ "previous_id": c, "next_id": b })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(app.titles("/api/todos", &alice).await, ["c", "a", "b"]);
+
+    let (status, _) = app.send(Method::POST, &move_a, &alice, json!({ "next_id": c })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(app.titles("/api/todos", &alice).await, ["a", "c", "b"]);
+
+    // Neighbours in the wrong order, the todo itself, or no neighbour at all
+    let (status, _) = app.send(Method::POST, &move_a, &alice, json!({ "previous_id": b, "next_id": c })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = app.send(Method::POST, &move_a, &alice, json!({ "previous_id": a })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app.send(Method::POST, &move_a, &alice, json!({})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn disabled_accounts_are_locked_out() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+    app.memory().set_disabled(&alice.id, true);
+
+    assert_eq!(app.get("/api/me", &alice).await.0, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .request(Method::POST, "/api/login", None, Some(json!({ "email": "alice@example.com", "password": "password" })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    app.memory().set_disabled(&alice.id, false);
+    assert_eq!(app.get("/api/me", &alice).await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn a_required_password_reset_blocks_everything_else() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+    app.memory().require_password_reset(&alice.id);
+
+    assert_eq!(app.get("/api/todos", &alice).await.0, StatusCode::FORBIDDEN);
+    let (status, me) = app.get("/api/me", &alice).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["password_reset_required"], true);
+
+    let (status, _) = app
+        .send(
+            Method::POST,
+            "/api/me/password",
+            &alice,
+            json!({ "current_password": "password", "new_password": "a new password" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(app.get("/api/todos", &alice).await.0, StatusCode::OK);
+}
+
+async fn check_profile_edits(app: TestApp) {
+    let alice = app.register("alice").await;
+    app.register("bob").await;
+
+    let changes = json!({
+        "username": " alice2 ",
+        "display_name": "Alice",
+        "timezone": "Europe/Paris",
+        "locale": "fr-FR",
+        "avatar_url": "https://example.com/alice.png",
+    });
+    let (status, me) = app.send(Method::PATCH, "/api/me", &alice, changes).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        (&me["username"], &me["display_name"], &me["timezone"], &me["locale"], &me["avatar_url"]),
+        (&json!("alice2"), &json!("Alice"), &json!("Europe/Paris"), &json!("fr-FR"), &json!("https://example.com/alice.png"))
+    );
+
+    // Empty strings clear the optional fields, and fields left out keep their value
+    let (_, me) = app.send(Method::PATCH, "/api/me", &alice, json!({ "display_name": "", "avatar_url": "" })).await;
+    assert_eq!((&me["display_name"], &me["avatar_url"]), (&Value::Null, &Value::Null));
+    assert_eq!((&me["username"], &me["locale"]), (&json!("alice2"), &json!("fr-FR")));
+
+    for invalid in [
+        json!({ "username": "  " }),
+        json!({ "email": "not an email" }),
+        json!({ "timezone": "Mars/Olympus" }),
+        json!({ "locale": "french" }),
+        json!({ "avatar_url": "javascript:alert(1)" }),
+    ] {
+        assert_eq!(app.send(Method::PATCH, "/api/me", &alice, invalid.clone()).await.0, StatusCode::BAD_REQUEST, "{}", invalid);
+    }
+    assert_eq!(app.send(Method::PATCH, "/api/me", &alice, json!({ "username": "bob" })).await.0, StatusCode::CONFLICT);
+    assert_eq!(app.get("/api/me", &alice).await.1["username"], "alice2");
+}
+
+#[tokio::test]
+async fn profiles_can_be_edited() {
+    check_profile_edits(TestApp::new()).await;
+    check_profile_edits(TestApp::sqlite().await).await;
+}
+
+async fn check_email_changes(app: TestApp) {
+    let alice = app.register("alice").await;
+    app.register("bob").await;
+
+    assert_eq!(app.send(Method::PATCH, "/api/me", &alice, json!({ "email": "bob@example.com" })).await.0, StatusCode::CONFLICT);
+    let (status, me) = app.send(Method::PATCH, "/api/me", &alice, json!({ "email": "alice@example.net" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((&me["email"], &me["pending_email"]), (&json!("alice@example.com"), &json!("alice@example.net")));
+
+    // Asking for the current address again cancels the change
+    let (_, me) = app.send(Method::PATCH, "/api/me", &alice, json!({ "email": "alice@example.com" })).await;
+    assert_eq!(me["pending_email"], Value::Null);
+
+    app.send(Method::PATCH, "/api/me", &alice, json!({ "email": "alice@example.net" })).await;
+    app.email_verification(&alice, "expired", Utc::now() - chrono::Duration::minutes(1)).await;
+    assert_eq!(app.text("/api/me/email/verify?token=expired").await.0, StatusCode::NOT_FOUND);
+    app.email_verification(&alice, "the-token", Utc::now() + chrono::Duration::hours(1)).await;
+    assert_eq!(app.text("/api/me/email/verify?token=wrong").await.0, StatusCode::NOT_FOUND);
+    assert_eq!(app.text("/api/me/email/verify?token=the-token").await.0, StatusCode::OK);
+    let (_, me) = app.get("/api/me", &alice).await;
+    assert_eq!((&me["email"], &me["pending_email"]), (&json!("alice@example.net"), &Value::Null));
+    assert_eq!(app.text("/api/me/email/verify?token=the-token").await.0, StatusCode::NOT_FOUND);
+
+    // Someone registered the address before the link was opened
+    app.send(Method::PATCH, "/api/me", &alice, json!({ "email": "carol@example.com" })).await;
+    app.email_verification(&alice, "too-late", Utc::now() + chrono::Duration::hours(1)).await;
+    app.register("carol").await;
+    assert_eq!(app.text("/api/me/email/verify?token=too-late").await.0, StatusCode::CONFLICT);
+    assert_eq!(app.get("/api/me", &alice).await.1["email"], "alice@example.net");
+}
+
+#[tokio::test]
+async fn email_changes_wait_for_verification() {
+    check_email_changes(TestApp::new()).await;
+    check_email_changes(TestApp::sqlite().await).await;
+}
+
+#[tokio::test]
+async fn email_changes_queue_a_verification_email() {
+    let app = TestApp::sqlite().await;
+    let alice = app.register("alice").await;
+
+    app.send(Method::PATCH, "/api/me", &alice, json!({ "email": "alice@example.net" })).await;
+    let queued: Vec<String> = db::query_scalar("SELECT payload FROM jobs WHERE kind = $1")
+        .bind(jobs::JOB_SEND_EMAIL_VERIFICATION)
+        .fetch_all(app.db())
+        .await
+        .unwrap();
+    assert_eq!(queued.len(), 1);
+    assert!(queued[0].contains(&alice.id), "{}", queued[0]);
+}
+
+#[tokio::test]
+async fn passwords_change_only_with_the_current_one() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+    let change = |current: &str, new: &str| json!({ "current_password": current, "new_password": new });
+
+    assert_eq!(app.send(Method::POST, "/api/me/password", &alice, change("wrong", "new password")).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(app.send(Method::POST, "/api/me/password", &alice, change("password", "")).await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(app.send(Method::POST, "/api/me/password", &alice, change("password", "password")).await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(app.send(Method::POST, "/api/me/password", &alice, change("password", "new password")).await.0, StatusCode::NO_CONTENT);
+
+    let login = |password: &str| json!({ "email": "alice@example.com", "password": password });
+    assert_eq!(app.request(Method::POST, "/api/login", None, Some(login("password"))).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(app.request(Method::POST, "/api/login", None, Some(login("new password"))).await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn account_deletion_can_be_cancelled() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+
+    let (status, _) = app.send(Method::DELETE, "/api/me", &alice, json!({ "password": "wrong" })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, me) = app.send(Method::DELETE, "/api/me", &alice, json!({ "password": "password" })).await;
+    assert_eq!(status, StatusCode::OK);
+    let scheduled_at = me["deletion_scheduled_at"].clone();
+    assert!(scheduled_at.is_string());
+
+    // Asking again keeps the first date
+    let (_, me) = app.send(Method::DELETE, "/api/me", &alice, json!({ "password": "password" })).await;
+    assert_eq!(me["deletion_scheduled_at"], scheduled_at);
+
+    let (status, me) = app.request(Method::POST, "/api/me/restore", Some(&alice.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["deletion_scheduled_at"], Value::Null);
+}
+
+// The JSON files in the user's account export, by name
+async fn account_export(app: &TestApp, user: &TestUser) -> Vec<(String, String)> {
+    let (status, archive) = app.download("/api/me/export", user).await;
+    assert_eq!(status, StatusCode::OK);
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+    let mut files: Vec<(String, String)> = (0..archive.len())
+        .map(|index| {
+            let file = archive.by_index(index).unwrap();
+            (file.name().to_string(), std::io::read_to_string(file).unwrap())
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn export_file(files: &[(String, String)], name: &str) -> (String, Value) {
+    let (_, text) = files.iter().find(|(file, _)| file == name).unwrap();
+    (text.clone(), serde_json::from_str(text).unwrap())
+}
+
+async fn check_account_export(app: TestApp) {
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let todo = app.create_todo(&alice, "Water the plants").await;
+    app.send(Method::PATCH, &format!("/api/todos/{}", todo), &alice, json!({ "completed": true })).await;
+    app.create_todo(&bob, "Feed the cat").await;
+
+    let files = account_export(&app, &alice).await;
+    let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["app_passwords.json", "history.json", "notification_preferences.json", "profile.json", "todos.json", "webhooks.json"]
+    );
+
+    let (text, profile) = export_file(&files, "profile.json");
+    assert_eq!(profile["username"], "alice");
+    // No bcrypt hash
+    assert!(!text.contains("$2b$"), "{}", text);
+    let (_, todos) = export_file(&files, "todos.json");
+    assert_eq!(todos.as_array().unwrap().iter().map(|todo| todo["title"].as_str().unwrap()).collect::<Vec<_>>(), ["Water the plants"]);
+    let (_, history) = export_file(&files, "history.json");
+    let events: Vec<&str> = history.as_array().unwrap().iter().map(|event| event["event_type"].as_str().unwrap()).collect();
+    assert_eq!(events, ["created", "completed"]);
+    let (_, preferences) = export_file(&files, "notification_preferences.json");
+    assert_eq!((&preferences["digest_frequency"], &preferences["timezone"]), (&json!("off"), &json!("UTC")));
+}
+
+#[tokio::test]
+async fn account_exports_hold_everything_but_secrets() {
+    check_account_export(TestApp::new()).await;
+    check_account_export(TestApp::sqlite().await).await;
+}
+
+#[tokio::test]
+async fn account_exports_leave_out_app_password_and_webhook_secrets() {
+    let app = TestApp::sqlite().await;
+    let alice = app.register("alice").await;
+    let (_, app_password) = app.send(Method::POST, "/api/app-passwords", &alice, json!({ "name": "Phone" })).await;
+    let (_, webhook) = app
+        .send(Method::POST, "/api/webhooks", &alice, json!({ "url": "https://example.com/hook", "events": ["todo.created"] }))
+        .await;
+
+    let files = account_export(&app, &alice).await;
+    let (text, app_passwords) = export_file(&files, "app_passwords.json");
+    assert_eq!(app_passwords[0]["name"], "Phone");
+    assert!(!text.contains(app_password["password"].as_str().unwrap()), "{}", text);
+    let (text, webhooks) = export_file(&files, "webhooks.json");
+    assert_eq!(webhooks[0]["url"], "https://example.com/hook");
+    assert!(!text.contains(webhook["secret"].as_str().unwrap()), "{}", text);
+}
+
+#[tokio::test]
+async fn changes_are_announced_to_their_owner() {
+    let app = TestApp::new();
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+
+    let request = Request::builder()
+        .uri("/api/events")
+        .header(header::AUTHORIZATION, format!("Bearer {}", alice.token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut events = response.into_body().into_data_stream();
+    let wait = Duration::from_millis(200);
+
+    app.create_todo(&bob, "bob's").await;
+    assert!(tokio::time::timeout(wait, events.next()).await.is_err());
+
+    app.create_todo(&alice, "alice's").await;
+    let event = tokio::time::timeout(wait, events.next()).await.expect("an event").unwrap().unwrap();
+    assert!(String::from_utf8_lossy(&event).starts_with("event: todos\n"));
+}
+
+#[tokio::test]
+async fn the_api_document_is_public() {
+    let app = TestApp::new();
+
+    let (status, document) = app.request(Method::GET, "/api/openapi.json", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(document["openapi"].as_str().unwrap().starts_with("3.1"));
+    assert_eq!(document["components"]["securitySchemes"]["bearer"]["scheme"], "bearer");
+
+    // Parameters in OpenAPI's syntax, and public routes without the bearer scheme
+    let paths = &document["paths"];
+    assert!(paths["/api/todos/{id}/move"]["post"].is_object());
+    assert!(paths["/api/admin/users/{id}"]["patch"].is_object());
+    assert_eq!(paths["/api/login"]["post"]["security"], json!([{}]));
+    assert!(paths["/api/todos"]["get"]["security"].is_null());
+
+    let (status, _) = app.request(Method::GET, "/api/docs", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn health_checks_and_metrics_are_public() {
+    let app = TestApp::sqlite().await;
+
+    assert_eq!(app.text("/healthz").await.0, StatusCode::OK);
+    assert_eq!(app.text("/readyz").await, (StatusCode::OK, "ready\n".to_string()));
+
+    let alice = app.register("alice").await;
+    let todo = app.create_todo(&alice, "Water the plants").await;
+    app.get(&format!("/api/todos/{}", todo), &alice).await;
+    assert_eq!(app.text("/api/todos").await.0, StatusCode::UNAUTHORIZED);
+    let done = app.create_todo(&alice, "Buy milk").await;
+    app.send(Method::PATCH, &format!("/api/todos/{}", done), &alice, json!({ "completed": true })).await;
+    let trashed = app.create_todo(&alice, "Call the bank").await;
+    app.request(Method::DELETE, &format!("/api/todos/{}", trashed), Some(&alice.token), None).await;
+
+    let (status, metrics) = app.text("/metrics").await;
+    assert_eq!(status, StatusCode::OK);
+    for line in [
+        "todo_http_requests_total{method=\"POST\",route=\"/api/register\",status=\"200\"} 1",
+        "todo_http_requests_total{method=\"GET\",route=\"/api/todos/:id\",status=\"200\"} 1",
+        "todo_http_requests_total{method=\"GET\",route=\"unmatched\",status=\"401\"} 1",
+        "todo_http_request_duration_seconds_count{method=\"GET\",route=\"/readyz\",status=\"200\"} 1",
+        "todo_db_pool_max_connections 1",
+        "todo_users 1",
+        "todo_active_sessions 1",
+        "todo_todos{state=\"open\"} 1",
+        "todo_todos{state=\"completed\"} 1",
+        "todo_todos{state=\"trashed\"} 1",
+        "todo_event_stream_clients 0",
+    ] {
+        assert!(metrics.lines().any(|metric| metric == line), "no {} in\n{}", line, metrics);
+    }
+    assert!(metrics.lines().any(|metric| metric.starts_with("todo_db_pool_connections ")), "{}", metrics);
+}
+
+#[tokio::test]
+async fn readiness_waits_for_every_migration() {
+    let app = TestApp::sqlite().await;
+    let latest = app.db().known_migrations().last().unwrap().0;
+    app.db().revert(latest - 1).await.unwrap();
+
+    assert_eq!(app.text("/readyz").await, (StatusCode::SERVICE_UNAVAILABLE, "migrations pending\n".to_string()));
+    // The process itself is fine
+    assert_eq!(app.text("/healthz").await.0, StatusCode::OK);
+
+    app.db().migrate().await.unwrap();
+    assert_eq!(app.text("/readyz").await.0, StatusCode::OK);
+}
+
+// The routes below keep what they store in SQL only, so they run against SQLite
+
+#[tokio::test]
+async fn the_calendar_feed_exists_once_made() {
+    let app = TestApp::sqlite().await;
+    let alice = app.register("alice").await;
+    app.create_todo(&alice, "Water the plants").await;
+
+    assert_eq!(app.get("/api/calendar/token", &alice).await.0, StatusCode::NOT_FOUND);
+    let (status, feed) = app.send(Method::POST, "/api/calendar/token", &alice, json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(app.get("/api/calendar/token", &alice).await, (StatusCode::OK, feed.clone()));
+
+    // The token is the only credential the feed needs
+    let path = feed["path"].as_str().unwrap();
+    let (status, calendar) = app.text(path).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(calendar.contains("BEGIN:VTODO") && calendar.contains("SUMMARY:Water the plants"), "{}", calendar);
+
+    let (_, replaced) = app.send(Method::POST, "/api/calendar/token", &alice, json!({})).await;
+    assert_ne!(replaced["token"], feed["token"]);
+    assert_eq!(app.text(path).await.0, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn changing_the_time_zone_moves_the_next_digest() {
+    let app = TestApp::sqlite().await;
+    let alice = app.register("alice").await;
+
+    let (status, preferences) = app
+        .send(Method::PATCH, "/api/notifications/preferences", &alice, json!({ "digest_frequency": "daily", "digest_hour": 8 }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let next_at = |preferences: &Value| preferences["digest_next_at"].as_str().unwrap().parse::<DateTime<Utc>>().unwrap();
+    assert_eq!(next_at(&preferences).hour(), 8);
+
+    // 8:00 in Tokyo, which has no daylight saving time, is 23:00 UTC
+    let (status, _) = app.send(Method::PATCH, "/api/me", &alice, json!({ "timezone": "Asia/Tokyo" })).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, preferences) = app.get("/api/notifications/preferences", &alice).await;
+    assert_eq!(preferences["timezone"], "Asia/Tokyo");
+    let moved = next_at(&preferences);
+    assert_eq!((moved.hour(), moved.minute()), (23, 0));
+
+    // Other changes to the profile leave it alone
+    app.send(Method::PATCH, "/api/me", &alice, json!({ "display_name": "Alice" })).await;
+    let (_, preferences) = app.get("/api/notifications/preferences", &alice).await;
+    assert_eq!(next_at(&preferences), moved);
+}
+
+#[tokio::test]
+async fn webhooks_queue_deliveries_for_their_events() {
+    let app = TestApp::sqlite().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+
+    let (status, _) = app
+        .send(Method::POST, "/api/webhooks", &alice, json!({ "url": "ftp://example.com/", "events": ["todo.created"] }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, webhook) = app
+        .send(Method::POST, "/api/webhooks", &alice, json!({ "url": "https://example.com/hook", "events": ["todo.created"] }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!webhook["secret"].as_str().unwrap().is_empty());
+    let deliveries = format!("/api/webhooks/{}/deliveries", webhook["id"].as_str().unwrap());
+
+    let todo = app.create_todo(&alice, "Water the plants").await;
+    app.send(Method::PATCH, &format!("/api/todos/{}", todo), &alice, json!({ "title": "Water the cactus" })).await;
+
+    // Only the subscribed event, and nothing is sent until the job queue runs
+    let (status, list) = app.get(&deliveries, &alice).await;
+    assert_eq!(status, StatusCode::OK);
+    let list = list.as_array().unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0]["event_type"], "todo.created");
+    assert_eq!(list[0]["status"], "pending");
+    assert_eq!(list[0]["payload"]["todo"]["title"], "Water the plants");
+
+    // Only failed deliveries can be retried
+    let retry = format!("{}/{}/retry", deliveries, list[0]["id"].as_str().unwrap());
+    assert_eq!(app.request(Method::POST, &retry, Some(&alice.token), None).await.0, StatusCode::CONFLICT);
+    assert_eq!(app.request(Method::POST, &retry, Some(&bob.token), None).await.0, StatusCode::NOT_FOUND);
+
+    assert_eq!(app.get(&deliveries, &bob).await.0, StatusCode::NOT_FOUND);
+    let (_, webhooks) = app.get("/api/webhooks", &bob).await;
+    assert_eq!(webhooks, json!([]));
+}
+
+#[tokio::test]
+async fn admins_manage_users() {
+    let app = TestApp::sqlite().await;
+    let admin = app.register("admin").await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob_smith").await;
+    app.make_admin(&admin).await;
+    app.create_todo(&alice, "Water the plants").await;
+    let trashed = app.create_todo(&alice, "Feed the cat").await;
+    app.request(Method::DELETE, &format!("/api/todos/{}", trashed), Some(&alice.token), None).await;
+
+    assert_eq!(app.get("/api/admin/users", &alice).await.0, StatusCode::FORBIDDEN);
+    let usernames = |users: Value| -> Vec<String> {
+        users.as_array().unwrap().iter().map(|user| user["username"].as_str().unwrap().to_string()).collect()
+    };
+    let (status, users) = app.get("/api/admin/users", &admin).await;
+    assert_eq!(status, StatusCode::OK);
+    let mut all = usernames(users);
+    all.sort();
+    assert_eq!(all, ["admin", "alice", "bob_smith"]);
+    assert_eq!(usernames(app.get("/api/admin/users?q=ALI", &admin).await.1), ["alice"]);
+    // Wildcards are matched literally
+    assert_eq!(usernames(app.get("/api/admin/users?q=b_s", &admin).await.1), ["bob_smith"]);
+    assert_eq!(usernames(app.get("/api/admin/users?q=a_i", &admin).await.1), Vec::<String>::new());
+    assert_eq!(usernames(app.get("/api/admin/users?q=%25", &admin).await.1), Vec::<String>::new());
+
+    let alice_path = format!("/api/admin/users/{}", alice.id);
+    let (_, user) = app.get(&alice_path, &admin).await;
+    assert_eq!((&user["username"], &user["todo_count"], &user["role"]), (&json!("alice"), &json!(1), &json!("user")));
+    assert_eq!(app.get("/api/admin/users/nobody", &admin).await.0, StatusCode::NOT_FOUND);
+
+    // Roles are read on every request, so they apply to tokens already given out
+    assert_eq!(app.send(Method::PATCH, &alice_path, &admin, json!({ "role": "owner" })).await.0, StatusCode::BAD_REQUEST);
+    let (_, user) = app.send(Method::PATCH, &alice_path, &admin, json!({ "role": "admin" })).await;
+    assert_eq!(user["role"], "admin");
+    assert_eq!(app.get("/api/admin/users", &alice).await.0, StatusCode::OK);
+    app.send(Method::PATCH, &alice_path, &admin, json!({ "role": "user" })).await;
+    assert_eq!(app.get("/api/admin/users", &alice).await.0, StatusCode::FORBIDDEN);
+
+    // Admins can't lock themselves out
+    let admin_path = format!("/api/admin/users/{}", admin.id);
+    assert_eq!(app.send(Method::PATCH, &admin_path, &admin, json!({ "role": "user" })).await.0, StatusCode::BAD_REQUEST);
+    let disable_admin = format!("{}/disable", admin_path);
+    assert_eq!(app.request(Method::POST, &disable_admin, Some(&admin.token), None).await.0, StatusCode::BAD_REQUEST);
+
+    let bob_path = format!("/api/admin/users/{}", bob.id);
+    let (status, user) = app.request(Method::POST, &format!("{}/disable", bob_path), Some(&admin.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(user["disabled_at"].is_string());
+    assert_eq!(app.get("/api/me", &bob).await.0, StatusCode::FORBIDDEN);
+    let (_, user) = app.request(Method::POST, &format!("{}/enable", bob_path), Some(&admin.token), None).await;
+    assert_eq!(user["disabled_at"], Value::Null);
+    assert_eq!(app.get("/api/me", &bob).await.0, StatusCode::OK);
+
+    let (_, user) = app.request(Method::POST, &format!("{}/reset-password", bob_path), Some(&admin.token), None).await;
+    assert_eq!(user["password_reset_required"], true);
+    assert_eq!(app.get("/api/todos", &bob).await.0, StatusCode::FORBIDDEN);
+    let missing = app.request(Method::POST, "/api/admin/users/nobody/disable", Some(&admin.token), None).await;
+    assert_eq!(missing.0, StatusCode::NOT_FOUND);
+
+    let (status, stats) = app.get("/api/admin/stats", &admin).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((&stats["users"], &stats["admins"], &stats["todos"], &stats["todos_in_trash"]), (&json!(3), &json!(1), &json!(1), &json!(1)));
+    assert_eq!((&stats["new_users"], &stats["new_todos"], &stats["disabled_users"]), (&json!(3), &json!(2), &json!(0)));
+}
+
+#[tokio::test]
+async fn admins_list_and_retry_failed_jobs() {
+    let app = TestApp::sqlite().await;
+    let admin = app.register("admin").await;
+    let alice = app.register("alice").await;
+    app.make_admin(&admin).await;
+    let failed = jobs::enqueue(app.db(), jobs::JOB_PURGE_TRASH, json!({}), Utc::now()).await.unwrap();
+    jobs::enqueue(app.db(), jobs::JOB_PRUNE_JOBS, json!({}), Utc::now()).await.unwrap();
+    db::query("UPDATE jobs SET status = $1, attempts = 5, last_error = 'disk full' WHERE id = $2")
+        .bind(jobs::STATUS_FAILED)
+        .bind(&failed)
+        .execute(app.db())
+        .await
+        .unwrap();
+
+    assert_eq!(app.get("/api/admin/jobs", &alice).await.0, StatusCode::FORBIDDEN);
+    let (status, listed) = app.get("/api/admin/jobs?status=failed", &admin).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["id"], failed.as_str());
+    assert_eq!(listed[0]["last_error"], "disk full");
+    let (_, listed) = app.get("/api/admin/jobs?kind=prune_jobs", &admin).await;
+    assert_eq!(listed[0]["status"], "pending");
+
+    // With a fresh set of attempts; only failed jobs can be retried
+    let retry = format!("/api/admin/jobs/{}/retry", failed);
+    assert_eq!(app.request(Method::POST, &retry, Some(&alice.token), None).await.0, StatusCode::FORBIDDEN);
+    let (status, job) = app.request(Method::POST, &retry, Some(&admin.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((job["status"].as_str(), job["attempts"].as_i64()), (Some("pending"), Some(0)));
+    assert_eq!(app.request(Method::POST, &retry, Some(&admin.token), None).await.0, StatusCode::NOT_FOUND);
+}
+
+async fn check_imports(app: TestApp) {
+    let alice = app.register("alice").await;
+    app.create_todo(&alice, "Buy milk").await;
+    let file = "(A) Pay rent due:2026-02-01\nbuy  MILK\ndue:2026-01-01\nCall mum";
+
+    let (status, report) = app.import(&alice, "todotxt", file, true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(report["created"].as_array().unwrap().len(), 2);
+    assert_eq!(report["duplicates"][0]["title"], "buy MILK");
+    assert_eq!(report["errors"], json!([{ "line": 3, "message": "Missing title" }]));
+    assert_eq!(app.titles("/api/todos", &alice).await, ["Buy milk"]);
+
+    let (status, report) = app.import(&alice, "todotxt", file, false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["created"].as_array().unwrap().len(), 2);
+    let (_, todos) = app.get("/api/todos", &alice).await;
+    assert_eq!(todos[0]["title"], "Pay rent");
+    assert_eq!(todos[0]["priority"], 1);
+    assert_eq!(todos[0]["due_at"], "2026-02-01T00:00:00Z");
+    assert_eq!(app.titles("/api/todos", &alice).await, ["Pay rent", "Call mum", "Buy milk"]);
+    let (_, history) = app.get(&format!("/api/todos/{}/history", todos[0]["id"].as_str().unwrap()), &alice).await;
+    assert_eq!(history[0]["event_type"], "created");
+
+    // Everything is a duplicate the second time
+    let (_, report) = app.import(&alice, "todotxt", file, false).await;
+    assert_eq!((report["created"].as_array().unwrap().len(), report["duplicates"].as_array().unwrap().len()), (0, 3));
+    assert_eq!(app.titles("/api/todos", &alice).await.len(), 3);
+
+    let (status, _) = app.import(&alice, "taskwarrior", "not json", false).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn imports_skip_duplicates_and_report_bad_lines() {
+    check_imports(TestApp::new()).await;
+    check_imports(TestApp::sqlite().await).await;
+}
+
+#[tokio::test]
+async fn imported_todos_are_not_announced_to_webhooks() {
+    let app = TestApp::sqlite().await;
+    let alice = app.register("alice").await;
+    let (_, webhook) = app
+        .send(Method::POST, "/api/webhooks", &alice, json!({ "url": "https://example.com/hook", "events": ["todo.created"] }))
+        .await;
+
+    let (_, report) = app.import(&alice, "todotxt", "Pay rent\nCall mum", false).await;
+    assert_eq!(report["created"].as_array().unwrap().len(), 2);
+    let (_, deliveries) = app.get(&format!("/api/webhooks/{}/deliveries", webhook["id"].as_str().unwrap()), &alice).await;
+    assert_eq!(deliveries, json!([]));
+}
+
+#[tokio::test]
+async fn app_passwords_are_shown_once() {
+    let app = TestApp::sqlite().await;
+    let alice = app.register("alice").await;
+
+    let (status, created) = app.send(Method::POST, "/api/app-passwords", &alice, json!({ "name": "Phone" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!created["password"].as_str().unwrap().is_empty());
+
+    let (_, passwords) = app.get("/api/app-passwords", &alice).await;
+    assert_eq!(passwords.as_array().unwrap().len(), 1);
+    assert_eq!(passwords[0]["name"], "Phone");
+    assert!(passwords[0].get("password").is_none());
+
+    let path = format!("/api/app-passwords/{}", created["id"].as_str().unwrap());
+    let (status, _) = app.request(Method::DELETE, &path, Some(&alice.token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, passwords) = app.get("/api/app-passwords", &alice).await;
+    assert_eq!(passwords, json!([]));
+}
+
+#[tokio::test]
+async fn export_has_only_the_users_todos() {
+    let app = TestApp::sqlite().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    app.create_todo(&alice, "Water the plants").await;
+    app.create_todo(&bob, "Feed the cat").await;
+
+    for format in ["json", "csv", "md", "todotxt"] {
+        let request = Request::builder()
+            .uri(format!("/api/export?format={}", format))
+            .header(header::AUTHORIZATION, format!("Bearer {}", alice.token))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()[header::CONTENT_DISPOSITION].to_str().unwrap().starts_with("attachment"));
+        let export = String::from_utf8(to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+        assert!(export.contains("Water the plants") && !export.contains("Feed the cat"), "{}", export);
+    }
+    let (status, _) = app.get("/api/export?format=xml", &alice).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+fn vtodo(uid: &str, summary: &str) -> String {
+    format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTODO\r\nUID:{}\r\nSUMMARY:{}\r\nEND:VTODO\r\nEND:VCALENDAR\r\n", uid, summary)
+}
+
+#[tokio::test]
+async fn caldav_clients_name_their_own_resources() {
+    let app = TestApp::sqlite().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let alices = app.create_todo(&alice, "Water the plants").await;
+    let credentials = app.dav_credentials(&bob, "bob").await;
+
+    // A name that is the id of someone else's todo is just a new name
+    let path = format!("/dav/{}/todos/{}.ics", bob.id, alices);
+    let (status, etag, _) = app.dav("PUT", &path, &credentials, None, &vtodo("feed-cat@phone", "Feed the cat")).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, todo) = app.get(&format!("/api/todos/{}", alices), &alice).await;
+    assert_eq!(todo["title"], "Water the plants");
+
+    // Served back under the client's name and UID, but stored under an id of its own
+    let (status, _, calendar) = app.dav("GET", &path, &credentials, None, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(calendar.contains("UID:feed-cat@phone") && calendar.contains("SUMMARY:Feed the cat"), "{}", calendar);
+    let (_, todos) = app.get("/api/todos", &bob).await;
+    assert_eq!(todos.as_array().unwrap().len(), 1);
+    assert_ne!(todos[0]["id"].as_str().unwrap(), alices);
+
+    let (status, _, _) = app.dav("PUT", &path, &credentials, etag.as_deref(), &vtodo("feed-cat@phone", "Feed the cats")).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(app.titles("/api/todos", &bob).await, ["Feed the cats"]);
+
+    // The name stays taken while the todo is in the trash
+    assert_eq!(app.dav("DELETE", &path, &credentials, None, "").await.0, StatusCode::NO_CONTENT);
+    assert_eq!(app.dav("GET", &path, &credentials, None, "").await.0, StatusCode::NOT_FOUND);
+    let (status, _, _) = app.dav("PUT", &path, &credentials, None, &vtodo("feed-cat@phone", "Feed the cat")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Todos made elsewhere are named by their id
+    let todo = app.create_todo(&bob, "Buy milk").await;
+    let (status, _, calendar) = app.dav("GET", &format!("/dav/{}/todos/{}.ics", bob.id, todo), &credentials, None, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(calendar.contains(&format!("UID:{}", todo)), "{}", calendar);
+}
+
+#[tokio::test]
+async fn caldav_due_dates_without_a_zone_are_in_the_users() {
+    let app = TestApp::sqlite().await;
+    let bob = app.register("bob").await;
+    let (status, _) = app.send(Method::PATCH, "/api/me", &bob, json!({ "timezone": "Europe/Berlin" })).await;
+    assert_eq!(status, StatusCode::OK);
+    let credentials = app.dav_credentials(&bob, "bob").await;
+
+    let cases = [
+        ("floating", "DUE:20260115T090000", "2026-01-15T08:00:00Z"),
+        ("zoned", "DUE;TZID=America/New_York:20260115T090000", "2026-01-15T14:00:00Z"),
+        ("utc", "DUE:20260115T090000Z", "2026-01-15T09:00:00Z"),
+    ];
+    for (name, due, _) in cases {
+        let body = vtodo(name, name).replace("END:VTODO", &format!("{}\r\nEND:VTODO", due));
+        let path = format!("/dav/{}/todos/{}.ics", bob.id, name);
+        assert_eq!(app.dav("PUT", &path, &credentials, None, &body).await.0, StatusCode::CREATED);
+    }
+
+    let (_, todos) = app.get("/api/todos", &bob).await;
+    for (name, _, due_at) in cases {
+        let todo = todos.as_array().unwrap().iter().find(|todo| todo["title"] == name).unwrap();
+        assert_eq!(todo["due_at"], due_at, "{}", name);
+    }
+}
+
+// The status and body of a sync-collection report, and the token in it
+async fn sync_collection(app: &TestApp, user: &TestUser, credentials: &str, token: &str) -> (StatusCode, String, String) {
+    let body = format!(
+        "<d:sync-collection xmlns:d=\"DAV:\"><d:sync-token>{}</d:sync-token><d:prop><d:getetag/></d:prop></d:sync-collection>",
+        token
+    );
+    let (status, _, body) = app.dav("REPORT", &format!("/dav/{}/todos/", user.id), credentials, None, &body).await;
+    let token = body.split("<d:sync-token>").nth(1).and_then(|rest| rest.split('<').next()).unwrap_or_default().to_string();
+    (status, body, token)
+}
+
+#[tokio::test]
+async fn sync_tokens_cover_todos_deleted_for_good() {
+    let app = TestApp::sqlite().await;
+    let bob = app.register("bob").await;
+    let credentials = app.dav_credentials(&bob, "bob").await;
+    let kept = app.create_todo(&bob, "Buy milk").await;
+    let purged = app.create_todo(&bob, "Feed the cat").await;
+    let (status, _, token) = sync_collection(&app, &bob, &credentials, "").await;
+    assert_eq!(status, StatusCode::MULTI_STATUS);
+
+    let purged_path = format!("/api/todos/{}", purged);
+    assert_eq!(app.request(Method::DELETE, &purged_path, Some(&bob.token), None).await.0, StatusCode::NO_CONTENT);
+    let purge = format!("{}?permanent=true", purged_path);
+    assert_eq!(app.request(Method::DELETE, &purge, Some(&bob.token), None).await.0, StatusCode::NO_CONTENT);
+    let added = app.create_todo(&bob, "Water the plants").await;
+
+    // Gone for good still shows up as a 404, and untouched todos don't show up at all
+    let (status, body, next) = sync_collection(&app, &bob, &credentials, &token).await;
+    assert_eq!(status, StatusCode::MULTI_STATUS);
+    assert_ne!(next, token);
+    let href = |id: &str| format!("<d:href>/dav/{}/todos/{}.ics</d:href>", bob.id, id);
+    assert!(body.contains(&format!("{}<d:status>HTTP/1.1 404 Not Found", href(&purged))), "{}", body);
+    assert!(body.contains(&href(&added)) && !body.contains(&href(&kept)), "{}", body);
+
+    let (_, body, _) = sync_collection(&app, &bob, &credentials, &next).await;
+    assert!(!body.contains("<d:response>"), "{}", body);
+
+    // Tokens it never gave out, such as the timestamps of older versions, mean starting over
+    for token in [format!("{}9", next), "http://todo-app/ns/sync/1700000000000000".to_string(), "garbage".to_string()] {
+        let (status, body, _) = sync_collection(&app, &bob, &credentials, &token).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body.contains("valid-sync-token"), "{}", body);
+    }
+}
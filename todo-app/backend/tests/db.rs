@@ -0,0 +1,65 @@
+// Queries written for Postgres, run on in-memory SQLite databases
+
+use todo_backend::{
+    config::DatabaseConfig,
+    db::{self, Pool},
+};
+
+async fn database() -> Pool {
+    // One connection, since each has its own in-memory database
+    let config = DatabaseConfig { url: "sqlite::memory:".to_string(), max_connections: 1, ..Default::default() };
+    let pool = Pool::connect(&config).await.unwrap();
+    db::query("CREATE TABLE notes (body TEXT NOT NULL)").execute(&pool).await.unwrap();
+    pool
+}
+
+#[tokio::test]
+async fn postgres_constructs_are_rewritten_outside_strings() {
+    let pool = database().await;
+    let literal = "NOW()  ILIKE\n GREATEST( FOR UPDATE";
+    db::query("INSERT INTO notes (body) VALUES ($1)").bind(literal).execute(&pool).await.unwrap();
+
+    let mut tx = pool.begin().await.unwrap();
+    let row = db::query(
+        "SELECT body, body = 'NOW()  ILIKE\n GREATEST( FOR UPDATE' AS same, GREATEST(1, 2) AS larger,
+                'Abc' ILIKE 'a%' AS matches, NOW() > '2000' AS now
+         FROM notes
+         FOR UPDATE OF notes SKIP LOCKED"
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+
+    assert_eq!(row.get::<String, _>("body"), literal);
+    assert!(row.get::<bool, _>("same"));
+    assert_eq!(row.get::<i64, _>("larger"), 2);
+    assert!(row.get::<bool, _>("matches"));
+    assert!(row.get::<bool, _>("now"));
+}
+
+#[tokio::test]
+async fn dropped_transactions_are_rolled_back() {
+    let pool = database().await;
+
+    let mut tx = pool.begin().await.unwrap();
+    db::query("INSERT INTO notes (body) VALUES ($1)").bind("dropped").execute(&mut *tx).await.unwrap();
+    drop(tx);
+
+    let mut tx = pool.begin().await.unwrap();
+    db::query("INSERT INTO notes (body) VALUES ($1)").bind("kept").execute(&mut *tx).await.unwrap();
+    tx.commit().await.unwrap();
+
+    let bodies: Vec<String> = db::query_scalar("SELECT body FROM notes").fetch_all(&pool).await.unwrap();
+    assert_eq!(bodies, ["kept"]);
+}
+
+#[tokio::test]
+async fn missing_columns_and_other_types_are_errors_with_try_get() {
+    let pool = database().await;
+    let row = db::query("SELECT 'text' AS body").fetch_one(&pool).await.unwrap();
+
+    assert_eq!(row.try_get::<String, _>("body").unwrap(), "text");
+    assert!(matches!(row.try_get::<String, _>("missing"), Err(sqlx::Error::ColumnNotFound(_))));
+    assert!(matches!(row.try_get::<i64, _>("body"), Err(sqlx::Error::ColumnDecode { .. })));
+}
@@ -0,0 +1,78 @@
+// Migrations and dumps against in-memory SQLite databases, as `todo-admin`
+// uses them
+
+use chrono::Utc;
+use todo_api_types::{CreateTodoRequest, TodoUpdate};
+
+use todo_backend::{
+    config::DatabaseConfig,
+    db::Pool,
+    dump,
+    repository::{NewUser, SqlRepository, TodoRepository, UserRepository},
+};
+
+// A fresh database, migrated to the latest version
+async fn database() -> Pool {
+    // One connection, since each has its own in-memory database
+    let config = DatabaseConfig { url: "sqlite::memory:".to_string(), max_connections: 1, ..Default::default() };
+    let pool = Pool::connect(&config).await.unwrap();
+    pool.migrate().await.unwrap();
+    pool
+}
+
+#[tokio::test]
+async fn migrations_can_be_reverted_and_applied_again() {
+    let pool = database().await;
+    let latest = pool.known_migrations().last().unwrap().0;
+    assert_eq!(pool.applied_migrations().await.unwrap().last(), Some(&latest));
+
+    pool.revert(latest - 1).await.unwrap();
+    assert_eq!(pool.applied_migrations().await.unwrap().last(), Some(&(latest - 1)));
+
+    pool.revert(0).await.unwrap();
+    assert!(pool.applied_migrations().await.unwrap().is_empty());
+
+    pool.migrate().await.unwrap();
+    assert_eq!(pool.applied_migrations().await.unwrap().last(), Some(&latest));
+}
+
+#[tokio::test]
+async fn a_dump_restores_to_the_same_rows() {
+    let source = database().await;
+    let repository = SqlRepository::new(source.clone());
+    let user = NewUser {
+        id: "user-1".to_string(),
+        username: "alice".to_string(),
+        email: "alice@example.com".to_string(),
+        password_hash: "hash".to_string(),
+    };
+    UserRepository::create(&repository, user).await.unwrap();
+    let request = CreateTodoRequest {
+        title: "Water the plants".to_string(),
+        description: Some("The ones on the balcony".to_string()),
+        due_at: Some(Utc::now()),
+        priority: Some(2),
+        remind_at: None,
+    };
+    let todo = TodoRepository::create(&repository, "user-1", request, Utc::now()).await.unwrap();
+    let update = TodoUpdate { completed: Some(true), ..Default::default() };
+    repository.update("user-1", &todo.id, update, Utc::now()).await.unwrap();
+
+    let dumped = dump::dump(&source).await.unwrap();
+    assert_eq!(dumped.tables["users"].len(), 1);
+    assert_eq!(dumped.tables["todos"].len(), 1);
+    assert_eq!(dumped.tables["todo_events"].len(), 2);
+
+    // Through JSON, as a dump file would be
+    let dumped: dump::Dump = serde_json::from_str(&serde_json::to_string(&dumped).unwrap()).unwrap();
+    let target = database().await;
+    dump::restore(&target, &dumped, false).await.unwrap();
+    assert_eq!(dump::dump(&target).await.unwrap().tables, dumped.tables);
+
+    // Only an empty database is restored into, unless it is to be replaced
+    assert!(dump::restore(&target, &dumped, false).await.is_err());
+    dump::restore(&target, &dumped, true).await.unwrap();
+    let restored = SqlRepository::new(target).list("user-1", false).await.unwrap();
+    assert_eq!(restored.len(), 1);
+    assert!(restored[0].completed);
+}
@@ -0,0 +1,117 @@
+// Background jobs against in-memory SQLite databases
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use std::sync::Arc;
+use todo_api_types::CreateTodoRequest;
+
+use todo_backend::{
+    config::{Config, DatabaseConfig},
+    db::{self, Pool},
+    jobs::{self, JobContext},
+    mailer::LogMailer,
+    repository::{NewUser, SqlRepository, TodoRepository, UserRepository},
+    webhooks,
+};
+
+// A fresh database, migrated to the latest version
+async fn database() -> Pool {
+    // One connection, since each has its own in-memory database
+    let config = DatabaseConfig { url: "sqlite::memory:".to_string(), max_connections: 1, ..Default::default() };
+    let pool = Pool::connect(&config).await.unwrap();
+    pool.migrate().await.unwrap();
+    pool
+}
+
+// A repository over `pool` with the user "user-1"
+async fn repository(pool: &Pool) -> SqlRepository {
+    let repository = SqlRepository::new(pool.clone());
+    let user = NewUser {
+        id: "user-1".to_string(),
+        username: "alice".to_string(),
+        email: "alice@example.com".to_string(),
+        password_hash: "hash".to_string(),
+    };
+    UserRepository::create(&repository, user).await.unwrap();
+    repository
+}
+
+fn new_todo(title: &str) -> CreateTodoRequest {
+    CreateTodoRequest { title: title.to_string(), description: None, due_at: None, priority: None, remind_at: None }
+}
+
+#[tokio::test]
+async fn only_todos_trashed_before_the_cutoff_are_purged() {
+    let pool = database().await;
+    let repository = repository(&pool).await;
+    let now = Utc::now();
+    let old = TodoRepository::create(&repository, "user-1", new_todo("old"), now).await.unwrap();
+    let recent = TodoRepository::create(&repository, "user-1", new_todo("recent"), now).await.unwrap();
+    TodoRepository::create(&repository, "user-1", new_todo("kept"), now).await.unwrap();
+    repository.trash("user-1", &old.id, now - Duration::days(31)).await.unwrap();
+    repository.trash("user-1", &recent.id, now - Duration::days(1)).await.unwrap();
+
+    let purged = jobs::purge_trashed_before(&pool, now - Duration::days(30)).await.unwrap();
+    assert_eq!(purged, 1);
+    assert!(TodoRepository::find(&repository, "user-1", &old.id).await.unwrap().is_none());
+    let trashed: Vec<String> = repository.list("user-1", true).await.unwrap().into_iter().map(|todo| todo.title).collect();
+    assert_eq!(trashed, ["recent"]);
+    let listed: Vec<String> = repository.list("user-1", false).await.unwrap().into_iter().map(|todo| todo.title).collect();
+    assert_eq!(listed, ["kept"]);
+}
+
+#[test]
+fn retries_back_off_exponentially_up_to_a_limit() {
+    let policy = jobs::retry_policy(jobs::JOB_PURGE_TRASH);
+    assert_eq!(policy.max_attempts, 5);
+    let backoffs: Vec<i64> = (1..=8).map(|attempts| policy.backoff(attempts).num_seconds()).collect();
+    assert_eq!(backoffs, [10, 20, 40, 80, 160, 320, 600, 600]);
+    assert_eq!(jobs::retry_policy(jobs::JOB_DELIVER_WEBHOOK).max_attempts, webhooks::RETRY_POLICY.max_attempts);
+}
+
+// Status, attempts, last error and when it runs next
+async fn job_state(pool: &Pool, id: &str) -> (String, i32, Option<String>, DateTime<Utc>) {
+    let row = db::query("SELECT status, attempts, last_error, run_at FROM jobs WHERE id = $1").bind(id).fetch_one(pool).await.unwrap();
+    (row.get("status"), row.get("attempts"), row.get("last_error"), row.get("run_at"))
+}
+
+#[tokio::test]
+async fn workers_retry_failed_jobs_until_they_run_out_of_attempts() {
+    let pool = database().await;
+    let now = Utc::now();
+    let retried = jobs::enqueue(&pool, "no_such_job", json!({}), now).await.unwrap();
+    let given_up = jobs::enqueue(&pool, "no_such_job", json!({}), now).await.unwrap();
+    db::query("UPDATE jobs SET max_attempts = 1 WHERE id = $1").bind(&given_up).execute(&pool).await.unwrap();
+    let succeeded = jobs::enqueue(&pool, jobs::JOB_PURGE_TRASH, json!({}), now).await.unwrap();
+    let later = jobs::enqueue(&pool, jobs::JOB_PURGE_TRASH, json!({}), now + Duration::hours(1)).await.unwrap();
+
+    let context = JobContext {
+        db: pool.clone(),
+        http: webhooks::http_client().unwrap(),
+        mailer: Arc::new(LogMailer),
+        vapid: None,
+        config: Arc::new(Config::default()),
+    };
+    jobs::start(context, 2);
+    for _ in 0..50 {
+        let finished: i64 = db::query_scalar("SELECT COUNT(*) FROM jobs WHERE attempts > 0 AND status <> 'running'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        if finished == 3 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    let (status, attempts, error, run_at) = job_state(&pool, &retried).await;
+    assert_eq!((status.as_str(), attempts), (jobs::STATUS_PENDING, 1));
+    assert_eq!(error.as_deref(), Some("Unknown job kind 'no_such_job'"));
+    assert!(run_at >= now + Duration::seconds(10), "{}", run_at);
+    let (status, attempts, error, _) = job_state(&pool, &given_up).await;
+    assert_eq!((status.as_str(), attempts, error.is_some()), (jobs::STATUS_FAILED, 1, true));
+    let (status, _, error, _) = job_state(&pool, &succeeded).await;
+    assert_eq!((status.as_str(), error), (jobs::STATUS_SUCCEEDED, None));
+    let (status, attempts, _, _) = job_state(&pool, &later).await;
+    assert_eq!((status.as_str(), attempts), (jobs::STATUS_PENDING, 0));
+}
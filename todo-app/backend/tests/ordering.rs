@@ -0,0 +1,79 @@
+// Todo positions in SQLite databases, through `SqlRepository`
+
+use chrono::{Duration, Utc};
+use std::collections::HashSet;
+use todo_api_types::CreateTodoRequest;
+
+use todo_backend::{
+    config::DatabaseConfig,
+    db::{self, Pool},
+    repository::{NewUser, SqlRepository, TodoRepository, UserRepository},
+};
+
+// A migrated database with the user "user-1"
+async fn repository(url: String, max_connections: u32) -> (Pool, SqlRepository) {
+    let config = DatabaseConfig { url, max_connections, ..Default::default() };
+    let pool = Pool::connect(&config).await.unwrap();
+    pool.migrate().await.unwrap();
+    let repository = SqlRepository::new(pool.clone());
+    let user = NewUser {
+        id: "user-1".to_string(),
+        username: "alice".to_string(),
+        email: "alice@example.com".to_string(),
+        password_hash: "hash".to_string(),
+    };
+    UserRepository::create(&repository, user).await.unwrap();
+    (pool, repository)
+}
+
+fn new_todo(title: &str) -> CreateTodoRequest {
+    CreateTodoRequest { title: title.to_string(), description: None, due_at: None, priority: None, remind_at: None }
+}
+
+async fn titles(repository: &SqlRepository) -> Vec<String> {
+    repository.list("user-1", false).await.unwrap().into_iter().map(|todo| todo.title).collect()
+}
+
+#[tokio::test]
+async fn todos_created_at_once_get_positions_of_their_own() {
+    // A file, so several connections share the database
+    let path = std::env::temp_dir().join(format!("todo-ordering-{}.db", uuid::Uuid::new_v4()));
+    let (_, repository) = repository(format!("sqlite://{}", path.display()), 8).await;
+
+    let creates = (0..20).map(|index| {
+        let repository = repository.clone();
+        tokio::spawn(async move { TodoRepository::create(&repository, "user-1", new_todo(&index.to_string()), Utc::now()).await })
+    });
+    for create in futures::future::join_all(creates).await {
+        create.unwrap().unwrap();
+    }
+
+    let todos = repository.list("user-1", false).await.unwrap();
+    let positions: HashSet<&str> = todos.iter().map(|todo| todo.position.as_str()).collect();
+    assert_eq!(positions.len(), 20);
+    drop(repository);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn moving_between_todos_sharing_a_position_renumbers_them() {
+    let (pool, repository) = repository("sqlite::memory:".to_string(), 1).await;
+    let mut ids = Vec::new();
+    for (index, title) in ["d", "c", "b", "a"].into_iter().enumerate() {
+        let created_at = Utc::now() + Duration::seconds(index as i64);
+        ids.push(TodoRepository::create(&repository, "user-1", new_todo(title), created_at).await.unwrap().id);
+    }
+    let [_, c, b, a] = ids.try_into().unwrap();
+    assert_eq!(titles(&repository).await, ["a", "b", "c", "d"]);
+
+    // b and c share a key, as two todos created at the same moment used to
+    let c_position: String = db::query_scalar("SELECT position FROM todos WHERE id = $1").bind(&c).fetch_one(&pool).await.unwrap();
+    db::query("UPDATE todos SET position = $1 WHERE id = $2").bind(&c_position).bind(&b).execute(&pool).await.unwrap();
+
+    repository.move_between("user-1", &a, Some(&b), Some(&c)).await.unwrap();
+    let listed = titles(&repository).await;
+    assert_eq!(listed.len(), 4);
+    let position = |title: &str| listed.iter().position(|listed| listed == title).unwrap();
+    assert!(position("b") < position("a") && position("a") < position("c"), "{:?}", listed);
+    assert_eq!(listed.last().unwrap(), "d");
+}
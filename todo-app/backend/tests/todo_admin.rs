@@ -0,0 +1,188 @@
+// The `todo-admin` binary, run against SQLite files with its configuration
+// in the environment
+
+use chrono::{Duration, Utc};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use todo_backend::{
+    auth,
+    config::DatabaseConfig,
+    db::{self, Pool},
+};
+
+struct Output {
+    success: bool,
+    stdout: String,
+    stderr: String,
+}
+
+// A database file of its own in a directory of its own, so no config.toml or
+// .env gets in the way
+fn database_path() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("todo-admin-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join("todos.db")
+}
+
+fn remove(database: &Path) {
+    let _ = std::fs::remove_dir_all(database.parent().unwrap());
+}
+
+fn todo_admin(database: &Path, args: &[&str], stdin: Option<&str>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_todo-admin"))
+        .args(args)
+        .current_dir(database.parent().unwrap())
+        .env_remove("CONFIG_FILE")
+        .env("DATABASE_URL", format!("sqlite://{}", database.display()))
+        .env("JWT_SECRET", "a-secret-for-the-todo-admin-tests")
+        // The lowest cost bcrypt accepts, to keep hashing fast
+        .env("BCRYPT_COST", "4")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    if let Some(stdin) = stdin {
+        child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    }
+    let output = child.wait_with_output().unwrap();
+    Output {
+        success: output.status.success(),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
+// Fails the test, showing what was printed, if the command fails
+fn succeeds(database: &Path, args: &[&str]) -> String {
+    let output = todo_admin(database, args, None);
+    assert!(output.success, "todo-admin {:?}: {}", args, output.stderr);
+    output.stdout
+}
+
+async fn connect(database: &Path) -> Pool {
+    let config = DatabaseConfig { url: format!("sqlite://{}", database.display()), ..Default::default() };
+    Pool::connect(&config).await.unwrap()
+}
+
+#[test]
+fn jwt_secrets_need_no_configuration() {
+    let output = Command::new(env!("CARGO_BIN_EXE_todo-admin"))
+        .arg("generate-jwt-secret")
+        .env_remove("DATABASE_URL")
+        .env_remove("JWT_SECRET")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let secret = String::from_utf8(output.stdout).unwrap();
+    // 48 bytes in unpadded base64
+    assert_eq!(secret.trim().len(), 64);
+    assert!(secret.trim().chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+}
+
+#[test]
+fn migrations_are_applied_listed_and_reverted() {
+    let database = database_path();
+
+    assert!(succeeds(&database, &["migrate", "run"]).starts_with("Applied "));
+    assert_eq!(succeeds(&database, &["migrate", "run"]), "Already up to date\n");
+    let status = succeeds(&database, &["migrate", "status"]);
+    assert!(!status.contains("pending"), "{}", status);
+
+    assert!(succeeds(&database, &["migrate", "revert"]).starts_with("Reverted to version "));
+    let status = succeeds(&database, &["migrate", "status"]);
+    assert_eq!(status.lines().last().map(|line| line.contains("pending")), Some(true), "{}", status);
+
+    assert!(succeeds(&database, &["migrate", "revert", "--to", "0"]).starts_with("Reverted to version 0"));
+    let output = todo_admin(&database, &["migrate", "revert"], None);
+    assert!(!output.success);
+    assert!(output.stderr.contains("No migrations have been applied"));
+    remove(&database);
+}
+
+#[tokio::test]
+async fn users_are_created_disabled_and_given_new_passwords() {
+    let database = database_path();
+    succeeds(&database, &["migrate", "run"]);
+
+    // A generated password is shown once, and must be changed
+    let output = succeeds(&database, &["user", "create", "--username", "root", "--email", "root@example.com", "--admin"]);
+    assert!(output.starts_with("Created admin root with ID "), "{}", output);
+    let password = output.lines().find_map(|line| line.strip_prefix("Password: ")).unwrap().to_string();
+
+    let pool = connect(&database).await;
+    let user = db::query("SELECT * FROM users WHERE username = 'root'").fetch_one(&pool).await.unwrap();
+    assert_eq!(user.get::<String, _>("role"), auth::ROLE_ADMIN);
+    assert!(user.get::<bool, _>("password_reset_required"));
+    assert!(auth::verify_password(&password, &user.get::<String, _>("password_hash")).unwrap());
+
+    let output = todo_admin(&database, &["user", "create", "--username", "root", "--email", "other@example.com"], None);
+    assert!(!output.success);
+    assert!(output.stderr.contains("taken"), "{}", output.stderr);
+
+    // By email, then by username
+    succeeds(&database, &["user", "disable", "root@example.com"]);
+    let disabled_at = |pool: Pool| async move {
+        db::query_scalar::<Option<chrono::DateTime<Utc>>>("SELECT disabled_at FROM users").fetch_one(&pool).await.unwrap()
+    };
+    assert!(disabled_at(pool.clone()).await.is_some());
+    succeeds(&database, &["user", "enable", "root"]);
+    assert!(disabled_at(pool.clone()).await.is_none());
+
+    let output = todo_admin(&database, &["user", "reset-password", "root", "--password-stdin"], Some("a new password\n"));
+    assert!(output.success, "{}", output.stderr);
+    let user = db::query("SELECT * FROM users").fetch_one(&pool).await.unwrap();
+    assert!(auth::verify_password("a new password", &user.get::<String, _>("password_hash")).unwrap());
+    assert!(!user.get::<bool, _>("password_reset_required"));
+
+    let output = todo_admin(&database, &["user", "disable", "nobody"], None);
+    assert!(!output.success);
+    assert!(output.stderr.contains("No user nobody"));
+    remove(&database);
+}
+
+#[tokio::test]
+async fn trash_is_purged_and_dumps_restore() {
+    let database = database_path();
+    succeeds(&database, &["migrate", "run"]);
+    let pool = connect(&database).await;
+    db::query("INSERT INTO users (id, username, email, password_hash) VALUES ('user-1', 'alice', 'alice@example.com', 'hash')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for (id, deleted_at) in [("old", Some(Utc::now() - Duration::days(10))), ("recent", Some(Utc::now())), ("kept", None)] {
+        db::query("INSERT INTO todos (id, user_id, title, position, deleted_at) VALUES ($1, 'user-1', $1, $1, $2)")
+            .bind(id)
+            .bind(deleted_at)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let output = succeeds(&database, &["purge-trash", "--older-than-days", "7"]);
+    assert_eq!(output, "Deleted 1 todos trashed more than 7 days ago\n");
+    let left: Vec<String> = db::query_scalar("SELECT id FROM todos ORDER BY id").fetch_all(&pool).await.unwrap();
+    assert_eq!(left, ["kept", "recent"]);
+
+    let dump = database.with_file_name("dump.json");
+    succeeds(&database, &["dump", "--output", dump.to_str().unwrap()]);
+
+    let copy = database_path();
+    succeeds(&copy, &["migrate", "run"]);
+    let output = succeeds(&copy, &["restore", dump.to_str().unwrap()]);
+    assert!(output.starts_with("Restored "), "{}", output);
+    let restored: Vec<String> =
+        db::query_scalar("SELECT id FROM todos ORDER BY id").fetch_all(&connect(&copy).await).await.unwrap();
+    assert_eq!(restored, ["kept", "recent"]);
+
+    // Only into an empty database, unless it's replaced
+    assert!(!todo_admin(&copy, &["restore", dump.to_str().unwrap()], None).success);
+    succeeds(&copy, &["restore", dump.to_str().unwrap(), "--replace"]);
+    remove(&database);
+    remove(&copy);
+}
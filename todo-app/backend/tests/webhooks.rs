@@ -0,0 +1,166 @@
+// Where webhooks and push messages may be sent: only to public addresses, so
+// users can't make the server reach into its own network
+
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::sync::Arc;
+use todo_api_types::CreateTodoRequest;
+
+use todo_backend::{
+    config::{Config, DatabaseConfig},
+    db::{self, Pool},
+    jobs::{Job, JobContext, JOB_DELIVER_WEBHOOK},
+    mailer::LogMailer,
+    repository::{NewUser, SqlRepository, TodoRepository, UserRepository},
+    webhooks::{self, check_destination, is_public_ip},
+};
+
+#[test]
+fn only_public_addresses_are_public() {
+    for ip in ["93.184.216.34", "2606:2800:220:1::1", "1.1.1.1"] {
+        assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+    }
+    for ip in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+        "64:ff9b::a9fe:a9fe",
+    ] {
+        assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+    }
+}
+
+#[test]
+fn addresses_in_urls_are_checked() {
+    for url in ["http://127.0.0.1:3001/hook", "http://[::1]/hook", "http://2130706433/", "https://169.254.169.254/"] {
+        assert!(check_destination(&url.parse().unwrap()).is_err(), "{}", url);
+    }
+    // Names are checked when they are resolved
+    assert!(check_destination(&"https://example.com/hook".parse().unwrap()).is_ok());
+}
+
+#[tokio::test]
+async fn names_resolving_to_private_addresses_are_refused() {
+    let client = webhooks::http_client().unwrap();
+    let error = client.post("http://localhost:9/hook").send().await.unwrap_err();
+    let message = format!("{:#}", anyhow::Error::new(error));
+    assert!(message.contains("not a public address"), "{}", message);
+}
+
+#[test]
+fn signatures_are_hmac_sha256() {
+    // RFC 4231 test case 2
+    assert_eq!(
+        webhooks::signature("Jefe", b"what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+}
+
+#[test]
+fn history_events_map_onto_webhook_events() {
+    assert_eq!(webhooks::event_for("created"), webhooks::EVENT_TODO_CREATED);
+    assert_eq!(webhooks::event_for("completed"), webhooks::EVENT_TODO_COMPLETED);
+    assert_eq!(webhooks::event_for("deleted"), webhooks::EVENT_TODO_DELETED);
+    for event in ["updated", "uncompleted", "restored"] {
+        assert_eq!(webhooks::event_for(event), webhooks::EVENT_TODO_UPDATED, "{}", event);
+    }
+}
+
+#[test]
+fn retries_back_off_to_an_hour() {
+    let backoffs: Vec<i64> = (1..=8).map(|attempts| webhooks::RETRY_POLICY.backoff(attempts).num_seconds()).collect();
+    assert_eq!(backoffs, [30, 60, 120, 240, 480, 960, 1920, 3600]);
+}
+
+// A migrated database where "user-1" has a webhook for created todos pointing
+// at `url`, and the id of the delivery queued for a new todo
+async fn delivery(url: &str) -> (Pool, String) {
+    let config = DatabaseConfig { url: "sqlite::memory:".to_string(), max_connections: 1, ..Default::default() };
+    let pool = Pool::connect(&config).await.unwrap();
+    pool.migrate().await.unwrap();
+    let repository = SqlRepository::new(pool.clone());
+    let user = NewUser {
+        id: "user-1".to_string(),
+        username: "alice".to_string(),
+        email: "alice@example.com".to_string(),
+        password_hash: "hash".to_string(),
+    };
+    UserRepository::create(&repository, user).await.unwrap();
+    db::query("INSERT INTO webhooks (id, user_id, url, events, secret) VALUES ('webhook-1', 'user-1', $1, $2, 'secret')")
+        .bind(url)
+        .bind(json!([webhooks::EVENT_TODO_CREATED]).to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let todo = CreateTodoRequest { title: "Water the plants".to_string(), description: None, due_at: None, priority: None, remind_at: None };
+    TodoRepository::create(&repository, "user-1", todo, Utc::now()).await.unwrap();
+    let id = db::query_scalar("SELECT id FROM webhook_deliveries").fetch_one(&pool).await.unwrap();
+    (pool, id)
+}
+
+fn context(db: &Pool) -> JobContext {
+    JobContext {
+        db: db.clone(),
+        http: webhooks::http_client().unwrap(),
+        mailer: Arc::new(LogMailer),
+        vapid: None,
+        config: Arc::new(Config::default()),
+    }
+}
+
+fn job(delivery_id: &str, attempts: i32) -> Job {
+    Job {
+        id: "job-1".to_string(),
+        kind: JOB_DELIVER_WEBHOOK.to_string(),
+        payload: json!({ "delivery_id": delivery_id }),
+        attempts,
+        max_attempts: webhooks::RETRY_POLICY.max_attempts,
+    }
+}
+
+async fn delivery_state(db: &Pool, delivery_id: &str) -> (String, i32, Option<String>) {
+    let status = db::query_scalar("SELECT status FROM webhook_deliveries WHERE id = $1").bind(delivery_id).fetch_one(db).await.unwrap();
+    let attempts = db::query_scalar("SELECT attempts FROM webhook_deliveries WHERE id = $1").bind(delivery_id).fetch_one(db).await.unwrap();
+    let error = db::query_scalar("SELECT last_error FROM webhook_deliveries WHERE id = $1").bind(delivery_id).fetch_one(db).await.unwrap();
+    (status, attempts, error)
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried_until_the_last_attempt() {
+    let (db, delivery_id) = delivery("http://127.0.0.1:9/hook").await;
+    let context = context(&db);
+
+    let error = webhooks::deliver(&context, &job(&delivery_id, 1)).await.unwrap_err();
+    assert!(error.contains("not a public address"), "{}", error);
+    let (status, attempts, last_error) = delivery_state(&db, &delivery_id).await;
+    assert_eq!((status.as_str(), attempts, last_error.as_deref()), (webhooks::STATUS_PENDING, 1, Some(error.as_str())));
+    let next_attempt_at: chrono::DateTime<Utc> = db::query_scalar("SELECT next_attempt_at FROM webhook_deliveries WHERE id = $1")
+        .bind(&delivery_id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert!(next_attempt_at > Utc::now() + Duration::seconds(20));
+
+    assert!(webhooks::deliver(&context, &job(&delivery_id, 8)).await.is_err());
+    let (status, attempts, _) = delivery_state(&db, &delivery_id).await;
+    assert_eq!((status.as_str(), attempts), (webhooks::STATUS_FAILED, 8));
+}
+
+#[tokio::test]
+async fn deliveries_to_disabled_webhooks_fail_without_a_retry() {
+    let (db, delivery_id) = delivery("https://example.com/hook").await;
+    db::query("UPDATE webhooks SET active = FALSE").execute(&db).await.unwrap();
+
+    webhooks::deliver(&context(&db), &job(&delivery_id, 1)).await.unwrap();
+    let (status, _, last_error) = delivery_state(&db, &delivery_id).await;
+    assert_eq!((status.as_str(), last_error.as_deref()), (webhooks::STATUS_FAILED, Some("Webhook disabled")));
+}
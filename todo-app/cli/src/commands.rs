@@ -0,0 +1,408 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+use todo_client::{
+    types::{BulkAction, BulkRequest, BulkResponse, CreateTodoRequest, CsvMapping, Todo, TodoUpdate},
+    Client,
+};
+
+use crate::{credentials, table::Table, ClearFields, Credentials, ListFilters, TodoFields};
+
+// Enough of an ID to tell todos apart in practice, and what `ls` shows
+const SHORT_ID: usize = 8;
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, value)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+// Local time as YYYY-MM-DD HH:MM (or with a T); a date alone means `date_only`
+// on that day, and is an error without it
+pub fn parse_local(value: &str, date_only: Option<NaiveTime>) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    let time = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+            Some(date.and_time(date_only?))
+        })
+        .with_context(|| format!("Invalid date \"{}\", expected YYYY-MM-DD HH:MM", value))?;
+
+    let local = time.and_local_timezone(Local).earliest().with_context(|| format!("{} doesn't exist here", value))?;
+    Ok(local.with_timezone(&Utc))
+}
+
+pub fn end_of_day() -> Option<NaiveTime> {
+    NaiveTime::from_hms_opt(23, 59, 0)
+}
+
+pub fn format_local(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn short_id(id: &str) -> &str {
+    &id[..id.len().min(SHORT_ID)]
+}
+
+// The todo an ID or ID prefix refers to, among `todos`
+fn resolve<'a>(todos: &'a [Todo], id: &str) -> Result<&'a Todo> {
+    if let Some(todo) = todos.iter().find(|todo| todo.id == id) {
+        return Ok(todo);
+    }
+    let matches: Vec<&Todo> = todos.iter().filter(|todo| todo.id.starts_with(id)).collect();
+    match matches.as_slice() {
+        [todo] => Ok(todo),
+        [] => bail!("No todo with ID {}", id),
+        _ => bail!("{} could be any of {} todos; give more of the ID", id, matches.len()),
+    }
+}
+
+fn resolve_all(todos: &[Todo], ids: &[String]) -> Result<Vec<String>> {
+    ids.iter().map(|id| resolve(todos, id).map(|todo| todo.id.clone())).collect()
+}
+
+pub async fn login(server: &str, email: &str, password_stdin: bool, json: bool) -> Result<()> {
+    let password = if password_stdin {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    } else {
+        rpassword::prompt_password("Password: ")?
+    };
+
+    let client = Client::new(server);
+    let response = client.login(email, &password).await?;
+    let token = client.token().context("The server sent no token")?;
+    credentials::save(&Credentials { server: client.base_url().to_string(), token })?;
+
+    if json {
+        return print_json(&json!({
+            "server": client.base_url(),
+            "password_reset_required": response.password_reset_required,
+        }));
+    }
+    println!("Logged in to {}", client.base_url());
+    if response.password_reset_required {
+        println!("Your password must be changed in the web app before anything else works");
+    }
+    Ok(())
+}
+
+pub async fn add(client: &Client, title: String, fields: TodoFields, json: bool) -> Result<()> {
+    let request = CreateTodoRequest {
+        title,
+        description: fields.description,
+        due_at: fields.due.map(|due| parse_local(&due, end_of_day())).transpose()?,
+        priority: fields.priority,
+        remind_at: fields.remind.map(|remind| parse_local(&remind, None)).transpose()?,
+    };
+    let todo = client.create_todo(&request).await?;
+
+    if json {
+        return print_json(&todo);
+    }
+    println!("Added {} {}", short_id(&todo.id), todo.title);
+    Ok(())
+}
+
+// The todos `ls` shows with these filters, as of `now`
+fn filter(todos: Vec<Todo>, filters: ListFilters, now: DateTime<Utc>) -> Result<Vec<Todo>> {
+    let due_before = filters.due_before.map(|date| parse_local(&date, NaiveTime::from_hms_opt(0, 0, 0))).transpose()?;
+    let search = filters.search.map(|search| search.to_lowercase());
+    Ok(todos
+        .into_iter()
+        .filter(|todo| !filters.done || todo.completed)
+        .filter(|todo| !filters.open || !todo.completed)
+        .filter(|todo| !filters.overdue || (!todo.completed && todo.due_at.is_some_and(|due| due < now)))
+        .filter(|todo| due_before.is_none_or(|before| todo.due_at.is_some_and(|due| due < before)))
+        .filter(|todo| filters.priority.is_none_or(|lowest| todo.priority.is_some_and(|priority| priority <= lowest)))
+        .filter(|todo| {
+            search.as_ref().is_none_or(|search| {
+                todo.title.to_lowercase().contains(search)
+                    || todo.description.as_ref().is_some_and(|description| description.to_lowercase().contains(search))
+            })
+        })
+        .collect())
+}
+
+pub async fn list(client: &Client, filters: ListFilters, json: bool) -> Result<()> {
+    let todos = if filters.trash { client.trash().await? } else { client.todos().await? };
+    let todos = filter(todos, filters, Utc::now())?;
+
+    if json {
+        return print_json(&todos);
+    }
+    if todos.is_empty() {
+        println!("No todos");
+        return Ok(());
+    }
+
+    let mut table = Table::new(vec!["ID", "DONE", "PRI", "DUE", "TITLE"]);
+    for todo in &todos {
+        table.row(vec![
+            short_id(&todo.id).to_string(),
+            if todo.completed { "x" } else { "" }.to_string(),
+            todo.priority.map(|priority| priority.to_string()).unwrap_or_default(),
+            todo.due_at.map(format_local).unwrap_or_default(),
+            todo.title.clone(),
+        ]);
+    }
+    table.print()?;
+    Ok(())
+}
+
+// Prints what happened to each todo, and fails if any of them failed
+fn report_bulk(response: &BulkResponse, verb: &str, json: bool) -> Result<()> {
+    if json {
+        print_json(response)?;
+    } else {
+        for result in &response.results {
+            match (&result.todo, &result.error) {
+                (Some(todo), _) if result.success => println!("{} {} {}", verb, short_id(&todo.id), todo.title),
+                (_, error) => {
+                    eprintln!("{}: {}", short_id(&result.id), error.as_deref().unwrap_or("failed"))
+                }
+            }
+        }
+    }
+
+    let failed = response.results.iter().filter(|result| !result.success).count();
+    if failed > 0 {
+        bail!("{} of {} todos failed", failed, response.results.len());
+    }
+    Ok(())
+}
+
+pub async fn done(client: &Client, ids: &[String], undo: bool, json: bool) -> Result<()> {
+    let ids = resolve_all(&client.todos().await?, ids)?;
+    let action = if undo { BulkAction::Uncomplete } else { BulkAction::Complete };
+    let response = client.bulk_update(&BulkRequest { action, ids: Some(ids), filter: None }).await?;
+    report_bulk(&response, if undo { "Reopened" } else { "Completed" }, json)
+}
+
+pub async fn edit(
+    client: &Client,
+    id: &str,
+    title: Option<String>,
+    fields: TodoFields,
+    clear: ClearFields,
+    json: bool,
+) -> Result<()> {
+    let due_at = fields.due.map(|due| parse_local(&due, end_of_day())).transpose()?;
+    let remind_at = fields.remind.map(|remind| parse_local(&remind, None)).transpose()?;
+    let update = TodoUpdate {
+        title,
+        description: fields.description,
+        completed: None,
+        // Some(None) removes the field
+        due_at: if clear.no_due { Some(None) } else { due_at.map(Some) },
+        priority: if clear.no_priority { Some(None) } else { fields.priority.map(Some) },
+        remind_at: if clear.no_remind { Some(None) } else { remind_at.map(Some) },
+    };
+    if update.title.is_none()
+        && update.description.is_none()
+        && update.due_at.is_none()
+        && update.priority.is_none()
+        && update.remind_at.is_none()
+    {
+        bail!("Nothing to change; see `todo edit --help`");
+    }
+
+    let todos = client.todos().await?;
+    let todo = client.update_todo(&resolve(&todos, id)?.id, &update).await?;
+
+    if json {
+        return print_json(&todo);
+    }
+    println!("Updated {} {}", short_id(&todo.id), todo.title);
+    Ok(())
+}
+
+pub async fn remove(client: &Client, ids: &[String], permanent: bool, json: bool) -> Result<()> {
+    if !permanent {
+        let ids = resolve_all(&client.todos().await?, ids)?;
+        let response = client.bulk_update(&BulkRequest { action: BulkAction::Delete, ids: Some(ids), filter: None }).await?;
+        return report_bulk(&response, "Trashed", json);
+    }
+
+    // Nothing deletes several todos for good at once, so one at a time
+    let trash = client.trash().await?;
+    let todos = ids.iter().map(|id| resolve(&trash, id)).collect::<Result<Vec<_>>>()?;
+    for todo in &todos {
+        client.purge_todo(&todo.id).await?;
+        if !json {
+            println!("Deleted {} {}", short_id(&todo.id), todo.title);
+        }
+    }
+    if json {
+        print_json(&todos.iter().map(|todo| &todo.id).collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+pub async fn export(client: &Client, format: &str, output: Option<std::path::PathBuf>) -> Result<()> {
+    let bytes = client.export_todos(format).await?;
+    match output {
+        Some(path) => std::fs::write(&path, bytes).with_context(|| format!("Can't write {}", path.display())),
+        None => {
+            std::io::stdout().write_all(&bytes)?;
+            Ok(())
+        }
+    }
+}
+
+// "title=Name,description=Notes" to a CSV mapping
+fn parse_mapping(value: &str) -> Result<CsvMapping> {
+    let mut mapping = CsvMapping { title: String::new(), description: None, completed: None };
+    for pair in value.split(',') {
+        let Some((field, column)) = pair.split_once('=') else {
+            bail!("Invalid mapping \"{}\", expected field=column", pair);
+        };
+        let column = column.trim().to_string();
+        match field.trim() {
+            "title" => mapping.title = column,
+            "description" => mapping.description = Some(column),
+            "completed" => mapping.completed = Some(column),
+            field => bail!("Unknown field \"{}\" in the mapping; use title, description or completed", field),
+        }
+    }
+    if mapping.title.is_empty() {
+        bail!("The mapping needs a title column");
+    }
+    Ok(mapping)
+}
+
+pub async fn import(
+    client: &Client,
+    file: &Path,
+    format: &str,
+    mapping: Option<&str>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let mut content = String::new();
+    if file == Path::new("-") {
+        std::io::stdin().read_to_string(&mut content)?;
+    } else {
+        content = std::fs::read_to_string(file).with_context(|| format!("Can't read {}", file.display()))?;
+    }
+    let mapping = mapping.map(parse_mapping).transpose()?;
+    if format == "csv" && mapping.is_none() {
+        bail!("CSV imports need --mapping");
+    }
+
+    let report = client.import_todos(format, &content, mapping.as_ref(), dry_run).await?;
+
+    if json {
+        return print_json(&report);
+    }
+    let verb = if report.dry_run { "Would import" } else { "Imported" };
+    println!("{} {} todos, skipping {} duplicates", verb, report.created.len(), report.duplicates.len());
+    for error in &report.errors {
+        eprintln!("line {}: {}", error.line, error.message);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cli, Command};
+    use chrono::{Duration, TimeZone};
+    use clap::Parser;
+
+    fn todo(id: &str, title: &str) -> Todo {
+        let now = Utc::now();
+        Todo {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            title: title.to_string(),
+            description: None,
+            completed: false,
+            position: "a".to_string(),
+            due_at: None,
+            priority: None,
+            remind_at: None,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        }
+    }
+
+    // The titles `todo ls` with these arguments shows
+    fn listed(todos: &[Todo], args: &[&str], now: DateTime<Utc>) -> Vec<String> {
+        let Command::Ls(filters) = Cli::parse_from([&["todo", "ls"], args].concat()).command else {
+            unreachable!();
+        };
+        filter(todos.to_vec(), filters, now).unwrap().into_iter().map(|todo| todo.title).collect()
+    }
+
+    #[test]
+    fn dates_are_read_as_local_time() {
+        let expected = Local.with_ymd_and_hms(2026, 3, 1, 9, 30, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(parse_local("2026-03-01 09:30", None).unwrap(), expected);
+        assert_eq!(parse_local(" 2026-03-01T09:30 ", None).unwrap(), expected);
+
+        let end = Local.with_ymd_and_hms(2026, 3, 1, 23, 59, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(parse_local("2026-03-01", end_of_day()).unwrap(), end);
+        assert!(parse_local("2026-03-01", None).is_err());
+        assert!(parse_local("tomorrow", end_of_day()).is_err());
+    }
+
+    #[test]
+    fn ids_can_be_shortened_while_they_are_unambiguous() {
+        let todos = [todo("abc123", "first"), todo("abd456", "second"), todo("abc", "third")];
+        assert_eq!(resolve(&todos, "abd").unwrap().title, "second");
+        // An exact match wins over longer IDs it is a prefix of
+        assert_eq!(resolve(&todos, "abc").unwrap().title, "third");
+        assert!(resolve(&todos, "ab").is_err());
+        assert!(resolve(&todos, "xyz").is_err());
+        assert_eq!(resolve_all(&todos, &["abc1".to_string(), "abd".to_string()]).unwrap(), ["abc123", "abd456"]);
+    }
+
+    #[test]
+    fn mappings_name_a_column_for_each_field() {
+        let mapping = parse_mapping("title=Name, description = Notes,completed=Done").unwrap();
+        assert_eq!(mapping.title, "Name");
+        assert_eq!(mapping.description.as_deref(), Some("Notes"));
+        assert_eq!(mapping.completed.as_deref(), Some("Done"));
+
+        assert!(parse_mapping("description=Notes").is_err());
+        assert!(parse_mapping("title=Name,due=Due").is_err());
+        assert!(parse_mapping("title").is_err());
+    }
+
+    #[test]
+    fn ls_filters_combine() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let mut done = todo("1", "Done");
+        done.completed = true;
+        done.due_at = Some(now - Duration::days(1));
+        let mut overdue = todo("2", "Overdue");
+        overdue.due_at = Some(now - Duration::days(1));
+        overdue.priority = Some(1);
+        let mut later = todo("3", "Later");
+        later.due_at = Some(now + Duration::days(30));
+        later.priority = Some(5);
+        let mut plain = todo("4", "Plain");
+        plain.description = Some("Call the BANK".to_string());
+        let todos = [done, overdue, later, plain];
+
+        assert_eq!(listed(&todos, &[], now), ["Done", "Overdue", "Later", "Plain"]);
+        assert_eq!(listed(&todos, &["--done"], now), ["Done"]);
+        assert_eq!(listed(&todos, &["--open"], now), ["Overdue", "Later", "Plain"]);
+        assert_eq!(listed(&todos, &["--overdue"], now), ["Overdue"]);
+        assert_eq!(listed(&todos, &["--priority", "3"], now), ["Overdue"]);
+        assert_eq!(listed(&todos, &["--due-before", "2026-03-15"], now), ["Done", "Overdue"]);
+        assert_eq!(listed(&todos, &["--search", "bank"], now), ["Plain"]);
+        assert_eq!(listed(&todos, &["--open", "--due-before", "2026-03-15"], now), ["Overdue"]);
+    }
+}
@@ -0,0 +1,56 @@
+// The server and token from `todo login`, kept in the user's config dir
+// (e.g. ~/.config/todo/credentials.json) between runs
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Credentials {
+    pub server: String,
+    pub token: String,
+}
+
+fn path() -> Result<PathBuf> {
+    let dir = dirs::config_dir().context("No config directory for this user")?;
+    Ok(dir.join("todo").join("credentials.json"))
+}
+
+// None before the first login, or after logging out
+pub fn load() -> Result<Option<Credentials>> {
+    let path = path()?;
+    match fs::read(&path) {
+        Ok(bytes) => Ok(Some(
+            serde_json::from_slice(&bytes).with_context(|| format!("Unreadable credentials in {}", path.display()))?,
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Can't read {}", path.display())),
+    }
+}
+
+// Readable by the user only, since the token is as good as the password until it expires
+pub fn save(credentials: &Credentials) -> Result<()> {
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Can't create {}", dir.display()))?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let file = options.open(&path).with_context(|| format!("Can't write {}", path.display()))?;
+    serde_json::to_writer_pretty(file, credentials)?;
+    Ok(())
+}
+
+pub fn remove() -> Result<()> {
+    let path = path()?;
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Can't remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
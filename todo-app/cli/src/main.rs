@@ -0,0 +1,219 @@
+// `todo`, a command-line client for the todo service. `todo login` stores a
+// token in the config dir; every other command uses it, and `--json` prints
+// the API's responses instead of tables for scripts. `todo tui` opens a
+// full-screen view instead
+
+use anyhow::{bail, Result};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use todo_client::{Client, Error};
+
+mod commands;
+mod credentials;
+mod table;
+mod tui;
+
+use credentials::Credentials;
+
+#[derive(Parser)]
+#[command(name = "todo", version, about = "Manage your todos from the terminal")]
+struct Cli {
+    #[arg(long, global = true, help = "Print JSON instead of tables and messages")]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Log in and remember the token")]
+    Login {
+        #[arg(long, env = "TODO_SERVER", default_value = "http://127.0.0.1:3001")]
+        server: String,
+        #[arg(long)]
+        email: String,
+        #[arg(long, help = "Read the password from standard input instead of prompting")]
+        password_stdin: bool,
+    },
+    #[command(about = "Forget the stored token")]
+    Logout,
+    #[command(about = "Add a todo")]
+    Add {
+        title: String,
+        #[command(flatten)]
+        fields: TodoFields,
+    },
+    #[command(about = "List todos")]
+    Ls(ListFilters),
+    #[command(about = "Mark todos as done")]
+    Done {
+        #[arg(required = true, help = "IDs, or unambiguous prefixes of them")]
+        ids: Vec<String>,
+        #[arg(long, help = "Mark them as not done instead")]
+        undo: bool,
+    },
+    #[command(about = "Change a todo")]
+    Edit {
+        #[arg(help = "ID, or an unambiguous prefix of it")]
+        id: String,
+        #[arg(long)]
+        title: Option<String>,
+        #[command(flatten)]
+        fields: TodoFields,
+        #[command(flatten)]
+        clear: ClearFields,
+    },
+    #[command(about = "Move todos to the trash")]
+    Rm {
+        #[arg(required = true, help = "IDs, or unambiguous prefixes of them")]
+        ids: Vec<String>,
+        #[arg(long, help = "Delete todos that are already in the trash for good")]
+        permanent: bool,
+    },
+    #[command(about = "Export all todos")]
+    Export {
+        #[arg(long, default_value = "json", value_parser = ["json", "csv", "md", "todotxt"])]
+        format: String,
+        #[arg(short, long, help = "Write to a file instead of standard output")]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "Import todos from a file")]
+    Import {
+        #[arg(help = "The file, or - for standard input")]
+        file: PathBuf,
+        #[arg(long, value_parser = ["todotxt", "csv", "todoist", "taskwarrior"])]
+        format: String,
+        #[arg(long, help = "CSV columns as field=column pairs, e.g. title=Name,description=Notes,completed=Done")]
+        mapping: Option<String>,
+        #[arg(long, help = "Only report what would be imported")]
+        dry_run: bool,
+    },
+    #[command(about = "Browse and change todos in a full-screen view")]
+    Tui,
+}
+
+// Dates are local, as YYYY-MM-DD HH:MM; a date alone means the end of that day
+#[derive(Args)]
+struct TodoFields {
+    #[arg(long)]
+    description: Option<String>,
+    #[arg(long, help = "Due date, as YYYY-MM-DD or YYYY-MM-DD HH:MM")]
+    due: Option<String>,
+    #[arg(long, help = "1 (highest) to 9 (lowest)")]
+    priority: Option<i16>,
+    #[arg(long, help = "When to send a reminder, as YYYY-MM-DD HH:MM")]
+    remind: Option<String>,
+}
+
+// What `todo edit` can remove
+#[derive(Args)]
+struct ClearFields {
+    #[arg(long, conflicts_with = "due", help = "Remove the due date")]
+    no_due: bool,
+    #[arg(long, conflicts_with = "priority", help = "Remove the priority")]
+    no_priority: bool,
+    #[arg(long, conflicts_with = "remind", help = "Cancel the reminder")]
+    no_remind: bool,
+}
+
+#[derive(Args)]
+struct ListFilters {
+    #[arg(long, conflicts_with = "open", help = "Only done todos")]
+    done: bool,
+    #[arg(long, help = "Only todos not done yet")]
+    open: bool,
+    #[arg(long, help = "The trash instead of the list")]
+    trash: bool,
+    #[arg(long, help = "Only todos past their due date")]
+    overdue: bool,
+    #[arg(long, help = "Only todos due before this date")]
+    due_before: Option<String>,
+    #[arg(long, help = "Only todos with at least this priority, e.g. 3 for 1 to 3")]
+    priority: Option<i16>,
+    #[arg(long, help = "Only todos whose title or description contains this")]
+    search: Option<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(error) = run(cli).await {
+        // Output piped into e.g. `head` that stopped reading
+        if error.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) {
+            return;
+        }
+        eprintln!("error: {:#}", error);
+        if matches!(error.downcast_ref::<Error>(), Some(Error::Unauthorized)) {
+            eprintln!("Log in again with `todo login`");
+        }
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let json = cli.json;
+    match cli.command {
+        Command::Login { server, email, password_stdin } => commands::login(&server, &email, password_stdin, json).await,
+        Command::Logout => {
+            credentials::remove()?;
+            if !json {
+                println!("Logged out");
+            }
+            Ok(())
+        }
+        command => {
+            let Some(stored) = credentials::load()? else {
+                bail!("Not logged in; run `todo login` first");
+            };
+            let client = Client::new(&stored.server);
+            client.set_token(Some(stored.token.clone()));
+
+            let result = match command {
+                Command::Add { title, fields } => commands::add(&client, title, fields, json).await,
+                Command::Ls(filters) => commands::list(&client, filters, json).await,
+                Command::Done { ids, undo } => commands::done(&client, &ids, undo, json).await,
+                Command::Edit { id, title, fields, clear } => commands::edit(&client, &id, title, fields, clear, json).await,
+                Command::Rm { ids, permanent } => commands::remove(&client, &ids, permanent, json).await,
+                Command::Export { format, output } => commands::export(&client, &format, output).await,
+                Command::Import { file, format, mapping, dry_run } => {
+                    commands::import(&client, &file, &format, mapping.as_deref(), dry_run, json).await
+                }
+                Command::Tui => tui::run(&client).await,
+                Command::Login { .. } | Command::Logout => unreachable!(),
+            };
+
+            // The client renews tokens as they age; keep the new one for next time
+            if let Some(token) = client.token().filter(|token| *token != stored.token) {
+                credentials::save(&Credentials { server: stored.server, token })?;
+            }
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn arguments_are_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn json_can_follow_the_command() {
+        let cli = Cli::try_parse_from(["todo", "ls", "--open", "--json"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(cli.command, Command::Ls(ListFilters { open: true, .. })));
+    }
+
+    #[test]
+    fn contradicting_flags_are_rejected() {
+        assert!(Cli::try_parse_from(["todo", "ls", "--done", "--open"]).is_err());
+        assert!(Cli::try_parse_from(["todo", "edit", "abc", "--due", "2026-03-01", "--no-due"]).is_err());
+        assert!(Cli::try_parse_from(["todo", "done"]).is_err());
+        assert!(Cli::try_parse_from(["todo", "export", "--format", "xml"]).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+// Plain-text tables: a header row, then columns padded to their widest cell
+
+use std::io::{self, Write};
+
+pub struct Table {
+    header: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(header: Vec<&'static str>) -> Table {
+        Table { header, rows: Vec::new() }
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    pub fn print(&self) -> io::Result<()> {
+        self.write(&mut io::stdout().lock())
+    }
+
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let width = |cell: &str| cell.chars().count();
+        let mut widths: Vec<usize> = self.header.iter().map(|cell| width(cell)).collect();
+        for row in &self.rows {
+            for (column, cell) in row.iter().enumerate() {
+                widths[column] = widths[column].max(width(cell));
+            }
+        }
+
+        let mut print_row = |cells: Vec<&str>| {
+            let line: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, column_width)| format!("{}{}", cell, " ".repeat(column_width - width(cell))))
+                .collect();
+            writeln!(out, "{}", line.join("  ").trim_end())
+        };
+
+        print_row(self.header.clone())?;
+        for row in &self.rows {
+            print_row(row.iter().map(String::as_str).collect())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written(table: &Table) -> String {
+        let mut out = Vec::new();
+        table.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn columns_are_as_wide_as_their_widest_cell() {
+        let mut table = Table::new(vec!["ID", "DONE", "TITLE"]);
+        table.row(vec!["1234abcd".to_string(), "x".to_string(), "Buy milk".to_string()]);
+        table.row(vec!["ef".to_string(), String::new(), "Café".to_string()]);
+
+        assert_eq!(
+            written(&table),
+            "ID        DONE  TITLE\n1234abcd  x     Buy milk\nef              Café\n"
+        );
+    }
+
+    #[test]
+    fn lines_have_no_trailing_spaces() {
+        let mut table = Table::new(vec!["TITLE", "DUE"]);
+        table.row(vec!["A long title".to_string(), String::new()]);
+        assert_eq!(written(&table), "TITLE         DUE\nA long title\n");
+    }
+}
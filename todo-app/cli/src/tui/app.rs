@@ -0,0 +1,614 @@
+// What the TUI shows, and what each key does to it. Every change is sent to
+// the server and followed by fetching the todos again, so the screen always
+// shows what the server has
+
+use anyhow::{bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::widgets::ListState;
+use todo_client::{
+    types::{CreateTodoRequest, MoveTodoRequest, Todo, TodoUpdate},
+    Client, Error,
+};
+
+use crate::commands::{end_of_day, format_local, parse_local};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Filter {
+    All,
+    Open,
+    Done,
+}
+
+impl Filter {
+    fn next(self) -> Filter {
+        match self {
+            Filter::All => Filter::Open,
+            Filter::Open => Filter::Done,
+            Filter::Done => Filter::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Filter::All => "all",
+            Filter::Open => "open",
+            Filter::Done => "done",
+        }
+    }
+
+    fn matches(self, todo: &Todo) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Open => !todo.completed,
+            Filter::Done => todo.completed,
+        }
+    }
+}
+
+// What a line of input is for
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    Search,
+    NewTitle,
+    Title,
+    Description,
+    Due,
+}
+
+impl Field {
+    pub fn prompt(self) -> &'static str {
+        match self {
+            Field::Search => "Search",
+            Field::NewTitle => "New todo",
+            Field::Title => "Title",
+            Field::Description => "Description",
+            Field::Due => "Due (YYYY-MM-DD or YYYY-MM-DD HH:MM, empty to remove)",
+        }
+    }
+}
+
+pub enum Mode {
+    Normal,
+    Help,
+    Input { field: Field, text: String },
+    // Waiting for y before deleting the selected todo for good
+    ConfirmPurge,
+}
+
+pub struct Status {
+    pub text: String,
+    pub error: bool,
+}
+
+pub struct App {
+    client: Client,
+    // In the server's order, whatever the filter and search
+    pub todos: Vec<Todo>,
+    pub trash: bool,
+    pub filter: Filter,
+    pub search: String,
+    // Index into `visible`
+    pub selected: usize,
+    pub list: ListState,
+    pub mode: Mode,
+    pub status: Option<Status>,
+    pub live: bool,
+    pub quit: bool,
+    // The first key of gg or dd
+    pending: Option<char>,
+}
+
+impl App {
+    pub fn new(client: Client) -> App {
+        App {
+            client,
+            todos: Vec::new(),
+            trash: false,
+            filter: Filter::All,
+            search: String::new(),
+            selected: 0,
+            list: ListState::default(),
+            mode: Mode::Normal,
+            status: None,
+            live: false,
+            quit: false,
+            pending: None,
+        }
+    }
+
+    // Indices into `todos` of those the filter and search let through
+    pub fn visible(&self) -> Vec<usize> {
+        let search = self.search.to_lowercase();
+        (0..self.todos.len())
+            .filter(|&index| {
+                let todo = &self.todos[index];
+                (self.trash || self.filter.matches(todo))
+                    && (todo.title.to_lowercase().contains(&search)
+                        || todo.description.as_ref().is_some_and(|text| text.to_lowercase().contains(&search)))
+            })
+            .collect()
+    }
+
+    pub fn selected_todo(&self) -> Option<&Todo> {
+        self.visible().get(self.selected).map(|&index| &self.todos[index])
+    }
+
+    fn selected_id(&self) -> Option<String> {
+        self.selected_todo().map(|todo| todo.id.clone())
+    }
+
+    // Keeps the todo with this ID selected if it's still shown, and the
+    // selection in range otherwise
+    fn select(&mut self, id: Option<&str>) {
+        let visible = self.visible();
+        match id.and_then(|id| visible.iter().position(|&index| self.todos[index].id == id)) {
+            Some(position) => self.selected = position,
+            None => self.selected = self.selected.min(visible.len().saturating_sub(1)),
+        }
+    }
+
+    pub async fn reload(&mut self) -> Result<()> {
+        let selected = self.selected_id();
+        self.todos = if self.trash { self.client.trash().await? } else { self.client.todos().await? };
+        self.select(selected.as_deref());
+        Ok(())
+    }
+
+    // Like `reload`, for when nothing is waiting to hear that it failed
+    pub async fn refresh(&mut self) {
+        if let Err(error) = self.reload().await {
+            self.fail(error);
+        }
+    }
+
+    fn inform(&mut self, text: impl Into<String>) {
+        self.status = Some(Status { text: text.into(), error: false });
+    }
+
+    fn fail(&mut self, error: anyhow::Error) {
+        let text = match error.downcast_ref::<Error>() {
+            Some(Error::Unauthorized) => "Logged out; quit and log in again with `todo login`".to_string(),
+            _ => format!("{:#}", error),
+        };
+        self.status = Some(Status { text, error: true });
+    }
+
+    pub async fn on_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        self.status = None;
+
+        let result = match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Normal => self.normal_key(key).await,
+            // Any key closes it
+            Mode::Help => Ok(()),
+            Mode::Input { field, text } => self.input_key(field, text, key).await,
+            Mode::ConfirmPurge if key.code == KeyCode::Char('y') => self.purge().await,
+            Mode::ConfirmPurge => Ok(()),
+        };
+        if let Err(error) = result {
+            self.fail(error);
+        }
+    }
+
+    async fn normal_key(&mut self, key: KeyEvent) -> Result<()> {
+        let last = self.visible().len().saturating_sub(1);
+        match (self.pending.take(), key.code) {
+            (_, KeyCode::Char('q')) => self.quit = true,
+            (_, KeyCode::Char('?')) => self.mode = Mode::Help,
+            (_, KeyCode::Char('j') | KeyCode::Down) => self.selected = (self.selected + 1).min(last),
+            (_, KeyCode::Char('k') | KeyCode::Up) => self.selected = self.selected.saturating_sub(1),
+            (Some('g'), KeyCode::Char('g')) | (_, KeyCode::Home) => self.selected = 0,
+            (_, KeyCode::Char('G') | KeyCode::End) => self.selected = last,
+            (Some('d'), KeyCode::Char('d')) => self.delete().await?,
+            (_, KeyCode::Char(key @ ('g' | 'd'))) => self.pending = Some(key),
+            (_, KeyCode::Char('/')) => self.mode = Mode::Input { field: Field::Search, text: self.search.clone() },
+            (_, KeyCode::Esc) => self.set_search(String::new()),
+            (_, KeyCode::Char('f')) => {
+                let selected = self.selected_id();
+                self.filter = self.filter.next();
+                self.select(selected.as_deref());
+            }
+            (_, KeyCode::Char('t')) => {
+                self.trash = !self.trash;
+                self.selected = 0;
+                if let Err(error) = self.reload().await {
+                    self.trash = !self.trash;
+                    return Err(error);
+                }
+            }
+            (_, KeyCode::Char('r')) => {
+                self.reload().await?;
+                self.inform("Refreshed");
+            }
+            (_, KeyCode::Char('u')) if self.trash => self.restore().await?,
+            // The rest only make sense on the list
+            _ if self.trash => {}
+            (_, KeyCode::Char('o' | 'a')) => self.mode = Mode::Input { field: Field::NewTitle, text: String::new() },
+            (_, KeyCode::Char('x' | ' ')) => self.toggle().await?,
+            (_, KeyCode::Char('e' | 'i')) => self.edit(Field::Title),
+            (_, KeyCode::Char('E')) => self.edit(Field::Description),
+            (_, KeyCode::Char('s')) => self.edit(Field::Due),
+            (_, KeyCode::Char(digit @ '0'..='9')) => {
+                // 0 removes the priority
+                let priority = digit.to_digit(10).map(|priority| priority as i16).filter(|priority| *priority > 0);
+                self.update(TodoUpdate { priority: Some(priority), ..Default::default() }).await?;
+            }
+            (_, KeyCode::Char('J')) => self.move_selected(true).await?,
+            (_, KeyCode::Char('K')) => self.move_selected(false).await?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn input_key(&mut self, field: Field, mut text: String, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Enter => return self.submit(field, text).await,
+            KeyCode::Esc => {
+                if field == Field::Search {
+                    self.set_search(String::new());
+                }
+                return Ok(());
+            }
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Char(character) if !key.modifiers.contains(KeyModifiers::CONTROL) => text.push(character),
+            _ => {}
+        }
+        // Searches narrow the list as they're typed
+        if field == Field::Search {
+            self.set_search(text.clone());
+        }
+        self.mode = Mode::Input { field, text };
+        Ok(())
+    }
+
+    fn set_search(&mut self, search: String) {
+        let selected = self.selected_id();
+        self.search = search;
+        self.select(selected.as_deref());
+    }
+
+    // Starts editing a field of the selected todo, from what it holds now
+    fn edit(&mut self, field: Field) {
+        let Some(todo) = self.selected_todo() else {
+            return;
+        };
+        let text = match field {
+            Field::Title => todo.title.clone(),
+            Field::Description => todo.description.clone().unwrap_or_default(),
+            Field::Due => todo.due_at.map(format_local).unwrap_or_default(),
+            Field::Search | Field::NewTitle => String::new(),
+        };
+        self.mode = Mode::Input { field, text };
+    }
+
+    async fn submit(&mut self, field: Field, text: String) -> Result<()> {
+        let text = text.trim().to_string();
+        match field {
+            Field::Search => {}
+            Field::NewTitle => {
+                if text.is_empty() {
+                    return Ok(());
+                }
+                let request =
+                    CreateTodoRequest { title: text, description: None, due_at: None, priority: None, remind_at: None };
+                let todo = self.client.create_todo(&request).await?;
+                self.reload().await?;
+                self.select(Some(&todo.id));
+            }
+            Field::Title => {
+                if text.is_empty() {
+                    bail!("A todo needs a title");
+                }
+                self.update(TodoUpdate { title: Some(text), ..Default::default() }).await?;
+            }
+            Field::Description => self.update(TodoUpdate { description: Some(text), ..Default::default() }).await?,
+            Field::Due => {
+                // Left empty, the due date is removed
+                let due_at = Some(text).filter(|text| !text.is_empty()).map(|text| parse_local(&text, end_of_day()));
+                self.update(TodoUpdate { due_at: Some(due_at.transpose()?), ..Default::default() }).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn update(&mut self, update: TodoUpdate) -> Result<()> {
+        let Some(id) = self.selected_id() else {
+            return Ok(());
+        };
+        self.client.update_todo(&id, &update).await?;
+        self.reload().await
+    }
+
+    async fn toggle(&mut self) -> Result<()> {
+        let Some(completed) = self.selected_todo().map(|todo| !todo.completed) else {
+            return Ok(());
+        };
+        self.update(TodoUpdate { completed: Some(completed), ..Default::default() }).await
+    }
+
+    // Trashes the selected todo, or asks before deleting it for good when it's
+    // already in the trash
+    async fn delete(&mut self) -> Result<()> {
+        let Some(id) = self.selected_id() else {
+            return Ok(());
+        };
+        if self.trash {
+            self.mode = Mode::ConfirmPurge;
+            return Ok(());
+        }
+        self.client.delete_todo(&id).await?;
+        self.reload().await?;
+        self.inform("Moved to the trash; t shows it");
+        Ok(())
+    }
+
+    async fn purge(&mut self) -> Result<()> {
+        let Some(id) = self.selected_id() else {
+            return Ok(());
+        };
+        self.client.purge_todo(&id).await?;
+        self.reload().await?;
+        self.inform("Deleted for good");
+        Ok(())
+    }
+
+    async fn restore(&mut self) -> Result<()> {
+        let Some(id) = self.selected_id() else {
+            return Ok(());
+        };
+        self.client.restore_todo(&id).await?;
+        self.reload().await?;
+        self.inform("Restored");
+        Ok(())
+    }
+
+    // Swaps the selected todo with the one shown below or above it. Todos
+    // hidden by the filter or search stay where they are, so the neighbours are
+    // looked up in the whole list
+    async fn move_selected(&mut self, down: bool) -> Result<()> {
+        let visible = self.visible();
+        let Some(&from) = visible.get(self.selected) else {
+            return Ok(());
+        };
+        let target = if down { self.selected.checked_add(1) } else { self.selected.checked_sub(1) };
+        let Some(&target) = target.and_then(|position| visible.get(position)) else {
+            return Ok(());
+        };
+
+        let id = |index: usize| self.todos.get(index).map(|todo| todo.id.clone());
+        let neighbours = if down {
+            MoveTodoRequest { previous_id: id(target), next_id: id(target + 1) }
+        } else {
+            MoveTodoRequest { previous_id: target.checked_sub(1).and_then(id), next_id: id(target) }
+        };
+        let moving = self.todos[from].id.clone();
+        self.client.move_todo(&moving, &neighbours).await?;
+        self.reload().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use axum::{
+        body::{to_bytes, Body},
+        http::Request,
+        Router,
+    };
+    use todo_backend::{app, config::Config, AppState};
+    use todo_client::{HttpRequest, HttpResponse, Transport};
+    use tower::ServiceExt;
+
+    // Hands requests straight to the backend's router, kept in memory
+    struct RouterTransport {
+        router: Router,
+    }
+
+    #[async_trait]
+    impl Transport for RouterTransport {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+            let path = request.url.strip_prefix("http://todo.test").ok_or("unexpected host")?;
+            let mut builder = Request::builder().method(request.method.as_str()).uri(path);
+            for (name, value) in request.headers {
+                builder = builder.header(name, value);
+            }
+            let request = builder.body(Body::from(request.body.unwrap_or_default())).map_err(|e| e.to_string())?;
+
+            let response = self.router.clone().oneshot(request).await.map_err(|e| e.to_string())?;
+            let status = response.status().as_u16();
+            let body = to_bytes(response.into_body(), usize::MAX).await.map_err(|e| e.to_string())?;
+            Ok(HttpResponse { status, body: body.to_vec() })
+        }
+    }
+
+    // Logged in, with these todos in this order
+    async fn app_with(titles: &[&str]) -> App {
+        let mut config = Config::default();
+        config.auth.jwt_secret = "a-secret-for-the-tui-tests".to_string();
+        config.auth.bcrypt_cost = 4;
+        let (state, _) = AppState::in_memory(config);
+        let client = Client::with_transport("http://todo.test", RouterTransport { router: app(state) });
+        client.register("alice", "alice@example.com", "password").await.unwrap();
+
+        // New todos go to the top
+        for title in titles.iter().rev() {
+            let request = CreateTodoRequest { title: title.to_string(), ..Default::default() };
+            client.create_todo(&request).await.unwrap();
+        }
+        let mut app = App::new(client);
+        app.reload().await.unwrap();
+        app
+    }
+
+    async fn press(app: &mut App, keys: &str) {
+        for key in keys.chars() {
+            app.on_key(KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE)).await;
+        }
+    }
+
+    async fn press_code(app: &mut App, code: KeyCode) {
+        app.on_key(KeyEvent::new(code, KeyModifiers::NONE)).await;
+    }
+
+    fn shown(app: &App) -> Vec<&str> {
+        app.visible().into_iter().map(|index| app.todos[index].title.as_str()).collect()
+    }
+
+    fn selected(app: &App) -> &str {
+        &app.selected_todo().unwrap().title
+    }
+
+    #[tokio::test]
+    async fn vim_keys_move_the_selection() {
+        let mut app = app_with(&["a", "b", "c"]).await;
+        press(&mut app, "j").await;
+        assert_eq!(selected(&app), "b");
+        press(&mut app, "jjj").await;
+        assert_eq!(selected(&app), "c");
+        press(&mut app, "gg").await;
+        assert_eq!(selected(&app), "a");
+        press(&mut app, "G").await;
+        assert_eq!(selected(&app), "c");
+        press(&mut app, "kkkk").await;
+        assert_eq!(selected(&app), "a");
+
+        // A g followed by anything else is forgotten
+        press(&mut app, "Ggjg").await;
+        assert_eq!(selected(&app), "c");
+
+        press(&mut app, "q").await;
+        assert!(app.quit);
+    }
+
+    #[tokio::test]
+    async fn filters_and_searches_narrow_the_list() {
+        let mut app = app_with(&["Buy milk", "Call bank", "Buy bread"]).await;
+        press(&mut app, "x").await;
+        assert!(app.todos[0].completed);
+
+        press(&mut app, "f").await;
+        assert_eq!(shown(&app), ["Call bank", "Buy bread"]);
+        press(&mut app, "f").await;
+        assert_eq!(shown(&app), ["Buy milk"]);
+        press(&mut app, "f").await;
+        assert_eq!(shown(&app), ["Buy milk", "Call bank", "Buy bread"]);
+
+        // As it's typed, and Esc clears it
+        press(&mut app, "/BU").await;
+        assert_eq!(shown(&app), ["Buy milk", "Buy bread"]);
+        press(&mut app, "y b").await;
+        assert_eq!(shown(&app), ["Buy bread"]);
+        press_code(&mut app, KeyCode::Enter).await;
+        assert!(matches!(app.mode, Mode::Normal));
+        assert_eq!(app.search, "BUy b");
+        press_code(&mut app, KeyCode::Esc).await;
+        assert_eq!(shown(&app).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn todos_are_added_edited_and_completed() {
+        let mut app = app_with(&["a"]).await;
+
+        press(&mut app, "oBuy milk").await;
+        press_code(&mut app, KeyCode::Enter).await;
+        assert_eq!(shown(&app), ["Buy milk", "a"]);
+        assert_eq!(selected(&app), "Buy milk");
+
+        press(&mut app, "e").await;
+        press_code(&mut app, KeyCode::Backspace).await;
+        press(&mut app, "k and eggs").await;
+        press_code(&mut app, KeyCode::Enter).await;
+        assert_eq!(selected(&app), "Buy milk and eggs");
+
+        press(&mut app, "s2026-03-01").await;
+        press_code(&mut app, KeyCode::Enter).await;
+        assert_eq!(app.selected_todo().unwrap().due_at, Some(parse_local("2026-03-01", end_of_day()).unwrap()));
+        press(&mut app, "s").await;
+        for _ in 0.."2026-03-01 23:59".len() {
+            press_code(&mut app, KeyCode::Backspace).await;
+        }
+        press_code(&mut app, KeyCode::Enter).await;
+        assert_eq!(app.selected_todo().unwrap().due_at, None);
+
+        press(&mut app, "3").await;
+        assert_eq!(app.selected_todo().unwrap().priority, Some(3));
+        press(&mut app, "0").await;
+        assert_eq!(app.selected_todo().unwrap().priority, None);
+
+        press(&mut app, " ").await;
+        assert!(app.selected_todo().unwrap().completed);
+        press(&mut app, "x").await;
+        assert!(!app.selected_todo().unwrap().completed);
+
+        // An empty title is refused, and said so
+        press(&mut app, "e").await;
+        for _ in 0.."Buy milk and eggs".len() {
+            press_code(&mut app, KeyCode::Backspace).await;
+        }
+        press_code(&mut app, KeyCode::Enter).await;
+        assert!(app.status.as_ref().is_some_and(|status| status.error));
+        assert_eq!(selected(&app), "Buy milk and eggs");
+    }
+
+    #[tokio::test]
+    async fn todos_are_reordered_past_hidden_ones() {
+        let mut app = app_with(&["a", "b", "c", "d"]).await;
+        press(&mut app, "J").await;
+        assert_eq!(shown(&app), ["b", "a", "c", "d"]);
+        assert_eq!(selected(&app), "a");
+        press(&mut app, "GK").await;
+        assert_eq!(shown(&app), ["b", "a", "d", "c"]);
+
+        // With c done and hidden, a moves below d and stays above c
+        press(&mut app, "jx").await;
+        press(&mut app, "f").await;
+        assert_eq!(shown(&app), ["b", "a", "d"]);
+        press(&mut app, "ggjJ").await;
+        assert_eq!(shown(&app), ["b", "d", "a"]);
+        press(&mut app, "f").await;
+        press(&mut app, "f").await;
+        assert_eq!(shown(&app), ["b", "d", "a", "c"]);
+    }
+
+    #[tokio::test]
+    async fn deleting_goes_through_the_trash() {
+        let mut app = app_with(&["a", "b"]).await;
+        press(&mut app, "dd").await;
+        assert_eq!(shown(&app), ["b"]);
+
+        press(&mut app, "t").await;
+        assert!(app.trash);
+        assert_eq!(shown(&app), ["a"]);
+        press(&mut app, "u").await;
+        assert!(shown(&app).is_empty());
+        press(&mut app, "t").await;
+        assert_eq!(shown(&app), ["a", "b"]);
+
+        // For good only after a y
+        press(&mut app, "ddt").await;
+        press(&mut app, "dd").await;
+        assert!(matches!(app.mode, Mode::ConfirmPurge));
+        press(&mut app, "n").await;
+        assert_eq!(shown(&app), ["a"]);
+        press(&mut app, "ddy").await;
+        assert!(shown(&app).is_empty());
+        press(&mut app, "t").await;
+        assert_eq!(shown(&app), ["b"]);
+    }
+
+    #[tokio::test]
+    async fn an_expired_login_says_how_to_log_in_again() {
+        let mut app = app_with(&["a"]).await;
+        app.client.set_token(None);
+        press(&mut app, "r").await;
+        let status = app.status.as_ref().unwrap();
+        assert!(status.error);
+        assert!(status.text.contains("todo login"));
+    }
+}
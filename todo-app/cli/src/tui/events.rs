@@ -0,0 +1,93 @@
+// Follows the server's change events for the TUI, saying when the todos changed
+// and whether it is hearing about changes at all. Dropped connections are
+// retried every few seconds
+
+use anyhow::Result;
+use futures::StreamExt;
+use reqwest::header::ACCEPT;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use todo_client::{types::routes, Client};
+
+const RETRY_AFTER: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum Message {
+    Changed,
+    Live(bool),
+}
+
+// Returns once the TUI stops listening
+pub async fn listen(client: Client, sender: UnboundedSender<Message>) {
+    let http = reqwest::Client::new();
+    let mut reconnecting = false;
+    loop {
+        let _ = follow(&http, &client, &sender, reconnecting).await;
+        if sender.send(Message::Live(false)).is_err() {
+            return;
+        }
+        reconnecting = true;
+        tokio::time::sleep(RETRY_AFTER).await;
+    }
+}
+
+async fn follow(
+    http: &reqwest::Client,
+    client: &Client,
+    sender: &UnboundedSender<Message>,
+    reconnecting: bool,
+) -> Result<()> {
+    // The client keeps its token fresh, so each attempt takes the latest one
+    let mut request = http.get(format!("{}{}", client.base_url(), routes::EVENTS)).header(ACCEPT, "text/event-stream");
+    if let Some(token) = client.token() {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await?.error_for_status()?;
+
+    sender.send(Message::Live(true))?;
+    if reconnecting {
+        // Nothing announced what changed while disconnected
+        sender.send(Message::Changed)?;
+    }
+
+    let mut body = response.bytes_stream();
+    let mut buffer = String::new();
+    while let Some(chunk) = body.next().await {
+        buffer.push_str(&String::from_utf8_lossy(&chunk?));
+        for name in take_events(&mut buffer) {
+            if name.as_deref() == Some(routes::EVENT_TODOS) {
+                sender.send(Message::Changed)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Removes the events received in full from the buffer, and returns their
+// names. Each event ends with a blank line
+fn take_events(buffer: &mut String) -> Vec<Option<String>> {
+    let mut names = Vec::new();
+    while let Some(end) = buffer.find("\n\n") {
+        let event: String = buffer.drain(..end + 2).collect();
+        names.push(event.lines().find_map(|line| line.strip_prefix("event:")).map(|name| name.trim().to_string()));
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_taken_once_they_are_complete() {
+        let mut buffer = "event: todos\ndata: {}\n\n: keep-alive\n\nevent: to".to_string();
+        assert_eq!(take_events(&mut buffer), [Some("todos".to_string()), None]);
+        assert_eq!(buffer, "event: to");
+
+        buffer.push_str("dos\ndata: {}\n");
+        assert!(take_events(&mut buffer).is_empty());
+        buffer.push('\n');
+        assert_eq!(take_events(&mut buffer), [Some("todos".to_string())]);
+        assert!(buffer.is_empty());
+    }
+}
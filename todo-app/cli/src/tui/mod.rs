@@ -0,0 +1,57 @@
+// `todo tui`, a full-screen view of the todos to browse and change them with
+// vim-style keys. It follows /api/events and fetches the todos again whenever
+// the server says they changed, so changes made elsewhere show up as they happen
+
+use anyhow::Result;
+use crossterm::event::{Event, EventStream, KeyEventKind};
+use futures::StreamExt;
+use ratatui::DefaultTerminal;
+use tokio::sync::mpsc;
+use todo_client::Client;
+
+mod app;
+mod events;
+mod ui;
+
+use app::App;
+use events::Message;
+
+pub async fn run(client: &Client) -> Result<()> {
+    // Before taking over the terminal, so that e.g. an expired login is reported as usual
+    let mut app = App::new(client.clone());
+    app.reload().await?;
+
+    let (sender, mut messages) = mpsc::unbounded_channel();
+    let listener = tokio::spawn(events::listen(client.clone(), sender));
+
+    let mut terminal = ratatui::init();
+    let result = run_loop(&mut terminal, &mut app, &mut messages).await;
+    ratatui::restore();
+    listener.abort();
+    result
+}
+
+async fn run_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    messages: &mut mpsc::UnboundedReceiver<Message>,
+) -> Result<()> {
+    let mut terminal_events = EventStream::new();
+    while !app.quit {
+        terminal.draw(|frame| ui::draw(frame, app))?;
+
+        tokio::select! {
+            event = terminal_events.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => app.on_key(key).await,
+                Some(Ok(_)) => {}
+                Some(Err(error)) => return Err(error.into()),
+                None => break,
+            },
+            Some(message) = messages.recv() => match message {
+                Message::Changed => app.refresh().await,
+                Message::Live(live) => app.live = live,
+            },
+        }
+    }
+    Ok(())
+}
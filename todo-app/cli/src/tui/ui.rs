@@ -0,0 +1,175 @@
+// Draws the TUI: a header, the todos with the selected one's details beside
+// them, and a line for messages and input at the bottom
+
+use chrono::Utc;
+use ratatui::{
+    layout::{Constraint, Layout, Margin, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
+    Frame,
+};
+use todo_client::types::Todo;
+
+use super::app::{App, Mode};
+use crate::commands::format_local;
+
+const HELP: &[(&str, &str)] = &[
+    ("j k", "down, up"),
+    ("gg G", "first, last"),
+    ("o", "add a todo"),
+    ("x space", "done or not done"),
+    ("e E", "edit title, description"),
+    ("s", "set or remove the due date"),
+    ("1-9 0", "set, remove the priority"),
+    ("J K", "move down, up"),
+    ("dd", "trash, or delete from the trash"),
+    ("u", "restore from the trash"),
+    ("t", "the list or the trash"),
+    ("f", "show all, open or done"),
+    ("/ Esc", "search, clear the search"),
+    ("r", "fetch the todos again"),
+    ("q", "quit"),
+];
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let [header, body, footer] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let [list, detail] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(body);
+
+    draw_header(frame, app, header);
+    draw_list(frame, app, list);
+    draw_detail(frame, app, detail);
+    draw_footer(frame, app, footer);
+    if matches!(app.mode, Mode::Help) {
+        draw_help(frame);
+    }
+}
+
+fn draw_header(frame: &mut Frame, app: &App, area: Rect) {
+    let mut spans = vec![Span::from(if app.trash { " Trash" } else { " Todos" }).bold()];
+    if !app.trash {
+        spans.push(Span::from(format!("  {}", app.filter.label())));
+    }
+    if !app.search.is_empty() {
+        spans.push(Span::from(format!("  matching \"{}\"", app.search)));
+    }
+    frame.render_widget(Line::from(spans), area);
+
+    let live = if app.live {
+        Span::from("● live ").fg(Color::Green)
+    } else {
+        Span::from("○ offline ").fg(Color::DarkGray)
+    };
+    frame.render_widget(Line::from(live).right_aligned(), area);
+}
+
+fn item(todo: &Todo) -> ListItem<'static> {
+    let mut spans = vec![Span::from(if todo.completed { "[x] " } else { "[ ] " })];
+    if let Some(priority) = todo.priority {
+        spans.push(Span::from(format!("{} ", priority)).fg(Color::Yellow));
+    }
+    spans.push(Span::from(todo.title.clone()));
+    if let Some(due) = todo.due_at {
+        let color = if !todo.completed && due < Utc::now() { Color::Red } else { Color::DarkGray };
+        spans.push(Span::from(format!("  {}", format_local(due))).fg(color));
+    }
+
+    let line = Line::from(spans);
+    if todo.completed {
+        ListItem::new(line.add_modifier(Modifier::DIM))
+    } else {
+        ListItem::new(line)
+    }
+}
+
+fn draw_list(frame: &mut Frame, app: &mut App, area: Rect) {
+    let visible = app.visible();
+    let block = Block::new().borders(Borders::RIGHT).title(format!(" {} of {} ", visible.len(), app.todos.len()));
+    if visible.is_empty() {
+        let empty = if app.todos.is_empty() && !app.trash { "No todos yet; o adds one" } else { "Nothing here" };
+        frame.render_widget(Paragraph::new(empty).dark_gray().block(block), area);
+        return;
+    }
+
+    let items: Vec<ListItem> = visible.iter().map(|&index| item(&app.todos[index])).collect();
+    let list = List::new(items).block(block).highlight_style(Style::new().reversed());
+    app.list.select(Some(app.selected));
+    frame.render_stateful_widget(list, area, &mut app.list);
+}
+
+fn draw_detail(frame: &mut Frame, app: &App, area: Rect) {
+    let Some(todo) = app.selected_todo() else {
+        return;
+    };
+    let field = |name: &str, value: String| {
+        Line::from(vec![Span::from(format!("{:<10}", name)).dark_gray(), Span::from(value)])
+    };
+
+    let mut lines = vec![Line::from(todo.title.clone()).bold(), Line::default()];
+    match &todo.description {
+        Some(description) if !description.is_empty() => {
+            lines.extend(description.lines().map(|line| Line::from(line.to_string())))
+        }
+        _ => lines.push(Line::from("No description").dark_gray()),
+    }
+    lines.push(Line::default());
+    lines.push(field("Done", if todo.completed { "yes" } else { "no" }.to_string()));
+    if let Some(due) = todo.due_at {
+        lines.push(field("Due", format_local(due)));
+    }
+    if let Some(priority) = todo.priority {
+        lines.push(field("Priority", priority.to_string()));
+    }
+    if let Some(remind) = todo.remind_at {
+        lines.push(field("Reminder", format_local(remind)));
+    }
+    if let Some(deleted) = todo.deleted_at {
+        lines.push(field("Trashed", format_local(deleted)));
+    }
+    lines.push(field("Created", format_local(todo.created_at)));
+    lines.push(field("Updated", format_local(todo.updated_at)));
+    lines.push(field("ID", todo.id.clone()));
+
+    let details = Paragraph::new(lines).wrap(Wrap { trim: false }).block(Block::new().title(" Details "));
+    frame.render_widget(details, area.inner(Margin::new(1, 0)));
+}
+
+fn draw_footer(frame: &mut Frame, app: &App, area: Rect) {
+    match &app.mode {
+        Mode::Input { field, text } => {
+            let prompt = format!(" {}: ", field.prompt());
+            let cursor = area.x + (prompt.chars().count() + text.chars().count()) as u16;
+            frame.render_widget(Line::from(vec![Span::from(prompt).bold(), Span::from(text.as_str())]), area);
+            frame.set_cursor_position((cursor.min(area.right().saturating_sub(1)), area.y));
+        }
+        Mode::ConfirmPurge => {
+            let title = app.selected_todo().map(|todo| todo.title.as_str()).unwrap_or_default();
+            frame.render_widget(Line::from(format!(" Delete \"{}\" for good? y/n", title)).yellow(), area);
+        }
+        Mode::Normal | Mode::Help => match &app.status {
+            Some(status) if status.error => frame.render_widget(Line::from(format!(" {}", status.text)).red(), area),
+            Some(status) => frame.render_widget(Line::from(format!(" {}", status.text)), area),
+            None => frame.render_widget(Line::from(" ? for keys, q to quit").dark_gray(), area),
+        },
+    }
+}
+
+fn draw_help(frame: &mut Frame) {
+    let width = 48;
+    let height = HELP.len() as u16 + 2;
+    let screen = frame.area();
+    let area = Rect {
+        x: screen.x + screen.width.saturating_sub(width) / 2,
+        y: screen.y + screen.height.saturating_sub(height) / 2,
+        width: width.min(screen.width),
+        height: height.min(screen.height),
+    };
+
+    let lines: Vec<Line> = HELP
+        .iter()
+        .map(|(keys, action)| Line::from(vec![Span::from(format!(" {:<9}", keys)).bold(), Span::from(*action)]))
+        .collect();
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Keys ")), area);
+}
@@ -0,0 +1,40 @@
+use todo_api_types::{routes, ChangePasswordRequest, DeleteAccountRequest, User, UserUpdate};
+
+use crate::{Client, Method, Result};
+
+impl Client {
+    pub async fn me(&self) -> Result<User> {
+        self.get(routes::ME).await
+    }
+
+    // Fails with `Conflict` if the username or email is taken
+    pub async fn update_me(&self, update: &UserUpdate) -> Result<User> {
+        self.send_json(Method::Patch, routes::ME, update).await
+    }
+
+    // Fails with `Forbidden` if the current password is wrong
+    pub async fn change_password(&self, current_password: &str, new_password: &str) -> Result<()> {
+        let request = ChangePasswordRequest {
+            current_password: current_password.to_string(),
+            new_password: new_password.to_string(),
+        };
+        self.send(Method::Post, routes::ME_PASSWORD, Some(crate::Body::json(&request)?)).await?;
+        Ok(())
+    }
+
+    // Schedules the account for deletion, after the grace period
+    pub async fn delete_me(&self, password: &str) -> Result<User> {
+        let request = DeleteAccountRequest { password: password.to_string() };
+        self.send_json(Method::Delete, routes::ME, &request).await
+    }
+
+    // Cancels a scheduled deletion
+    pub async fn restore_me(&self) -> Result<User> {
+        self.send_json(Method::Post, routes::ME_RESTORE, &()).await
+    }
+
+    // A ZIP archive of everything stored about the account
+    pub async fn export_me(&self) -> Result<Vec<u8>> {
+        self.send(Method::Get, routes::ME_EXPORT, None).await
+    }
+}
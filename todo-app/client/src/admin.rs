@@ -0,0 +1,54 @@
+// Everything here requires the admin role, and fails with `Forbidden` without it
+
+use futures::Stream;
+use todo_api_types::{routes, AdminStats, AdminUser, AdminUserQuery, AdminUserUpdate, Job, JobQuery};
+
+use crate::{offset_pages, with_query, Client, Method, Result};
+
+impl Client {
+    pub async fn admin_stats(&self) -> Result<AdminStats> {
+        self.get(routes::ADMIN_STATS).await
+    }
+
+    // Every user matching `q` (in username, email or display name), newest
+    // first, fetched a page at a time as the stream is read
+    pub fn admin_users<'a>(&'a self, q: Option<&'a str>) -> impl Stream<Item = Result<AdminUser>> + 'a {
+        offset_pages(move |offset| self.admin_users_page(q, offset))
+    }
+
+    // One page of `admin_users`, starting at `offset`
+    pub async fn admin_users_page(&self, q: Option<&str>, offset: i64) -> Result<Vec<AdminUser>> {
+        let query = AdminUserQuery { q: q.map(str::to_string), offset };
+        self.get(&with_query(routes::ADMIN_USERS, &query)?).await
+    }
+
+    pub async fn admin_user(&self, id: &str) -> Result<AdminUser> {
+        self.get(&routes::path(routes::ADMIN_USER, &[id])).await
+    }
+
+    // Fails with `BadRequest` for an unknown role, or when admins take away their own
+    pub async fn update_admin_user(&self, id: &str, update: &AdminUserUpdate) -> Result<AdminUser> {
+        self.send_json(Method::Patch, &routes::path(routes::ADMIN_USER, &[id]), update).await
+    }
+
+    pub async fn disable_user(&self, id: &str) -> Result<AdminUser> {
+        self.send_json(Method::Post, &routes::path(routes::ADMIN_USER_DISABLE, &[id]), &()).await
+    }
+
+    pub async fn enable_user(&self, id: &str) -> Result<AdminUser> {
+        self.send_json(Method::Post, &routes::path(routes::ADMIN_USER_ENABLE, &[id]), &()).await
+    }
+
+    // The user can only change their password until they do
+    pub async fn require_password_reset(&self, id: &str) -> Result<AdminUser> {
+        self.send_json(Method::Post, &routes::path(routes::ADMIN_USER_RESET_PASSWORD, &[id]), &()).await
+    }
+
+    pub async fn admin_jobs(&self, query: &JobQuery) -> Result<Vec<Job>> {
+        self.get(&with_query(routes::ADMIN_JOBS, query)?).await
+    }
+
+    pub async fn retry_job(&self, id: &str) -> Result<Job> {
+        self.send_json(Method::Post, &routes::path(routes::ADMIN_JOB_RETRY, &[id]), &()).await
+    }
+}
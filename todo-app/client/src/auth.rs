@@ -0,0 +1,38 @@
+use todo_api_types::{routes, AuthResponse, LoginRequest, RegisterRequest};
+
+use crate::{decode, Client, Method, Result};
+
+impl Client {
+    // Creates an account and logs into it
+    pub async fn register(&self, username: &str, email: &str, password: &str) -> Result<AuthResponse> {
+        let request = RegisterRequest {
+            username: username.to_string(),
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+        let response: AuthResponse = self.send_json(Method::Post, routes::REGISTER, &request).await?;
+        self.set_token(Some(response.token.clone()));
+        Ok(response)
+    }
+
+    pub async fn login(&self, email: &str, password: &str) -> Result<AuthResponse> {
+        let request = LoginRequest { email: email.to_string(), password: password.to_string() };
+        let response: AuthResponse = self.send_json(Method::Post, routes::LOGIN, &request).await?;
+        self.set_token(Some(response.token.clone()));
+        Ok(response)
+    }
+
+    // Swaps the token for a new one. Requests do this by themselves once the
+    // token is halfway to expiring
+    pub async fn refresh_token(&self) -> Result<AuthResponse> {
+        let response: AuthResponse = decode(self.dispatch(Method::Post, routes::TOKEN_REFRESH, None).await?)?;
+        self.set_token(Some(response.token.clone()));
+        Ok(response)
+    }
+
+    // Tokens can't be revoked, so this only forgets it
+    pub fn logout(&self) {
+        self.set_token(None);
+    }
+}
+
@@ -0,0 +1,73 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    // No response arrived, e.g. the server can't be reached
+    Transport(String),
+    // The request was malformed or failed validation, with the server's
+    // explanation when it gave one
+    BadRequest(String),
+    // Not logged in, or the token expired or is invalid
+    Unauthorized,
+    // Not allowed: the account is disabled, must change its password first,
+    // or lacks the role; or a password given to confirm an action was wrong
+    Forbidden,
+    NotFound,
+    // A unique value such as a username is taken, or the request contradicts
+    // the current state
+    Conflict,
+    PayloadTooLarge,
+    // Any other unsuccessful status
+    Status(u16),
+    // The response wasn't what this version of the client expects
+    Decode(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub(crate) fn from_response(status: u16, body: &[u8]) -> Error {
+        match status {
+            400 => Error::BadRequest(String::from_utf8_lossy(body).trim().to_string()),
+            401 => Error::Unauthorized,
+            403 => Error::Forbidden,
+            404 => Error::NotFound,
+            409 => Error::Conflict,
+            413 => Error::PayloadTooLarge,
+            status => Error::Status(status),
+        }
+    }
+
+    // The HTTP status behind the error, if there was a response
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::BadRequest(_) => Some(400),
+            Error::Unauthorized => Some(401),
+            Error::Forbidden => Some(403),
+            Error::NotFound => Some(404),
+            Error::Conflict => Some(409),
+            Error::PayloadTooLarge => Some(413),
+            Error::Status(status) => Some(*status),
+            Error::Transport(_) | Error::Decode(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Transport(message) => write!(f, "Network error: {}", message),
+            Error::BadRequest(message) if message.is_empty() => write!(f, "Bad request"),
+            Error::BadRequest(message) => write!(f, "Bad request: {}", message),
+            Error::Unauthorized => write!(f, "Not logged in, or the session expired"),
+            Error::Forbidden => write!(f, "Not allowed"),
+            Error::NotFound => write!(f, "Not found"),
+            Error::Conflict => write!(f, "Conflicts with existing data"),
+            Error::PayloadTooLarge => write!(f, "Too large"),
+            Error::Status(status) => write!(f, "Request failed with status {}", status),
+            Error::Decode(message) => write!(f, "Unexpected response: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,69 @@
+// Calendar feeds, app passwords for CalDAV, and webhooks
+
+use todo_api_types::{
+    routes, AppPassword, CalendarFeed, CreateAppPasswordRequest, CreateWebhookRequest, CreatedAppPassword,
+    CreatedWebhook, Webhook, WebhookDelivery, WebhookUpdate,
+};
+
+use crate::{Client, Method, Result};
+
+impl Client {
+    // Fails with `NotFound` until one is made with `regenerate_calendar_feed`
+    pub async fn calendar_feed(&self) -> Result<CalendarFeed> {
+        self.get(routes::CALENDAR_TOKEN).await
+    }
+
+    // Makes the feed, or replaces it so the old URL stops working
+    pub async fn regenerate_calendar_feed(&self) -> Result<CalendarFeed> {
+        self.send_json(Method::Post, routes::CALENDAR_TOKEN, &()).await
+    }
+
+    // The absolute URL of a feed, for calendar apps
+    pub fn calendar_feed_url(&self, feed: &CalendarFeed) -> String {
+        format!("{}{}", self.base_url(), feed.path)
+    }
+
+    pub async fn app_passwords(&self) -> Result<Vec<AppPassword>> {
+        self.get(routes::APP_PASSWORDS).await
+    }
+
+    // The password is only ever returned here
+    pub async fn create_app_password(&self, name: &str) -> Result<CreatedAppPassword> {
+        let request = CreateAppPasswordRequest { name: name.to_string() };
+        self.send_json(Method::Post, routes::APP_PASSWORDS, &request).await
+    }
+
+    pub async fn delete_app_password(&self, id: &str) -> Result<()> {
+        self.send(Method::Delete, &routes::path(routes::APP_PASSWORD, &[id]), None).await?;
+        Ok(())
+    }
+
+    pub async fn webhooks(&self) -> Result<Vec<Webhook>> {
+        self.get(routes::WEBHOOKS).await
+    }
+
+    // The secret is only ever returned here
+    pub async fn create_webhook(&self, webhook: &CreateWebhookRequest) -> Result<CreatedWebhook> {
+        self.send_json(Method::Post, routes::WEBHOOKS, webhook).await
+    }
+
+    pub async fn update_webhook(&self, id: &str, update: &WebhookUpdate) -> Result<Webhook> {
+        self.send_json(Method::Patch, &routes::path(routes::WEBHOOK, &[id]), update).await
+    }
+
+    pub async fn delete_webhook(&self, id: &str) -> Result<()> {
+        self.send(Method::Delete, &routes::path(routes::WEBHOOK, &[id]), None).await?;
+        Ok(())
+    }
+
+    // The most recent deliveries, newest first
+    pub async fn webhook_deliveries(&self, id: &str) -> Result<Vec<WebhookDelivery>> {
+        self.get(&routes::path(routes::WEBHOOK_DELIVERIES, &[id])).await
+    }
+
+    pub async fn retry_webhook_delivery(&self, id: &str, delivery_id: &str) -> Result<()> {
+        let path = routes::path(routes::WEBHOOK_DELIVERY_RETRY, &[id, delivery_id]);
+        self.send(Method::Post, &path, None).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+// A typed async client for the backend's API. Requests and responses are the
+// `todo-api-types` structs the backend itself uses, and requests go through a
+// `Transport`, reqwest by default, so the same client works natively and in
+// the browser. After logging in, the client keeps the token and renews it
+// once half its lifetime has passed.
+//
+// Methods are grouped by resource, one module each; `send` reaches any route
+// that doesn't have a method yet
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures::{stream, Stream, TryStreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+mod account;
+mod admin;
+mod auth;
+mod error;
+mod integrations;
+mod notifications;
+mod todos;
+pub mod transport;
+
+pub use error::{Error, Result};
+pub use todo_api_types as types;
+#[cfg(all(feature = "gloo", target_arch = "wasm32"))]
+pub use transport::GlooTransport;
+#[cfg(feature = "reqwest")]
+pub use transport::ReqwestTransport;
+pub use transport::{HttpRequest, HttpResponse, Method, Transport};
+
+#[derive(Clone)]
+pub struct Client {
+    // Without a trailing slash, e.g. "http://127.0.0.1:3001"
+    base_url: String,
+    transport: Arc<dyn Transport>,
+    token: Arc<Mutex<Option<String>>>,
+}
+
+// A request body along with its content type
+pub struct Body {
+    content_type: String,
+    bytes: Vec<u8>,
+}
+
+impl Body {
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> Result<Body> {
+        let bytes = serde_json::to_vec(value).map_err(|e| Error::Decode(e.to_string()))?;
+        Ok(Body { content_type: "application/json".to_string(), bytes })
+    }
+
+    // A multipart form of text fields
+    pub fn form(fields: &[(&str, &str)]) -> Body {
+        // Any boundary works as long as no field contains it
+        let mut boundary = "todo-client-boundary".to_string();
+        while fields.iter().any(|(_, value)| value.contains(&boundary)) {
+            boundary.push('-');
+        }
+
+        let mut bytes = Vec::new();
+        for (name, value) in fields {
+            bytes.extend_from_slice(
+                format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", boundary, name, value)
+                    .as_bytes(),
+            );
+        }
+        bytes.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+        Body { content_type: format!("multipart/form-data; boundary={}", boundary), bytes }
+    }
+}
+
+// The claims of a token that the client looks at; the server checks the rest
+#[derive(Deserialize)]
+struct TokenTimes {
+    exp: i64,
+    #[serde(default)]
+    iat: i64,
+}
+
+// Past half their lifetime, tokens are renewed. Tokens from before the server
+// recorded the issue time count as due. Expired tokens can't be renewed
+fn refresh_due(token: &str, now: i64) -> bool {
+    let times = token
+        .split('.')
+        .nth(1)
+        .and_then(|payload| URL_SAFE_NO_PAD.decode(payload).ok())
+        .and_then(|payload| serde_json::from_slice::<TokenTimes>(&payload).ok());
+
+    match times {
+        Some(TokenTimes { exp, iat }) => now < exp && now >= iat + (exp - iat) / 2,
+        None => false,
+    }
+}
+
+pub(crate) fn decode<T: DeserializeOwned>(bytes: Vec<u8>) -> Result<T> {
+    serde_json::from_slice(&bytes).map_err(|e| Error::Decode(e.to_string()))
+}
+
+// Adds a query string to a path
+pub(crate) fn with_query<Q: Serialize + ?Sized>(path: &str, query: &Q) -> Result<String> {
+    let query = serde_urlencoded::to_string(query).map_err(|e| Error::Decode(e.to_string()))?;
+    Ok(if query.is_empty() { path.to_string() } else { format!("{}?{}", path, query) })
+}
+
+// Every item of a list the server hands out a page at a time, by offset,
+// fetching pages as the stream is read. An empty page ends the list
+pub(crate) fn offset_pages<T, F, Fut>(fetch_page: F) -> impl Stream<Item = Result<T>>
+where
+    F: Fn(i64) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    stream::try_unfold(Some(0), move |offset| {
+        let page = offset.map(&fetch_page);
+        async move {
+            let (Some(offset), Some(page)) = (offset, page) else {
+                return Ok(None);
+            };
+            let items = page.await?;
+            let next = (!items.is_empty()).then(|| offset + items.len() as i64);
+            Ok(Some((stream::iter(items.into_iter().map(Ok)), next)))
+        }
+    })
+    .try_flatten()
+}
+
+impl Client {
+    #[cfg(feature = "reqwest")]
+    pub fn new(base_url: &str) -> Client {
+        Client::with_transport(base_url, ReqwestTransport::default())
+    }
+
+    pub fn with_transport(base_url: &str, transport: impl Transport + 'static) -> Client {
+        Client {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            transport: Arc::new(transport),
+            token: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    // The current token, e.g. to store it between runs
+    pub fn token(&self) -> Option<String> {
+        self.token.lock().unwrap().clone()
+    }
+
+    // Uses a token from an earlier login, or `None` to forget it
+    pub fn set_token(&self, token: Option<String>) {
+        *self.token.lock().unwrap() = token;
+    }
+
+    // Sends a request to any route, authenticated if there is a token, and
+    // returns the body of a successful response. `path` is a route with its
+    // parameters filled in, e.g. `routes::path(routes::TODO, &[id])`
+    pub async fn send(&self, method: Method, path: &str, body: Option<Body>) -> Result<Vec<u8>> {
+        if self.token().is_some_and(|token| refresh_due(&token, chrono::Utc::now().timestamp())) {
+            // Best effort: if renewing fails, the current token is still good
+            let _ = self.refresh_token().await;
+        }
+        self.dispatch(method, path, body).await
+    }
+
+    // Without renewing the token first
+    pub(crate) async fn dispatch(&self, method: Method, path: &str, body: Option<Body>) -> Result<Vec<u8>> {
+        let mut headers = Vec::new();
+        if let Some(token) = self.token() {
+            headers.push(("Authorization", format!("Bearer {}", token)));
+        }
+        let body = body.map(|body| {
+            headers.push(("Content-Type", body.content_type));
+            body.bytes
+        });
+
+        let request = HttpRequest { method, url: format!("{}{}", self.base_url, path), headers, body };
+        let response = self.transport.send(request).await.map_err(Error::Transport)?;
+
+        if (200..300).contains(&response.status) {
+            Ok(response.body)
+        } else {
+            Err(Error::from_response(response.status, &response.body))
+        }
+    }
+
+    pub(crate) async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        decode(self.send(Method::Get, path, None).await?)
+    }
+
+    pub(crate) async fn send_json<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: &B,
+    ) -> Result<T> {
+        decode(self.send(method, path, Some(Body::json(body)?)).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn token_with(claims: &str) -> String {
+        format!("header.{}.signature", URL_SAFE_NO_PAD.encode(claims))
+    }
+
+    #[test]
+    fn tokens_are_due_from_halfway_until_they_expire() {
+        let token = token_with(r#"{"iat":1000,"exp":2000}"#);
+        assert!(!refresh_due(&token, 1499));
+        assert!(refresh_due(&token, 1500));
+        assert!(refresh_due(&token, 1999));
+        assert!(!refresh_due(&token, 2000));
+
+        // Without an issue time, any token that hasn't expired is due
+        assert!(refresh_due(&token_with(r#"{"exp":1700003600}"#), 1_700_000_000));
+        assert!(!refresh_due("not-a-token", 100));
+    }
+
+    #[test]
+    fn pages_are_fetched_until_one_is_empty() {
+        let offsets = Mutex::new(Vec::new());
+        let items: Vec<i64> = futures::executor::block_on(
+            offset_pages(|offset| {
+                offsets.lock().unwrap().push(offset);
+                async move { Ok((offset..5).take(2).collect()) }
+            })
+            .try_collect(),
+        )
+        .unwrap();
+
+        assert_eq!(items, [0, 1, 2, 3, 4]);
+        assert_eq!(*offsets.lock().unwrap(), [0, 2, 4, 5]);
+    }
+
+    #[test]
+    fn failed_pages_end_the_stream_with_the_error() {
+        let items: Vec<Result<i64>> = futures::executor::block_on(
+            offset_pages(|offset| async move { if offset == 0 { Ok(vec![1, 2]) } else { Err(Error::Forbidden) } })
+                .collect(),
+        );
+        assert!(matches!(items.as_slice(), [Ok(1), Ok(2), Err(Error::Forbidden)]));
+    }
+
+    #[test]
+    fn form_boundaries_avoid_the_fields() {
+        let body = Body::form(&[("file", "--todo-client-boundary"), ("format", "csv")]);
+        assert_eq!(body.content_type, "multipart/form-data; boundary=todo-client-boundary-");
+
+        let text = String::from_utf8(body.bytes).unwrap();
+        assert!(text.starts_with("--todo-client-boundary-\r\nContent-Disposition: form-data; name=\"file\"\r\n"));
+        assert!(text.ends_with("name=\"format\"\r\n\r\ncsv\r\n--todo-client-boundary---\r\n"));
+    }
+}
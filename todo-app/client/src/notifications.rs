@@ -0,0 +1,44 @@
+// Reminder and digest settings, and Web Push subscriptions
+
+use todo_api_types::{
+    routes, DigestPreviewQuery, NotificationPreferences, NotificationPreferencesUpdate, PushPublicKey,
+    PushSubscriptionRequest, PushUnsubscribeRequest,
+};
+
+use crate::{with_query, Body, Client, Error, Method, Result};
+
+impl Client {
+    pub async fn notification_preferences(&self) -> Result<NotificationPreferences> {
+        self.get(routes::NOTIFICATION_PREFERENCES).await
+    }
+
+    pub async fn update_notification_preferences(
+        &self,
+        update: &NotificationPreferencesUpdate,
+    ) -> Result<NotificationPreferences> {
+        self.send_json(Method::Patch, routes::NOTIFICATION_PREFERENCES, update).await
+    }
+
+    // The digest as it would be sent now, as "html" or "text"
+    pub async fn digest_preview(&self, format: &str) -> Result<String> {
+        let query = DigestPreviewQuery { format: Some(format.to_string()) };
+        let body = self.send(Method::Get, &with_query(routes::DIGEST_PREVIEW, &query)?, None).await?;
+        String::from_utf8(body).map_err(|e| Error::Decode(e.to_string()))
+    }
+
+    // Fails with `NotFound` when the server doesn't have Web Push configured
+    pub async fn push_public_key(&self) -> Result<PushPublicKey> {
+        self.get(routes::PUSH_PUBLIC_KEY).await
+    }
+
+    pub async fn subscribe_push(&self, subscription: &PushSubscriptionRequest) -> Result<()> {
+        self.send(Method::Post, routes::PUSH_SUBSCRIPTIONS, Some(Body::json(subscription)?)).await?;
+        Ok(())
+    }
+
+    pub async fn unsubscribe_push(&self, endpoint: &str) -> Result<()> {
+        let request = PushUnsubscribeRequest { endpoint: endpoint.to_string() };
+        self.send(Method::Delete, routes::PUSH_SUBSCRIPTIONS, Some(Body::json(&request)?)).await?;
+        Ok(())
+    }
+}
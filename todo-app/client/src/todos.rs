@@ -0,0 +1,85 @@
+use todo_api_types::{
+    routes, BulkRequest, BulkResponse, CreateTodoRequest, CsvMapping, DeleteQuery, ImportReport, MoveTodoRequest,
+    Todo, TodoEvent, TodoQuery, TodoUpdate,
+};
+
+use crate::{decode, with_query, Body, Client, Method, Result};
+
+impl Client {
+    // In list order, without the trash
+    pub async fn todos(&self) -> Result<Vec<Todo>> {
+        self.get(routes::TODOS).await
+    }
+
+    // Most recently deleted first
+    pub async fn trash(&self) -> Result<Vec<Todo>> {
+        self.get(&with_query(routes::TODOS, &TodoQuery { deleted: true })?).await
+    }
+
+    pub async fn todo(&self, id: &str) -> Result<Todo> {
+        self.get(&routes::path(routes::TODO, &[id])).await
+    }
+
+    // New todos go to the top of the list
+    pub async fn create_todo(&self, todo: &CreateTodoRequest) -> Result<Todo> {
+        self.send_json(Method::Post, routes::TODOS, todo).await
+    }
+
+    pub async fn update_todo(&self, id: &str, update: &TodoUpdate) -> Result<Todo> {
+        self.send_json(Method::Patch, &routes::path(routes::TODO, &[id]), update).await
+    }
+
+    // Moves a todo to the trash
+    pub async fn delete_todo(&self, id: &str) -> Result<()> {
+        self.send(Method::Delete, &routes::path(routes::TODO, &[id]), None).await?;
+        Ok(())
+    }
+
+    // Deletes a todo in the trash for good
+    pub async fn purge_todo(&self, id: &str) -> Result<()> {
+        let path = with_query(&routes::path(routes::TODO, &[id]), &DeleteQuery { permanent: true })?;
+        self.send(Method::Delete, &path, None).await?;
+        Ok(())
+    }
+
+    pub async fn restore_todo(&self, id: &str) -> Result<Todo> {
+        self.send_json(Method::Post, &routes::path(routes::TODO_RESTORE, &[id]), &()).await
+    }
+
+    pub async fn todo_history(&self, id: &str) -> Result<Vec<TodoEvent>> {
+        self.get(&routes::path(routes::TODO_HISTORY, &[id])).await
+    }
+
+    // Fails with `Conflict` if the neighbours aren't next to each other in that order
+    pub async fn move_todo(&self, id: &str, neighbours: &MoveTodoRequest) -> Result<Todo> {
+        self.send_json(Method::Post, &routes::path(routes::TODO_MOVE, &[id]), neighbours).await
+    }
+
+    // Fails with `PayloadTooLarge` if it targets too many todos, in which case none is changed
+    pub async fn bulk_update(&self, request: &BulkRequest) -> Result<BulkResponse> {
+        self.send_json(Method::Post, routes::TODOS_BULK, request).await
+    }
+
+    // `format` is "json", "csv", "md" or "todotxt"
+    pub async fn export_todos(&self, format: &str) -> Result<Vec<u8>> {
+        self.send(Method::Get, &with_query(routes::EXPORT, &[("format", format)])?, None).await
+    }
+
+    // `format` is "todotxt", "csv", "todoist" or "taskwarrior"; CSV files need
+    // a `mapping`. A dry run reports what would be created without saving it
+    pub async fn import_todos(
+        &self,
+        format: &str,
+        content: &str,
+        mapping: Option<&CsvMapping>,
+        dry_run: bool,
+    ) -> Result<ImportReport> {
+        let mapping = mapping.map(serde_json::to_string).transpose().map_err(|e| crate::Error::Decode(e.to_string()))?;
+        let dry_run = dry_run.to_string();
+        let mut fields = vec![("format", format), ("file", content), ("dry_run", dry_run.as_str())];
+        if let Some(mapping) = &mapping {
+            fields.push(("mapping", mapping));
+        }
+        decode(self.send(Method::Post, routes::IMPORT, Some(Body::form(&fields))).await?)
+    }
+}
@@ -0,0 +1,121 @@
+// How requests reach the server. The client only builds requests and reads
+// responses, so it runs wherever a `Transport` does; in the browser its
+// futures need not be `Send`
+
+use async_trait::async_trait;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Get,
+    Post,
+    Patch,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Patch => "PATCH",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct HttpRequest {
+    pub method: Method,
+    // Absolute, query string included
+    pub url: String,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+// Fails only when no response arrived; unsuccessful statuses are responses too
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Transport: Send + Sync {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String>;
+}
+
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    // For a client with its own timeouts, proxy or TLS settings
+    pub fn new(client: reqwest::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Transport for ReqwestTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let method = match request.method {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+            Method::Patch => reqwest::Method::PATCH,
+            Method::Delete => reqwest::Method::DELETE,
+        };
+
+        let mut builder = self.client.request(method, &request.url);
+        for (name, value) in request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+
+        let response = builder.send().await.map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        Ok(HttpResponse { status, body: body.to_vec() })
+    }
+}
+
+#[cfg(all(feature = "gloo", target_arch = "wasm32"))]
+#[derive(Clone, Copy, Default)]
+pub struct GlooTransport;
+
+#[cfg(all(feature = "gloo", target_arch = "wasm32"))]
+#[async_trait(?Send)]
+impl Transport for GlooTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        use gloo_net::http::{Method as GlooMethod, RequestBuilder};
+
+        let method = match request.method {
+            Method::Get => GlooMethod::GET,
+            Method::Post => GlooMethod::POST,
+            Method::Patch => GlooMethod::PATCH,
+            Method::Delete => GlooMethod::DELETE,
+        };
+
+        let mut builder = RequestBuilder::new(&request.url).method(method);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        let request = match request.body {
+            Some(body) => builder.body(js_sys::Uint8Array::from(body.as_slice())),
+            None => builder.build(),
+        }
+        .map_err(|e| e.to_string())?;
+
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        let body = response.binary().await.map_err(|e| e.to_string())?;
+        Ok(HttpResponse { status, body })
+    }
+}
@@ -0,0 +1,225 @@
+// Drives the client against the backend's router in-process, through a
+// transport that hands requests straight to it; no server or database is needed
+
+use async_trait::async_trait;
+use axum::{
+    body::{to_bytes, Body},
+    http::Request,
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures::TryStreamExt;
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+
+use todo_backend::{app, auth, config::Config, db, AppState};
+use todo_client::{
+    types::{routes, CreateTodoRequest, CreateWebhookRequest, NotificationPreferencesUpdate, TodoUpdate},
+    Client, Error, HttpRequest, HttpResponse, Transport,
+};
+
+struct RouterTransport {
+    router: Router,
+    // Every request sent, as "METHOD /path"
+    sent: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Transport for RouterTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let path = request.url.strip_prefix("http://todo.test").ok_or("unexpected host")?;
+        self.sent.lock().unwrap().push(format!("{} {}", request.method.as_str(), path));
+        let mut builder = Request::builder().method(request.method.as_str()).uri(path);
+        for (name, value) in request.headers {
+            builder = builder.header(name, value);
+        }
+        let request = builder.body(Body::from(request.body.unwrap_or_default())).map_err(|e| e.to_string())?;
+
+        let response = self.router.clone().oneshot(request).await.map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        let body = to_bytes(response.into_body(), usize::MAX).await.map_err(|e| e.to_string())?;
+        Ok(HttpResponse { status, body: body.to_vec() })
+    }
+}
+
+fn config() -> Config {
+    let mut config = Config::default();
+    config.auth.jwt_secret = "a-secret-for-the-client-tests".to_string();
+    // The lowest cost bcrypt accepts, to keep registering fast
+    config.auth.bcrypt_cost = 4;
+    config
+}
+
+// A client of the app, and the requests it has sent
+fn client_of(state: AppState) -> (Client, Arc<Mutex<Vec<String>>>) {
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let transport = RouterTransport { router: app(state), sent: sent.clone() };
+    (Client::with_transport("http://todo.test/", transport), sent)
+}
+
+fn client() -> Client {
+    client_of(AppState::in_memory(config()).0).0
+}
+
+// For the routes the repositories don't cover yet
+async fn sqlite_client() -> (Client, AppState) {
+    let state = AppState::sqlite(config()).await;
+    (client_of(state.clone()).0, state)
+}
+
+fn new_todo(title: &str) -> CreateTodoRequest {
+    CreateTodoRequest { title: title.to_string(), ..Default::default() }
+}
+
+#[tokio::test]
+async fn logging_in_keeps_the_token() {
+    let client = client();
+    assert!(matches!(client.me().await, Err(Error::Unauthorized)));
+
+    client.register("alice", "alice@example.com", "password").await.unwrap();
+    client.logout();
+    assert!(client.token().is_none());
+
+    client.login("alice@example.com", "password").await.unwrap();
+    assert!(client.token().is_some());
+    assert_eq!(client.me().await.unwrap().username, "alice");
+}
+
+#[tokio::test]
+async fn failures_map_to_typed_errors() {
+    let client = client();
+    assert!(matches!(client.login("nobody@example.com", "password").await, Err(Error::Unauthorized)));
+
+    client.register("alice", "alice@example.com", "password").await.unwrap();
+
+    assert!(matches!(client.todo("no-such-todo").await, Err(Error::NotFound)));
+    assert!(matches!(client.change_password("wrong", "a new password").await, Err(Error::Forbidden)));
+    assert!(matches!(
+        client.register("alice", "alice@example.com", "password").await,
+        Err(Error::Conflict)
+    ));
+}
+
+#[tokio::test]
+async fn todo_lifecycle() {
+    let client = client();
+    client.register("alice", "alice@example.com", "password").await.unwrap();
+
+    let todo = client.create_todo(&new_todo("Buy milk")).await.unwrap();
+    let update = TodoUpdate { completed: Some(true), ..Default::default() };
+    assert!(client.update_todo(&todo.id, &update).await.unwrap().completed);
+    assert_eq!(client.todos().await.unwrap().len(), 1);
+
+    client.delete_todo(&todo.id).await.unwrap();
+    assert!(client.todos().await.unwrap().is_empty());
+    assert_eq!(client.trash().await.unwrap()[0].id, todo.id);
+
+    client.restore_todo(&todo.id).await.unwrap();
+    client.delete_todo(&todo.id).await.unwrap();
+    client.purge_todo(&todo.id).await.unwrap();
+    assert!(client.trash().await.unwrap().is_empty());
+    assert!(matches!(client.todo(&todo.id).await, Err(Error::NotFound)));
+}
+
+#[tokio::test]
+async fn tokens_can_be_refreshed() {
+    let client = client();
+    client.register("alice", "alice@example.com", "password").await.unwrap();
+
+    client.refresh_token().await.unwrap();
+    assert!(client.me().await.is_ok());
+
+    client.set_token(Some("not-a-token".to_string()));
+    assert!(matches!(client.refresh_token().await, Err(Error::Unauthorized)));
+}
+
+#[tokio::test]
+async fn import_forms_reach_the_server() {
+    let client = client();
+    client.register("alice", "alice@example.com", "password").await.unwrap();
+
+    // Without a mapping the CSV is rejected, which the server can only tell
+    // once it has read every field of the form
+    let result = client.import_todos("csv", "title\nBuy milk\n", None, true).await;
+    assert!(matches!(result, Err(Error::Status(422))));
+
+    let result = client.import_todos("spreadsheet", "title\n", None, true).await;
+    assert!(matches!(result, Err(Error::BadRequest(_))));
+}
+
+#[tokio::test]
+async fn tokens_halfway_to_expiring_are_renewed_first() {
+    let (client, sent) = client_of(AppState::in_memory(config()).0);
+    client.register("alice", "alice@example.com", "password").await.unwrap();
+
+    // A fresh token is used as it is
+    sent.lock().unwrap().clear();
+    client.me().await.unwrap();
+    assert_eq!(*sent.lock().unwrap(), [format!("GET {}", routes::ME)]);
+
+    // Issued an hour ago and good for another minute. Its signature is made
+    // up, so the server turns down both requests, but the renewal comes first
+    let now = chrono::Utc::now().timestamp();
+    let claims = URL_SAFE_NO_PAD.encode(format!(r#"{{"sub":"user","iat":{},"exp":{}}}"#, now - 3600, now + 60));
+    client.set_token(Some(format!("header.{}.signature", claims)));
+
+    sent.lock().unwrap().clear();
+    assert!(matches!(client.me().await, Err(Error::Unauthorized)));
+    assert_eq!(*sent.lock().unwrap(), [format!("POST {}", routes::TOKEN_REFRESH), format!("GET {}", routes::ME)]);
+}
+
+#[tokio::test]
+async fn admin_users_are_read_a_page_at_a_time() {
+    let (client, state) = sqlite_client().await;
+    for name in ["bob", "carol"] {
+        client.register(name, &format!("{}@example.com", name), "password").await.unwrap();
+    }
+    client.register("admin", "admin@example.com", "password").await.unwrap();
+    db::query("UPDATE users SET role = $1 WHERE id = $2")
+        .bind(auth::ROLE_ADMIN)
+        .bind(client.me().await.unwrap().id)
+        .execute(state.db())
+        .await
+        .unwrap();
+
+    // Newest first
+    let users: Vec<_> = client.admin_users(None).try_collect().await.unwrap();
+    let usernames: Vec<_> = users.iter().map(|admin_user| admin_user.user.username.as_str()).collect();
+    assert_eq!(usernames, ["admin", "carol", "bob"]);
+
+    let page = client.admin_users_page(None, 2).await.unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].user.username, "bob");
+    let matching: Vec<_> = client.admin_users(Some("car")).try_collect().await.unwrap();
+    assert_eq!(matching.len(), 1);
+
+    let user = client.disable_user(&page[0].user.id).await.unwrap();
+    assert!(user.disabled_at.is_some());
+    assert!(client.enable_user(&page[0].user.id).await.unwrap().disabled_at.is_none());
+}
+
+#[tokio::test]
+async fn other_resources_round_trip() {
+    let (client, _state) = sqlite_client().await;
+    client.register("alice", "alice@example.com", "password").await.unwrap();
+
+    let update = NotificationPreferencesUpdate { email_reminders: Some(false), ..Default::default() };
+    assert!(!client.update_notification_preferences(&update).await.unwrap().email_reminders);
+    assert!(!client.notification_preferences().await.unwrap().email_reminders);
+
+    let created = client.create_app_password("phone").await.unwrap();
+    assert!(!created.password.is_empty());
+    assert_eq!(client.app_passwords().await.unwrap()[0].id, created.id);
+    client.delete_app_password(&created.id).await.unwrap();
+    assert!(client.app_passwords().await.unwrap().is_empty());
+
+    let webhook = CreateWebhookRequest {
+        url: "https://example.com/hook".to_string(),
+        events: vec!["todo.created".to_string()],
+        secret: None,
+    };
+    let created = client.create_webhook(&webhook).await.unwrap();
+    assert_eq!(client.webhooks().await.unwrap()[0].id, created.webhook.id);
+    client.delete_webhook(&created.webhook.id).await.unwrap();
+    assert!(matches!(client.webhook_deliveries(&created.webhook.id).await, Err(Error::NotFound)));
+}
@@ -0,0 +1,251 @@
+use chrono::Local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::prelude::*;
+use todo_api_types::{routes, AdminStats, AdminUser, AdminUserUpdate};
+use crate::{
+    services::api::ApiService,
+    types::Route,
+};
+
+// Usage numbers and user management, only reachable by admins
+#[function_component(AdminPanel)]
+pub fn admin_panel() -> Html {
+    let stats = use_state(|| None::<AdminStats>);
+    let users = use_state(Vec::<AdminUser>::new);
+    let error = use_state(|| None::<String>);
+
+    let search_ref = use_node_ref();
+
+    // Load stats and the newest users on mount
+    {
+        let stats = stats.clone();
+        let users = users.clone();
+        let error = error.clone();
+
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = match ApiService::get_admin_stats().await {
+                    Ok(fetched_stats) => ApiService::get_admin_users("")
+                        .await
+                        .map(|fetched_users| (fetched_stats, fetched_users)),
+                    Err(err) => Err(err),
+                };
+
+                match result {
+                    Ok((fetched_stats, fetched_users)) => {
+                        stats.set(Some(fetched_stats));
+                        users.set(fetched_users);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+            || ()
+        });
+    }
+
+    let handle_search = {
+        let users = users.clone();
+        let error = error.clone();
+        let search_ref = search_ref.clone();
+
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+
+            let Some(input) = search_ref.cast::<HtmlInputElement>() else {
+                return;
+            };
+            let query = input.value();
+            let users = users.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::get_admin_users(&query).await {
+                    Ok(fetched_users) => {
+                        users.set(fetched_users);
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    // Replaces the user in the list with the server's copy after a change
+    let replace_user = {
+        let users = users.clone();
+        let error = error.clone();
+
+        move |result: Result<AdminUser, String>| match result {
+            Ok(updated) => {
+                let current_users = (*users)
+                    .iter()
+                    .map(|user| if user.user.id == updated.user.id { updated.clone() } else { user.clone() })
+                    .collect();
+                users.set(current_users);
+                error.set(None);
+            }
+            Err(err) => error.set(Some(err)),
+        }
+    };
+
+    html! {
+        <div class="max-w-6xl mx-auto px-4">
+            <div class="mb-8">
+                <Link<Route> to={Route::Home} classes="text-indigo-600 hover:text-indigo-800">
+                    {"← Back to todos"}
+                </Link<Route>>
+            </div>
+
+            <h1 class="text-2xl font-bold text-gray-900 mb-6">{"Administration"}</h1>
+
+            {if let Some(error_msg) = error.as_ref() {
+                html! {
+                    <div class="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4">
+                        {error_msg}
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+
+            {if let Some(current) = stats.as_ref() {
+                let cards = [
+                    ("Users", current.users, format!("{} new this week", current.new_users)),
+                    ("Active users", current.active_users, "logged in within 30 days".to_string()),
+                    ("Disabled users", current.disabled_users, format!("{} admins", current.admins)),
+                    ("Todos", current.todos, format!("{} completed, {} in the trash", current.completed_todos, current.todos_in_trash)),
+                    ("New todos", current.new_todos, "this week".to_string()),
+                    ("Jobs", current.pending_jobs, format!("pending, {} failed", current.failed_jobs)),
+                ];
+
+                html! {
+                    <div class="grid grid-cols-2 md:grid-cols-3 gap-4 mb-6">
+                        {for cards.into_iter().map(|(label, value, detail)| html! {
+                            <div class="bg-white shadow rounded-lg p-4">
+                                <p class="text-sm text-gray-500">{label}</p>
+                                <p class="text-2xl font-bold text-gray-900">{value}</p>
+                                <p class="text-xs text-gray-400">{detail}</p>
+                            </div>
+                        })}
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+
+            <div class="bg-white shadow rounded-lg p-6">
+                <h2 class="text-lg font-semibold text-gray-900 mb-4">{"Users"}</h2>
+                <form onsubmit={handle_search} class="flex space-x-2 mb-4">
+                    <input
+                        ref={search_ref}
+                        type="search"
+                        placeholder="Search by username, email or name"
+                        class="flex-1 px-3 py-2 border border-gray-300 rounded-md"
+                    />
+                    <button
+                        type="submit"
+                        class="bg-indigo-600 hover:bg-indigo-700 text-white font-bold py-2 px-4 rounded"
+                    >
+                        {"Search"}
+                    </button>
+                </form>
+                <table class="w-full text-sm">
+                    <thead>
+                        <tr class="text-left text-gray-500 border-b">
+                            <th class="py-2">{"User"}</th>
+                            <th class="py-2">{"Role"}</th>
+                            <th class="py-2">{"Status"}</th>
+                            <th class="py-2">{"Todos"}</th>
+                            <th class="py-2">{"Last login"}</th>
+                            <th class="py-2"></th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {for users.iter().map(|admin_user| {
+                            let id = admin_user.user.id.clone();
+                            let is_admin = admin_user.user.role == "admin";
+                            let is_disabled = admin_user.disabled_at.is_some();
+
+                            let toggle_disabled = {
+                                let id = id.clone();
+                                let replace_user = replace_user.clone();
+                                Callback::from(move |_| {
+                                    let id = id.clone();
+                                    let replace_user = replace_user.clone();
+                                    let action = if is_disabled { routes::ADMIN_USER_ENABLE } else { routes::ADMIN_USER_DISABLE };
+                                    wasm_bindgen_futures::spawn_local(async move {
+                                        replace_user(ApiService::admin_user_action(&id, action).await);
+                                    });
+                                })
+                            };
+
+                            let require_reset = {
+                                let id = id.clone();
+                                let replace_user = replace_user.clone();
+                                Callback::from(move |_| {
+                                    let id = id.clone();
+                                    let replace_user = replace_user.clone();
+                                    wasm_bindgen_futures::spawn_local(async move {
+                                        replace_user(ApiService::admin_user_action(&id, routes::ADMIN_USER_RESET_PASSWORD).await);
+                                    });
+                                })
+                            };
+
+                            let toggle_role = {
+                                let id = id.clone();
+                                let replace_user = replace_user.clone();
+                                Callback::from(move |_| {
+                                    let id = id.clone();
+                                    let replace_user = replace_user.clone();
+                                    let role = if is_admin { "user" } else { "admin" };
+                                    wasm_bindgen_futures::spawn_local(async move {
+                                        let update = AdminUserUpdate { role: Some(role.to_string()) };
+                                        replace_user(ApiService::update_admin_user(&id, update).await);
+                                    });
+                                })
+                            };
+
+                            html! {
+                                <tr key={id} class="border-b">
+                                    <td class="py-2">
+                                        <p class="font-medium text-gray-900">{&admin_user.user.username}</p>
+                                        <p class="text-gray-500">{&admin_user.user.email}</p>
+                                    </td>
+                                    <td class="py-2">{&admin_user.user.role}</td>
+                                    <td class="py-2">
+                                        {if is_disabled {
+                                            html! { <span class="text-red-600">{"Disabled"}</span> }
+                                        } else if admin_user.user.password_reset_required {
+                                            html! { <span class="text-yellow-600">{"Must reset password"}</span> }
+                                        } else {
+                                            html! { <span class="text-green-600">{"Active"}</span> }
+                                        }}
+                                    </td>
+                                    <td class="py-2">{admin_user.todo_count}</td>
+                                    <td class="py-2 text-gray-500">
+                                        {admin_user
+                                            .last_login_at
+                                            .map(|at| at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+                                            .unwrap_or_else(|| "Never".to_string())}
+                                    </td>
+                                    <td class="py-2 space-x-2 text-right whitespace-nowrap">
+                                        <button onclick={toggle_disabled} class="text-red-600 hover:text-red-800">
+                                            {if is_disabled { "Enable" } else { "Disable" }}
+                                        </button>
+                                        <button onclick={require_reset} class="text-indigo-600 hover:text-indigo-800">
+                                            {"Require password reset"}
+                                        </button>
+                                        <button onclick={toggle_role} class="text-indigo-600 hover:text-indigo-800">
+                                            {if is_admin { "Remove admin" } else { "Make admin" }}
+                                        </button>
+                                    </td>
+                                </tr>
+                            }
+                        })}
+                    </tbody>
+                </table>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,420 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use todo_api_types::{CsvMapping, ImportReport, ImportedTodo};
+use crate::{
+    services::api::ApiService,
+    types::Route,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Step {
+    ChooseFile,
+    MapColumns,
+    Preview,
+    Done,
+}
+
+const FORMATS: [(&str, &str); 4] = [
+    ("todotxt", "todo.txt"),
+    ("csv", "CSV (choose columns)"),
+    ("todoist", "Todoist CSV export"),
+    ("taskwarrior", "Taskwarrior JSON export"),
+];
+
+// Column names from the first line of a CSV file, honouring quoted fields
+fn csv_headers(content: &str) -> Vec<String> {
+    let line = content.lines().next().unwrap_or_default();
+    let mut headers = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => headers.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    headers.push(current.trim().to_string());
+
+    headers.into_iter().filter(|h| !h.is_empty()).collect()
+}
+
+fn read_select(e: &Event) -> String {
+    let select: HtmlSelectElement = e.target_unchecked_into();
+    select.value()
+}
+
+fn todo_rows(todos: &[ImportedTodo]) -> Html {
+    html! {
+        <ul class="divide-y divide-gray-200">
+            {for todos.iter().map(|todo| html! {
+                <li class="py-2">
+                    <span class={if todo.completed { "line-through text-gray-500" } else { "text-gray-900" }}>
+                        {&todo.title}
+                    </span>
+                    {if let Some(description) = &todo.description {
+                        html! { <p class="text-xs text-gray-500">{description}</p> }
+                    } else {
+                        html! {}
+                    }}
+                </li>
+            })}
+        </ul>
+    }
+}
+
+#[function_component(ImportWizard)]
+pub fn import_wizard() -> Html {
+    let step = use_state(|| Step::ChooseFile);
+    let format = use_state(|| "todotxt".to_string());
+    let content = use_state(String::new);
+    let headers = use_state(Vec::<String>::new);
+    let mapping = use_state(|| None::<CsvMapping>);
+    let report = use_state(|| None::<ImportReport>);
+    let loading = use_state(|| false);
+    let error = use_state(|| None::<String>);
+
+    let file_ref = use_node_ref();
+
+    // Sends the file to the server; a dry run fills the preview, a real run finishes the wizard
+    let run_import = {
+        let format = format.clone();
+        let content = content.clone();
+        let mapping = mapping.clone();
+        let report = report.clone();
+        let step = step.clone();
+        let loading = loading.clone();
+        let error = error.clone();
+
+        move |file_content: String, dry_run: bool| {
+            let format = (*format).clone();
+            let mapping = (*mapping).clone();
+            let report = report.clone();
+            let step = step.clone();
+            let loading = loading.clone();
+            let error = error.clone();
+            content.set(file_content.clone());
+            loading.set(true);
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let csv_mapping = if format == "csv" { mapping.as_ref() } else { None };
+                match ApiService::import_todos(&format, &file_content, csv_mapping, dry_run).await {
+                    Ok(import_report) => {
+                        report.set(Some(import_report));
+                        step.set(if dry_run { Step::Preview } else { Step::Done });
+                        error.set(None);
+                    }
+                    Err(err) => {
+                        error.set(Some(err));
+                    }
+                }
+                loading.set(false);
+            });
+        }
+    };
+
+    let handle_format_change = {
+        let format = format.clone();
+        Callback::from(move |e: Event| format.set(read_select(&e)))
+    };
+
+    let handle_file_next = {
+        let format = format.clone();
+        let headers = headers.clone();
+        let mapping = mapping.clone();
+        let content = content.clone();
+        let step = step.clone();
+        let error = error.clone();
+        let file_ref = file_ref.clone();
+        let run_import = run_import.clone();
+
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+
+            let file = file_ref
+                .cast::<HtmlInputElement>()
+                .and_then(|input| input.files())
+                .and_then(|files| files.get(0));
+            let Some(file) = file else {
+                error.set(Some("Please choose a file to import".to_string()));
+                return;
+            };
+
+            let format = (*format).clone();
+            let headers = headers.clone();
+            let mapping = mapping.clone();
+            let content = content.clone();
+            let step = step.clone();
+            let error = error.clone();
+            let run_import = run_import.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let text = match JsFuture::from(file.text()).await {
+                    Ok(text) => text.as_string().unwrap_or_default(),
+                    Err(_) => {
+                        error.set(Some("Could not read the selected file".to_string()));
+                        return;
+                    }
+                };
+
+                if format == "csv" {
+                    let columns = csv_headers(&text);
+                    let Some(first_column) = columns.first().cloned() else {
+                        error.set(Some("The CSV file has no header row".to_string()));
+                        return;
+                    };
+                    mapping.set(Some(CsvMapping {
+                        title: first_column,
+                        description: None,
+                        completed: None,
+                    }));
+                    headers.set(columns);
+                    content.set(text);
+                    error.set(None);
+                    step.set(Step::MapColumns);
+                } else {
+                    run_import(text, true);
+                }
+            });
+        })
+    };
+
+    let create_mapping_handler = |field: &'static str| {
+        let mapping = mapping.clone();
+        Callback::from(move |e: Event| {
+            let value = read_select(&e);
+            let optional_value = Some(value.clone()).filter(|v| !v.is_empty());
+            let mut current = (*mapping).clone().unwrap_or(CsvMapping {
+                title: String::new(),
+                description: None,
+                completed: None,
+            });
+            match field {
+                "title" => current.title = value,
+                "description" => current.description = optional_value,
+                _ => current.completed = optional_value,
+            }
+            mapping.set(Some(current));
+        })
+    };
+
+    let handle_preview = {
+        let content = content.clone();
+        let run_import = run_import.clone();
+        Callback::from(move |_| run_import((*content).clone(), true))
+    };
+
+    let handle_confirm = {
+        let content = content.clone();
+        let run_import = run_import.clone();
+        Callback::from(move |_| run_import((*content).clone(), false))
+    };
+
+    let handle_back = {
+        let step = step.clone();
+        let report = report.clone();
+        Callback::from(move |_| {
+            report.set(None);
+            step.set(Step::ChooseFile);
+        })
+    };
+
+    let column_options = |selected: Option<&String>, optional: bool| {
+        html! {
+            <>
+                {if optional {
+                    html! { <option value="" selected={selected.is_none()}>{"(none)"}</option> }
+                } else {
+                    html! {}
+                }}
+                {for headers.iter().map(|header| html! {
+                    <option value={header.clone()} selected={selected == Some(header)}>{header}</option>
+                })}
+            </>
+        }
+    };
+
+    let body = match *step {
+        Step::ChooseFile => html! {
+            <form onsubmit={handle_file_next}>
+                <div class="mb-4">
+                    <label for="import-format" class="block text-sm font-medium text-gray-700 mb-2">
+                        {"Format"}
+                    </label>
+                    <select
+                        id="import-format"
+                        onchange={handle_format_change}
+                        class="block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm"
+                    >
+                        {for FORMATS.iter().map(|(value, label)| html! {
+                            <option value={*value} selected={*format == *value}>{*label}</option>
+                        })}
+                    </select>
+                </div>
+                <div class="mb-4">
+                    <label for="import-file" class="block text-sm font-medium text-gray-700 mb-2">
+                        {"File"}
+                    </label>
+                    <input ref={file_ref} id="import-file" type="file" class="block w-full text-sm" />
+                </div>
+                <button
+                    type="submit"
+                    disabled={*loading}
+                    class="bg-indigo-600 hover:bg-indigo-700 text-white font-bold py-2 px-4 rounded disabled:opacity-50"
+                >
+                    {if *loading { "Reading..." } else { "Next" }}
+                </button>
+            </form>
+        },
+        Step::MapColumns => {
+            let current = (*mapping).clone();
+            html! {
+                <div>
+                    <p class="text-sm text-gray-600 mb-4">{"Choose which CSV columns hold each field."}</p>
+                    <div class="mb-4">
+                        <label class="block text-sm font-medium text-gray-700 mb-2">{"Title"}</label>
+                        <select
+                            onchange={create_mapping_handler("title")}
+                            class="block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm"
+                        >
+                            {column_options(current.as_ref().map(|m| &m.title), false)}
+                        </select>
+                    </div>
+                    <div class="mb-4">
+                        <label class="block text-sm font-medium text-gray-700 mb-2">{"Description"}</label>
+                        <select
+                            onchange={create_mapping_handler("description")}
+                            class="block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm"
+                        >
+                            {column_options(current.as_ref().and_then(|m| m.description.as_ref()), true)}
+                        </select>
+                    </div>
+                    <div class="mb-4">
+                        <label class="block text-sm font-medium text-gray-700 mb-2">{"Completed"}</label>
+                        <select
+                            onchange={create_mapping_handler("completed")}
+                            class="block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm"
+                        >
+                            {column_options(current.as_ref().and_then(|m| m.completed.as_ref()), true)}
+                        </select>
+                    </div>
+                    <div class="flex space-x-2">
+                        <button onclick={handle_back.clone()} class="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded">
+                            {"Back"}
+                        </button>
+                        <button
+                            onclick={handle_preview}
+                            disabled={*loading}
+                            class="bg-indigo-600 hover:bg-indigo-700 text-white font-bold py-2 px-4 rounded disabled:opacity-50"
+                        >
+                            {"Preview"}
+                        </button>
+                    </div>
+                </div>
+            }
+        }
+        Step::Preview => {
+            let report = (*report).clone();
+            html! {
+                <div>
+                    {if let Some(report) = report {
+                        html! {
+                            <>
+                                <h3 class="font-semibold text-gray-900 mb-2">
+                                    {format!("{} todo(s) will be created", report.created.len())}
+                                </h3>
+                                {todo_rows(&report.created)}
+                                {if report.duplicates.is_empty() {
+                                    html! {}
+                                } else {
+                                    html! {
+                                        <>
+                                            <h3 class="font-semibold text-gray-900 mt-4 mb-2">
+                                                {format!("{} duplicate(s) will be skipped", report.duplicates.len())}
+                                            </h3>
+                                            {todo_rows(&report.duplicates)}
+                                        </>
+                                    }
+                                }}
+                                {if report.errors.is_empty() {
+                                    html! {}
+                                } else {
+                                    html! {
+                                        <>
+                                            <h3 class="font-semibold text-red-700 mt-4 mb-2">
+                                                {format!("{} row(s) could not be read", report.errors.len())}
+                                            </h3>
+                                            <ul class="text-sm text-red-700">
+                                                {for report.errors.iter().map(|err| html! {
+                                                    <li>{format!("Line {}: {}", err.line, err.message)}</li>
+                                                })}
+                                            </ul>
+                                        </>
+                                    }
+                                }}
+                            </>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                    <div class="flex space-x-2 mt-6">
+                        <button onclick={handle_back.clone()} class="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded">
+                            {"Back"}
+                        </button>
+                        <button
+                            onclick={handle_confirm}
+                            disabled={*loading}
+                            class="bg-indigo-600 hover:bg-indigo-700 text-white font-bold py-2 px-4 rounded disabled:opacity-50"
+                        >
+                            {if *loading { "Importing..." } else { "Import" }}
+                        </button>
+                    </div>
+                </div>
+            }
+        }
+        Step::Done => {
+            let created = report.as_ref().map(|r| r.created.len()).unwrap_or_default();
+            html! {
+                <div>
+                    <p class="text-gray-900 mb-4">{format!("Imported {} todo(s).", created)}</p>
+                    <Link<Route> to={Route::Home} classes="bg-indigo-600 hover:bg-indigo-700 text-white font-bold py-2 px-4 rounded">
+                        {"Go to my todos"}
+                    </Link<Route>>
+                </div>
+            }
+        }
+    };
+
+    html! {
+        <div class="max-w-4xl mx-auto px-4">
+            <div class="flex justify-between items-center mb-8">
+                <h1 class="text-2xl font-bold text-gray-900">{"Import Todos"}</h1>
+                <Link<Route> to={Route::Home} classes="text-indigo-600 hover:text-indigo-800">
+                    {"← Back to todos"}
+                </Link<Route>>
+            </div>
+
+            {if let Some(error_msg) = error.as_ref() {
+                html! {
+                    <div class="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4">
+                        {error_msg}
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+
+            <div class="bg-white shadow rounded-lg p-6">
+                {body}
+            </div>
+        </div>
+    }
+}
@@ -1,5 +1,16 @@
+mod admin;
 mod auth;
+mod importwizard;
+mod notifications;
+mod profile;
+mod settings;
+mod tododetail;
 mod todolist;
+mod webhooks;
 
+pub use admin::AdminPanel;
 pub use auth::Auth;
+pub use importwizard::ImportWizard;
+pub use settings::Settings;
+pub use tododetail::TodoDetail;
 pub use todolist::TodoList;
\ No newline at end of file
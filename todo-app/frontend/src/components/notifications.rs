@@ -0,0 +1,392 @@
+use chrono::Local;
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+use todo_api_types::{NotificationPreferences, NotificationPreferencesUpdate};
+use crate::{
+    services::{api::ApiService, push},
+};
+
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+// IANA name of the browser's time zone, e.g. "Europe/Berlin"
+pub fn browser_timezone() -> Option<String> {
+    let options = js_sys::Intl::DateTimeFormat::new(&js_sys::Array::new(), &js_sys::Object::new()).resolved_options();
+    js_sys::Reflect::get(&options, &"timeZone".into()).ok()?.as_string()
+}
+
+// Reminder channels, browser push subscription and digest emails, shown on the settings page
+#[function_component(NotificationSettings)]
+pub fn notification_settings() -> Html {
+    let preferences = use_state(|| None::<NotificationPreferences>);
+    // None until loaded; Some(None) when the server has no Web Push keys
+    let push_key = use_state(|| None::<Option<String>>);
+    let push_subscribed = use_state(|| false);
+    let digest_preview = use_state(|| None::<String>);
+    let error = use_state(|| None::<String>);
+
+    // Load preferences and the push state of this browser on mount
+    {
+        let preferences = preferences.clone();
+        let push_key = push_key.clone();
+        let push_subscribed = push_subscribed.clone();
+        let error = error.clone();
+
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = match ApiService::get_notification_preferences().await {
+                    Ok(fetched_preferences) => ApiService::get_push_public_key()
+                        .await
+                        .map(|fetched_key| (fetched_preferences, fetched_key)),
+                    Err(err) => Err(err),
+                };
+
+                match result {
+                    Ok((fetched_preferences, fetched_key)) => {
+                        if fetched_key.is_some() && push::is_supported() {
+                            push_subscribed.set(push::is_subscribed().await.unwrap_or(false));
+                        }
+                        preferences.set(Some(fetched_preferences));
+                        push_key.set(Some(fetched_key));
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+            || ()
+        });
+    }
+
+    let update_preferences = {
+        let preferences = preferences.clone();
+        let error = error.clone();
+
+        move |update: NotificationPreferencesUpdate| {
+            let preferences = preferences.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::update_notification_preferences(update).await {
+                    Ok(updated) => {
+                        preferences.set(Some(updated));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        }
+    };
+
+    let handle_email_toggle = {
+        let preferences = preferences.clone();
+        let update_preferences = update_preferences.clone();
+
+        Callback::from(move |_| {
+            if let Some(current) = preferences.as_ref() {
+                update_preferences(NotificationPreferencesUpdate {
+                    email_reminders: Some(!current.email_reminders),
+                    ..Default::default()
+                });
+            }
+        })
+    };
+
+    let handle_push_toggle = {
+        let preferences = preferences.clone();
+        let update_preferences = update_preferences.clone();
+
+        Callback::from(move |_| {
+            if let Some(current) = preferences.as_ref() {
+                update_preferences(NotificationPreferencesUpdate {
+                    push_reminders: Some(!current.push_reminders),
+                    ..Default::default()
+                });
+            }
+        })
+    };
+
+    // Turning digests on also adopts the browser's time zone, so they arrive in the morning
+    let handle_frequency_change = {
+        let preferences = preferences.clone();
+        let update_preferences = update_preferences.clone();
+
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            let frequency = select.value();
+            let timezone = preferences
+                .as_ref()
+                .filter(|current| current.digest_frequency == "off" && frequency != "off")
+                .and_then(|_| browser_timezone());
+
+            update_preferences(NotificationPreferencesUpdate {
+                digest_frequency: Some(frequency),
+                timezone,
+                ..Default::default()
+            });
+        })
+    };
+
+    let handle_hour_change = {
+        let update_preferences = update_preferences.clone();
+
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            update_preferences(NotificationPreferencesUpdate {
+                digest_hour: select.value().parse().ok(),
+                ..Default::default()
+            });
+        })
+    };
+
+    let handle_weekday_change = {
+        let update_preferences = update_preferences.clone();
+
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            update_preferences(NotificationPreferencesUpdate {
+                digest_weekday: select.value().parse().ok(),
+                ..Default::default()
+            });
+        })
+    };
+
+    let handle_use_browser_timezone = {
+        let update_preferences = update_preferences.clone();
+
+        Callback::from(move |_| {
+            update_preferences(NotificationPreferencesUpdate {
+                timezone: browser_timezone(),
+                ..Default::default()
+            });
+        })
+    };
+
+    let handle_preview = {
+        let digest_preview = digest_preview.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            if digest_preview.is_some() {
+                digest_preview.set(None);
+                return;
+            }
+
+            let digest_preview = digest_preview.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::preview_digest().await {
+                    Ok(html) => {
+                        digest_preview.set(Some(html));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let handle_push_subscription = {
+        let push_key = push_key.clone();
+        let push_subscribed = push_subscribed.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            let Some(Some(public_key)) = (*push_key).clone() else {
+                return;
+            };
+            let push_subscribed = push_subscribed.clone();
+            let error = error.clone();
+            let subscribed = *push_subscribed;
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = if subscribed {
+                    match push::unsubscribe().await {
+                        Ok(Some(endpoint)) => ApiService::unsubscribe_push(endpoint).await,
+                        Ok(None) => Ok(()),
+                        Err(err) => Err(err),
+                    }
+                } else {
+                    match push::subscribe(&public_key).await {
+                        Ok(subscription) => ApiService::subscribe_push(subscription).await,
+                        Err(err) => Err(err),
+                    }
+                };
+
+                match result {
+                    Ok(()) => {
+                        push_subscribed.set(!subscribed);
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    html! {
+        <div class="bg-white shadow rounded-lg p-6 mt-6">
+            <h2 class="text-lg font-semibold text-gray-900 mb-2">{"Reminders"}</h2>
+            <p class="text-sm text-gray-600 mb-4">
+                {"Todos with a reminder time notify you when it comes. Choose how you want to be reminded."}
+            </p>
+
+            {if let Some(error_msg) = error.as_ref() {
+                html! {
+                    <div class="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4">
+                        {error_msg}
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+
+            {if let Some(current) = preferences.as_ref() {
+                html! {
+                    <div class="space-y-2 mb-4">
+                        <label class="flex items-center space-x-2">
+                            <input
+                                type="checkbox"
+                                checked={current.email_reminders}
+                                onchange={handle_email_toggle}
+                                class="h-4 w-4 text-indigo-600 border-gray-300 rounded"
+                            />
+                            <span>{"By email"}</span>
+                        </label>
+                        <label class="flex items-center space-x-2">
+                            <input
+                                type="checkbox"
+                                checked={current.push_reminders}
+                                onchange={handle_push_toggle}
+                                class="h-4 w-4 text-indigo-600 border-gray-300 rounded"
+                            />
+                            <span>{"By browser notification, on the browsers enabled below"}</span>
+                        </label>
+                    </div>
+                }
+            } else {
+                html! { <p class="text-gray-500 mb-4">{"Loading..."}</p> }
+            }}
+
+            {match push_key.as_ref() {
+                Some(Some(_)) if push::is_supported() => html! {
+                    <button
+                        onclick={handle_push_subscription}
+                        class="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded"
+                    >
+                        {if *push_subscribed { "Disable notifications in this browser" } else { "Enable notifications in this browser" }}
+                    </button>
+                },
+                Some(Some(_)) => html! {
+                    <p class="text-sm text-gray-500">{"This browser doesn't support push notifications."}</p>
+                },
+                Some(None) => html! {
+                    <p class="text-sm text-gray-500">{"Browser notifications aren't set up on this server."}</p>
+                },
+                None => html! {},
+            }}
+
+            <h2 class="text-lg font-semibold text-gray-900 mt-8 mb-2">{"Digest emails"}</h2>
+            <p class="text-sm text-gray-600 mb-4">
+                {"A summary of what's overdue, due today and recently completed, sent in the morning of your time zone."}
+            </p>
+            {if let Some(current) = preferences.as_ref() {
+                let browser_tz = browser_timezone();
+
+                html! {
+                    <>
+                        <div class="flex flex-wrap items-center gap-2 mb-4">
+                            <select
+                                onchange={handle_frequency_change}
+                                class="px-3 py-2 border border-gray-300 rounded-md"
+                            >
+                                <option value="off" selected={current.digest_frequency == "off"}>{"Off"}</option>
+                                <option value="daily" selected={current.digest_frequency == "daily"}>{"Daily"}</option>
+                                <option value="weekly" selected={current.digest_frequency == "weekly"}>{"Weekly"}</option>
+                            </select>
+                            {if current.digest_frequency == "weekly" {
+                                html! {
+                                    <>
+                                        <span>{"on"}</span>
+                                        <select
+                                            onchange={handle_weekday_change}
+                                            class="px-3 py-2 border border-gray-300 rounded-md"
+                                        >
+                                            {for WEEKDAYS.iter().enumerate().map(|(index, name)| {
+                                                let weekday = index as i16 + 1;
+                                                html! {
+                                                    <option value={weekday.to_string()} selected={current.digest_weekday == weekday}>
+                                                        {*name}
+                                                    </option>
+                                                }
+                                            })}
+                                        </select>
+                                    </>
+                                }
+                            } else {
+                                html! {}
+                            }}
+                            {if current.digest_frequency != "off" {
+                                html! {
+                                    <>
+                                        <span>{"at"}</span>
+                                        <select
+                                            onchange={handle_hour_change}
+                                            class="px-3 py-2 border border-gray-300 rounded-md"
+                                        >
+                                            {for (0..24).map(|hour: i16| html! {
+                                                <option value={hour.to_string()} selected={current.digest_hour == hour}>
+                                                    {format!("{:02}:00", hour)}
+                                                </option>
+                                            })}
+                                        </select>
+                                    </>
+                                }
+                            } else {
+                                html! {}
+                            }}
+                        </div>
+                        <p class="text-sm text-gray-600 mb-4">
+                            {"Time zone: "}{&current.timezone}
+                            {if browser_tz.as_ref().is_some_and(|tz| *tz != current.timezone) {
+                                html! {
+                                    <button onclick={handle_use_browser_timezone} class="ml-2 text-indigo-600 hover:text-indigo-800">
+                                        {format!("Use {}", browser_tz.unwrap_or_default())}
+                                    </button>
+                                }
+                            } else {
+                                html! {}
+                            }}
+                            {if let Some(next_at) = current.digest_next_at {
+                                html! {
+                                    <span class="text-gray-400">
+                                        {" · Next digest: "}{next_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()}
+                                    </span>
+                                }
+                            } else {
+                                html! {}
+                            }}
+                        </p>
+                    </>
+                }
+            } else {
+                html! {}
+            }}
+            <button
+                onclick={handle_preview}
+                class="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded"
+            >
+                {if digest_preview.is_some() { "Hide preview" } else { "Preview digest" }}
+            </button>
+            {if let Some(html) = digest_preview.as_ref() {
+                html! {
+                    <iframe
+                        srcdoc={html.clone()}
+                        sandbox=""
+                        class="w-full h-96 mt-4 border border-gray-300 rounded-md"
+                    />
+                }
+            } else {
+                html! {}
+            }}
+        </div>
+    }
+}
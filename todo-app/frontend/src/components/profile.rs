@@ -0,0 +1,406 @@
+use chrono::Local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use todo_api_types::{User, UserUpdate};
+use crate::{
+    components::notifications::browser_timezone,
+    services::{api::ApiService, download::download_file},
+};
+
+fn input_value(node_ref: &NodeRef) -> String {
+    node_ref
+        .cast::<HtmlInputElement>()
+        .map(|input| input.value().trim().to_string())
+        .unwrap_or_default()
+}
+
+// Profile fields, password change, data export and account deletion, shown at
+// the top of the settings page
+#[function_component(ProfileSettings)]
+pub fn profile_settings() -> Html {
+    let user = use_state(|| None::<User>);
+    let notice = use_state(|| None::<String>);
+    let error = use_state(|| None::<String>);
+
+    let username_ref = use_node_ref();
+    let display_name_ref = use_node_ref();
+    let email_ref = use_node_ref();
+    let timezone_ref = use_node_ref();
+    let locale_ref = use_node_ref();
+    let avatar_url_ref = use_node_ref();
+    let current_password_ref = use_node_ref();
+    let new_password_ref = use_node_ref();
+    let confirm_password_ref = use_node_ref();
+    let delete_password_ref = use_node_ref();
+
+    // Load the profile on mount
+    {
+        let user = user.clone();
+        let error = error.clone();
+
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::get_me().await {
+                    Ok(fetched_user) => user.set(Some(fetched_user)),
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+            || ()
+        });
+    }
+
+    let save = {
+        let user = user.clone();
+        let notice = notice.clone();
+        let error = error.clone();
+
+        move |update: UserUpdate| {
+            let user = user.clone();
+            let notice = notice.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::update_me(update).await {
+                    Ok(updated) => {
+                        notice.set(Some(match &updated.pending_email {
+                            Some(pending) => format!("Profile saved. Open the link we sent to {} to start using it.", pending),
+                            None => "Profile saved.".to_string(),
+                        }));
+                        user.set(Some(updated));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        }
+    };
+
+    let handle_save = {
+        let user = user.clone();
+        let save = save.clone();
+        let username_ref = username_ref.clone();
+        let display_name_ref = display_name_ref.clone();
+        let email_ref = email_ref.clone();
+        let timezone_ref = timezone_ref.clone();
+        let locale_ref = locale_ref.clone();
+        let avatar_url_ref = avatar_url_ref.clone();
+
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+
+            let Some(current) = user.as_ref() else {
+                return;
+            };
+            // Only a different address starts a new verification
+            let email = input_value(&email_ref);
+            let email_changed = email != current.email && Some(&email) != current.pending_email.as_ref();
+
+            save(UserUpdate {
+                username: Some(input_value(&username_ref)),
+                email: email_changed.then_some(email),
+                display_name: Some(input_value(&display_name_ref)),
+                timezone: Some(input_value(&timezone_ref)),
+                locale: Some(input_value(&locale_ref)),
+                avatar_url: Some(input_value(&avatar_url_ref)),
+            });
+        })
+    };
+
+    // Asking for the current address again drops the pending one
+    let handle_cancel_email_change = {
+        let user = user.clone();
+        let save = save.clone();
+
+        Callback::from(move |_| {
+            if let Some(current) = user.as_ref() {
+                save(UserUpdate {
+                    email: Some(current.email.clone()),
+                    ..Default::default()
+                });
+            }
+        })
+    };
+
+    let handle_use_browser_timezone = {
+        let timezone_ref = timezone_ref.clone();
+
+        Callback::from(move |_| {
+            if let (Some(input), Some(timezone)) = (timezone_ref.cast::<HtmlInputElement>(), browser_timezone()) {
+                input.set_value(&timezone);
+            }
+        })
+    };
+
+    let handle_change_password = {
+        let notice = notice.clone();
+        let error = error.clone();
+        let current_password_ref = current_password_ref.clone();
+        let new_password_ref = new_password_ref.clone();
+        let confirm_password_ref = confirm_password_ref.clone();
+
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+
+            let (Some(current_input), Some(new_input), Some(confirm_input)) = (
+                current_password_ref.cast::<HtmlInputElement>(),
+                new_password_ref.cast::<HtmlInputElement>(),
+                confirm_password_ref.cast::<HtmlInputElement>(),
+            ) else {
+                return;
+            };
+            if new_input.value() != confirm_input.value() {
+                error.set(Some("The new passwords don't match".to_string()));
+                return;
+            }
+
+            let notice = notice.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::change_password(current_input.value(), new_input.value()).await {
+                    Ok(()) => {
+                        current_input.set_value("");
+                        new_input.set_value("");
+                        confirm_input.set_value("");
+                        notice.set(Some("Password changed.".to_string()));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let handle_export = {
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = match ApiService::export_account().await {
+                    Ok(bytes) => download_file("todo-account-export.zip", "application/zip", &bytes),
+                    Err(err) => Err(err),
+                };
+
+                match result {
+                    Ok(()) => error.set(None),
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let handle_delete_account = {
+        let user = user.clone();
+        let error = error.clone();
+        let delete_password_ref = delete_password_ref.clone();
+
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+
+            let Some(password_input) = delete_password_ref.cast::<HtmlInputElement>() else {
+                return;
+            };
+            let confirmed = web_sys::window()
+                .and_then(|window| {
+                    window
+                        .confirm_with_message("Your account, todos and settings will be deleted for good once the grace period ends. Continue?")
+                        .ok()
+                })
+                .unwrap_or(false);
+            if !confirmed {
+                return;
+            }
+
+            let user = user.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::delete_account(password_input.value()).await {
+                    Ok(updated) => {
+                        password_input.set_value("");
+                        user.set(Some(updated));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let handle_restore_account = {
+        let user = user.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            let user = user.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::restore_account().await {
+                    Ok(updated) => {
+                        user.set(Some(updated));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let input_classes = "block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500";
+
+    html! {
+        <div class="bg-white shadow rounded-lg p-6">
+            <h2 class="text-lg font-semibold text-gray-900 mb-4">{"Profile"}</h2>
+
+            {if let Some(error_msg) = error.as_ref() {
+                html! {
+                    <div class="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4">
+                        {error_msg}
+                    </div>
+                }
+            } else if let Some(notice_msg) = notice.as_ref() {
+                html! {
+                    <div class="bg-green-50 border border-green-300 text-green-800 px-4 py-3 rounded mb-4">
+                        {notice_msg}
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+
+            {if let Some(current) = user.as_ref() {
+                html! {
+                    <form onsubmit={handle_save} class="space-y-4 mb-8">
+                        <div class="flex items-center space-x-4">
+                            {if let Some(avatar_url) = &current.avatar_url {
+                                html! { <img src={avatar_url.clone()} alt="" class="h-16 w-16 rounded-full object-cover bg-gray-100"/> }
+                            } else {
+                                html! {
+                                    <div class="h-16 w-16 rounded-full bg-indigo-100 text-indigo-700 flex items-center justify-center text-2xl font-bold">
+                                        {current.display_name.as_deref().unwrap_or(&current.username).chars().next().unwrap_or('?').to_uppercase().to_string()}
+                                    </div>
+                                }
+                            }}
+                            <p class="text-sm text-gray-500">{"Member since "}{current.created_at.format("%Y-%m-%d").to_string()}</p>
+                        </div>
+                        <label class="block">
+                            <span class="text-sm font-medium text-gray-700">{"Username"}</span>
+                            <input ref={username_ref} type="text" value={current.username.clone()} class={input_classes}/>
+                        </label>
+                        <label class="block">
+                            <span class="text-sm font-medium text-gray-700">{"Display name"}</span>
+                            <input
+                                ref={display_name_ref}
+                                type="text"
+                                value={current.display_name.clone().unwrap_or_default()}
+                                placeholder={current.username.clone()}
+                                class={input_classes}
+                            />
+                        </label>
+                        <label class="block">
+                            <span class="text-sm font-medium text-gray-700">{"Email"}</span>
+                            <input ref={email_ref} type="email" value={current.email.clone()} class={input_classes}/>
+                        </label>
+                        {if let Some(pending) = &current.pending_email {
+                            html! {
+                                <p class="text-sm text-gray-600">
+                                    {"Waiting for you to confirm "}{pending}{". Until then you keep using "}{&current.email}{"."}
+                                    <button type="button" onclick={handle_cancel_email_change} class="ml-2 text-indigo-600 hover:text-indigo-800">
+                                        {"Cancel change"}
+                                    </button>
+                                </p>
+                            }
+                        } else {
+                            html! {}
+                        }}
+                        <label class="block">
+                            <span class="text-sm font-medium text-gray-700">{"Time zone"}</span>
+                            <div class="flex space-x-2">
+                                <input
+                                    ref={timezone_ref}
+                                    type="text"
+                                    value={current.timezone.clone()}
+                                    placeholder="Europe/Berlin"
+                                    class={input_classes}
+                                />
+                                <button
+                                    type="button"
+                                    onclick={handle_use_browser_timezone}
+                                    class="whitespace-nowrap text-indigo-600 hover:text-indigo-800"
+                                >
+                                    {"Use this browser's"}
+                                </button>
+                            </div>
+                        </label>
+                        <label class="block">
+                            <span class="text-sm font-medium text-gray-700">{"Language"}</span>
+                            <input ref={locale_ref} type="text" value={current.locale.clone()} placeholder="en" class={input_classes}/>
+                        </label>
+                        <label class="block">
+                            <span class="text-sm font-medium text-gray-700">{"Avatar URL"}</span>
+                            <input
+                                ref={avatar_url_ref}
+                                type="url"
+                                value={current.avatar_url.clone().unwrap_or_default()}
+                                placeholder="https://"
+                                class={input_classes}
+                            />
+                        </label>
+                        <button type="submit" class="bg-indigo-600 hover:bg-indigo-700 text-white font-bold py-2 px-4 rounded">
+                            {"Save profile"}
+                        </button>
+                    </form>
+                }
+            } else {
+                html! { <p class="text-gray-500 mb-8">{"Loading..."}</p> }
+            }}
+
+            <h3 class="text-md font-semibold text-gray-900 mb-2">{"Change password"}</h3>
+            <form onsubmit={handle_change_password} class="space-y-2">
+                <input ref={current_password_ref} type="password" placeholder="Current password" autocomplete="current-password" class={input_classes}/>
+                <input ref={new_password_ref} type="password" placeholder="New password" autocomplete="new-password" class={input_classes}/>
+                <input ref={confirm_password_ref} type="password" placeholder="Repeat new password" autocomplete="new-password" class={input_classes}/>
+                <button type="submit" class="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded">
+                    {"Change password"}
+                </button>
+            </form>
+
+            <h3 class="text-md font-semibold text-gray-900 mt-8 mb-2">{"Your data"}</h3>
+            <p class="text-sm text-gray-600 mb-4">
+                {"Download your profile, todos, their history and your settings as JSON files in a ZIP archive."}
+            </p>
+            <button onclick={handle_export} class="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded">
+                {"Download my data"}
+            </button>
+
+            <h3 class="text-md font-semibold text-red-700 mt-8 mb-2">{"Delete account"}</h3>
+            {match user.as_ref().and_then(|current| current.deletion_scheduled_at) {
+                Some(deletion_at) => html! {
+                    <div class="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded">
+                        <p class="mb-2">
+                            {"Your account and all its data will be deleted on "}
+                            {deletion_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()}{"."}
+                        </p>
+                        <button onclick={handle_restore_account} class="bg-white hover:bg-gray-100 text-red-700 font-bold py-2 px-4 rounded border border-red-400">
+                            {"Keep my account"}
+                        </button>
+                    </div>
+                },
+                None => html! {
+                    <form onsubmit={handle_delete_account} class="space-y-2">
+                        <p class="text-sm text-gray-600">
+                            {"Your account is deleted after a grace period, during which you can still log in and cancel."}
+                        </p>
+                        <input ref={delete_password_ref} type="password" placeholder="Password" autocomplete="current-password" class={input_classes}/>
+                        <button type="submit" class="bg-red-600 hover:bg-red-700 text-white font-bold py-2 px-4 rounded">
+                            {"Delete account"}
+                        </button>
+                    </form>
+                },
+            }}
+        </div>
+    }
+}
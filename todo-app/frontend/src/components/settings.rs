@@ -0,0 +1,301 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::prelude::*;
+use todo_api_types::{AppPassword, CalendarFeed, CreatedAppPassword};
+use crate::{
+    components::{notifications::NotificationSettings, profile::ProfileSettings, webhooks::WebhookSettings},
+    services::api::ApiService,
+    types::Route,
+};
+
+#[function_component(Settings)]
+pub fn settings() -> Html {
+    // None while loading, then Some(None) until a feed is made
+    let feed = use_state(|| None::<Option<CalendarFeed>>);
+    let app_passwords = use_state(Vec::<AppPassword>::new);
+    let new_password = use_state(|| None::<CreatedAppPassword>);
+    let error = use_state(|| None::<String>);
+    // Only the profile is available until the user picks a new password
+    let password_reset_required = use_state(|| false);
+
+    let password_name_ref = use_node_ref();
+
+    // Load the calendar feed URL and app passwords on mount
+    {
+        let feed = feed.clone();
+        let app_passwords = app_passwords.clone();
+        let error = error.clone();
+        let password_reset_required = password_reset_required.clone();
+
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                if ApiService::get_me().await.is_ok_and(|me| me.password_reset_required) {
+                    password_reset_required.set(true);
+                    return;
+                }
+
+                let result = match ApiService::get_calendar_feed().await {
+                    Ok(fetched_feed) => ApiService::get_app_passwords()
+                        .await
+                        .map(|fetched_passwords| (fetched_feed, fetched_passwords)),
+                    Err(err) => Err(err),
+                };
+
+                match result {
+                    Ok((fetched_feed, fetched_passwords)) => {
+                        feed.set(Some(fetched_feed));
+                        app_passwords.set(fetched_passwords);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+            || ()
+        });
+    }
+
+    let handle_create_password = {
+        let app_passwords = app_passwords.clone();
+        let new_password = new_password.clone();
+        let error = error.clone();
+        let password_name_ref = password_name_ref.clone();
+
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+
+            let Some(input) = password_name_ref.cast::<HtmlInputElement>() else {
+                return;
+            };
+            let name = input.value();
+            if name.trim().is_empty() {
+                return;
+            }
+            input.set_value("");
+
+            let app_passwords = app_passwords.clone();
+            let new_password = new_password.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::create_app_password(name).await {
+                    Ok(created) => {
+                        let mut current_passwords = (*app_passwords).clone();
+                        current_passwords.insert(0, AppPassword {
+                            id: created.id.clone(),
+                            name: created.name.clone(),
+                            created_at: created.created_at,
+                            last_used_at: None,
+                        });
+                        app_passwords.set(current_passwords);
+                        new_password.set(Some(created));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let create_revoke_handler = |password_id: String| {
+        let app_passwords = app_passwords.clone();
+        let new_password = new_password.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            let app_passwords = app_passwords.clone();
+            let new_password = new_password.clone();
+            let error = error.clone();
+            let password_id = password_id.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::delete_app_password(&password_id).await {
+                    Ok(()) => {
+                        let mut current_passwords = (*app_passwords).clone();
+                        current_passwords.retain(|p| p.id != password_id);
+                        app_passwords.set(current_passwords);
+                        if new_password.as_ref().is_some_and(|p| p.id == password_id) {
+                            new_password.set(None);
+                        }
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let handle_regenerate = {
+        let feed = feed.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            // Only replacing a feed breaks subscriptions
+            let replacing = matches!(*feed, Some(Some(_)));
+            let confirmed = !replacing || web_sys::window()
+                .and_then(|window| {
+                    window
+                        .confirm_with_message("Calendar apps subscribed to the current URL will stop receiving updates. Continue?")
+                        .ok()
+                })
+                .unwrap_or(false);
+            if !confirmed {
+                return;
+            }
+
+            let feed = feed.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::regenerate_calendar_feed().await {
+                    Ok(new_feed) => {
+                        feed.set(Some(Some(new_feed)));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let calendar_and_app_passwords = html! {
+        <>
+            <div class="bg-white shadow rounded-lg p-6 mt-6">
+                <h2 class="text-lg font-semibold text-gray-900 mb-2">{"Calendar feed"}</h2>
+                <p class="text-sm text-gray-600 mb-4">
+                    {"Subscribe to this URL in your calendar app to see your todos and their due dates. "}
+                    {"Anyone with the URL can read your todos, so keep it private."}
+                </p>
+                {match feed.as_ref() {
+                    Some(Some(feed)) => html! {
+                        <input
+                            type="text"
+                            readonly=true
+                            value={ApiService::calendar_feed_url(feed)}
+                            onfocus={Callback::from(|e: FocusEvent| {
+                                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                                input.select();
+                            })}
+                            class="block w-full px-3 py-2 mb-4 border border-gray-300 rounded-md bg-gray-50 font-mono text-sm"
+                        />
+                    },
+                    Some(None) => html! { <p class="text-gray-500 mb-4">{"You don't have a feed URL yet."}</p> },
+                    None => html! { <p class="text-gray-500 mb-4">{"Loading..."}</p> },
+                }}
+                <button
+                    onclick={handle_regenerate}
+                    class="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded"
+                >
+                    {if matches!(*feed, Some(None)) { "Create URL" } else { "Regenerate URL" }}
+                </button>
+            </div>
+
+            <div class="bg-white shadow rounded-lg p-6 mt-6">
+                <h2 class="text-lg font-semibold text-gray-900 mb-2">{"CalDAV sync"}</h2>
+                <p class="text-sm text-gray-600 mb-4">
+                    {"Edit your todos from Thunderbird, Apple Reminders or any other CalDAV client. "}
+                    {"Use the server address below with your username and an app password created here."}
+                </p>
+                <input
+                    type="text"
+                    readonly=true
+                    value={ApiService::caldav_url()}
+                    class="block w-full px-3 py-2 mb-4 border border-gray-300 rounded-md bg-gray-50 font-mono text-sm"
+                />
+
+                {if let Some(created) = new_password.as_ref() {
+                    html! {
+                        <div class="bg-green-50 border border-green-300 text-green-800 px-4 py-3 rounded mb-4">
+                            <p class="text-sm mb-2">
+                                {"Password for \""}{&created.name}{"\". Copy it now, it won't be shown again:"}
+                            </p>
+                            <code class="font-mono text-sm break-all">{&created.password}</code>
+                        </div>
+                    }
+                } else {
+                    html! {}
+                }}
+
+                <form onsubmit={handle_create_password} class="flex space-x-2 mb-4">
+                    <input
+                        ref={password_name_ref}
+                        type="text"
+                        placeholder="Device or app name"
+                        class="flex-1 px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500"
+                    />
+                    <button
+                        type="submit"
+                        class="bg-indigo-600 hover:bg-indigo-700 text-white font-bold py-2 px-4 rounded"
+                    >
+                        {"Create app password"}
+                    </button>
+                </form>
+
+                <ul class="divide-y divide-gray-200">
+                    {for app_passwords.iter().map(|password| {
+                        let revoke_handler = create_revoke_handler(password.id.clone());
+
+                        html! {
+                            <li key={password.id.clone()} class="flex items-center justify-between py-3">
+                                <div>
+                                    <p class="font-medium text-gray-900">{&password.name}</p>
+                                    <p class="text-xs text-gray-400">
+                                        {"Created: "}{password.created_at.format("%Y-%m-%d %H:%M").to_string()}
+                                        {" · Last used: "}
+                                        {password
+                                            .last_used_at
+                                            .map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+                                            .unwrap_or_else(|| "never".to_string())}
+                                    </p>
+                                </div>
+                                <button onclick={revoke_handler} class="text-red-600 hover:text-red-800">
+                                    {"Revoke"}
+                                </button>
+                            </li>
+                        }
+                    })}
+                </ul>
+            </div>
+        </>
+    };
+
+    html! {
+        <div class="max-w-4xl mx-auto px-4">
+            <div class="mb-8">
+                <Link<Route> to={Route::Home} classes="text-indigo-600 hover:text-indigo-800">
+                    {"← Back to todos"}
+                </Link<Route>>
+            </div>
+
+            <h1 class="text-2xl font-bold text-gray-900 mb-6">{"Settings"}</h1>
+
+            {if let Some(error_msg) = error.as_ref() {
+                html! {
+                    <div class="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4">
+                        {error_msg}
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+
+            {if *password_reset_required {
+                html! {
+                    <>
+                        <div class="bg-yellow-100 border border-yellow-400 text-yellow-800 px-4 py-3 rounded mb-4">
+                            {"An administrator asked you to choose a new password. Change it below to keep using your account."}
+                        </div>
+                        <ProfileSettings/>
+                    </>
+                }
+            } else {
+                html! {
+                    <>
+                        <ProfileSettings/>
+                        {calendar_and_app_passwords}
+                        <NotificationSettings/>
+                        <WebhookSettings/>
+                    </>
+                }
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,204 @@
+use chrono::Local;
+use yew::prelude::*;
+use yew_router::prelude::*;
+use todo_api_types::{Todo, TodoEvent};
+use crate::{
+    components::todolist::priority_label,
+    services::api::ApiService,
+    types::Route,
+};
+
+#[derive(Properties, PartialEq)]
+pub struct TodoDetailProps {
+    pub id: String,
+}
+
+fn describe_event(event: &TodoEvent) -> &'static str {
+    match event.event_type.as_str() {
+        "created" => "created this todo",
+        "completed" => "marked this todo as completed",
+        "uncompleted" => "marked this todo as not completed",
+        "deleted" => "moved this todo to the trash",
+        "restored" => "restored this todo from the trash",
+        _ => "updated this todo",
+    }
+}
+
+fn format_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "(empty)".to_string(),
+        serde_json::Value::String(s) if s.is_empty() => "(empty)".to_string(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[function_component(TodoDetail)]
+pub fn todo_detail(props: &TodoDetailProps) -> Html {
+    let todo = use_state(|| None::<Todo>);
+    let events = use_state(Vec::<TodoEvent>::new);
+    let loading = use_state(|| true);
+    let error = use_state(|| None::<String>);
+
+    // Load the todo and its history whenever the id changes
+    {
+        let todo = todo.clone();
+        let events = events.clone();
+        let loading = loading.clone();
+        let error = error.clone();
+
+        use_effect_with(props.id.clone(), move |id| {
+            let id = id.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = match ApiService::get_todo(&id).await {
+                    Ok(fetched_todo) => ApiService::get_todo_history(&id)
+                        .await
+                        .map(|fetched_events| (fetched_todo, fetched_events)),
+                    Err(err) => Err(err),
+                };
+
+                match result {
+                    Ok((fetched_todo, fetched_events)) => {
+                        todo.set(Some(fetched_todo));
+                        events.set(fetched_events);
+                    }
+                    Err(err) => {
+                        error.set(Some(err));
+                    }
+                }
+                loading.set(false);
+            });
+            || ()
+        });
+    }
+
+    if *loading {
+        return html! {
+            <div class="flex justify-center items-center min-h-96">
+                <div class="text-lg">{"Loading..."}</div>
+            </div>
+        };
+    }
+
+    html! {
+        <div class="max-w-4xl mx-auto px-4">
+            <div class="mb-8">
+                <Link<Route> to={Route::Home} classes="text-indigo-600 hover:text-indigo-800">
+                    {"← Back to todos"}
+                </Link<Route>>
+            </div>
+
+            {if let Some(error_msg) = error.as_ref() {
+                html! {
+                    <div class="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4">
+                        {error_msg}
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+
+            {if let Some(todo) = todo.as_ref() {
+                html! {
+                    <div class="bg-white shadow rounded-lg p-6 mb-6">
+                        <h1 class={format!(
+                            "text-2xl font-bold {}",
+                            if todo.completed { "line-through text-gray-500" } else { "text-gray-900" }
+                        )}>
+                            {&todo.title}
+                        </h1>
+                        {if let Some(description) = todo.description.as_ref().filter(|d| !d.is_empty()) {
+                            html! { <p class="text-gray-600 mt-2">{description}</p> }
+                        } else {
+                            html! {}
+                        }}
+                        <p class="text-xs text-gray-400 mt-4">
+                            {"Created: "}{todo.created_at.format("%Y-%m-%d %H:%M").to_string()}
+                            {" · Updated: "}{todo.updated_at.format("%Y-%m-%d %H:%M").to_string()}
+                        </p>
+                        {if let Some(due_at) = todo.due_at {
+                            html! {
+                                <p class="text-sm text-gray-600 mt-2">
+                                    {"Due: "}{due_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()}
+                                </p>
+                            }
+                        } else {
+                            html! {}
+                        }}
+                        {if let Some(priority) = todo.priority {
+                            html! { <p class="text-sm text-gray-600 mt-1">{"Priority: "}{priority_label(priority)}</p> }
+                        } else {
+                            html! {}
+                        }}
+                        {if let Some(remind_at) = todo.remind_at {
+                            html! {
+                                <p class="text-sm text-gray-600 mt-1">
+                                    {"Reminder: "}{remind_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()}
+                                </p>
+                            }
+                        } else {
+                            html! {}
+                        }}
+                        {if todo.deleted_at.is_some() {
+                            html! { <p class="text-sm text-red-600 mt-2">{"This todo is in the trash."}</p> }
+                        } else {
+                            html! {}
+                        }}
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+
+            <div class="bg-white shadow rounded-lg p-6">
+                <h2 class="text-lg font-semibold text-gray-900 mb-4">{"History"}</h2>
+                <ol class="relative border-l border-gray-200 ml-2">
+                    {for events.iter().rev().map(|event| {
+                        let changes: Vec<(String, String, String)> = event
+                            .changes
+                            .as_object()
+                            .map(|fields| {
+                                fields
+                                    .iter()
+                                    .filter(|_| event.event_type != "created")
+                                    .map(|(field, change)| (
+                                        field.clone(),
+                                        format_value(&change["before"]),
+                                        format_value(&change["after"]),
+                                    ))
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+
+                        html! {
+                            <li key={event.id.clone()} class="mb-6 ml-4">
+                                <div class="absolute w-3 h-3 bg-indigo-500 rounded-full -left-1.5 mt-1.5"></div>
+                                <time class="text-xs text-gray-400">
+                                    {event.created_at.format("%Y-%m-%d %H:%M:%S").to_string()}
+                                </time>
+                                <p class="text-sm text-gray-900">
+                                    <span class="font-medium">
+                                        {event.actor_username.clone().unwrap_or_else(|| event.actor_id.clone())}
+                                    </span>
+                                    {" "}{describe_event(event)}
+                                </p>
+                                {for changes.into_iter().map(|(field, before, after)| html! {
+                                    <p class="text-xs text-gray-600">
+                                        <span class="font-medium">{field}</span>{": "}
+                                        <span class="line-through text-gray-400">{before}</span>
+                                        {" → "}{after}
+                                    </p>
+                                })}
+                            </li>
+                        }
+                    })}
+                </ol>
+                {if events.is_empty() {
+                    html! { <p class="text-gray-500">{"No history recorded yet."}</p> }
+                } else {
+                    html! {}
+                }}
+            </div>
+        </div>
+    }
+}
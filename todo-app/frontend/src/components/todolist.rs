@@ -1,10 +1,58 @@
+use std::collections::HashSet;
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
 use yew::prelude::*;
-use web_sys::HtmlInputElement;
+use yew_router::prelude::*;
+use gloo_timers::callback::Timeout;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use todo_api_types::{BulkAction, BulkFilter, BulkRequest, BulkResponse, Todo, TodoUpdate};
 use crate::{
-    services::{api::ApiService, auth::AuthService},
-    types::{Todo, TodoUpdate},
+    services::{api::ApiService, auth::AuthService, download::download_file},
+    types::Route,
 };
 
+// Merges the outcome of a bulk action into the visible list and reports any per-item failures
+fn apply_bulk_response(todos: &[Todo], response: BulkResponse) -> (Vec<Todo>, Option<String>) {
+    let mut current_todos = todos.to_vec();
+    let mut failures = 0;
+
+    for result in response.results {
+        if !result.success {
+            failures += 1;
+            continue;
+        }
+        match result.todo {
+            Some(todo) if todo.deleted_at.is_some() => current_todos.retain(|t| t.id != todo.id),
+            Some(todo) => {
+                if let Some(index) = current_todos.iter().position(|t| t.id == todo.id) {
+                    current_todos[index] = todo;
+                }
+            }
+            None => {}
+        }
+    }
+
+    let error = (failures > 0).then(|| format!("{} todo(s) could not be updated", failures));
+    (current_todos, error)
+}
+
+// RFC 5545 priorities: 1-4 high, 5 medium, 6-9 low
+pub fn priority_label(priority: i16) -> &'static str {
+    match priority {
+        1..=4 => "High",
+        5 => "Medium",
+        _ => "Low",
+    }
+}
+
+// `<input type="datetime-local">` values are in the browser's time zone
+fn parse_datetime_input(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .ok()?
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+}
+
 #[function_component(TodoList)]
 pub fn todo_list() -> Html {
     let todos = use_state(Vec::<Todo>::new);
@@ -12,20 +60,44 @@ pub fn todo_list() -> Html {
     let error = use_state(|| None::<String>);
     let new_todo_title = use_state(String::new);
     let new_todo_description = use_state(String::new);
+    let show_trash = use_state(|| false);
+    let trash = use_state(Vec::<Todo>::new);
+    let recently_deleted = use_state(|| None::<Todo>);
+    let selected = use_state(HashSet::<String>::new);
+    let last_selected = use_state(|| None::<usize>);
+    let dragged = use_state(|| None::<String>);
+    let export_format = use_state(|| "json".to_string());
+    let is_admin = use_state(|| false);
+    let navigator = use_navigator();
 
     let auth_service = use_memo((), |_| AuthService::new());
 
     let title_ref = use_node_ref();
     let description_ref = use_node_ref();
+    let due_ref = use_node_ref();
+    let priority_ref = use_node_ref();
+    let remind_ref = use_node_ref();
 
-    // Load todos on component mount
+    // Load todos on component mount. Users who must choose a new password are
+    // sent to the settings page instead
     {
         let todos = todos.clone();
         let loading = loading.clone();
         let error = error.clone();
+        let is_admin = is_admin.clone();
 
         use_effect_with((), move |_| {
             wasm_bindgen_futures::spawn_local(async move {
+                if let Ok(me) = ApiService::get_me().await {
+                    if me.password_reset_required {
+                        if let Some(navigator) = navigator {
+                            navigator.push(&Route::Settings);
+                        }
+                        return;
+                    }
+                    is_admin.set(me.role == "admin");
+                }
+
                 match ApiService::get_todos().await {
                     Ok(fetched_todos) => {
                         todos.set(fetched_todos);
@@ -41,6 +113,18 @@ pub fn todo_list() -> Html {
         });
     }
 
+    // Hide the undo toast a few seconds after a todo is moved to the trash
+    {
+        let recently_deleted = recently_deleted.clone();
+
+        use_effect_with((*recently_deleted).clone(), move |deleted| {
+            let timeout = deleted.as_ref().map(|_| {
+                Timeout::new(5_000, move || recently_deleted.set(None))
+            });
+            move || drop(timeout)
+        });
+    }
+
     let handle_logout = {
         let auth_service = auth_service.clone();
         Callback::from(move |_| {
@@ -48,6 +132,43 @@ pub fn todo_list() -> Html {
         })
     };
 
+    let handle_export_format_change = {
+        let export_format = export_format.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            export_format.set(select.value());
+        })
+    };
+
+    let handle_export = {
+        let export_format = export_format.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            let format = (*export_format).clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let (file_name, content_type) = match format.as_str() {
+                    "csv" => ("todos.csv", "text/csv"),
+                    "md" => ("todos.md", "text/markdown"),
+                    "todotxt" => ("todo.txt", "text/plain"),
+                    _ => ("todos.json", "application/json"),
+                };
+
+                let result = match ApiService::export_todos(&format).await {
+                    Ok(bytes) => download_file(file_name, content_type, &bytes),
+                    Err(err) => Err(err),
+                };
+
+                match result {
+                    Ok(()) => error.set(None),
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
     let handle_create_todo = {
         let todos = todos.clone();
         let error = error.clone();
@@ -55,6 +176,9 @@ pub fn todo_list() -> Html {
         let new_todo_description = new_todo_description.clone();
         let title_ref = title_ref.clone();
         let description_ref = description_ref.clone();
+        let due_ref = due_ref.clone();
+        let priority_ref = priority_ref.clone();
+        let remind_ref = remind_ref.clone();
 
         Callback::from(move |e: SubmitEvent| {
             e.prevent_default();
@@ -69,17 +193,39 @@ pub fn todo_list() -> Html {
                 .map(|input| input.value())
                 .filter(|s| !s.is_empty());
 
+            let due_at = due_ref
+                .cast::<HtmlInputElement>()
+                .and_then(|input| parse_datetime_input(&input.value()));
+
+            let priority = priority_ref
+                .cast::<HtmlSelectElement>()
+                .and_then(|select| select.value().parse::<i16>().ok());
+
+            let remind_at = remind_ref
+                .cast::<HtmlInputElement>()
+                .and_then(|input| parse_datetime_input(&input.value()));
+
             if title.trim().is_empty() {
                 return;
             }
 
+            if let Some(input) = due_ref.cast::<HtmlInputElement>() {
+                input.set_value("");
+            }
+            if let Some(select) = priority_ref.cast::<HtmlSelectElement>() {
+                select.set_value("");
+            }
+            if let Some(input) = remind_ref.cast::<HtmlInputElement>() {
+                input.set_value("");
+            }
+
             let todos = todos.clone();
             let error = error.clone();
             let new_todo_title = new_todo_title.clone();
             let new_todo_description = new_todo_description.clone();
 
             wasm_bindgen_futures::spawn_local(async move {
-                match ApiService::create_todo(title, description).await {
+                match ApiService::create_todo(title, description, due_at, priority, remind_at).await {
                     Ok(new_todo) => {
                         let mut current_todos = (*todos).clone();
                         current_todos.insert(0, new_todo);
@@ -110,6 +256,9 @@ pub fn todo_list() -> Html {
                     title: None,
                     description: None,
                     completed: Some(!completed),
+                    due_at: None,
+                    priority: None,
+                    remind_at: None,
                 };
 
                 match ApiService::update_todo(&todo_id, update).await {
@@ -132,24 +281,325 @@ pub fn todo_list() -> Html {
     let create_delete_handler = |todo_id: String| {
         let todos = todos.clone();
         let error = error.clone();
+        let recently_deleted = recently_deleted.clone();
 
         Callback::from(move |_| {
             let todos = todos.clone();
             let error = error.clone();
+            let recently_deleted = recently_deleted.clone();
             let todo_id = todo_id.clone();
 
             wasm_bindgen_futures::spawn_local(async move {
                 match ApiService::delete_todo(&todo_id).await {
                     Ok(()) => {
                         let current_todos = (*todos).clone();
+                        let deleted_todo = current_todos.iter().find(|t| t.id == todo_id).cloned();
                         let filtered_todos: Vec<Todo> = current_todos
                             .into_iter()
                             .filter(|t| t.id != todo_id)
                             .collect();
                         todos.set(filtered_todos);
+                        recently_deleted.set(deleted_todo);
+                        error.set(None);
+                    }
+                    Err(err) => {
+                        error.set(Some(err));
+                    }
+                }
+            });
+        })
+    };
+
+    let handle_undo_delete = {
+        let todos = todos.clone();
+        let error = error.clone();
+        let recently_deleted = recently_deleted.clone();
+
+        Callback::from(move |_| {
+            let Some(deleted_todo) = (*recently_deleted).clone() else {
+                return;
+            };
+            let todos = todos.clone();
+            let error = error.clone();
+            recently_deleted.set(None);
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::restore_todo(&deleted_todo.id).await {
+                    Ok(restored_todo) => {
+                        let mut current_todos = (*todos).clone();
+                        current_todos.push(restored_todo);
+                        current_todos.sort_by(|a, b| a.position.cmp(&b.position));
+                        todos.set(current_todos);
+                        error.set(None);
+                    }
+                    Err(err) => {
+                        error.set(Some(err));
+                    }
+                }
+            });
+        })
+    };
+
+    let handle_toggle_trash = {
+        let show_trash = show_trash.clone();
+        let trash = trash.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            let showing_trash = !*show_trash;
+            show_trash.set(showing_trash);
+            if !showing_trash {
+                return;
+            }
+
+            let trash = trash.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::get_trash().await {
+                    Ok(trashed_todos) => {
+                        trash.set(trashed_todos);
+                        error.set(None);
+                    }
+                    Err(err) => {
+                        error.set(Some(err));
+                    }
+                }
+            });
+        })
+    };
+
+    let create_restore_handler = |todo_id: String| {
+        let todos = todos.clone();
+        let trash = trash.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            let todos = todos.clone();
+            let trash = trash.clone();
+            let error = error.clone();
+            let todo_id = todo_id.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::restore_todo(&todo_id).await {
+                    Ok(restored_todo) => {
+                        let remaining: Vec<Todo> = (*trash)
+                            .iter()
+                            .filter(|t| t.id != todo_id)
+                            .cloned()
+                            .collect();
+                        trash.set(remaining);
+
+                        let mut current_todos = (*todos).clone();
+                        current_todos.push(restored_todo);
+                        current_todos.sort_by(|a, b| a.position.cmp(&b.position));
+                        todos.set(current_todos);
+                        error.set(None);
+                    }
+                    Err(err) => {
+                        error.set(Some(err));
+                    }
+                }
+            });
+        })
+    };
+
+    let create_purge_handler = |todo_id: String| {
+        let trash = trash.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            let trash = trash.clone();
+            let error = error.clone();
+            let todo_id = todo_id.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::delete_todo_permanently(&todo_id).await {
+                    Ok(()) => {
+                        let remaining: Vec<Todo> = (*trash)
+                            .iter()
+                            .filter(|t| t.id != todo_id)
+                            .cloned()
+                            .collect();
+                        trash.set(remaining);
+                        error.set(None);
+                    }
+                    Err(err) => {
+                        error.set(Some(err));
+                    }
+                }
+            });
+        })
+    };
+
+    // Shift-click selects every todo between the previous selection and this one
+    let create_select_handler = |index: usize, todo_id: String| {
+        let todos = todos.clone();
+        let selected = selected.clone();
+        let last_selected = last_selected.clone();
+
+        Callback::from(move |e: MouseEvent| {
+            let mut current_selection = (*selected).clone();
+
+            match *last_selected {
+                Some(anchor) if e.shift_key() => {
+                    let (start, end) = if anchor <= index { (anchor, index) } else { (index, anchor) };
+                    for todo in todos.iter().skip(start).take(end - start + 1) {
+                        current_selection.insert(todo.id.clone());
+                    }
+                }
+                _ => {
+                    if !current_selection.remove(&todo_id) {
+                        current_selection.insert(todo_id.clone());
+                    }
+                }
+            }
+
+            selected.set(current_selection);
+            last_selected.set(Some(index));
+        })
+    };
+
+    let handle_select_all = {
+        let todos = todos.clone();
+        let selected = selected.clone();
+
+        Callback::from(move |_| {
+            selected.set(todos.iter().map(|t| t.id.clone()).collect());
+        })
+    };
+
+    let handle_clear_selection = {
+        let selected = selected.clone();
+        let last_selected = last_selected.clone();
+
+        Callback::from(move |_| {
+            selected.set(HashSet::new());
+            last_selected.set(None);
+        })
+    };
+
+    let create_bulk_handler = |action: BulkAction, filter: Option<BulkFilter>| {
+        let todos = todos.clone();
+        let error = error.clone();
+        let selected = selected.clone();
+        let last_selected = last_selected.clone();
+
+        Callback::from(move |_| {
+            let todos = todos.clone();
+            let error = error.clone();
+            let selected = selected.clone();
+            let last_selected = last_selected.clone();
+
+            let request = match &filter {
+                Some(filter) => BulkRequest {
+                    action,
+                    ids: None,
+                    filter: Some(BulkFilter {
+                        completed: filter.completed,
+                        deleted: filter.deleted,
+                    }),
+                },
+                None => BulkRequest {
+                    action,
+                    ids: Some(
+                        todos
+                            .iter()
+                            .filter(|t| selected.contains(&t.id))
+                            .map(|t| t.id.clone())
+                            .collect(),
+                    ),
+                    filter: None,
+                },
+            };
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::bulk_update(request).await {
+                    Ok(response) => {
+                        let (current_todos, bulk_error) = apply_bulk_response(&todos, response);
+                        todos.set(current_todos);
+                        selected.set(HashSet::new());
+                        last_selected.set(None);
+                        error.set(bulk_error);
+                    }
+                    Err(err) => {
+                        error.set(Some(err));
+                    }
+                }
+            });
+        })
+    };
+
+    let create_drag_start_handler = |todo_id: String| {
+        let dragged = dragged.clone();
+
+        Callback::from(move |e: DragEvent| {
+            // Firefox only starts a drag when the transfer carries some data
+            if let Some(data_transfer) = e.data_transfer() {
+                let _ = data_transfer.set_data("text/plain", &todo_id);
+            }
+            dragged.set(Some(todo_id.clone()));
+        })
+    };
+
+    let handle_drag_over = Callback::from(|e: DragEvent| {
+        // Allow dropping onto other todos
+        e.prevent_default();
+    });
+
+    let handle_drag_end = {
+        let dragged = dragged.clone();
+        Callback::from(move |_: DragEvent| {
+            dragged.set(None);
+        })
+    };
+
+    // Dropping onto a todo moves the dragged one into its place and persists the new neighbours
+    let create_drop_handler = |target_index: usize| {
+        let todos = todos.clone();
+        let error = error.clone();
+        let dragged = dragged.clone();
+
+        Callback::from(move |e: DragEvent| {
+            e.prevent_default();
+
+            let Some(dragged_id) = (*dragged).clone() else {
+                return;
+            };
+            dragged.set(None);
+
+            let previous_todos = (*todos).clone();
+            let Some(from_index) = previous_todos.iter().position(|t| t.id == dragged_id) else {
+                return;
+            };
+            if from_index == target_index {
+                return;
+            }
+
+            let mut reordered = previous_todos.clone();
+            let moved_todo = reordered.remove(from_index);
+            reordered.insert(target_index, moved_todo);
+
+            let previous_id = target_index.checked_sub(1).map(|i| reordered[i].id.clone());
+            let next_id = reordered.get(target_index + 1).map(|t| t.id.clone());
+            todos.set(reordered);
+
+            let todos = todos.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::move_todo(&dragged_id, previous_id, next_id).await {
+                    Ok(moved_todo) => {
+                        let mut current_todos = previous_todos;
+                        if let Some(index) = current_todos.iter().position(|t| t.id == moved_todo.id) {
+                            current_todos[index] = moved_todo;
+                        }
+                        current_todos.sort_by(|a, b| a.position.cmp(&b.position));
+                        todos.set(current_todos);
                         error.set(None);
                     }
                     Err(err) => {
+                        todos.set(previous_todos);
                         error.set(Some(err));
                     }
                 }
@@ -168,13 +618,53 @@ pub fn todo_list() -> Html {
     html! {
         <div class="max-w-4xl mx-auto px-4">
             <div class="flex justify-between items-center mb-8">
-                <h1 class="text-2xl font-bold text-gray-900">{"My Todos"}</h1>
-                <button
-                    onclick={handle_logout}
-                    class="bg-red-600 hover:bg-red-700 text-white font-bold py-2 px-4 rounded"
-                >
-                    {"Logout"}
-                </button>
+                <h1 class="text-2xl font-bold text-gray-900">
+                    {if *show_trash { "Trash" } else { "My Todos" }}
+                </h1>
+                <div class="flex space-x-2">
+                    <select
+                        onchange={handle_export_format_change}
+                        class="border border-gray-300 rounded py-2 px-2 text-sm text-gray-700"
+                    >
+                        <option value="json" selected={*export_format == "json"}>{"JSON"}</option>
+                        <option value="csv" selected={*export_format == "csv"}>{"CSV"}</option>
+                        <option value="md" selected={*export_format == "md"}>{"Markdown"}</option>
+                        <option value="todotxt" selected={*export_format == "todotxt"}>{"todo.txt"}</option>
+                    </select>
+                    <button
+                        onclick={handle_export}
+                        class="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded"
+                    >
+                        {"Export"}
+                    </button>
+                    <Link<Route> to={Route::Import} classes="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded">
+                        {"Import"}
+                    </Link<Route>>
+                    <Link<Route> to={Route::Settings} classes="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded">
+                        {"Settings"}
+                    </Link<Route>>
+                    {if *is_admin {
+                        html! {
+                            <Link<Route> to={Route::Admin} classes="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded">
+                                {"Admin"}
+                            </Link<Route>>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                    <button
+                        onclick={handle_toggle_trash}
+                        class="bg-gray-200 hover:bg-gray-300 text-gray-800 font-bold py-2 px-4 rounded"
+                    >
+                        {if *show_trash { "Back to Todos" } else { "Trash" }}
+                    </button>
+                    <button
+                        onclick={handle_logout}
+                        class="bg-red-600 hover:bg-red-700 text-white font-bold py-2 px-4 rounded"
+                    >
+                        {"Logout"}
+                    </button>
+                </div>
             </div>
 
             {if let Some(error_msg) = error.as_ref() {
@@ -187,111 +677,335 @@ pub fn todo_list() -> Html {
                 html! {}
             }}
 
-            // Create new todo form
-            <div class="bg-white shadow rounded-lg p-6 mb-6">
-                <h2 class="text-lg font-semibold text-gray-900 mb-4">{"Add New Todo"}</h2>
-                <form onsubmit={handle_create_todo}>
-                    <div class="mb-4">
-                        <label for="title" class="block text-sm font-medium text-gray-700 mb-2">
-                            {"Title"}
-                        </label>
-                        <input
-                            ref={title_ref}
-                            type="text"
-                            id="title"
-                            class="block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500"
-                            placeholder="Enter todo title"
-                            value={(*new_todo_title).clone()}
-                        />
-                    </div>
-                    <div class="mb-4">
-                        <label for="description" class="block text-sm font-medium text-gray-700 mb-2">
-                            {"Description (Optional)"}
-                        </label>
-                        <input
-                            ref={description_ref}
-                            type="text"
-                            id="description"
-                            class="block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500"
-                            placeholder="Enter todo description"
-                            value={(*new_todo_description).clone()}
-                        />
-                    </div>
-                    <button
-                        type="submit"
-                        class="bg-indigo-600 hover:bg-indigo-700 text-white font-bold py-2 px-4 rounded"
-                    >
-                        {"Add Todo"}
-                    </button>
-                </form>
-            </div>
+            {if *show_trash {
+                html! {
+                    <div class="space-y-4">
+                        <p class="text-sm text-gray-500">
+                            {"Todos in the trash are permanently deleted after the retention period."}
+                        </p>
+                        {for trash.iter().map(|todo| {
+                            let restore_handler = create_restore_handler(todo.id.clone());
+                            let purge_handler = create_purge_handler(todo.id.clone());
+
+                            html! {
+                                <div key={todo.id.clone()} class="bg-white shadow rounded-lg p-6 opacity-75">
+                                    <div class="flex items-center justify-between">
+                                        <div>
+                                            <h3 class="text-lg font-medium text-gray-500">{&todo.title}</h3>
+                                            {if let Some(deleted_at) = todo.deleted_at {
+                                                html! {
+                                                    <p class="text-xs text-gray-400 mt-1">
+                                                        {"Deleted: "}{deleted_at.format("%Y-%m-%d %H:%M").to_string()}
+                                                    </p>
+                                                }
+                                            } else {
+                                                html! {}
+                                            }}
+                                        </div>
+                                        <div class="flex space-x-4">
+                                            <button
+                                                onclick={restore_handler}
+                                                class="text-indigo-600 hover:text-indigo-800"
+                                            >
+                                                {"Restore"}
+                                            </button>
+                                            <button
+                                                onclick={purge_handler}
+                                                class="text-red-600 hover:text-red-800"
+                                            >
+                                                {"Delete forever"}
+                                            </button>
+                                        </div>
+                                    </div>
+                                </div>
+                            }
+                        })}
 
-            // Todo list
-            <div class="space-y-4">
-                {for todos.iter().map(|todo| {
-                    let toggle_handler = create_toggle_handler(todo.id.clone(), todo.completed);
-                    let delete_handler = create_delete_handler(todo.id.clone());
-
-                    html! {
-                        <div key={todo.id.clone()} class={format!(
-                            "bg-white shadow rounded-lg p-6 {}",
-                            if todo.completed { "opacity-75" } else { "" }
-                        )}>
-                            <div class="flex items-center justify-between">
-                                <div class="flex items-center space-x-3">
+                        {if trash.is_empty() {
+                            html! {
+                                <div class="text-center py-12">
+                                    <div class="text-gray-500 text-lg">{"Trash is empty."}</div>
+                                </div>
+                            }
+                        } else {
+                            html! {}
+                        }}
+                    </div>
+                }
+            } else {
+                html! {
+                    <>
+                        // Create new todo form
+                        <div class="bg-white shadow rounded-lg p-6 mb-6">
+                            <h2 class="text-lg font-semibold text-gray-900 mb-4">{"Add New Todo"}</h2>
+                            <form onsubmit={handle_create_todo}>
+                                <div class="mb-4">
+                                    <label for="title" class="block text-sm font-medium text-gray-700 mb-2">
+                                        {"Title"}
+                                    </label>
                                     <input
-                                        type="checkbox"
-                                        checked={todo.completed}
-                                        onchange={toggle_handler}
-                                        class="h-4 w-4 text-indigo-600 focus:ring-indigo-500 border-gray-300 rounded"
+                                        ref={title_ref}
+                                        type="text"
+                                        id="title"
+                                        class="block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500"
+                                        placeholder="Enter todo title"
+                                        value={(*new_todo_title).clone()}
                                     />
+                                </div>
+                                <div class="mb-4">
+                                    <label for="description" class="block text-sm font-medium text-gray-700 mb-2">
+                                        {"Description (Optional)"}
+                                    </label>
+                                    <input
+                                        ref={description_ref}
+                                        type="text"
+                                        id="description"
+                                        class="block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500"
+                                        placeholder="Enter todo description"
+                                        value={(*new_todo_description).clone()}
+                                    />
+                                </div>
+                                <div class="flex space-x-4 mb-4">
                                     <div>
-                                        <h3 class={format!(
-                                            "text-lg font-medium {}",
-                                            if todo.completed { "line-through text-gray-500" } else { "text-gray-900" }
-                                        )}>
-                                            {&todo.title}
-                                        </h3>
-                                        {if let Some(description) = &todo.description {
-                                            html! {
-                                                <p class={format!(
-                                                    "text-sm {}",
-                                                    if todo.completed { "text-gray-400" } else { "text-gray-600" }
-                                                )}>
-                                                    {description}
-                                                </p>
-                                            }
-                                        } else {
-                                            html! {}
-                                        }}
-                                        <p class="text-xs text-gray-400 mt-1">
-                                            {"Created: "}{todo.created_at.format("%Y-%m-%d %H:%M").to_string()}
-                                        </p>
+                                        <label for="due" class="block text-sm font-medium text-gray-700 mb-2">
+                                            {"Due (Optional)"}
+                                        </label>
+                                        <input
+                                            ref={due_ref}
+                                            type="datetime-local"
+                                            id="due"
+                                            class="block px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500"
+                                        />
+                                    </div>
+                                    <div>
+                                        <label for="priority" class="block text-sm font-medium text-gray-700 mb-2">
+                                            {"Priority"}
+                                        </label>
+                                        <select
+                                            ref={priority_ref}
+                                            id="priority"
+                                            class="block px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500"
+                                        >
+                                            <option value="">{"None"}</option>
+                                            <option value="1">{"High"}</option>
+                                            <option value="5">{"Medium"}</option>
+                                            <option value="9">{"Low"}</option>
+                                        </select>
+                                    </div>
+                                    <div>
+                                        <label for="remind" class="block text-sm font-medium text-gray-700 mb-2">
+                                            {"Remind me (Optional)"}
+                                        </label>
+                                        <input
+                                            ref={remind_ref}
+                                            type="datetime-local"
+                                            id="remind"
+                                            class="block px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500"
+                                        />
                                     </div>
                                 </div>
                                 <button
-                                    onclick={delete_handler}
-                                    class="text-red-600 hover:text-red-800"
+                                    type="submit"
+                                    class="bg-indigo-600 hover:bg-indigo-700 text-white font-bold py-2 px-4 rounded"
                                 >
-                                    <svg class="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24">
-                                        <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M19 7l-.867 12.142A2 2 0 0116.138 21H7.862a2 2 0 01-1.995-1.858L5 7m5 4v6m4-6v6m1-10V4a1 1 0 00-1-1h-4a1 1 0 00-1 1v3M4 7h16"></path>
-                                    </svg>
+                                    {"Add Todo"}
                                 </button>
-                            </div>
+                            </form>
                         </div>
-                    }
-                })}
 
-                {if todos.is_empty() {
-                    html! {
-                        <div class="text-center py-12">
-                            <div class="text-gray-500 text-lg">{"No todos yet! Create your first todo above."}</div>
+                        // Bulk actions
+                        {if selected.is_empty() {
+                            html! {
+                                <div class="flex justify-end space-x-4 mb-4 text-sm">
+                                    {if todos.is_empty() {
+                                        html! {}
+                                    } else {
+                                        html! {
+                                            <button onclick={handle_select_all} class="text-indigo-600 hover:text-indigo-800">
+                                                {"Select all"}
+                                            </button>
+                                        }
+                                    }}
+                                    {if todos.iter().any(|t| t.completed) {
+                                        html! {
+                                            <button
+                                                onclick={create_bulk_handler(BulkAction::Delete, Some(BulkFilter { completed: Some(true), deleted: false }))}
+                                                class="text-red-600 hover:text-red-800"
+                                            >
+                                                {"Clear completed"}
+                                            </button>
+                                        }
+                                    } else {
+                                        html! {}
+                                    }}
+                                </div>
+                            }
+                        } else {
+                            html! {
+                                <div class="sticky top-0 z-10 bg-indigo-50 border border-indigo-200 rounded-lg px-4 py-3 mb-4 flex items-center justify-between">
+                                    <span class="text-sm font-medium text-indigo-900">
+                                        {format!("{} selected", selected.len())}
+                                    </span>
+                                    <div class="flex space-x-2">
+                                        <button
+                                            onclick={create_bulk_handler(BulkAction::Complete, None)}
+                                            class="bg-indigo-600 hover:bg-indigo-700 text-white text-sm font-bold py-1 px-3 rounded"
+                                        >
+                                            {"Complete"}
+                                        </button>
+                                        <button
+                                            onclick={create_bulk_handler(BulkAction::Uncomplete, None)}
+                                            class="bg-gray-200 hover:bg-gray-300 text-gray-800 text-sm font-bold py-1 px-3 rounded"
+                                        >
+                                            {"Mark incomplete"}
+                                        </button>
+                                        <button
+                                            onclick={create_bulk_handler(BulkAction::Delete, None)}
+                                            class="bg-red-600 hover:bg-red-700 text-white text-sm font-bold py-1 px-3 rounded"
+                                        >
+                                            {"Delete"}
+                                        </button>
+                                        <button
+                                            onclick={handle_clear_selection}
+                                            class="text-sm text-gray-600 hover:text-gray-800 px-2"
+                                        >
+                                            {"Clear selection"}
+                                        </button>
+                                    </div>
+                                </div>
+                            }
+                        }}
+
+                        // Todo list
+                        <div class="space-y-4">
+                            {for todos.iter().enumerate().map(|(index, todo)| {
+                                let toggle_handler = create_toggle_handler(todo.id.clone(), todo.completed);
+                                let delete_handler = create_delete_handler(todo.id.clone());
+                                let select_handler = create_select_handler(index, todo.id.clone());
+                                let drag_start_handler = create_drag_start_handler(todo.id.clone());
+                                let drop_handler = create_drop_handler(index);
+                                let is_selected = selected.contains(&todo.id);
+                                let is_dragged = dragged.as_ref() == Some(&todo.id);
+
+                                html! {
+                                    <div
+                                        key={todo.id.clone()}
+                                        draggable="true"
+                                        ondragstart={drag_start_handler}
+                                        ondragover={handle_drag_over.clone()}
+                                        ondrop={drop_handler}
+                                        ondragend={handle_drag_end.clone()}
+                                        class={format!(
+                                            "bg-white shadow rounded-lg p-6 cursor-move {} {} {}",
+                                            if todo.completed { "opacity-75" } else { "" },
+                                            if is_selected { "ring-2 ring-indigo-500" } else { "" },
+                                            if is_dragged { "opacity-50" } else { "" }
+                                        )}
+                                    >
+                                        <div class="flex items-center justify-between">
+                                            <div class="flex items-center space-x-3">
+                                                <input
+                                                    type="checkbox"
+                                                    title="Select (shift-click to select a range)"
+                                                    checked={is_selected}
+                                                    onclick={select_handler}
+                                                    class="h-4 w-4 rounded-full border-gray-300 text-gray-600 focus:ring-gray-500"
+                                                />
+                                                <input
+                                                    type="checkbox"
+                                                    checked={todo.completed}
+                                                    onchange={toggle_handler}
+                                                    class="h-4 w-4 text-indigo-600 focus:ring-indigo-500 border-gray-300 rounded"
+                                                />
+                                                <div>
+                                                    <h3 class={format!(
+                                                        "text-lg font-medium {}",
+                                                        if todo.completed { "line-through text-gray-500" } else { "text-gray-900" }
+                                                    )}>
+                                                        <Link<Route> to={Route::TodoDetail { id: todo.id.clone() }}>
+                                                            {&todo.title}
+                                                        </Link<Route>>
+                                                    </h3>
+                                                    {if let Some(description) = &todo.description {
+                                                        html! {
+                                                            <p class={format!(
+                                                                "text-sm {}",
+                                                                if todo.completed { "text-gray-400" } else { "text-gray-600" }
+                                                            )}>
+                                                                {description}
+                                                            </p>
+                                                        }
+                                                    } else {
+                                                        html! {}
+                                                    }}
+                                                    <p class="text-xs text-gray-400 mt-1">
+                                                        {"Created: "}{todo.created_at.format("%Y-%m-%d %H:%M").to_string()}
+                                                        {if let Some(due_at) = todo.due_at {
+                                                            html! {
+                                                                <span class={if !todo.completed && due_at < Utc::now() { "text-red-600" } else { "" }}>
+                                                                    {" · Due: "}{due_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()}
+                                                                </span>
+                                                            }
+                                                        } else {
+                                                            html! {}
+                                                        }}
+                                                        {if let Some(priority) = todo.priority {
+                                                            html! { <span>{" · "}{priority_label(priority)}{" priority"}</span> }
+                                                        } else {
+                                                            html! {}
+                                                        }}
+                                                        {if let Some(remind_at) = todo.remind_at {
+                                                            html! {
+                                                                <span>{" · Reminder: "}{remind_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()}</span>
+                                                            }
+                                                        } else {
+                                                            html! {}
+                                                        }}
+                                                    </p>
+                                                </div>
+                                            </div>
+                                            <button
+                                                onclick={delete_handler}
+                                                class="text-red-600 hover:text-red-800"
+                                            >
+                                                <svg class="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                                                    <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M19 7l-.867 12.142A2 2 0 0116.138 21H7.862a2 2 0 01-1.995-1.858L5 7m5 4v6m4-6v6m1-10V4a1 1 0 00-1-1h-4a1 1 0 00-1 1v3M4 7h16"></path>
+                                                </svg>
+                                            </button>
+                                        </div>
+                                    </div>
+                                }
+                            })}
+
+                            {if todos.is_empty() {
+                                html! {
+                                    <div class="text-center py-12">
+                                        <div class="text-gray-500 text-lg">{"No todos yet! Create your first todo above."}</div>
+                                    </div>
+                                }
+                            } else {
+                                html! {}
+                            }}
                         </div>
-                    }
-                } else {
-                    html! {}
-                }}
-            </div>
+                    </>
+                }
+            }}
+
+            {if recently_deleted.is_some() {
+                html! {
+                    <div class="fixed bottom-6 left-1/2 transform -translate-x-1/2 bg-gray-900 text-white px-4 py-3 rounded-lg shadow-lg flex items-center space-x-4">
+                        <span>{"Todo moved to trash"}</span>
+                        <button
+                            onclick={handle_undo_delete}
+                            class="font-bold text-indigo-300 hover:text-indigo-100"
+                        >
+                            {"Undo"}
+                        </button>
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
         </div>
     }
 }
\ No newline at end of file
@@ -0,0 +1,380 @@
+use std::collections::HashSet;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use todo_api_types::{CreateWebhookRequest, CreatedWebhook, Webhook, WebhookDelivery, WebhookUpdate};
+use crate::{
+    services::api::ApiService,
+};
+
+const EVENTS: [(&str, &str); 4] = [
+    ("todo.created", "Created"),
+    ("todo.updated", "Updated"),
+    ("todo.completed", "Completed"),
+    ("todo.deleted", "Deleted"),
+];
+
+fn status_classes(status: &str) -> &'static str {
+    match status {
+        "succeeded" => "bg-green-100 text-green-800",
+        "failed" => "bg-red-100 text-red-800",
+        _ => "bg-yellow-100 text-yellow-800",
+    }
+}
+
+// Webhook subscriptions and their delivery logs, shown on the settings page
+#[function_component(WebhookSettings)]
+pub fn webhook_settings() -> Html {
+    let webhooks = use_state(Vec::<Webhook>::new);
+    let new_events = use_state(|| EVENTS.iter().map(|(event, _)| event.to_string()).collect::<HashSet<_>>());
+    let created = use_state(|| None::<CreatedWebhook>);
+    // Webhook whose delivery log is open, with its deliveries
+    let open_log = use_state(|| None::<(String, Vec<WebhookDelivery>)>);
+    let error = use_state(|| None::<String>);
+
+    let url_ref = use_node_ref();
+    let secret_ref = use_node_ref();
+
+    // Load webhooks on mount
+    {
+        let webhooks = webhooks.clone();
+        let error = error.clone();
+
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::get_webhooks().await {
+                    Ok(fetched_webhooks) => webhooks.set(fetched_webhooks),
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+            || ()
+        });
+    }
+
+    let create_event_toggle = |event: &'static str| {
+        let new_events = new_events.clone();
+        Callback::from(move |_| {
+            let mut events = (*new_events).clone();
+            if !events.remove(event) {
+                events.insert(event.to_string());
+            }
+            new_events.set(events);
+        })
+    };
+
+    let handle_create = {
+        let webhooks = webhooks.clone();
+        let new_events = new_events.clone();
+        let created = created.clone();
+        let error = error.clone();
+        let url_ref = url_ref.clone();
+        let secret_ref = secret_ref.clone();
+
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+
+            let (Some(url_input), Some(secret_input)) = (
+                url_ref.cast::<HtmlInputElement>(),
+                secret_ref.cast::<HtmlInputElement>(),
+            ) else {
+                return;
+            };
+            let request = CreateWebhookRequest {
+                url: url_input.value(),
+                // Keep the order the events are listed in
+                events: EVENTS
+                    .iter()
+                    .map(|(event, _)| event.to_string())
+                    .filter(|event| new_events.contains(event))
+                    .collect(),
+                secret: Some(secret_input.value()).filter(|s| !s.is_empty()),
+            };
+
+            let webhooks = webhooks.clone();
+            let created = created.clone();
+            let error = error.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::create_webhook(request).await {
+                    Ok(new_webhook) => {
+                        url_input.set_value("");
+                        secret_input.set_value("");
+                        let mut current_webhooks = (*webhooks).clone();
+                        current_webhooks.insert(0, new_webhook.webhook.clone());
+                        webhooks.set(current_webhooks);
+                        created.set(Some(new_webhook));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let create_active_toggle = |webhook: &Webhook| {
+        let webhooks = webhooks.clone();
+        let error = error.clone();
+        let id = webhook.id.clone();
+        let active = webhook.active;
+
+        Callback::from(move |_| {
+            let webhooks = webhooks.clone();
+            let error = error.clone();
+            let id = id.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let update = WebhookUpdate { url: None, events: None, active: Some(!active) };
+                match ApiService::update_webhook(&id, update).await {
+                    Ok(updated) => {
+                        let mut current_webhooks = (*webhooks).clone();
+                        if let Some(index) = current_webhooks.iter().position(|w| w.id == id) {
+                            current_webhooks[index] = updated;
+                        }
+                        webhooks.set(current_webhooks);
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let create_delete_handler = |id: String| {
+        let webhooks = webhooks.clone();
+        let open_log = open_log.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            let webhooks = webhooks.clone();
+            let open_log = open_log.clone();
+            let error = error.clone();
+            let id = id.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::delete_webhook(&id).await {
+                    Ok(()) => {
+                        let mut current_webhooks = (*webhooks).clone();
+                        current_webhooks.retain(|w| w.id != id);
+                        webhooks.set(current_webhooks);
+                        if open_log.as_ref().is_some_and(|(open_id, _)| *open_id == id) {
+                            open_log.set(None);
+                        }
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    // Opens (or refreshes) the delivery log of a webhook, or closes it if it's open
+    let create_log_handler = |id: String, refresh: bool| {
+        let open_log = open_log.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            if !refresh && open_log.as_ref().is_some_and(|(open_id, _)| *open_id == id) {
+                open_log.set(None);
+                return;
+            }
+
+            let open_log = open_log.clone();
+            let error = error.clone();
+            let id = id.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match ApiService::get_webhook_deliveries(&id).await {
+                    Ok(deliveries) => {
+                        open_log.set(Some((id, deliveries)));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    let create_retry_handler = |id: String, delivery_id: String| {
+        let open_log = open_log.clone();
+        let error = error.clone();
+
+        Callback::from(move |_| {
+            let open_log = open_log.clone();
+            let error = error.clone();
+            let id = id.clone();
+            let delivery_id = delivery_id.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = match ApiService::retry_webhook_delivery(&id, &delivery_id).await {
+                    Ok(()) => ApiService::get_webhook_deliveries(&id).await,
+                    Err(err) => Err(err),
+                };
+                match result {
+                    Ok(deliveries) => {
+                        open_log.set(Some((id, deliveries)));
+                        error.set(None);
+                    }
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    html! {
+        <div class="bg-white shadow rounded-lg p-6 mt-6">
+            <h2 class="text-lg font-semibold text-gray-900 mb-2">{"Webhooks"}</h2>
+            <p class="text-sm text-gray-600 mb-4">
+                {"We POST a JSON payload to your URL when a todo changes. Each request carries an "}
+                <code class="font-mono">{"X-Todo-Signature-256"}</code>
+                {" header: the HMAC-SHA256 of the body keyed with the webhook's secret. Failed deliveries are retried with exponential backoff."}
+            </p>
+
+            {if let Some(error_msg) = error.as_ref() {
+                html! {
+                    <div class="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4">
+                        {error_msg}
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+
+            {if let Some(new_webhook) = created.as_ref() {
+                html! {
+                    <div class="bg-green-50 border border-green-300 text-green-800 px-4 py-3 rounded mb-4">
+                        <p class="text-sm mb-2">{"Signing secret for "}{&new_webhook.webhook.url}{". Copy it now, it won't be shown again:"}</p>
+                        <code class="font-mono text-sm break-all">{&new_webhook.secret}</code>
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+
+            <form onsubmit={handle_create} class="mb-6 space-y-3">
+                <input
+                    ref={url_ref}
+                    type="url"
+                    placeholder="https://example.com/hooks/todos"
+                    class="block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500"
+                />
+                <input
+                    ref={secret_ref}
+                    type="text"
+                    placeholder="Secret (optional, generated if empty)"
+                    class="block w-full px-3 py-2 border border-gray-300 rounded-md shadow-sm focus:outline-none focus:ring-indigo-500 focus:border-indigo-500"
+                />
+                <div class="flex items-center space-x-4 text-sm text-gray-700">
+                    {for EVENTS.iter().map(|(event, label)| html! {
+                        <label class="flex items-center space-x-1">
+                            <input
+                                type="checkbox"
+                                checked={new_events.contains(*event)}
+                                onchange={create_event_toggle(event)}
+                                class="h-4 w-4 text-indigo-600 border-gray-300 rounded"
+                            />
+                            <span>{*label}</span>
+                        </label>
+                    })}
+                </div>
+                <button
+                    type="submit"
+                    class="bg-indigo-600 hover:bg-indigo-700 text-white font-bold py-2 px-4 rounded"
+                >
+                    {"Add webhook"}
+                </button>
+            </form>
+
+            <ul class="divide-y divide-gray-200">
+                {for webhooks.iter().map(|webhook| {
+                    let log = open_log
+                        .as_ref()
+                        .filter(|(open_id, _)| *open_id == webhook.id)
+                        .map(|(_, deliveries)| deliveries);
+
+                    html! {
+                        <li key={webhook.id.clone()} class="py-3">
+                            <div class="flex items-center justify-between">
+                                <div>
+                                    <p class={format!("font-mono text-sm {}", if webhook.active { "text-gray-900" } else { "text-gray-400 line-through" })}>
+                                        {&webhook.url}
+                                    </p>
+                                    <p class="text-xs text-gray-400">{webhook.events.join(", ")}</p>
+                                </div>
+                                <div class="flex space-x-4 text-sm">
+                                    <button onclick={create_log_handler(webhook.id.clone(), false)} class="text-indigo-600 hover:text-indigo-800">
+                                        {if log.is_some() { "Hide deliveries" } else { "Deliveries" }}
+                                    </button>
+                                    <button onclick={create_active_toggle(webhook)} class="text-gray-600 hover:text-gray-800">
+                                        {if webhook.active { "Disable" } else { "Enable" }}
+                                    </button>
+                                    <button onclick={create_delete_handler(webhook.id.clone())} class="text-red-600 hover:text-red-800">
+                                        {"Delete"}
+                                    </button>
+                                </div>
+                            </div>
+
+                            {if let Some(deliveries) = log {
+                                html! {
+                                    <div class="mt-3 bg-gray-50 rounded p-3">
+                                        <div class="flex justify-end mb-2">
+                                            <button onclick={create_log_handler(webhook.id.clone(), true)} class="text-xs text-indigo-600 hover:text-indigo-800">
+                                                {"Refresh"}
+                                            </button>
+                                        </div>
+                                        <table class="w-full text-xs">
+                                            <thead>
+                                                <tr class="text-left text-gray-500">
+                                                    <th class="py-1">{"Time"}</th>
+                                                    <th class="py-1">{"Event"}</th>
+                                                    <th class="py-1">{"Status"}</th>
+                                                    <th class="py-1">{"Attempts"}</th>
+                                                    <th class="py-1">{"Response"}</th>
+                                                    <th class="py-1"></th>
+                                                </tr>
+                                            </thead>
+                                            <tbody>
+                                                {for deliveries.iter().map(|delivery| html! {
+                                                    <tr key={delivery.id.clone()} class="border-t border-gray-200">
+                                                        <td class="py-1">{delivery.created_at.format("%Y-%m-%d %H:%M:%S").to_string()}</td>
+                                                        <td class="py-1">{&delivery.event_type}</td>
+                                                        <td class="py-1">
+                                                            <span class={format!("px-2 rounded {}", status_classes(&delivery.status))}>{&delivery.status}</span>
+                                                        </td>
+                                                        <td class="py-1">{delivery.attempts}</td>
+                                                        <td class="py-1 text-gray-600">
+                                                            {delivery.last_error.clone().or_else(|| delivery.response_status.map(|s| s.to_string())).unwrap_or_default()}
+                                                        </td>
+                                                        <td class="py-1 text-right">
+                                                            {if delivery.status == "failed" {
+                                                                html! {
+                                                                    <button
+                                                                        onclick={create_retry_handler(webhook.id.clone(), delivery.id.clone())}
+                                                                        class="text-indigo-600 hover:text-indigo-800"
+                                                                    >
+                                                                        {"Retry"}
+                                                                    </button>
+                                                                }
+                                                            } else {
+                                                                html! {}
+                                                            }}
+                                                        </td>
+                                                    </tr>
+                                                })}
+                                            </tbody>
+                                        </table>
+                                        {if deliveries.is_empty() {
+                                            html! { <p class="text-xs text-gray-500 mt-2">{"No deliveries yet."}</p> }
+                                        } else {
+                                            html! {}
+                                        }}
+                                    </div>
+                                }
+                            } else {
+                                html! {}
+                            }}
+                        </li>
+                    }
+                })}
+            </ul>
+        </div>
+    }
+}
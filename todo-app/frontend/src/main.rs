@@ -5,7 +5,7 @@ mod components;
 mod services;
 mod types;
 
-use components::{Auth, TodoList};
+use components::{AdminPanel, Auth, ImportWizard, Settings, TodoDetail, TodoList};
 use services::auth::AuthService;
 use types::Route;
 
@@ -35,6 +35,34 @@ fn app() -> Html{
                                     }
                                 }
                                 Route::Login => html! {<Auth/>},
+                                Route::TodoDetail { id } => {
+                                    if auth_service.is_logged_in(){
+                                        html!{<TodoDetail id={id}/>}
+                                    }else{
+                                        html!{<Auth/>}
+                                    }
+                                }
+                                Route::Import => {
+                                    if auth_service.is_logged_in(){
+                                        html!{<ImportWizard/>}
+                                    }else{
+                                        html!{<Auth/>}
+                                    }
+                                }
+                                Route::Settings => {
+                                    if auth_service.is_logged_in(){
+                                        html!{<Settings/>}
+                                    }else{
+                                        html!{<Auth/>}
+                                    }
+                                }
+                                Route::Admin => {
+                                    if auth_service.is_logged_in(){
+                                        html!{<AdminPanel/>}
+                                    }else{
+                                        html!{<Auth/>}
+                                    }
+                                }
                             }
                         }}/>
                     </div>
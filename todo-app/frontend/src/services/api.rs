@@ -1,10 +1,22 @@
+use chrono::{DateTime, Utc};
 use gloo_net::http::Request;
 use gloo_storage::{LocalStorage, Storage};
-use crate::types::{CreateTodoRequest, Todo, TodoUpdate};
+use web_sys::FormData;
+use todo_api_types::{
+    routes, AdminStats, AdminUser, AdminUserUpdate, AppPassword, BulkRequest, ChangePasswordRequest, DeleteAccountRequest, User, UserUpdate, BulkResponse, CalendarFeed, CreateAppPasswordRequest,
+    CreatedAppPassword, CreateTodoRequest, CreateWebhookRequest, CreatedWebhook, CsvMapping, ImportReport, MoveTodoRequest,
+    NotificationPreferences, NotificationPreferencesUpdate, PushPublicKey, PushUnsubscribeRequest, Todo,
+    TodoEvent, TodoUpdate, Webhook, WebhookDelivery, WebhookUpdate,
+};
 
-const API_BASE_URL: &str = "http://127.0.0.1:3001/api";
+const SERVER_URL: &str = "http://127.0.0.1:3001";
 const TOKEN_KEY: &str = "auth_token";
 
+// The full URL of an API route, with its parameters filled in
+fn url(route: &str, params: &[&str]) -> String {
+    format!("{}{}", SERVER_URL, routes::path(route, params))
+}
+
 pub struct ApiService;
 
 impl ApiService {
@@ -14,10 +26,242 @@ impl ApiService {
         Ok(format!("Bearer {}", token))
     }
 
+    pub async fn get_me() -> Result<User, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::ME, &[]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let user: User = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(user)
+        } else {
+            Err(format!("Failed to fetch profile: {}", response.status()))
+        }
+    }
+
+    pub async fn update_me(update: UserUpdate) -> Result<User, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::patch(&url(routes::ME, &[]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&update)
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let user: User = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(user)
+        } else {
+            match response.status() {
+                400 => Err("Check the email address, time zone, language and avatar URL".to_string()),
+                409 => Err("That username or email address is already taken".to_string()),
+                status => Err(format!("Failed to update profile: {}", status)),
+            }
+        }
+    }
+
+    pub async fn change_password(current_password: String, new_password: String) -> Result<(), String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::post(&url(routes::ME_PASSWORD, &[]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&ChangePasswordRequest { current_password, new_password })
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            Ok(())
+        } else {
+            match response.status() {
+                400 => Err("Enter a new password".to_string()),
+                403 => Err("The current password is wrong".to_string()),
+                status => Err(format!("Failed to change password: {}", status)),
+            }
+        }
+    }
+
+    // Schedules the account for deletion after the server's grace period
+    pub async fn delete_account(password: String) -> Result<User, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::delete(&url(routes::ME, &[]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&DeleteAccountRequest { password })
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let user: User = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(user)
+        } else {
+            match response.status() {
+                403 => Err("The password is wrong".to_string()),
+                status => Err(format!("Failed to delete account: {}", status)),
+            }
+        }
+    }
+
+    pub async fn restore_account() -> Result<User, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::post(&url(routes::ME_RESTORE, &[]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let user: User = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(user)
+        } else {
+            Err(format!("Failed to cancel account deletion: {}", response.status()))
+        }
+    }
+
+    // ZIP archive of everything stored about the user
+    pub async fn export_account() -> Result<Vec<u8>, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::ME_EXPORT, &[]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            response
+                .binary()
+                .await
+                .map_err(|e| format!("Read error: {}", e))
+        } else {
+            Err(format!("Failed to export account data: {}", response.status()))
+        }
+    }
+
+    pub async fn get_admin_stats() -> Result<AdminStats, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::ADMIN_STATS, &[]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let stats: AdminStats = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(stats)
+        } else {
+            match response.status() {
+                403 => Err("Only administrators can see this page".to_string()),
+                status => Err(format!("Failed to fetch stats: {}", status)),
+            }
+        }
+    }
+
+    // Users whose username, email or display name contain `query`
+    pub async fn get_admin_users(query: &str) -> Result<Vec<AdminUser>, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::ADMIN_USERS, &[]))
+            .query([("q", query)])
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let users: Vec<AdminUser> = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(users)
+        } else {
+            Err(format!("Failed to fetch users: {}", response.status()))
+        }
+    }
+
+    pub async fn update_admin_user(id: &str, update: AdminUserUpdate) -> Result<AdminUser, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::patch(&url(routes::ADMIN_USER, &[id]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&update)
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let user: AdminUser = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(user)
+        } else {
+            match response.status() {
+                400 => Err("You can't remove your own admin role".to_string()),
+                status => Err(format!("Failed to update user: {}", status)),
+            }
+        }
+    }
+
+    // `action` is `routes::ADMIN_USER_DISABLE`, `ADMIN_USER_ENABLE` or `ADMIN_USER_RESET_PASSWORD`
+    pub async fn admin_user_action(id: &str, action: &str) -> Result<AdminUser, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::post(&url(action, &[id]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let user: AdminUser = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(user)
+        } else {
+            match response.status() {
+                400 => Err("You can't disable your own account".to_string()),
+                status => Err(format!("Failed to update user: {}", status)),
+            }
+        }
+    }
+
     pub async fn get_todos() -> Result<Vec<Todo>, String> {
         let auth_header = Self::get_auth_header()?;
 
-        let response = Request::get(&format!("{}/todos", API_BASE_URL))
+        let response = Request::get(&url(routes::TODOS, &[]))
             .header("Authorization", &auth_header)
             .send()
             .await
@@ -34,11 +278,57 @@ impl ApiService {
         }
     }
 
-    pub async fn create_todo(title: String, description: Option<String>) -> Result<Todo, String> {
+    pub async fn get_todo(id: &str) -> Result<Todo, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::TODO, &[id]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let todo: Todo = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(todo)
+        } else {
+            Err(format!("Failed to fetch todo: {}", response.status()))
+        }
+    }
+
+    pub async fn get_todo_history(id: &str) -> Result<Vec<TodoEvent>, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::TODO_HISTORY, &[id]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let events: Vec<TodoEvent> = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(events)
+        } else {
+            Err(format!("Failed to fetch todo history: {}", response.status()))
+        }
+    }
+
+    pub async fn create_todo(
+        title: String,
+        description: Option<String>,
+        due_at: Option<DateTime<Utc>>,
+        priority: Option<i16>,
+        remind_at: Option<DateTime<Utc>>,
+    ) -> Result<Todo, String> {
         let auth_header = Self::get_auth_header()?;
-        let request = CreateTodoRequest { title, description };
+        let request = CreateTodoRequest { title, description, due_at, priority, remind_at };
 
-        let response = Request::post(&format!("{}/todos", API_BASE_URL))
+        let response = Request::post(&url(routes::TODOS, &[]))
             .header("Authorization", &auth_header)
             .header("Content-Type", "application/json")
             .json(&request)
@@ -61,7 +351,7 @@ impl ApiService {
     pub async fn update_todo(id: &str, update: TodoUpdate) -> Result<Todo, String> {
         let auth_header = Self::get_auth_header()?;
 
-        let response = Request::patch(&format!("{}/todos/{}", API_BASE_URL, id))
+        let response = Request::patch(&url(routes::TODO, &[id]))
             .header("Authorization", &auth_header)
             .header("Content-Type", "application/json")
             .json(&update)
@@ -84,7 +374,7 @@ impl ApiService {
     pub async fn delete_todo(id: &str) -> Result<(), String> {
         let auth_header = Self::get_auth_header()?;
 
-        let response = Request::delete(&format!("{}/todos/{}", API_BASE_URL, id))
+        let response = Request::delete(&url(routes::TODO, &[id]))
             .header("Authorization", &auth_header)
             .send()
             .await
@@ -97,21 +387,529 @@ impl ApiService {
         }
     }
 
-    pub async fn toggle_todo_completion(id: &str, completed: bool) -> Result<Todo, String> {
-        let update = TodoUpdate {
-            title: None,
-            description: None,
-            completed: Some(!completed),
-        };
-        Self::update_todo(id, update).await
+    pub async fn move_todo(id: &str, previous_id: Option<String>, next_id: Option<String>) -> Result<Todo, String> {
+        let auth_header = Self::get_auth_header()?;
+        let request = MoveTodoRequest { previous_id, next_id };
+
+        let response = Request::post(&url(routes::TODO_MOVE, &[id]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let todo: Todo = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(todo)
+        } else {
+            Err(format!("Failed to move todo: {}", response.status()))
+        }
+    }
+
+    pub async fn bulk_update(request: BulkRequest) -> Result<BulkResponse, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::post(&url(routes::TODOS_BULK, &[]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let bulk_response: BulkResponse = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(bulk_response)
+        } else {
+            Err(format!("Failed to apply bulk action: {}", response.status()))
+        }
+    }
+
+    pub async fn get_trash() -> Result<Vec<Todo>, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&format!("{}?deleted=true", url(routes::TODOS, &[])))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let todos: Vec<Todo> = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(todos)
+        } else {
+            Err(format!("Failed to fetch trash: {}", response.status()))
+        }
+    }
+
+    pub async fn restore_todo(id: &str) -> Result<Todo, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::post(&url(routes::TODO_RESTORE, &[id]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let todo: Todo = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(todo)
+        } else {
+            Err(format!("Failed to restore todo: {}", response.status()))
+        }
+    }
+
+    pub async fn delete_todo_permanently(id: &str) -> Result<(), String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::delete(&format!("{}?permanent=true", url(routes::TODO, &[id])))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            Ok(())
+        } else {
+            Err(format!("Failed to delete todo: {}", response.status()))
+        }
+    }
+
+    pub async fn export_todos(format: &str) -> Result<Vec<u8>, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&format!("{}?format={}", url(routes::EXPORT, &[]), format))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            response
+                .binary()
+                .await
+                .map_err(|e| format!("Read error: {}", e))
+        } else {
+            Err(format!("Failed to export todos: {}", response.status()))
+        }
+    }
+
+    pub async fn import_todos(
+        format: &str,
+        content: &str,
+        mapping: Option<&CsvMapping>,
+        dry_run: bool,
+    ) -> Result<ImportReport, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let form = FormData::new().map_err(|_| "Request error: could not create form".to_string())?;
+        let mut fields = vec![
+            ("format", format.to_string()),
+            ("file", content.to_string()),
+            ("dry_run", dry_run.to_string()),
+        ];
+        if let Some(mapping) = mapping {
+            let mapping = serde_json::to_string(mapping).map_err(|e| format!("Request error: {}", e))?;
+            fields.push(("mapping", mapping));
+        }
+        for (name, value) in fields {
+            form.append_with_str(name, &value)
+                .map_err(|_| "Request error: could not build form".to_string())?;
+        }
+
+        let response = Request::post(&url(routes::IMPORT, &[]))
+            .header("Authorization", &auth_header)
+            .body(form)
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let report: ImportReport = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(report)
+        } else {
+            match response.status() {
+                422 => Err("The file could not be read in the selected format".to_string()),
+                status => Err(format!("Failed to import todos: {}", status)),
+            }
+        }
+    }
+
+    // None until a feed is made with `regenerate_calendar_feed`
+    pub async fn get_calendar_feed() -> Result<Option<CalendarFeed>, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::CALENDAR_TOKEN, &[]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let feed: CalendarFeed = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(Some(feed))
+        } else if response.status() == 404 {
+            Ok(None)
+        } else {
+            Err(format!("Failed to fetch calendar feed: {}", response.status()))
+        }
+    }
+
+    pub async fn regenerate_calendar_feed() -> Result<CalendarFeed, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::post(&url(routes::CALENDAR_TOKEN, &[]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let feed: CalendarFeed = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(feed)
+        } else {
+            Err(format!("Failed to regenerate calendar feed: {}", response.status()))
+        }
+    }
+
+    // Absolute URL to paste into a calendar app
+    pub fn calendar_feed_url(feed: &CalendarFeed) -> String {
+        format!("{}{}", SERVER_URL, feed.path)
+    }
+
+    pub async fn get_app_passwords() -> Result<Vec<AppPassword>, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::APP_PASSWORDS, &[]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let passwords: Vec<AppPassword> = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(passwords)
+        } else {
+            Err(format!("Failed to fetch app passwords: {}", response.status()))
+        }
+    }
+
+    pub async fn create_app_password(name: String) -> Result<CreatedAppPassword, String> {
+        let auth_header = Self::get_auth_header()?;
+        let request = CreateAppPasswordRequest { name };
+
+        let response = Request::post(&url(routes::APP_PASSWORDS, &[]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let password: CreatedAppPassword = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(password)
+        } else {
+            Err(format!("Failed to create app password: {}", response.status()))
+        }
+    }
+
+    pub async fn delete_app_password(id: &str) -> Result<(), String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::delete(&url(routes::APP_PASSWORD, &[id]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            Ok(())
+        } else {
+            Err(format!("Failed to revoke app password: {}", response.status()))
+        }
+    }
+
+    pub async fn get_webhooks() -> Result<Vec<Webhook>, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::WEBHOOKS, &[]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let webhooks: Vec<Webhook> = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(webhooks)
+        } else {
+            Err(format!("Failed to fetch webhooks: {}", response.status()))
+        }
+    }
+
+    pub async fn create_webhook(request: CreateWebhookRequest) -> Result<CreatedWebhook, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::post(&url(routes::WEBHOOKS, &[]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let webhook: CreatedWebhook = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(webhook)
+        } else {
+            match response.status() {
+                400 => Err("Enter an http(s) URL and pick at least one event".to_string()),
+                status => Err(format!("Failed to create webhook: {}", status)),
+            }
+        }
+    }
+
+    pub async fn update_webhook(id: &str, update: WebhookUpdate) -> Result<Webhook, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::patch(&url(routes::WEBHOOK, &[id]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&update)
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let webhook: Webhook = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(webhook)
+        } else {
+            Err(format!("Failed to update webhook: {}", response.status()))
+        }
+    }
+
+    pub async fn delete_webhook(id: &str) -> Result<(), String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::delete(&url(routes::WEBHOOK, &[id]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            Ok(())
+        } else {
+            Err(format!("Failed to delete webhook: {}", response.status()))
+        }
+    }
+
+    pub async fn get_webhook_deliveries(id: &str) -> Result<Vec<WebhookDelivery>, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::WEBHOOK_DELIVERIES, &[id]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let deliveries: Vec<WebhookDelivery> = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(deliveries)
+        } else {
+            Err(format!("Failed to fetch webhook deliveries: {}", response.status()))
+        }
+    }
+
+    pub async fn retry_webhook_delivery(id: &str, delivery_id: &str) -> Result<(), String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::post(&url(routes::WEBHOOK_DELIVERY_RETRY, &[id, delivery_id]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            Ok(())
+        } else {
+            Err(format!("Failed to retry webhook delivery: {}", response.status()))
+        }
+    }
+
+    pub async fn get_notification_preferences() -> Result<NotificationPreferences, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::NOTIFICATION_PREFERENCES, &[]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let preferences: NotificationPreferences = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(preferences)
+        } else {
+            Err(format!("Failed to fetch notification preferences: {}", response.status()))
+        }
+    }
+
+    pub async fn update_notification_preferences(
+        update: NotificationPreferencesUpdate,
+    ) -> Result<NotificationPreferences, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::patch(&url(routes::NOTIFICATION_PREFERENCES, &[]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&update)
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            let preferences: NotificationPreferences = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(preferences)
+        } else {
+            Err(format!("Failed to update notification preferences: {}", response.status()))
+        }
+    }
+
+    // The HTML digest email the user would get now
+    pub async fn preview_digest() -> Result<String, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&format!("{}?format=html", url(routes::DIGEST_PREVIEW, &[])))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            response.text().await.map_err(|e| format!("Parse error: {}", e))
+        } else {
+            Err(format!("Failed to preview digest: {}", response.status()))
+        }
+    }
+
+    // None when the server has no Web Push keys configured
+    pub async fn get_push_public_key() -> Result<Option<String>, String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::get(&url(routes::PUSH_PUBLIC_KEY, &[]))
+            .header("Authorization", &auth_header)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.status() == 404 {
+            Ok(None)
+        } else if response.ok() {
+            let key: PushPublicKey = response
+                .json()
+                .await
+                .map_err(|e| format!("Parse error: {}", e))?;
+            Ok(Some(key.public_key))
+        } else {
+            Err(format!("Failed to fetch push key: {}", response.status()))
+        }
+    }
+
+    // `subscription` is the browser's PushSubscription serialized as JSON
+    pub async fn subscribe_push(subscription: String) -> Result<(), String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::post(&url(routes::PUSH_SUBSCRIPTIONS, &[]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .body(subscription)
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if response.ok() {
+            Ok(())
+        } else {
+            Err(format!("Failed to enable push notifications: {}", response.status()))
+        }
+    }
+
+    pub async fn unsubscribe_push(endpoint: String) -> Result<(), String> {
+        let auth_header = Self::get_auth_header()?;
+
+        let response = Request::delete(&url(routes::PUSH_SUBSCRIPTIONS, &[]))
+            .header("Authorization", &auth_header)
+            .header("Content-Type", "application/json")
+            .json(&PushUnsubscribeRequest { endpoint })
+            .map_err(|e| format!("Request error: {}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        // Already gone on the server is as good as removed
+        if response.ok() || response.status() == 404 {
+            Ok(())
+        } else {
+            Err(format!("Failed to disable push notifications: {}", response.status()))
+        }
     }
 
-    pub async fn update_todo_content(id: &str, title: Option<String>, description: Option<String>) -> Result<Todo, String> {
-        let update = TodoUpdate {
-            title,
-            description,
-            completed: None,
-        };
-        Self::update_todo(id, update).await
+    // Server address to enter in CalDAV clients; they discover the calendar from there
+    pub fn caldav_url() -> String {
+        format!("{}{}", SERVER_URL, routes::DAV_ROOT)
     }
-}
\ No newline at end of file
+}
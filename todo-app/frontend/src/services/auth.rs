@@ -1,8 +1,8 @@
 use gloo_storage::{LocalStorage, Storage};
 use gloo_net::http::Request;
-use crate::types::{AuthResponse,LoginRequest,RegisterRequest};
+use todo_api_types::{routes, AuthResponse, LoginRequest, RegisterRequest};
 
-const API_BASE_URL: &str = "http://127.0.0.1:3001/api";
+const SERVER_URL: &str = "http://127.0.0.1:3001";
 const TOKEN_KEY: &str = "auth_token";
 
 #[derive(Clone)]
@@ -17,12 +17,8 @@ impl AuthService{
         LocalStorage::get::<String>(TOKEN_KEY).is_ok()
     }
 
-    pub fn get_token(&self) -> Option<String> {
-        LocalStorage::get(TOKEN_KEY).ok()
-    }
-
     pub fn logout(&self){
-        let _ = LocalStorage::delete(TOKEN_KEY);
+        LocalStorage::delete(TOKEN_KEY);
         web_sys::window()
         .unwrap()
         .location()
@@ -33,7 +29,7 @@ impl AuthService{
     pub async fn login(&self, email: String, password: String) -> Result<(), String>{
         let request = LoginRequest{email, password};
         
-        let response = Request::post(&format!("{}/login",API_BASE_URL))
+        let response = Request::post(&format!("{}{}", SERVER_URL, routes::LOGIN))
             .header("Content-Type", "application/json")
             .json(&request)
             .map_err(|e| format!("Request Error: {}",e))?
@@ -59,7 +55,7 @@ impl AuthService{
     pub async fn register(&self, username: String, email: String, password: String) -> Result<(),String>{
         let request = RegisterRequest{username, email, password};
 
-        let response = Request::post(&format!("{}/register",API_BASE_URL))
+        let response = Request::post(&format!("{}{}", SERVER_URL, routes::REGISTER))
             .header("Content-Type", "application/json")
             .json(&request)
             .map_err(|e| format!("Request Error: {}",e))?
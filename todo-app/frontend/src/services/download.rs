@@ -0,0 +1,30 @@
+use wasm_bindgen::JsCast;
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+// Hands `bytes` to the browser as a file download named `file_name`
+pub fn download_file(file_name: &str, content_type: &str, bytes: &[u8]) -> Result<(), String> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = BlobPropertyBag::new();
+    options.set_type(content_type);
+
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)
+        .map_err(|_| "Failed to create file".to_string())?;
+    let url = Url::create_object_url_with_blob(&blob)
+        .map_err(|_| "Failed to create download link".to_string())?;
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("No document available")?;
+    let anchor: HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(|_| "Failed to create download link".to_string())?
+        .dyn_into()
+        .map_err(|_| "Failed to create download link".to_string())?;
+
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    let _ = Url::revoke_object_url(&url);
+    Ok(())
+}
@@ -1,2 +1,4 @@
 pub mod api;
-pub mod auth;
\ No newline at end of file
+pub mod auth;
+pub mod download;
+pub mod push;
\ No newline at end of file
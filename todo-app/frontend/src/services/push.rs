@@ -0,0 +1,100 @@
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Notification, PushManager, PushSubscription, PushSubscriptionOptionsInit, ServiceWorkerRegistration};
+
+// Served from the root of the site so it controls every page
+const SERVICE_WORKER_URL: &str = "/sw.js";
+
+pub fn is_supported() -> bool {
+    web_sys::window().is_some_and(|window| {
+        let navigator = window.navigator();
+        js_sys::Reflect::has(&navigator, &JsValue::from_str("serviceWorker")).unwrap_or(false)
+            && js_sys::Reflect::has(&window, &JsValue::from_str("PushManager")).unwrap_or(false)
+    })
+}
+
+async fn push_manager() -> Result<PushManager, String> {
+    let window = web_sys::window().ok_or("No window available")?;
+    let container = window.navigator().service_worker();
+
+    JsFuture::from(container.register(SERVICE_WORKER_URL))
+        .await
+        .map_err(|_| "Failed to register the service worker".to_string())?;
+    let registration: ServiceWorkerRegistration = JsFuture::from(
+        container.ready().map_err(|_| "Service worker not available".to_string())?,
+    )
+    .await
+    .map_err(|_| "Service worker not available".to_string())?
+    .unchecked_into();
+
+    registration
+        .push_manager()
+        .map_err(|_| "Push messaging not available".to_string())
+}
+
+async fn current_subscription(push_manager: &PushManager) -> Result<Option<PushSubscription>, String> {
+    let subscription = JsFuture::from(
+        push_manager
+            .get_subscription()
+            .map_err(|_| "Failed to read the push subscription".to_string())?,
+    )
+    .await
+    .map_err(|_| "Failed to read the push subscription".to_string())?;
+
+    Ok((!subscription.is_null()).then(|| subscription.unchecked_into()))
+}
+
+// Whether this browser already receives push notifications
+pub async fn is_subscribed() -> Result<bool, String> {
+    let push_manager = push_manager().await?;
+    Ok(current_subscription(&push_manager).await?.is_some())
+}
+
+// Asks for permission and subscribes this browser; returns the subscription as
+// the JSON the backend expects
+pub async fn subscribe(public_key: &str) -> Result<String, String> {
+    let permission = JsFuture::from(
+        Notification::request_permission().map_err(|_| "Failed to ask for permission".to_string())?,
+    )
+    .await
+    .map_err(|_| "Failed to ask for permission".to_string())?;
+    if permission.as_string().as_deref() != Some("granted") {
+        return Err("Notifications are blocked for this site".to_string());
+    }
+
+    let push_manager = push_manager().await?;
+    let options = PushSubscriptionOptionsInit::new();
+    options.set_user_visible_only(true);
+    options.set_application_server_key(&JsValue::from_str(public_key));
+
+    let subscription = JsFuture::from(
+        push_manager
+            .subscribe_with_options(&options)
+            .map_err(|_| "Failed to subscribe".to_string())?,
+    )
+    .await
+    .map_err(|_| "Failed to subscribe".to_string())?;
+
+    js_sys::JSON::stringify(&subscription)
+        .map(String::from)
+        .map_err(|_| "Failed to read the push subscription".to_string())
+}
+
+// Unsubscribes this browser; returns the endpoint it was subscribed with, if any
+pub async fn unsubscribe() -> Result<Option<String>, String> {
+    let push_manager = push_manager().await?;
+    let Some(subscription) = current_subscription(&push_manager).await? else {
+        return Ok(None);
+    };
+
+    let endpoint = subscription.endpoint();
+    JsFuture::from(
+        subscription
+            .unsubscribe()
+            .map_err(|_| "Failed to unsubscribe".to_string())?,
+    )
+    .await
+    .map_err(|_| "Failed to unsubscribe".to_string())?;
+
+    Ok(Some(endpoint))
+}
@@ -1,5 +1,3 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
 use yew_router::prelude::*;
 
 #[derive(Clone, Routable, PartialEq)]
@@ -8,60 +6,12 @@ pub enum Route {
     Home,
     #[at("/login")]
     Login,
+    #[at("/todos/:id")]
+    TodoDetail { id: String },
+    #[at("/import")]
+    Import,
+    #[at("/settings")]
+    Settings,
+    #[at("/admin")]
+    Admin,
 }
-
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub struct User{
-    pub id: String,
-    pub username: String,
-    pub email: String,
-    pub created_at: DateTime<Utc>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub struct Todo{
-    pub id: String,
-    pub user_id: String,
-    pub title: String,
-    pub description: Option<String>,
-    pub completed: bool,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct RegisterRequest{
-    pub username: String,
-    pub email: String,
-    pub password: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LoginRequest{
-    pub email: String,
-    pub password: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateTodoRequest{
-    pub title: String,
-    pub description: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TodoUpdate{
-    pub title: Option<String>,
-    pub description: Option<String>,
-    pub completed: Option<bool>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct AuthResponse{
-    pub message: String,
-    pub token: String,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct ErrorResponse{
-    pub error: String,
-}
\ No newline at end of file
@@ -0,0 +1,32 @@
+// Service worker showing reminder push notifications sent by the backend.
+// Payload: {"title", "body", "todo_id", "url"}
+
+self.addEventListener('push', (event) => {
+  const data = event.data ? event.data.json() : {};
+
+  event.waitUntil(
+    self.registration.showNotification(data.title || 'Reminder', {
+      body: data.body || '',
+      // A newer reminder for the same todo replaces the old one
+      tag: data.todo_id,
+      data: { url: data.url || '/' },
+    })
+  );
+});
+
+// Focus an open tab of the app on the todo, or open a new one
+self.addEventListener('notificationclick', (event) => {
+  event.notification.close();
+  const url = new URL(event.notification.data.url, self.location.origin).href;
+
+  event.waitUntil(
+    self.clients.matchAll({ type: 'window', includeUncontrolled: true }).then((windows) => {
+      for (const client of windows) {
+        if ('focus' in client && 'navigate' in client) {
+          return client.navigate(url).then((navigated) => (navigated || client).focus());
+        }
+      }
+      return self.clients.openWindow(url);
+    })
+  );
+});